@@ -2,7 +2,9 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::{
     parse::{Parse, ParseStream, Result},
-    parse_macro_input, Block, Ident, Token, Type,
+    parse_macro_input,
+    punctuated::Punctuated,
+    Attribute, Block, Data, DeriveInput, Expr, Ident, ItemFn, Token, Type,
 };
 
 #[proc_macro]
@@ -111,11 +113,11 @@ pub fn try_(tokens: TokenStream) -> TokenStream {
         error_ident,
     } = parse_macro_input!(tokens as TryCatchInput);
     let expanded = quote! {
-        match || -> ::std::result::Result<_, #error_ty> {
-            ::std::result::Result::Ok(#try_block)
+        match || -> ::core::result::Result<_, #error_ty> {
+            ::core::result::Result::Ok(#try_block)
         }() {
-          ::std::result::Result::Ok(ret) => ret,
-          ::std::result::Result::Err(#error_ident) => #catch_block
+          ::core::result::Result::Ok(ret) => ret,
+          ::core::result::Result::Err(#error_ident) => #catch_block
        }
     };
     TokenStream::from(expanded)
@@ -146,3 +148,673 @@ impl Parse for TryCatchInput {
         })
     }
 }
+
+#[proc_macro_attribute]
+/// `main` is an attribute macro that wraps a `main` function in a
+/// `futility::terminate::Terminate` so that small binaries don't need to write
+/// out the builder chain themselves
+///
+/// Each argument to the attribute is turned into a call to the method of the
+/// same name on `Terminate`, with `name = value` arguments passing the value to
+/// the method and bare `name` arguments calling it with no arguments. The
+/// annotated function is then passed to `execute`:
+///
+/// ```ignore
+/// use color_eyre::eyre::{Report, Result};
+///
+/// #[futility::main(install = setup, on_error = report, at_exit = cleanup, report_runtime)]
+/// fn main() -> Result<()> {
+///     println!("Hello, world!");
+///     Ok(())
+/// }
+/// ```
+///
+/// expands out to:
+///
+/// ```ignore
+/// fn main() -> Result<()> {
+///     fn __futility_main() -> Result<()> {
+///         println!("Hello, world!");
+///         Ok(())
+///     }
+///     ::futility::terminate::Terminate::new()
+///         .install(setup)
+///         .on_error(report)
+///         .at_exit(cleanup)
+///         .report_runtime()
+///         .execute(__futility_main)
+/// }
+/// ```
+pub fn main(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args with Punctuated::<MainArg, Token![,]>::parse_terminated);
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = parse_macro_input!(item as ItemFn);
+    let output = &sig.output;
+    let calls = method_calls(&args);
+    let expanded = quote! {
+        #(#attrs)*
+        #vis #sig {
+            fn __futility_main() #output #block
+            ::futility::terminate::Terminate::new()
+                #(#calls)*
+                .execute(__futility_main)
+        }
+    };
+    TokenStream::from(expanded)
+}
+
+#[proc_macro_attribute]
+/// `test` is an attribute macro that turns a function into a test run by
+/// `futility::terminate::harness::TestCase`, which runs setup before the test,
+/// turns a panic into a failure with a readable report, and runs cleanup after
+/// the test even if it failed
+///
+/// Like `#[futility::main]` each argument to the attribute is turned into a
+/// call to the method of the same name on `TestCase`, which are `install`,
+/// `install_once`, and `at_exit`:
+///
+/// ```ignore
+/// #[futility::test(install_once = start_database, at_exit = clear_tables)]
+/// fn inserts_user() -> Result<(), DbError> {
+///     insert_user("ferris")?;
+///     Ok(())
+/// }
+/// ```
+///
+/// The test can return either `()` or any `Result<(), E>` where `E: Debug`.
+/// As panics are caught and turned into failures, `#[should_panic]` can not
+/// be used with this attribute.
+pub fn test(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args with Punctuated::<MainArg, Token![,]>::parse_terminated);
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = parse_macro_input!(item as ItemFn);
+    let name = &sig.ident;
+    let output = &sig.output;
+    let calls = method_calls(&args);
+    let expanded = quote! {
+        #[::std::prelude::v1::test]
+        #(#attrs)*
+        #vis fn #name() -> ::std::result::Result<(), ::futility::terminate::harness::TestFailure> {
+            fn __futility_test() #output #block
+            ::futility::terminate::harness::TestCase::new()
+                #(#calls)*
+                .run(__futility_test)
+        }
+    };
+    TokenStream::from(expanded)
+}
+
+#[proc_macro_attribute]
+/// `fixture` is an attribute macro for a test that needs a value set up
+/// before it runs and torn down after, even if it fails, using
+/// `futility::test::fixture`
+///
+/// `setup` is a function returning the value, which the test takes as a
+/// `&mut` argument, and `teardown` is an optional function the value is
+/// passed to afterwards. Without `teardown` the value is just dropped:
+///
+/// ```ignore
+/// #[futility::fixture(setup = start_database, teardown = stop_database)]
+/// fn inserts_user(db: &mut Database) -> Result<(), DbError> {
+///     db.insert_user("ferris")?;
+///     Ok(())
+/// }
+/// ```
+///
+/// expands out to:
+///
+/// ```ignore
+/// #[test]
+/// fn inserts_user() -> Result<(), ::futility::test::TestFailure> {
+///     fn __futility_test(db: &mut Database) -> Result<(), DbError> {
+///         db.insert_user("ferris")?;
+///         Ok(())
+///     }
+///     ::futility::test::fixture(start_database, stop_database, __futility_test)
+/// }
+/// ```
+///
+/// Like `#[futility::test]`, panics are caught and turned into failures, so
+/// `#[should_panic]` can not be used with this attribute.
+pub fn fixture(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args with Punctuated::<MainArg, Token![,]>::parse_terminated);
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = parse_macro_input!(item as ItemFn);
+    let mut setup = None;
+    let mut teardown = None;
+    for MainArg { name, value } in args {
+        let slot = match name.to_string().as_str() {
+            "setup" => &mut setup,
+            "teardown" => &mut teardown,
+            _ => {
+                return syn::Error::new(name.span(), "expected `setup` or `teardown`")
+                    .to_compile_error()
+                    .into()
+            }
+        };
+        match value {
+            Some(value) => *slot = Some(value),
+            None => {
+                return syn::Error::new(name.span(), format!("`{name}` needs a function"))
+                    .to_compile_error()
+                    .into()
+            }
+        }
+    }
+    let Some(setup) = setup else {
+        return syn::Error::new(sig.ident.span(), "a fixture needs a `setup` function")
+            .to_compile_error()
+            .into();
+    };
+    let teardown = teardown.map_or_else(
+        || quote! { ::core::mem::drop },
+        |teardown| quote! { #teardown },
+    );
+    let name = sig.ident.clone();
+    let mut inner = sig;
+    inner.ident = Ident::new("__futility_test", name.span());
+    let expanded = quote! {
+        #[::std::prelude::v1::test]
+        #(#attrs)*
+        #vis fn #name() -> ::std::result::Result<(), ::futility::test::TestFailure> {
+            #inner #block
+            ::futility::test::fixture(#setup, #teardown, __futility_test)
+        }
+    };
+    TokenStream::from(expanded)
+}
+
+/// Turn every argument into a method call on the builder being expanded to
+fn method_calls(
+    args: &Punctuated<MainArg, Token![,]>,
+) -> impl Iterator<Item = proc_macro2::TokenStream> + '_ {
+    args.iter().map(|MainArg { name, value }| match value {
+        Some(value) => quote! { .#name(#value) },
+        None => quote! { .#name() },
+    })
+}
+
+struct MainArg {
+    name: Ident,
+    value: Option<Expr>,
+}
+
+impl Parse for MainArg {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name: Ident = input.parse()?;
+        let value = if input.peek(Token![=]) {
+            let _: Token![=] = input.parse()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        Ok(Self { name, value })
+    }
+}
+
+#[proc_macro_derive(ExitCoded, attributes(exit_code))]
+/// `ExitCoded` derives `futility::exit::ExitCoded` for an error type
+///
+/// Each variant of an enum can be given the code to exit with using
+/// `#[exit_code(...)]`, which takes any expression that evaluates to a `u8`
+/// such as one of the constants in `futility::exit`. Variants without one use
+/// the code given with `#[exit_code(...)]` on the type itself, or `1` if there
+/// isn't one. Structs always exit with the code on the type.
+///
+/// ```ignore
+/// use futility::exit::{ExitCoded, EX_CONFIG, EX_TEMPFAIL};
+///
+/// #[derive(Debug, ExitCoded)]
+/// #[exit_code(EX_SOFTWARE)]
+/// enum AppError {
+///     #[exit_code(64)]
+///     Usage(String),
+///     #[exit_code(EX_CONFIG)]
+///     MissingConfig { key: String },
+///     #[exit_code(EX_TEMPFAIL)]
+///     Unreachable,
+///     Bug,
+/// }
+/// ```
+///
+/// expands out to:
+///
+/// ```ignore
+/// impl ::futility::exit::ExitCoded for AppError {
+///     fn code(&self) -> u8 {
+///         match self {
+///             Self::Usage { .. } => 64,
+///             Self::MissingConfig { .. } => EX_CONFIG,
+///             Self::Unreachable { .. } => EX_TEMPFAIL,
+///             Self::Bug { .. } => EX_SOFTWARE,
+///         }
+///     }
+/// }
+/// ```
+pub fn derive_exit_coded(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    match exit_coded(input) {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+fn exit_coded(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let default = exit_code_attr(&input.attrs)?.unwrap_or_else(|| syn::parse_quote!(1));
+    let body = match &input.data {
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let name = &variant.ident;
+                    let code = exit_code_attr(&variant.attrs)?.unwrap_or_else(|| default.clone());
+                    Ok(quote! { Self::#name { .. } => #code, })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Struct(_) => quote! { #default },
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "ExitCoded can't be derived for unions",
+            ))
+        }
+    };
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::futility::exit::ExitCoded for #name #ty_generics #where_clause {
+            fn code(&self) -> u8 {
+                #body
+            }
+        }
+    })
+}
+
+/// The expression in the `#[exit_code(...)]` attribute, if there is one
+fn exit_code_attr(attrs: &[Attribute]) -> Result<Option<Expr>> {
+    let mut attrs = attrs.iter().filter(|attr| attr.path.is_ident("exit_code"));
+    let Some(attr) = attrs.next() else {
+        return Ok(None);
+    };
+    if let Some(duplicate) = attrs.next() {
+        return Err(syn::Error::new_spanned(
+            duplicate,
+            "only one #[exit_code(...)] is allowed",
+        ));
+    }
+    attr.parse_args().map(Some)
+}
+
+#[proc_macro_derive(FromEnv, attributes(env))]
+/// `FromEnv` derives `futility::env::FromEnv` for a struct with named fields,
+/// reading each field from an environment variable
+///
+/// Each field is parsed with its type's `FromStr` impl from the variable
+/// named by `#[env(name = "...")]`, or the field's name in upper case if
+/// there isn't one. `#[env(default = "...")]` gives the value to parse if the
+/// variable isn't set, and fields with an `Option` type are `None` if it
+/// isn't set and has no default. Otherwise the variable is required. `#[env(prefix = "...")]` on
+/// the struct is put in front of every variable name.
+///
+/// ```ignore
+/// use futility::env::FromEnv;
+///
+/// #[derive(FromEnv)]
+/// #[env(prefix = "APP_")]
+/// struct Config {
+///     #[env(name = "PORT", default = "8080")]
+///     port: u16,
+///     database_url: String,
+///     workers: Option<usize>,
+/// }
+/// ```
+///
+/// reads `APP_PORT`, `APP_DATABASE_URL`, and `APP_WORKERS`, returning every
+/// problem with them at once from `Config::from_env()`.
+pub fn derive_from_env(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    match from_env(input) {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+fn from_env(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "FromEnv can only be derived for structs with named fields",
+            ))
+        }
+    };
+    let mut attrs = env_attrs(&input.attrs)?;
+    let prefix = attrs
+        .remove("prefix")
+        .map(|prefix| prefix.value())
+        .unwrap_or_default();
+    if let Some(key) = attrs.into_keys().next() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            format!("unknown env attribute `{key}` on a struct"),
+        ));
+    }
+    let mut reads = Vec::new();
+    let mut inits = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named fields have names");
+        let mut attrs = env_attrs(&field.attrs)?;
+        let name = attrs
+            .remove("name")
+            .map(|name| name.value())
+            .unwrap_or_else(|| ident.to_string().to_uppercase());
+        let name = format!("{prefix}{name}");
+        let read = match (attrs.remove("default"), is_option(&field.ty)) {
+            (Some(default), true) => quote! {
+                ::futility::env::var_opt(#name).and_then(|value| match value {
+                    ::std::option::Option::Some(value) => {
+                        ::std::result::Result::Ok(::std::option::Option::Some(value))
+                    }
+                    ::std::option::Option::None => {
+                        ::futility::env::parse(#name, #default).map(::std::option::Option::Some)
+                    }
+                })
+            },
+            (Some(default), false) => quote! {
+                ::futility::env::var_opt(#name).and_then(|value| match value {
+                    ::std::option::Option::Some(value) => ::std::result::Result::Ok(value),
+                    ::std::option::Option::None => ::futility::env::parse(#name, #default),
+                })
+            },
+            (None, true) => quote! { ::futility::env::var_opt(#name) },
+            (None, false) => quote! { ::futility::env::var_parsed(#name) },
+        };
+        if let Some(key) = attrs.into_keys().next() {
+            return Err(syn::Error::new_spanned(
+                field,
+                format!("unknown env attribute `{key}` on a field"),
+            ));
+        }
+        reads.push(quote! { let #ident = __missing.collect(#read); });
+        inits.push(quote! { #ident: #ident.expect("checked for errors above") });
+    }
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::futility::env::FromEnv for #name #ty_generics #where_clause {
+            fn from_env() -> ::std::result::Result<Self, ::futility::env::MissingVars> {
+                let mut __missing = ::futility::env::MissingVars::default();
+                #(#reads)*
+                __missing.into_result()?;
+                ::std::result::Result::Ok(Self {
+                    #(#inits,)*
+                })
+            }
+        }
+    })
+}
+
+/// The `key = "value"` pairs in `#[env(...)]` attributes
+fn env_attrs(attrs: &[Attribute]) -> Result<std::collections::BTreeMap<String, syn::LitStr>> {
+    let mut pairs = std::collections::BTreeMap::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("env")) {
+        let args =
+            attr.parse_args_with(Punctuated::<syn::MetaNameValue, Token![,]>::parse_terminated)?;
+        for arg in args {
+            let key = arg
+                .path
+                .get_ident()
+                .map(ToString::to_string)
+                .ok_or_else(|| syn::Error::new_spanned(&arg.path, "expected a name"))?;
+            let syn::Lit::Str(value) = arg.lit else {
+                return Err(syn::Error::new_spanned(arg.lit, "expected a string"));
+            };
+            pairs.insert(key, value);
+        }
+    }
+    Ok(pairs)
+}
+
+/// Whether `ty` mentions any of the type parameters in `generics`
+fn uses_type_params(ty: &Type, generics: &syn::Generics) -> bool {
+    fn mentions(tokens: proc_macro2::TokenStream, params: &[&Ident]) -> bool {
+        tokens.into_iter().any(|token| match token {
+            proc_macro2::TokenTree::Ident(ident) => params.contains(&&ident),
+            proc_macro2::TokenTree::Group(group) => mentions(group.stream(), params),
+            _ => false,
+        })
+    }
+    let params = generics
+        .type_params()
+        .map(|param| &param.ident)
+        .collect::<Vec<_>>();
+    mentions(quote! { #ty }, &params)
+}
+
+/// Whether `ty` is written as an `Option`
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+#[proc_macro_derive(ContextError, attributes(context, source))]
+/// `ContextError` derives `Display`, `core::error::Error`, and `From` for a
+/// struct that wraps another error with a message
+///
+/// The message is given with `#[context("...")]` on the struct, a format
+/// string that can use the struct's fields by name, or `_0`, `_1`, and so on
+/// for the fields of a tuple struct, followed by any arguments the same as
+/// `write!` takes. The wrapped error is the field marked
+/// with `#[source]`, or the only field if there's just one, and is returned
+/// from `source()`. It can be any error, a `Box<dyn Error>`, or a
+/// `futility::error::Adhoc`. If it's the only field `From` is derived for its
+/// type, so `?` wraps the inner error on its own. A source whose type uses one
+/// of the struct's type parameters is bound by `Error + 'static` in the
+/// `Error` impl.
+///
+/// ```ignore
+/// use futility::error::ContextError;
+///
+/// #[derive(Debug, ContextError)]
+/// #[context("failed to read the config")]
+/// struct ReadConfig(std::io::Error);
+///
+/// #[derive(Debug, ContextError)]
+/// #[context("{path} has an invalid port")]
+/// struct InvalidPort {
+///     path: String,
+///     #[source]
+///     err: std::num::ParseIntError,
+/// }
+/// ```
+///
+/// expands out to:
+///
+/// ```ignore
+/// impl ::core::fmt::Display for ReadConfig {
+///     fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+///         let Self(_0) = self;
+///         ::core::write!(f, "failed to read the config")
+///     }
+/// }
+///
+/// impl ::core::error::Error for ReadConfig {
+///     fn source(&self) -> Option<&(dyn ::core::error::Error + 'static)> {
+///         use ::futility::__private::AsDynError;
+///         Some(self.0.as_dyn_error())
+///     }
+/// }
+///
+/// impl ::core::convert::From<std::io::Error> for ReadConfig {
+///     fn from(source: std::io::Error) -> Self {
+///         Self(source)
+///     }
+/// }
+/// ```
+///
+/// along with the same `Display` and `Error` impls for `InvalidPort`, which
+/// has no `From` impl since `path` can't be made from the `ParseIntError`.
+pub fn derive_context_error(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    match context_error(input) {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(err) => TokenStream::from(err.to_compile_error()),
+    }
+}
+
+fn context_error(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) if !data.fields.is_empty() => &data.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "ContextError can only be derived for structs with fields",
+            ))
+        }
+    };
+    let mut contexts = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("context"));
+    let Some(context) = contexts.next() else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "ContextError needs a message in #[context(\"...\")]",
+        ));
+    };
+    if let Some(duplicate) = contexts.next() {
+        return Err(syn::Error::new_spanned(
+            duplicate,
+            "only one #[context(...)] is allowed",
+        ));
+    }
+    let context = context.parse_args_with(|input: ParseStream| {
+        let _: syn::LitStr = input.fork().parse()?;
+        input.parse::<proc_macro2::TokenStream>()
+    })?;
+
+    let marked = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| field.attrs.iter().any(|attr| attr.path.is_ident("source")))
+        .collect::<Vec<_>>();
+    let (index, source) = match (marked.as_slice(), fields.len()) {
+        ([marked], _) => *marked,
+        ([], 1) => (0, fields.iter().next().expect("there is one field")),
+        ([], _) => {
+            return Err(syn::Error::new_spanned(
+                fields,
+                "mark the wrapped error with #[source]",
+            ))
+        }
+        ([_, duplicate, ..], _) => {
+            return Err(syn::Error::new_spanned(
+                duplicate.1,
+                "only one field can be the #[source]",
+            ))
+        }
+    };
+    let member = match &source.ident {
+        Some(ident) => quote! { #ident },
+        None => {
+            let index = syn::Index::from(index);
+            quote! { #index }
+        }
+    };
+
+    // Bind every field to a local so the message can use them by name
+    let bindings = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => ident.clone(),
+            None => quote::format_ident!("_{}", i),
+        })
+        .collect::<Vec<_>>();
+    let pattern = match fields {
+        syn::Fields::Named(_) => quote! { Self { #(#bindings,)* } },
+        _ => quote! { Self(#(#bindings,)*) },
+    };
+
+    // The formatter has its own hygiene so a field named `f` doesn't shadow it
+    let formatter = Ident::new("f", proc_macro2::Span::mixed_site());
+
+    // A source whose type is a type parameter, such as `E`, needs to be an
+    // error for `source()` to return it
+    let mut error_generics = input.generics.clone();
+    if uses_type_params(&source.ty, &input.generics) {
+        let ty = &source.ty;
+        error_generics
+            .make_where_clause()
+            .predicates
+            .push(syn::parse_quote! { #ty: ::core::error::Error + 'static });
+    }
+    let (_, _, error_where_clause) = error_generics.split_for_impl();
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let from = match fields.len() {
+        1 => {
+            let ty = &source.ty;
+            let construct = match &source.ident {
+                Some(ident) => quote! { Self { #ident: source } },
+                None => quote! { Self(source) },
+            };
+            quote! {
+                impl #impl_generics ::core::convert::From<#ty> for #name #ty_generics #where_clause {
+                    fn from(source: #ty) -> Self {
+                        #construct
+                    }
+                }
+            }
+        }
+        _ => quote! {},
+    };
+    Ok(quote! {
+        impl #impl_generics ::core::fmt::Display for #name #ty_generics #where_clause {
+            fn fmt(&self, #formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                #[allow(unused_variables)]
+                let #pattern = self;
+                ::core::write!(#formatter, #context)
+            }
+        }
+
+        impl #impl_generics ::core::error::Error for #name #ty_generics #error_where_clause {
+            fn source(&self) -> ::core::option::Option<&(dyn ::core::error::Error + 'static)> {
+                use ::futility::__private::AsDynError;
+                ::core::option::Option::Some(self.#member.as_dyn_error())
+            }
+        }
+
+        #from
+    })
+}
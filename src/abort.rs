@@ -0,0 +1,66 @@
+//! Aborting instead of unwinding out of a critical section
+//!
+//! Catching a panic or cleaning up after one assumes that whatever the panic
+//! interrupted can be left half done. Some code can't be: a callback that
+//! foreign code is in the middle of calling, a data structure that is only
+//! consistent again once a sequence of writes finishes, or memory handed out
+//! with `unsafe` that still has pointers into it. An [`AbortGuard`] covers
+//! such a section and aborts the process if a panic unwinds through it, since
+//! anything catching the panic further up would see the broken state.
+//!
+//! ```
+//! # use futility::abort::AbortGuard;
+//! fn swap_halves(buf: &mut [u8]) {
+//!     let guard = AbortGuard::new("swapping the halves of the buffer");
+//!     let (front, back) = buf.split_at_mut(buf.len() / 2);
+//!     front.swap_with_slice(&mut back[..front.len()]);
+//!     guard.disarm();
+//! }
+//!
+//! let mut buf = *b"abcd";
+//! swap_halves(&mut buf);
+//! assert_eq!(&buf, b"cdab");
+//! ```
+
+use std::{
+    io::{self, Write},
+    process, thread,
+};
+
+/// Aborts the process if it's dropped while a panic is unwinding the thread
+#[derive(Debug)]
+#[must_use = "the guard only covers the section while it's held"]
+pub struct AbortGuard {
+    reason: &'static str,
+}
+
+impl AbortGuard {
+    /// Start a critical section, described by `reason` in the message printed
+    /// before aborting
+    pub const fn new(reason: &'static str) -> Self {
+        Self { reason }
+    }
+
+    /// What the critical section is doing
+    pub fn reason(&self) -> &'static str {
+        self.reason
+    }
+
+    /// End the critical section now that it has finished
+    pub fn disarm(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for AbortGuard {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            let _ = writeln!(
+                io::stderr(),
+                "aborting: a panic unwound out of a critical section while {}",
+                self.reason
+            );
+            process::abort();
+        }
+    }
+}
@@ -0,0 +1,479 @@
+//! Parsing command line arguments without a dependency
+//!
+//! Small utilities usually need a couple of flags, an option or two, and some
+//! positional arguments, which isn't worth pulling in a full argument parser
+//! for. [`Args`] declares them with the same syntax they're shown with in the
+//! help text, parses them, and generates `--help` from the declarations.
+//!
+//! ```
+//! # use futility::args::Args;
+//! let args = Args::new("grep")
+//!     .about("Search files for a pattern")
+//!     .flag("-i, --ignore-case", "Match case insensitively")
+//!     .option("-m, --max-count <NUM>", "Stop after NUM matches")
+//!     .positional("PATTERN", "The pattern to search for")
+//!     .rest("FILE", "The files to search")
+//!     .parse(["-i", "--max-count=3", "fn main", "src/lib.rs", "src/args.rs"])
+//!     .unwrap();
+//!
+//! assert!(args.flag("ignore-case"));
+//! assert_eq!(args.parse::<usize>("max-count").unwrap(), Some(3));
+//! assert_eq!(args.value("PATTERN"), Some("fn main"));
+//! assert_eq!(args.rest(), ["src/lib.rs", "src/args.rs"]);
+//! ```
+//!
+//! Flags and options are declared as `-s, --long`, `-s`, or `--long`, with
+//! options followed by the name of their value in angle brackets. They're
+//! looked up by their long name, or their short name if they don't have one.
+//! Short flags can be combined like `-abc`, and option values can be given as
+//! `-o VALUE`, `-oVALUE`, `--output VALUE`, or `--output=VALUE`. Everything
+//! after `--` is positional.
+//!
+//! [`ArgsError`] implements [`ExitCoded`], exiting with
+//! [`EX_USAGE`](crate::exit::EX_USAGE), so it can be returned through
+//! [`Terminate`](crate::terminate::Terminate) with
+//! [`Terminate::exit_coded`](crate::terminate::Terminate::exit_coded) or
+//! passed to [`exit_with`](crate::exit::exit_with). [`Args::parse_env`] handles
+//! `--help` itself and is usually all that a program needs.
+
+use crate::exit::{self, ExitCoded};
+use std::{
+    env,
+    fmt::{Display, Write},
+    process,
+    str::FromStr,
+};
+use thiserror::Error;
+
+/// What was wrong with the arguments
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum ArgsError {
+    /// `-h` or `--help` was given, with the help text to print
+    #[error("{0}")]
+    Help(String),
+    /// A flag or option that wasn't declared was given
+    #[error("unknown argument `{0}`")]
+    Unknown(String),
+    /// An option was given without a value
+    #[error("`{0}` needs a value")]
+    MissingValue(String),
+    /// A flag was given a value with `--flag=value`
+    #[error("`{0}` doesn't take a value")]
+    UnexpectedValue(String),
+    /// A required positional argument wasn't given
+    #[error("missing {0}")]
+    MissingPositional(String),
+    /// More positional arguments were given than were declared
+    #[error("unexpected argument `{0}`")]
+    UnexpectedPositional(String),
+    /// A value couldn't be parsed
+    #[error("invalid value {value:?} for {name}: {reason}")]
+    Invalid {
+        /// The name of the option or positional argument
+        name: String,
+        /// The value that couldn't be parsed
+        value: String,
+        /// Why it couldn't be parsed
+        reason: String,
+    },
+}
+
+impl ExitCoded for ArgsError {
+    fn code(&self) -> u8 {
+        match self {
+            ArgsError::Help(_) => exit::EX_OK,
+            _ => exit::EX_USAGE,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Named {
+    short: Option<char>,
+    long: Option<String>,
+    value: Option<String>,
+    help: String,
+}
+
+impl Named {
+    /// The name it's looked up by
+    fn key(&self) -> String {
+        match (&self.long, self.short) {
+            (Some(long), _) => long.clone(),
+            (None, Some(short)) => short.to_string(),
+            (None, None) => unreachable!("checked when declared"),
+        }
+    }
+
+    /// How it's written in the help text
+    fn usage(&self) -> String {
+        let mut usage = match (self.short, &self.long) {
+            (Some(short), Some(long)) => format!("-{short}, --{long}"),
+            (Some(short), None) => format!("-{short}"),
+            (None, Some(long)) => format!("    --{long}"),
+            (None, None) => unreachable!("checked when declared"),
+        };
+        if let Some(value) = &self.value {
+            write!(usage, " <{value}>").unwrap();
+        }
+        usage
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Positional {
+    name: String,
+    help: String,
+}
+
+/// The arguments a program takes
+#[derive(Clone, Debug)]
+pub struct Args {
+    program: String,
+    about: Option<String>,
+    named: Vec<Named>,
+    positionals: Vec<Positional>,
+    rest: Option<Positional>,
+}
+
+impl Args {
+    /// Declare the arguments of `program`, whose name is shown in the help
+    /// text
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            about: None,
+            named: Vec::new(),
+            positionals: Vec::new(),
+            rest: None,
+        }
+    }
+
+    /// Describe what the program does at the top of the help text
+    pub fn about(mut self, about: impl Into<String>) -> Self {
+        self.about = Some(about.into());
+        self
+    }
+
+    /// Declare a flag like `-v, --verbose`
+    ///
+    /// # Panics
+    ///
+    /// If `spec` isn't a short name, a long name, or both separated by a comma
+    pub fn flag(mut self, spec: &str, help: impl Into<String>) -> Self {
+        self.named.push(parse_spec(spec, false, help.into()));
+        self
+    }
+
+    /// Declare an option like `-o, --output <FILE>`
+    ///
+    /// # Panics
+    ///
+    /// If `spec` isn't a short name, a long name, or both separated by a
+    /// comma, followed by the name of the value in angle brackets
+    pub fn option(mut self, spec: &str, help: impl Into<String>) -> Self {
+        self.named.push(parse_spec(spec, true, help.into()));
+        self
+    }
+
+    /// Declare a required positional argument, after the ones already
+    /// declared
+    pub fn positional(mut self, name: impl Into<String>, help: impl Into<String>) -> Self {
+        self.positionals.push(Positional {
+            name: name.into(),
+            help: help.into(),
+        });
+        self
+    }
+
+    /// Accept any number of positional arguments after the declared ones
+    pub fn rest(mut self, name: impl Into<String>, help: impl Into<String>) -> Self {
+        self.rest = Some(Positional {
+            name: name.into(),
+            help: help.into(),
+        });
+        self
+    }
+
+    /// The help text printed for `--help`
+    pub fn help(&self) -> String {
+        let mut help = String::new();
+        if let Some(about) = &self.about {
+            writeln!(help, "{about}\n").unwrap();
+        }
+        write!(help, "Usage: {} [OPTIONS]", self.program).unwrap();
+        for positional in &self.positionals {
+            write!(help, " {}", positional.name).unwrap();
+        }
+        if let Some(rest) = &self.rest {
+            write!(help, " [{}]...", rest.name).unwrap();
+        }
+        let arguments = self
+            .positionals
+            .iter()
+            .chain(&self.rest)
+            .map(|positional| (positional.name.clone(), &positional.help))
+            .collect::<Vec<_>>();
+        let help_flag = Named {
+            short: Some('h'),
+            long: Some("help".into()),
+            value: None,
+            help: "Print help".into(),
+        };
+        let options = self
+            .named
+            .iter()
+            .chain([&help_flag])
+            .map(|named| (named.usage(), &named.help))
+            .collect::<Vec<_>>();
+        for (heading, entries) in [("Arguments", arguments), ("Options", options)] {
+            if entries.is_empty() {
+                continue;
+            }
+            write!(help, "\n\n{heading}:").unwrap();
+            let width = entries
+                .iter()
+                .map(|(usage, _)| usage.len())
+                .max()
+                .unwrap_or(0);
+            for (usage, text) in entries {
+                write!(help, "\n  {usage:width$}  {text}").unwrap();
+            }
+        }
+        help
+    }
+
+    /// Parse the arguments the program was run with, skipping the program's
+    /// name. If `--help` was given the help text is printed and the program
+    /// exits successfully, and if the arguments are invalid the error is
+    /// printed along with how to get help and the program exits with
+    /// [`EX_USAGE`](crate::exit::EX_USAGE).
+    pub fn parse_env(&self) -> Parsed {
+        match self.parse(env::args().skip(1)) {
+            Ok(parsed) => parsed,
+            Err(ArgsError::Help(help)) => {
+                println!("{help}");
+                process::exit(0);
+            }
+            Err(err) => {
+                eprintln!("{}: {err}\n", crate::tty::error_label());
+                eprintln!("For more information, try '--help'.");
+                process::exit(err.code().into());
+            }
+        }
+    }
+
+    /// Parse `args`, which shouldn't include the program's name
+    pub fn parse<I>(&self, args: I) -> Result<Parsed, ArgsError>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let mut parsed = Parsed {
+            named: Vec::new(),
+            positionals: Vec::new(),
+            rest: Vec::new(),
+            declared: self.named.iter().map(Named::key).collect(),
+            declared_positionals: self.positionals.iter().map(|p| p.name.clone()).collect(),
+        };
+        let mut args = args.into_iter().map(Into::into);
+        let mut positionals = Vec::new();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                positionals.extend(args.by_ref());
+                break;
+            }
+            if arg == "-h" || arg == "--help" {
+                return Err(ArgsError::Help(self.help()));
+            }
+            if let Some(long) = arg.strip_prefix("--") {
+                let (long, inline) = match long.split_once('=') {
+                    Some((long, value)) => (long, Some(value.to_string())),
+                    None => (long, None),
+                };
+                let named = self
+                    .named
+                    .iter()
+                    .find(|named| named.long.as_deref() == Some(long))
+                    .ok_or_else(|| ArgsError::Unknown(arg.clone()))?;
+                let value = match (&named.value, inline) {
+                    (Some(_), Some(value)) => Some(value),
+                    (Some(_), None) => Some(args.next().ok_or(ArgsError::MissingValue(arg))?),
+                    (None, Some(_)) => return Err(ArgsError::UnexpectedValue(format!("--{long}"))),
+                    (None, None) => None,
+                };
+                parsed.named.push((named.key(), value));
+            } else if let Some(shorts) = arg.strip_prefix('-').filter(|shorts| !shorts.is_empty()) {
+                for (index, short) in shorts.char_indices() {
+                    let named = self
+                        .named
+                        .iter()
+                        .find(|named| named.short == Some(short))
+                        .ok_or_else(|| ArgsError::Unknown(format!("-{short}")))?;
+                    if named.value.is_none() {
+                        parsed.named.push((named.key(), None));
+                        continue;
+                    }
+                    let inline = &shorts[index + short.len_utf8()..];
+                    let value = match inline.is_empty() {
+                        true => args
+                            .next()
+                            .ok_or_else(|| ArgsError::MissingValue(format!("-{short}")))?,
+                        false => inline.to_string(),
+                    };
+                    parsed.named.push((named.key(), Some(value)));
+                    break;
+                }
+            } else {
+                positionals.push(arg);
+            }
+        }
+
+        let mut positionals = positionals.into_iter();
+        for positional in &self.positionals {
+            let value = positionals
+                .next()
+                .ok_or_else(|| ArgsError::MissingPositional(positional.name.clone()))?;
+            parsed.positionals.push(value);
+        }
+        parsed.rest.extend(positionals);
+        if let (None, Some(unexpected)) = (&self.rest, parsed.rest.first()) {
+            return Err(ArgsError::UnexpectedPositional(unexpected.clone()));
+        }
+        Ok(parsed)
+    }
+}
+
+/// Turn a spec like `-o, --output <FILE>` into a named argument
+fn parse_spec(spec: &str, takes_value: bool, help: String) -> Named {
+    let (names, value) = match spec.split_once('<') {
+        Some((names, value)) => {
+            let value = value
+                .strip_suffix('>')
+                .unwrap_or_else(|| panic!("`{spec}` is missing a `>`"));
+            (names.trim(), Some(value.to_string()))
+        }
+        None => (spec.trim(), None),
+    };
+    assert_eq!(
+        takes_value,
+        value.is_some(),
+        "`{spec}` should {}have a value like `<VALUE>`",
+        if takes_value { "" } else { "not " }
+    );
+    let mut named = Named {
+        short: None,
+        long: None,
+        value,
+        help,
+    };
+    for name in names.split(',').map(str::trim) {
+        if let Some(long) = name.strip_prefix("--").filter(|long| !long.is_empty()) {
+            named.long = Some(long.into());
+        } else if let Some(short) = name.strip_prefix('-') {
+            let mut chars = short.chars();
+            match (chars.next(), chars.next()) {
+                (Some(short), None) => named.short = Some(short),
+                _ => panic!("`{name}` in `{spec}` should be a single character"),
+            }
+        } else {
+            panic!("`{name}` in `{spec}` should start with `-` or `--`");
+        }
+    }
+    assert!(
+        named.short.is_some() || named.long.is_some(),
+        "`{spec}` has no names"
+    );
+    named
+}
+
+/// The arguments given to the program
+#[derive(Clone, Debug)]
+pub struct Parsed {
+    named: Vec<(String, Option<String>)>,
+    positionals: Vec<String>,
+    rest: Vec<String>,
+    declared: Vec<String>,
+    declared_positionals: Vec<String>,
+}
+
+impl Parsed {
+    /// How many times the flag or option `name` was given
+    ///
+    /// # Panics
+    ///
+    /// If no flag or option is named `name`
+    pub fn count(&self, name: &str) -> usize {
+        assert!(
+            self.declared.iter().any(|declared| declared == name),
+            "no flag or option is named `{name}`"
+        );
+        self.named.iter().filter(|(key, _)| key == name).count()
+    }
+
+    /// Whether the flag `name` was given
+    ///
+    /// # Panics
+    ///
+    /// If no flag or option is named `name`
+    pub fn flag(&self, name: &str) -> bool {
+        self.count(name) > 0
+    }
+
+    /// Every value given for the option `name`, in order
+    ///
+    /// # Panics
+    ///
+    /// If no flag or option is named `name`
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        self.count(name);
+        self.named
+            .iter()
+            .filter(|(key, _)| key == name)
+            .filter_map(|(_, value)| value.as_deref())
+            .collect()
+    }
+
+    /// The value of the positional argument or option `name`, using the last
+    /// one if the option was given more than once
+    ///
+    /// # Panics
+    ///
+    /// If no positional argument, flag, or option is named `name`
+    pub fn value(&self, name: &str) -> Option<&str> {
+        match self
+            .declared_positionals
+            .iter()
+            .position(|declared| declared == name)
+        {
+            Some(index) => self.positionals.get(index).map(String::as_str),
+            None => self.values(name).pop(),
+        }
+    }
+
+    /// Parse the value of the positional argument or option `name`
+    ///
+    /// # Panics
+    ///
+    /// If no positional argument, flag, or option is named `name`
+    pub fn parse<T>(&self, name: &str) -> Result<Option<T>, ArgsError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.value(name)
+            .map(|value| {
+                value.parse().map_err(|err: T::Err| ArgsError::Invalid {
+                    name: name.into(),
+                    value: value.into(),
+                    reason: err.to_string(),
+                })
+            })
+            .transpose()
+    }
+
+    /// The positional arguments after the declared ones
+    pub fn rest(&self) -> &[String] {
+        &self.rest
+    }
+}
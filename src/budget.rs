@@ -0,0 +1,140 @@
+//! Passing down how much time is left
+//!
+//! A request handler given five seconds calls a database, a cache, and another
+//! service, each of which should give up when the request's time is up rather
+//! than after a timeout of their own. A [`Deadline`] is that time: it's
+//! created once at the top, passed down through each layer, and checked with
+//! [`Deadline::checkpoint`] between steps so that `?` stops the work once it
+//! has expired.
+//!
+//! A layer that wants to leave time for what comes after it can hand on a
+//! smaller deadline with [`Deadline::limit`] or [`Deadline::split`], which
+//! never end after the deadline they were made from. Deadlines can also bound
+//! retries with [`RetryPolicy::deadline`](crate::retry::RetryPolicy::deadline)
+//! and worker threads with [`timeout::run_within`](crate::timeout::run_within).
+//!
+//! ```
+//! # use futility::budget::{Deadline, Expired};
+//! # use std::time::Duration;
+//! fn handle(deadline: Deadline) -> Result<String, Expired> {
+//!     let user = load_user(deadline.split(2))?;
+//!     deadline.checkpoint()?;
+//!     let posts = load_posts(deadline)?;
+//!     Ok(format!("{user}: {posts} posts"))
+//! }
+//!
+//! fn load_user(deadline: Deadline) -> Result<&'static str, Expired> {
+//!     deadline.checkpoint()?;
+//!     Ok("ferris")
+//! }
+//!
+//! fn load_posts(deadline: Deadline) -> Result<u32, Expired> {
+//!     deadline.checkpoint()?;
+//!     Ok(3)
+//! }
+//!
+//! assert_eq!(handle(Deadline::after(Duration::from_secs(5))).unwrap(), "ferris: 3 posts");
+//! assert!(handle(Deadline::after(Duration::ZERO)).is_err());
+//! ```
+
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+/// The error returned by [`Deadline::checkpoint`] once the deadline has
+/// passed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+#[error("deadline expired after {budget:?}")]
+pub struct Expired {
+    /// How long the deadline was set for when it was created
+    pub budget: Duration,
+}
+
+impl From<Expired> for io::Error {
+    fn from(err: Expired) -> Self {
+        io::Error::new(io::ErrorKind::TimedOut, err)
+    }
+}
+
+/// About thirty years, which is used in place of budgets that can't be added
+/// to the current time
+const FAR_FUTURE: Duration = Duration::from_secs(30 * 365 * 24 * 60 * 60);
+
+/// A point in time that some work should be done by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Deadline {
+    at: Instant,
+    budget: Duration,
+}
+
+impl Deadline {
+    /// A deadline `budget` from now. A budget too big to add to the current
+    /// time, such as [`Duration::MAX`], is a deadline that never comes in
+    /// practice.
+    pub fn after(budget: Duration) -> Self {
+        let now = Instant::now();
+        Self {
+            at: now
+                .checked_add(budget)
+                .or_else(|| now.checked_add(FAR_FUTURE))
+                .unwrap_or(now),
+            budget,
+        }
+    }
+
+    /// A deadline at `at`
+    pub fn until(at: Instant) -> Self {
+        Self {
+            at,
+            budget: at.saturating_duration_since(Instant::now()),
+        }
+    }
+
+    /// When the deadline is
+    pub fn at(&self) -> Instant {
+        self.at
+    }
+
+    /// How long the deadline was set for when it was created
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// How long is left until the deadline, which is zero once it has passed
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+
+    /// Whether the deadline has passed
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.at
+    }
+
+    /// Return [`Expired`] if the deadline has passed, so that `?` can be used
+    /// to stop once it has
+    pub fn checkpoint(&self) -> Result<(), Expired> {
+        match self.is_expired() {
+            true => Err(Expired {
+                budget: self.budget,
+            }),
+            false => Ok(()),
+        }
+    }
+
+    /// A deadline `budget` from now, or this one if it's sooner
+    pub fn limit(&self, budget: Duration) -> Self {
+        let limited = Self::after(budget);
+        match limited.at < self.at {
+            true => limited,
+            false => Self::until(self.at),
+        }
+    }
+
+    /// A deadline an equal share of the time that's left from now, for one of
+    /// `parts` steps that are still to run. Zero parts is treated as one.
+    pub fn split(&self, parts: u32) -> Self {
+        self.limit(self.remaining() / parts.max(1))
+    }
+}
@@ -0,0 +1,144 @@
+//! What a binary was built from, for `--version` output and reports
+//!
+//! A bug report is much more useful when it says exactly which build it came
+//! from. [`build_info!`](crate::build_info) captures the crate's name and
+//! version, the git commit, the target triple, and the profile when the
+//! program is compiled, into a [`BuildInfo`] that prints as a `--version`
+//! line and can be handed to
+//! [`ErrorContext::build_info`](crate::terminate::ErrorContext::build_info)
+//! and [`CrashReports::build_info`](crate::terminate::crash::CrashReports::build_info).
+//!
+//! ```
+//! # use futility::{build_info, buildinfo::BuildInfo};
+//! const BUILD: BuildInfo = build_info!();
+//! assert_eq!(BUILD.version, env!("CARGO_PKG_VERSION"));
+//! if std::env::args().any(|arg| arg == "--version") {
+//!     // futility 0.1.1 (3f2a9c1d8 x86_64-unknown-linux-gnu release)
+//!     println!("{BUILD}");
+//! }
+//! ```
+//!
+//! The version comes from Cargo, but the git commit and target triple are only
+//! known to a build script. Calling [`emit`] from `build.rs`, with `futility`
+//! as a build dependency, passes them on to the macro. Without it they're
+//! `None` and the profile is worked out from whether debug assertions are on.
+//!
+//! ```no_run
+//! // In `main` in `build.rs`
+//! futility::buildinfo::emit();
+//! ```
+
+use std::{env, fmt, path::Path, process::Command};
+
+/// The environment variable [`emit`] sets to the git commit
+const GIT_SHA_VAR: &str = "FUTILITY_BUILD_GIT_SHA";
+/// The environment variable [`emit`] sets to the target triple
+const TARGET_VAR: &str = "FUTILITY_BUILD_TARGET";
+/// The environment variable [`emit`] sets to the profile
+const PROFILE_VAR: &str = "FUTILITY_BUILD_PROFILE";
+
+/// What a binary was built from, created with [`build_info!`](crate::build_info)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct BuildInfo {
+    /// The name of the crate
+    pub name: &'static str,
+    /// The version of the crate
+    pub version: &'static str,
+    /// The full hash of the git commit the crate was built from, if [`emit`]
+    /// ran and the crate is in a git repository
+    pub git_sha: Option<&'static str>,
+    /// The target triple the crate was built for, if [`emit`] ran
+    pub target: Option<&'static str>,
+    /// `debug` or `release`
+    pub profile: &'static str,
+}
+
+impl BuildInfo {
+    #[doc(hidden)]
+    pub const fn __new(
+        name: &'static str,
+        version: &'static str,
+        git_sha: Option<&'static str>,
+        target: Option<&'static str>,
+        profile: Option<&'static str>,
+        debug_assertions: bool,
+    ) -> Self {
+        Self {
+            name,
+            version,
+            git_sha,
+            target,
+            profile: match (profile, debug_assertions) {
+                (Some(profile), _) => profile,
+                (None, true) => "debug",
+                (None, false) => "release",
+            },
+        }
+    }
+
+    /// The first nine characters of the git commit, the same as `cargo -V`
+    /// shows
+    pub fn short_sha(&self) -> Option<&'static str> {
+        self.git_sha.map(|sha| sha.get(..9).unwrap_or(sha))
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    /// `name version (sha target profile)`, leaving out what isn't known
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} (", self.name, self.version)?;
+        for part in [self.short_sha(), self.target].into_iter().flatten() {
+            write!(f, "{part} ")?;
+        }
+        write!(f, "{})", self.profile)
+    }
+}
+
+/// Capture the [`BuildInfo`] of the crate calling this. It can be used in a
+/// `const`.
+#[macro_export]
+macro_rules! build_info {
+    () => {
+        $crate::buildinfo::BuildInfo::__new(
+            ::std::env!("CARGO_PKG_NAME"),
+            ::std::env!("CARGO_PKG_VERSION"),
+            ::std::option_env!("FUTILITY_BUILD_GIT_SHA"),
+            ::std::option_env!("FUTILITY_BUILD_TARGET"),
+            ::std::option_env!("FUTILITY_BUILD_PROFILE"),
+            ::std::cfg!(debug_assertions),
+        )
+    };
+}
+
+/// Pass the git commit, target triple, and profile on to
+/// [`build_info!`](crate::build_info), to be called from a build script
+///
+/// The git commit is left out if `git` can't be run or the crate isn't in a
+/// repository. The build script is rerun when the commit changes.
+pub fn emit() {
+    let dir = env::var_os("CARGO_MANIFEST_DIR").unwrap_or_else(|| ".".into());
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(&dir)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let output = String::from_utf8(output.stdout).ok()?;
+        Some(output.trim().to_string())
+    };
+    if let Some(sha) = git(&["rev-parse", "HEAD"]) {
+        println!("cargo:rustc-env={GIT_SHA_VAR}={sha}");
+        if let Some(git_dir) = git(&["rev-parse", "--git-dir"]) {
+            let git_dir = Path::new(&dir).join(git_dir);
+            println!("cargo:rerun-if-changed={}", git_dir.join("HEAD").display());
+            println!("cargo:rerun-if-changed={}", git_dir.join("refs").display());
+        }
+    }
+    for (var, from) in [(TARGET_VAR, "TARGET"), (PROFILE_VAR, "PROFILE")] {
+        if let Ok(value) = env::var(from) {
+            println!("cargo:rustc-env={var}={value}");
+        }
+    }
+}
@@ -0,0 +1,637 @@
+//! Loading configuration from defaults, files, the environment, and arguments
+//!
+//! Most programs read their configuration from more than one place: defaults
+//! in the code, a file, environment variables that override the file in a
+//! container, and arguments that override everything for a single run. A
+//! [`Loader`] merges each of these layers in the order they're added, later
+//! ones overriding earlier ones key by key, and deserializes the result into
+//! a typed struct. It remembers which layer each key came from, so when a
+//! value is invalid the error says where to fix it.
+//!
+//! ```
+//! # use futility::config::Loader;
+//! # use serde::{Deserialize, Serialize};
+//! #[derive(Debug, Deserialize, Serialize)]
+//! struct Config {
+//!     name: String,
+//!     server: Server,
+//! }
+//!
+//! #[derive(Debug, Deserialize, Serialize)]
+//! struct Server {
+//!     host: String,
+//!     port: u16,
+//! }
+//!
+//! let defaults = Config {
+//!     name: "app".into(),
+//!     server: Server {
+//!         host: "localhost".into(),
+//!         port: 8080,
+//!     },
+//! };
+//! # std::env::set_var("APP_SERVER__HOST", "0.0.0.0");
+//! let config = Loader::new()
+//!     .defaults(&defaults)
+//!     .optional_file("app.json")
+//!     // `APP_SERVER__HOST=0.0.0.0` sets `server.host`
+//!     .env("APP_")
+//!     .args(["server.port=9090"])
+//!     .load()
+//!     .unwrap();
+//! assert_eq!(config.server.host, "0.0.0.0");
+//! assert_eq!(config.server.port, 9090);
+//!
+//! let err = Loader::<Config>::new()
+//!     .defaults(&defaults)
+//!     .args(["server.port=http"])
+//!     .load()
+//!     .unwrap_err();
+//! assert_eq!(
+//!     err.to_string(),
+//!     "invalid value for `server.port` from the argument `server.port=http`: \
+//!      invalid value: \"http\", expected u16",
+//! );
+//! ```
+//!
+//! Files are read as JSON, or as TOML with the `config-toml` feature, based
+//! on their extension. Values from the environment and arguments are always
+//! strings, and are parsed when the struct expects a number or a boolean.
+
+use serde::{
+    de::{self, DeserializeOwned, IntoDeserializer, MapAccess, SeqAccess, Visitor},
+    forward_to_deserialize_any, Serialize,
+};
+use serde_json::{Map, Value};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    ffi::OsStr,
+    fmt::{self, Display},
+    fs, io,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// Where the value of a key came from
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Source {
+    /// The defaults given to [`Loader::defaults`]
+    Defaults,
+    /// A config file
+    File(PathBuf),
+    /// The environment variable with this name
+    Env(String),
+    /// The `key=value` argument given to [`Loader::args`]
+    Arg(String),
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Defaults => f.write_str("the defaults"),
+            Self::File(path) => write!(f, "`{}`", path.display()),
+            Self::Env(name) => write!(f, "the environment variable `{name}`"),
+            Self::Arg(arg) => write!(f, "the argument `{arg}`"),
+        }
+    }
+}
+
+/// Where each key of a loaded configuration came from, returned by
+/// [`Loader::load_with_sources`]
+///
+/// Keys are written with a `.` between each level, such as `server.port`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sources {
+    keys: BTreeMap<String, Source>,
+}
+
+impl Sources {
+    /// Where `key` came from. For a key inside of an array this is where the
+    /// whole array came from.
+    pub fn get(&self, key: &str) -> Option<&Source> {
+        let mut key = key;
+        loop {
+            if let Some(source) = self.keys.get(key) {
+                return Some(source);
+            }
+            key = &key[..key.rfind('.')?];
+        }
+    }
+
+    /// Every key that was set along with where it came from, in order of key
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Source)> {
+        self.keys.iter().map(|(key, source)| (key.as_str(), source))
+    }
+
+    /// Record that `value` at `key` came from `source`, replacing whatever
+    /// was there
+    fn set(&mut self, key: &str, value: &Value, source: &Source) {
+        let nested = format!("{key}.");
+        self.keys
+            .retain(|existing, _| existing != key && !existing.starts_with(&nested));
+        self.record(key, value, source);
+    }
+
+    fn record(&mut self, key: &str, value: &Value, source: &Source) {
+        match value {
+            Value::Object(map) if !map.is_empty() => {
+                for (name, value) in map {
+                    self.record(&join(key, name), value, source);
+                }
+            }
+            _ => {
+                self.keys.insert(key.into(), source.clone());
+            }
+        }
+    }
+}
+
+/// A validation failure returned from a function given to
+/// [`Loader::validate`]
+#[derive(Debug, Error)]
+#[error("{message}")]
+pub struct Invalid {
+    key: String,
+    message: String,
+}
+
+impl Invalid {
+    /// The value of `key` is invalid because of `message`
+    pub fn new(key: impl Into<String>, message: impl Display) -> Self {
+        Self {
+            key: key.into(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Why a [`Loader`] couldn't load a configuration
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum LoadError {
+    /// The defaults couldn't be serialized
+    #[error("failed to serialize the defaults: {0}")]
+    Defaults(#[source] Box<dyn Error + Send + Sync>),
+    /// A config file couldn't be read
+    #[error("failed to read `{}`: {source}", path.display())]
+    Read {
+        /// The file that couldn't be read
+        path: PathBuf,
+        /// Why it couldn't be read
+        source: io::Error,
+    },
+    /// A config file couldn't be parsed
+    #[error("failed to parse `{}`: {source}", path.display())]
+    Parse {
+        /// The file that couldn't be parsed
+        path: PathBuf,
+        /// Why it couldn't be parsed
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// A config file's extension isn't a format that can be read
+    #[error("`{}` isn't a format config files can be read from", path.display())]
+    UnknownFormat {
+        /// The file with the unknown extension
+        path: PathBuf,
+    },
+    /// An argument given to [`Loader::args`] didn't have an `=`
+    #[error("the argument `{arg}` isn't in the form `key=value`")]
+    Arg {
+        /// The argument without an `=`
+        arg: String,
+    },
+    /// A value didn't deserialize or didn't pass validation
+    #[error("{}", describe_invalid(key, from.as_ref(), message))]
+    Invalid {
+        /// The key with the invalid value, or an empty string if the whole
+        /// configuration is invalid
+        key: String,
+        /// Where the value came from, if it was set
+        from: Option<Source>,
+        /// What was wrong with the value
+        message: String,
+    },
+}
+
+fn describe_invalid(key: &str, from: Option<&Source>, message: &str) -> String {
+    match (key, from) {
+        ("", _) => format!("invalid configuration: {message}"),
+        (key, Some(from)) => format!("invalid value for `{key}` from {from}: {message}"),
+        (key, None) => format!("invalid value for `{key}`: {message}"),
+    }
+}
+
+enum Layer {
+    Defaults(Result<Value, serde_json::Error>),
+    File { path: PathBuf, required: bool },
+    Env { prefix: String },
+    Args(Vec<String>),
+}
+
+type Validator<T> = Box<dyn Fn(&T) -> Result<(), Invalid>>;
+
+/// Merges layers of configuration into a `T`, each overriding the ones added
+/// before it
+pub struct Loader<T> {
+    layers: Vec<Layer>,
+    validators: Vec<Validator<T>>,
+    config: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for Loader<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Loader<T> {
+    /// A loader without any layers
+    pub fn new() -> Self {
+        Self {
+            layers: Vec::new(),
+            validators: Vec::new(),
+            config: PhantomData,
+        }
+    }
+
+    /// Add `defaults` as a layer, which is usually the first one
+    pub fn defaults(mut self, defaults: &T) -> Self
+    where
+        T: Serialize,
+    {
+        self.layers
+            .push(Layer::Defaults(serde_json::to_value(defaults)));
+        self
+    }
+
+    /// Add the config file at `path` as a layer, which has to exist
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.layers.push(Layer::File {
+            path: path.into(),
+            required: true,
+        });
+        self
+    }
+
+    /// Add the config file at `path` as a layer if it exists
+    pub fn optional_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.layers.push(Layer::File {
+            path: path.into(),
+            required: false,
+        });
+        self
+    }
+
+    /// Add every environment variable starting with `prefix` as a layer, read
+    /// when the configuration is loaded. The rest of each variable's name is
+    /// the key in lower case with `__` between levels, so with the prefix
+    /// `APP_` the variable `APP_SERVER__PORT` sets `server.port`.
+    pub fn env(mut self, prefix: impl Into<String>) -> Self {
+        self.layers.push(Layer::Env {
+            prefix: prefix.into(),
+        });
+        self
+    }
+
+    /// Add arguments in the form `key=value` as a layer, such as
+    /// `server.port=9090`
+    pub fn args<I>(mut self, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.layers
+            .push(Layer::Args(args.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    /// Check the configuration once it's loaded, after any functions added
+    /// before this one. The source of the key named by the [`Invalid`] is
+    /// added to the error.
+    pub fn validate(mut self, validate: impl Fn(&T) -> Result<(), Invalid> + 'static) -> Self {
+        self.validators.push(Box::new(validate));
+        self
+    }
+}
+
+impl<T: DeserializeOwned> Loader<T> {
+    /// Read and merge every layer, then deserialize and validate the result
+    pub fn load(self) -> Result<T, LoadError> {
+        self.load_with_sources().map(|(config, _)| config)
+    }
+
+    /// [`Loader::load`], also returning where each key came from
+    pub fn load_with_sources(self) -> Result<(T, Sources), LoadError> {
+        let mut merged = Value::Object(Map::new());
+        let mut sources = Sources::default();
+        for layer in self.layers {
+            match layer {
+                Layer::Defaults(defaults) => {
+                    let defaults = defaults.map_err(|err| LoadError::Defaults(err.into()))?;
+                    merge(&mut merged, &mut sources, "", defaults, &Source::Defaults);
+                }
+                Layer::File { path, required } => {
+                    if let Some(file) = read_file(&path, required)? {
+                        merge(&mut merged, &mut sources, "", file, &Source::File(path));
+                    }
+                }
+                Layer::Env { prefix } => {
+                    for (name, value) in std::env::vars_os() {
+                        let (Some(name), Some(value)) = (name.to_str(), value.to_str()) else {
+                            continue;
+                        };
+                        let Some(key) = name.strip_prefix(&prefix) else {
+                            continue;
+                        };
+                        let key = key.to_lowercase().replace("__", ".");
+                        let source = Source::Env(name.into());
+                        set(&mut merged, &mut sources, &key, value, &source);
+                    }
+                }
+                Layer::Args(args) => {
+                    for arg in args {
+                        let Some((key, value)) = arg.split_once('=') else {
+                            return Err(LoadError::Arg { arg });
+                        };
+                        let (key, value) = (key.trim().to_string(), value.to_string());
+                        set(&mut merged, &mut sources, &key, &value, &Source::Arg(arg));
+                    }
+                }
+            }
+        }
+
+        let invalid = |key: String, message: String| LoadError::Invalid {
+            from: sources.get(&key).cloned(),
+            key,
+            message,
+        };
+        let config = T::deserialize(Lenient(merged))
+            .map_err(|err| invalid(err.path.join("."), err.message))?;
+        for validate in &self.validators {
+            validate(&config).map_err(|err| invalid(err.key, err.message))?;
+        }
+        Ok((config, sources))
+    }
+}
+
+impl<T> fmt::Debug for Loader<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Loader")
+            .field("layers", &self.layers.len())
+            .field("validators", &self.validators.len())
+            .finish()
+    }
+}
+
+fn join(key: &str, name: &str) -> String {
+    match key {
+        "" => name.into(),
+        key => format!("{key}.{name}"),
+    }
+}
+
+/// Merge `layer` into `merged` at `key`, tables key by key and anything else
+/// by replacing what was there
+fn merge(merged: &mut Value, sources: &mut Sources, key: &str, layer: Value, source: &Source) {
+    match (merged, layer) {
+        (Value::Object(merged), Value::Object(layer)) => {
+            for (name, value) in layer {
+                let key = join(key, &name);
+                match merged.get_mut(&name) {
+                    Some(existing) => merge(existing, sources, &key, value, source),
+                    None => {
+                        sources.set(&key, &value, source);
+                        merged.insert(name, value);
+                    }
+                }
+            }
+        }
+        (merged, layer) => {
+            sources.set(key, &layer, source);
+            *merged = layer;
+        }
+    }
+}
+
+/// Set the dotted `key` to the string `value`
+fn set(merged: &mut Value, sources: &mut Sources, key: &str, value: &str, source: &Source) {
+    let layer = key
+        .rsplit('.')
+        .fold(Value::String(value.into()), |value, name| {
+            Value::Object(Map::from_iter([(name.to_string(), value)]))
+        });
+    merge(merged, sources, "", layer, source);
+}
+
+fn read_file(path: &Path, required: bool) -> Result<Option<Value>, LoadError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if !required && err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(source) => {
+            return Err(LoadError::Read {
+                path: path.into(),
+                source,
+            })
+        }
+    };
+    let parsed: Result<Value, Box<dyn Error + Send + Sync>> =
+        match path.extension().and_then(OsStr::to_str) {
+            Some("json") => serde_json::from_str(&text).map_err(Into::into),
+            #[cfg(feature = "config-toml")]
+            Some("toml") => toml::from_str(&text).map_err(Into::into),
+            _ => return Err(LoadError::UnknownFormat { path: path.into() }),
+        };
+    parsed.map(Some).map_err(|source| LoadError::Parse {
+        path: path.into(),
+        source,
+    })
+}
+
+/// A deserialization error along with the key it happened at
+#[derive(Debug)]
+struct DeError {
+    path: Vec<String>,
+    message: String,
+}
+
+impl DeError {
+    fn from_json(err: serde_json::Error) -> Self {
+        de::Error::custom(err)
+    }
+
+    /// The error happened inside of `key`
+    fn within(mut self, key: String) -> Self {
+        self.path.insert(0, key);
+        self
+    }
+}
+
+impl Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<M: Display>(message: M) -> Self {
+        Self {
+            path: Vec::new(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Deserializes a merged configuration, parsing strings into the numbers
+/// and booleans the struct expects and keeping track of the key an error
+/// happened at
+struct Lenient(Value);
+
+macro_rules! parse_strings {
+    ($($deserialize:ident => $ty:ty, $visit:ident;)*) => {
+        $(
+            fn $deserialize<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+                match &self.0 {
+                    Value::String(s) => match s.trim().parse::<$ty>() {
+                        Ok(value) => visitor.$visit(value),
+                        Err(_) => Err(de::Error::custom(format_args!(
+                            "invalid value: {s:?}, expected {}",
+                            stringify!($ty)
+                        ))),
+                    },
+                    _ => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Lenient {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.0 {
+            Value::Object(map) => visitor.visit_map(Entries {
+                entries: map.into_iter(),
+                value: None,
+            }),
+            Value::Array(items) => visitor.visit_seq(Items {
+                items: items.into_iter(),
+                index: 0,
+            }),
+            value => value.deserialize_any(visitor).map_err(DeError::from_json),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.0
+            .deserialize_enum(name, variants, visitor)
+            .map_err(DeError::from_json)
+    }
+
+    parse_strings! {
+        deserialize_bool => bool, visit_bool;
+        deserialize_i8 => i8, visit_i8;
+        deserialize_i16 => i16, visit_i16;
+        deserialize_i32 => i32, visit_i32;
+        deserialize_i64 => i64, visit_i64;
+        deserialize_u8 => u8, visit_u8;
+        deserialize_u16 => u16, visit_u16;
+        deserialize_u32 => u32, visit_u32;
+        deserialize_u64 => u64, visit_u64;
+        deserialize_f32 => f32, visit_f32;
+        deserialize_f64 => f64, visit_f64;
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct Entries {
+    entries: serde_json::map::IntoIter,
+    value: Option<(String, Value)>,
+}
+
+impl<'de> MapAccess<'de> for Entries {
+    type Error = DeError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, DeError>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        let deserialized = seed
+            .deserialize(key.as_str().into_deserializer())
+            .map_err(|err: DeError| err.within(key.clone()))?;
+        self.value = Some((key, value));
+        Ok(Some(deserialized))
+    }
+
+    fn next_value_seed<S>(&mut self, seed: S) -> Result<S::Value, DeError>
+    where
+        S: de::DeserializeSeed<'de>,
+    {
+        let (key, value) = self.value.take().expect("a key was deserialized first");
+        seed.deserialize(Lenient(value))
+            .map_err(|err| err.within(key))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct Items {
+    items: std::vec::IntoIter<Value>,
+    index: usize,
+}
+
+impl<'de> SeqAccess<'de> for Items {
+    type Error = DeError;
+
+    fn next_element_seed<S>(&mut self, seed: S) -> Result<Option<S::Value>, DeError>
+    where
+        S: de::DeserializeSeed<'de>,
+    {
+        let Some(item) = self.items.next() else {
+            return Ok(None);
+        };
+        let index = self.index;
+        self.index += 1;
+        seed.deserialize(Lenient(item))
+            .map(Some)
+            .map_err(|err| err.within(index.to_string()))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
@@ -0,0 +1,319 @@
+//! The state of the machine a program is running on, for crash reports
+//!
+//! A crash report from a user's machine often can't be explained without
+//! knowing more about the machine: a full disk, a file descriptor limit of
+//! 256, or a kernel older than the one the program was tested on.
+//! [`collect`] gathers what's cheap to find out into a [`Diagnostics`], which
+//! prints as `key = value` lines and with the `serde` feature can be
+//! serialized. With the `crash-reports` feature it's added to every crash
+//! report.
+//!
+//! ```
+//! # use futility::diagnostics;
+//! let diagnostics = diagnostics::collect();
+//! assert_eq!(diagnostics.os, std::env::consts::OS);
+//! println!("{diagnostics}");
+//! ```
+//!
+//! Everything other than the operating system and architecture is `None` or
+//! empty when it can't be found out, which includes most of it on Windows.
+
+// The fields of the structs filled in by libc differ in type across platforms
+#![allow(clippy::unnecessary_cast, clippy::useless_conversion)]
+
+use std::{
+    env, fmt,
+    path::{Path, PathBuf},
+    thread,
+};
+
+/// What is known about the machine a program is running on
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Diagnostics {
+    /// The operating system, such as `linux` or `macos`
+    pub os: &'static str,
+    /// The CPU architecture, such as `x86_64` or `aarch64`
+    pub arch: &'static str,
+    /// The name and release of the kernel, such as `Linux 6.8.0`
+    pub kernel: Option<String>,
+    /// How many threads can run at once
+    pub cpus: Option<usize>,
+    /// The physical memory of the machine
+    pub memory: Option<Memory>,
+    /// The average number of processes waiting to run over the last 1, 5,
+    /// and 15 minutes
+    pub load_average: Option<[f64; 3]>,
+    /// The space on the file systems holding each path that was asked about
+    pub disks: Vec<Disk>,
+    /// The resource limits of the process
+    pub rlimits: Vec<Rlimit>,
+}
+
+/// The physical memory of the machine, in bytes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Memory {
+    /// All of the memory
+    pub total: u64,
+    /// The memory that can be used without swapping, if known
+    pub available: Option<u64>,
+}
+
+/// The space on the file system holding a path, in bytes
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Disk {
+    /// The path that was asked about
+    pub path: PathBuf,
+    /// The size of the file system
+    pub total: u64,
+    /// The space an unprivileged process can use
+    pub available: u64,
+}
+
+/// A resource limit of the process, where `None` means unlimited
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Rlimit {
+    /// The name of the resource, such as `RLIMIT_NOFILE`
+    pub resource: &'static str,
+    /// The limit enforced by the kernel
+    pub soft: Option<u64>,
+    /// The ceiling for the soft limit
+    pub hard: Option<u64>,
+}
+
+/// Collect diagnostics, including the space on the file systems holding the
+/// current directory and the temporary directory
+pub fn collect() -> Diagnostics {
+    let paths = env::current_dir().into_iter().chain([env::temp_dir()]);
+    collect_for(paths)
+}
+
+/// Collect diagnostics, including the space on the file systems holding each
+/// of `paths`, such as where the program writes its data
+pub fn collect_for<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Diagnostics {
+    Diagnostics {
+        os: env::consts::OS,
+        arch: env::consts::ARCH,
+        kernel: sys::kernel(),
+        cpus: thread::available_parallelism().ok().map(Into::into),
+        memory: sys::memory(),
+        load_average: sys::load_average(),
+        disks: paths
+            .into_iter()
+            .filter_map(|path| sys::disk(path.as_ref()))
+            .collect(),
+        rlimits: sys::rlimits(),
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    /// Each field as a `key = value` line, leaving out those that aren't
+    /// known
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "os = {:?}\narch = {:?}", self.os, self.arch)?;
+        if let Some(kernel) = &self.kernel {
+            write!(f, "\nkernel = {kernel:?}")?;
+        }
+        if let Some(cpus) = self.cpus {
+            write!(f, "\ncpus = {cpus}")?;
+        }
+        if let Some(memory) = self.memory {
+            write!(f, "\nmemory_total = {}", memory.total)?;
+            if let Some(available) = memory.available {
+                write!(f, "\nmemory_available = {available}")?;
+            }
+        }
+        if let Some([one, five, fifteen]) = self.load_average {
+            write!(f, "\nload_average = [{one:.2}, {five:.2}, {fifteen:.2}]")?;
+        }
+        if !self.disks.is_empty() {
+            let disks = self
+                .disks
+                .iter()
+                .map(|disk| {
+                    format!(
+                        "{{ path = {:?}, total = {}, available = {} }}",
+                        disk.path, disk.total, disk.available
+                    )
+                })
+                .collect::<Vec<_>>();
+            write!(f, "\ndisks = [{}]", disks.join(", "))?;
+        }
+        if !self.rlimits.is_empty() {
+            let limit = |limit: Option<u64>| match limit {
+                Some(limit) => limit.to_string(),
+                None => "\"unlimited\"".into(),
+            };
+            let rlimits = self
+                .rlimits
+                .iter()
+                .map(|rlimit| {
+                    format!(
+                        "{} = {{ soft = {}, hard = {} }}",
+                        rlimit.resource,
+                        limit(rlimit.soft),
+                        limit(rlimit.hard)
+                    )
+                })
+                .collect::<Vec<_>>();
+            write!(f, "\nrlimits = {{ {} }}", rlimits.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use super::{Disk, Memory, Rlimit};
+    use std::{
+        ffi::{CStr, CString},
+        mem::MaybeUninit,
+        os::unix::ffi::OsStrExt,
+        path::Path,
+    };
+
+    pub(super) fn kernel() -> Option<String> {
+        let mut uts = MaybeUninit::<libc::utsname>::uninit();
+        // SAFETY: uts is valid for uname to write into
+        if unsafe { libc::uname(uts.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        // SAFETY: uname succeeded so it filled in uts
+        let uts = unsafe { uts.assume_init() };
+        // SAFETY: uname fills in each field with a nul terminated string
+        let field = |field: &[libc::c_char]| unsafe { CStr::from_ptr(field.as_ptr()) };
+        Some(format!(
+            "{} {}",
+            field(&uts.sysname).to_string_lossy(),
+            field(&uts.release).to_string_lossy()
+        ))
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(super) fn memory() -> Option<Memory> {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+        let field = |name: &str| {
+            meminfo.lines().find_map(|line| {
+                let kib = line.strip_prefix(name)?.strip_prefix(':')?;
+                let kib = kib
+                    .trim()
+                    .trim_end_matches("kB")
+                    .trim()
+                    .parse::<u64>()
+                    .ok()?;
+                Some(kib * 1024)
+            })
+        };
+        Some(Memory {
+            total: field("MemTotal")?,
+            available: field("MemAvailable"),
+        })
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub(super) fn memory() -> Option<Memory> {
+        // SAFETY: sysconf only reads the value asked for
+        let (pages, page_size) = unsafe {
+            (
+                libc::sysconf(libc::_SC_PHYS_PAGES),
+                libc::sysconf(libc::_SC_PAGESIZE),
+            )
+        };
+        let total = u64::try_from(pages).ok()? * u64::try_from(page_size).ok()?;
+        Some(Memory {
+            total,
+            available: None,
+        })
+    }
+
+    pub(super) fn load_average() -> Option<[f64; 3]> {
+        let mut load = [0.0; 3];
+        // SAFETY: load has room for the 3 samples asked for
+        match unsafe { libc::getloadavg(load.as_mut_ptr(), 3) } {
+            3 => Some(load),
+            _ => None,
+        }
+    }
+
+    pub(super) fn disk(path: &Path) -> Option<Disk> {
+        let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+        // SAFETY: c_path is a nul terminated path and stat is valid for
+        // statvfs to write into
+        if unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        // SAFETY: statvfs succeeded so it filled in stat
+        let stat = unsafe { stat.assume_init() };
+        let block = stat.f_frsize as u64;
+        Some(Disk {
+            path: path.into(),
+            total: (stat.f_blocks as u64).saturating_mul(block),
+            available: (stat.f_bavail as u64).saturating_mul(block),
+        })
+    }
+
+    pub(super) fn rlimits() -> Vec<Rlimit> {
+        let resources = [
+            ("RLIMIT_NOFILE", libc::RLIMIT_NOFILE),
+            ("RLIMIT_CORE", libc::RLIMIT_CORE),
+            ("RLIMIT_STACK", libc::RLIMIT_STACK),
+            ("RLIMIT_AS", libc::RLIMIT_AS),
+            ("RLIMIT_DATA", libc::RLIMIT_DATA),
+            ("RLIMIT_FSIZE", libc::RLIMIT_FSIZE),
+            ("RLIMIT_CPU", libc::RLIMIT_CPU),
+        ];
+        let limit = |limit: libc::rlim_t| (limit != libc::RLIM_INFINITY).then_some(limit as u64);
+        resources
+            .into_iter()
+            .filter_map(|(resource, raw)| {
+                let mut rlimit = libc::rlimit {
+                    rlim_cur: 0,
+                    rlim_max: 0,
+                };
+                // SAFETY: rlimit is valid for getrlimit to write into
+                if unsafe { libc::getrlimit(raw as _, &mut rlimit) } != 0 {
+                    return None;
+                }
+                Some(Rlimit {
+                    resource,
+                    soft: limit(rlimit.rlim_cur),
+                    hard: limit(rlimit.rlim_max),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    use super::{Disk, Memory, Rlimit};
+    use std::path::Path;
+
+    pub(super) fn kernel() -> Option<String> {
+        None
+    }
+
+    pub(super) fn memory() -> Option<Memory> {
+        None
+    }
+
+    pub(super) fn load_average() -> Option<[f64; 3]> {
+        None
+    }
+
+    pub(super) fn disk(_: &Path) -> Option<Disk> {
+        None
+    }
+
+    pub(super) fn rlimits() -> Vec<Rlimit> {
+        Vec::new()
+    }
+}
@@ -0,0 +1,323 @@
+//! Reading typed values from environment variables
+//!
+//! Startup code that reads its configuration from the environment tends to
+//! turn into a pile of `env::var(...).unwrap().parse().unwrap()`, which fails
+//! on the first problem with a message that doesn't say which variable it
+//! was. The functions here parse variables into any type implementing
+//! [`FromStr`] and return an [`EnvError`] naming the variable and what was
+//! wrong with it, and [`require_all`] checks a whole set of variables up front
+//! so that every problem is reported at once.
+//!
+//! ```
+//! # use futility::env;
+//! # use std::time::Duration;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # std::env::set_var("DATABASE_URL", "postgres://localhost/app");
+//! env::require_all(&["DATABASE_URL"])?;
+//! let url: String = env::var_parsed("DATABASE_URL")?;
+//! let port: u16 = env::var_or("PORT", 8080)?;
+//! let workers: Option<usize> = env::var_opt("WORKERS")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`FromEnv`] can be derived for a config struct to read all of its fields
+//! at once. Each field is read from the variable given with
+//! `#[env(name = "...")]`, or the field's name in upper case, with
+//! `#[env(default = "...")]` parsed if it isn't set and `Option` fields being
+//! `None` if it isn't set, or `Some` of their default if they have one. A
+//! prefix for every variable can be given on the struct with
+//! `#[env(prefix = "...")]`.
+//!
+//! ```
+//! # use futility::env::FromEnv;
+//! # std::env::set_var("APP_DATABASE_URL", "postgres://localhost/app");
+//! #[derive(Debug, FromEnv)]
+//! #[env(prefix = "APP_")]
+//! struct Config {
+//!     #[env(default = "8080")]
+//!     port: u16,
+//!     database_url: String,
+//!     workers: Option<usize>,
+//!     #[env(default = "info")]
+//!     log: Option<String>,
+//! }
+//!
+//! let config = Config::from_env().unwrap();
+//! assert_eq!(config.port, 8080);
+//! assert_eq!(config.workers, None);
+//! assert_eq!(config.log.as_deref(), Some("info"));
+//! ```
+//!
+//! Reading the config in [`Terminate::install`] makes a bad environment stop
+//! the program with every problem reported before it starts, as long as the
+//! program's error type can be created from a [`MissingVars`].
+//!
+//! [`ScopedVar`] goes the other way, setting a variable for as long as a
+//! guard is alive and putting back what was there before, such as for a test
+//! or a child process that needs a variable the rest of the program
+//! shouldn't see. [`scoped_vars!`](crate::scoped_vars) sets several at once.
+//!
+//! ```
+//! # use futility::env::ScopedVar;
+//! # std::env::remove_var("APP_LOG");
+//! {
+//!     let _log = ScopedVar::set("APP_LOG", "debug");
+//!     assert_eq!(std::env::var("APP_LOG").unwrap(), "debug");
+//! }
+//! assert!(std::env::var_os("APP_LOG").is_none());
+//! ```
+//!
+//! The environment is shared by the whole process, so a guard changes it for
+//! every thread and not only the one holding the guard. Guards that overlap on
+//! different threads can put back each other's values in the wrong order, and
+//! tests that set variables need to take turns rather than run in parallel.
+//! On Unix, setting a variable while another thread reads the environment
+//! through C code, such as `getaddrinfo` looking up a host name, is undefined
+//! behavior, so guards are best created before other threads are started.
+//!
+//! [`Terminate::install`]: crate::terminate::Terminate::install
+
+use std::{
+    env::{self, VarError},
+    ffi::{OsStr, OsString},
+    fmt::{self, Display},
+    str::FromStr,
+};
+use thiserror::Error;
+
+pub use futility_try_catch::FromEnv;
+
+/// What was wrong with an environment variable
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum EnvError {
+    /// The variable isn't set
+    #[error("{name} is not set")]
+    Missing {
+        /// The name of the variable
+        name: String,
+    },
+    /// The variable's value isn't valid unicode
+    #[error("{name} is not valid unicode")]
+    NotUnicode {
+        /// The name of the variable
+        name: String,
+    },
+    /// The variable's value couldn't be parsed
+    #[error("{name} has an invalid value {value:?}: {reason}")]
+    Invalid {
+        /// The name of the variable
+        name: String,
+        /// The value that couldn't be parsed
+        value: String,
+        /// Why it couldn't be parsed
+        reason: String,
+    },
+}
+
+impl EnvError {
+    /// The name of the variable
+    pub fn name(&self) -> &str {
+        match self {
+            EnvError::Missing { name }
+            | EnvError::NotUnicode { name }
+            | EnvError::Invalid { name, .. } => name,
+        }
+    }
+}
+
+/// Every problem found with a set of environment variables
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MissingVars {
+    /// The problem with each variable, in the order they were checked
+    pub errors: Vec<EnvError>,
+}
+
+impl MissingVars {
+    /// Whether there were no problems
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Turn the result of reading a variable into its value, keeping the
+    /// error if there was one
+    pub fn collect<T>(&mut self, result: Result<T, EnvError>) -> Option<T> {
+        result.map_err(|err| self.errors.push(err)).ok()
+    }
+
+    /// `Err(self)` if there were any problems, otherwise `Ok(())`
+    pub fn into_result(self) -> Result<(), Self> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(self),
+        }
+    }
+}
+
+impl Display for MissingVars {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid environment:")?;
+        for err in &self.errors {
+            write!(f, "\n  {err}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MissingVars {}
+
+/// A type that can be read from environment variables, usually derived
+pub trait FromEnv: Sized {
+    /// Read every field from the environment, returning every problem with
+    /// the variables if any of them are missing or invalid
+    fn from_env() -> Result<Self, MissingVars>;
+}
+
+/// Parse `value` read from the variable `name`
+pub fn parse<T>(name: &str, value: &str) -> Result<T, EnvError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value.parse().map_err(|err: T::Err| EnvError::Invalid {
+        name: name.into(),
+        value: value.into(),
+        reason: err.to_string(),
+    })
+}
+
+/// Parse the variable `name`, or return `None` if it isn't set
+pub fn var_opt<T>(name: &str) -> Result<Option<T>, EnvError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match env::var(name) {
+        Ok(value) => parse(name, &value).map(Some),
+        Err(VarError::NotPresent) => Ok(None),
+        Err(VarError::NotUnicode(_)) => Err(EnvError::NotUnicode { name: name.into() }),
+    }
+}
+
+/// Parse the variable `name`, which must be set
+pub fn var_parsed<T>(name: &str) -> Result<T, EnvError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    var_opt(name)?.ok_or_else(|| EnvError::Missing { name: name.into() })
+}
+
+/// Parse the variable `name`, or return `default` if it isn't set. A value
+/// that can't be parsed is still an error.
+pub fn var_or<T>(name: &str, default: T) -> Result<T, EnvError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    Ok(var_opt(name)?.unwrap_or(default))
+}
+
+/// Check that every variable in `names` is set to valid unicode
+pub fn require_all(names: &[&str]) -> Result<(), MissingVars> {
+    let mut missing = MissingVars::default();
+    for name in names {
+        missing.collect(var_parsed::<String>(name));
+    }
+    missing.into_result()
+}
+
+/// An environment variable that is set, or removed, until the guard is dropped,
+/// when it's put back the way it was. See the [module documentation](self)
+/// for why this isn't thread safe.
+#[derive(Debug)]
+#[must_use = "the variable is put back as soon as the guard is dropped"]
+pub struct ScopedVar {
+    key: OsString,
+    previous: Option<OsString>,
+}
+
+impl ScopedVar {
+    /// Set `key` to `value`
+    pub fn set(key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+        let key = key.as_ref().to_owned();
+        let previous = env::var_os(&key);
+        env::set_var(&key, value);
+        Self { key, previous }
+    }
+
+    /// Remove `key`, such as to check what happens when it isn't set
+    pub fn remove(key: impl AsRef<OsStr>) -> Self {
+        let key = key.as_ref().to_owned();
+        let previous = env::var_os(&key);
+        env::remove_var(&key);
+        Self { key, previous }
+    }
+
+    /// The name of the variable
+    pub fn key(&self) -> &OsStr {
+        &self.key
+    }
+
+    /// The value the variable had before, which it's set back to when the
+    /// guard is dropped
+    pub fn previous(&self) -> Option<&OsStr> {
+        self.previous.as_deref()
+    }
+}
+
+impl Drop for ScopedVar {
+    fn drop(&mut self) {
+        match &self.previous {
+            Some(previous) => env::set_var(&self.key, previous),
+            None => env::remove_var(&self.key),
+        }
+    }
+}
+
+/// Several [`ScopedVar`]s, created with [`scoped_vars!`](crate::scoped_vars),
+/// that are put back in the reverse of the order they were set in so that a
+/// variable set twice ends up with its original value
+#[derive(Debug, Default)]
+#[must_use = "the variables are put back as soon as the guard is dropped"]
+pub struct ScopedVars(Vec<ScopedVar>);
+
+impl ScopedVars {
+    /// The guard for each variable, in the order they were set
+    pub fn vars(&self) -> &[ScopedVar] {
+        &self.0
+    }
+}
+
+impl FromIterator<ScopedVar> for ScopedVars {
+    fn from_iter<I: IntoIterator<Item = ScopedVar>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl Drop for ScopedVars {
+    fn drop(&mut self) {
+        while self.0.pop().is_some() {}
+    }
+}
+
+/// Set several environment variables until the returned
+/// [`ScopedVars`](crate::env::ScopedVars) is dropped, putting back what was
+/// there before
+///
+/// ```
+/// # use futility::scoped_vars;
+/// let _vars = scoped_vars! {
+///     "APP_HOST" => "localhost",
+///     "APP_PORT" => "8080",
+/// };
+/// assert_eq!(std::env::var("APP_PORT").unwrap(), "8080");
+/// ```
+#[macro_export]
+macro_rules! scoped_vars {
+    ($($key:expr => $value:expr),* $(,)?) => {
+        <$crate::env::ScopedVars as ::std::iter::FromIterator<_>>::from_iter([
+            $($crate::env::ScopedVar::set($key, $value)),*
+        ])
+    };
+}
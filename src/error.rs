@@ -0,0 +1,521 @@
+//! Raising ad-hoc errors
+//!
+//! [`bail!`](crate::bail) and [`ensure!`](crate::ensure) return early with an
+//! error from the enclosing function, closure, or `try_!` block. Unlike the
+//! macros of the same name in `anyhow` or `eyre` they aren't tied to an error
+//! type: a message is formatted into a `String` and turned into the error
+//! with `From<String>`, which `Box<dyn Error>` and most error reporting types
+//! implement, and any other expression is turned into the error with `From`,
+//! the same conversion `?` does. [`ensure_eq!`](crate::ensure_eq),
+//! [`ensure_ne!`](crate::ensure_ne), and
+//! [`ensure_matches!`](crate::ensure_matches) are the assertions of the same
+//! names that return an error with the values that didn't match instead of
+//! panicking.
+//!
+#![cfg_attr(not(feature = "try-catch"), doc = "```ignore")]
+#![cfg_attr(feature = "try-catch", doc = "```")]
+//! # use futility::{bail, ensure, try_};
+//! # use core::error::Error;
+//! fn parse_port(input: &str) -> Result<u16, Box<dyn Error>> {
+//!     ensure!(!input.is_empty(), "no port given");
+//!     let port = input.parse::<u16>()?;
+//!     if port < 1024 {
+//!         bail!("port {port} is privileged");
+//!     }
+//!     Ok(port)
+//! }
+//!
+//! assert_eq!(parse_port("8080").unwrap(), 8080);
+//! assert_eq!(parse_port("80").unwrap_err().to_string(), "port 80 is privileged");
+//!
+//! let port = try_!({
+//!     let port = parse_port("")?;
+//!     ensure!(port != 8080, "port {port} is taken");
+//!     port
+//! } catch Box<dyn Error> as err {
+//!     assert_eq!(err.to_string(), "no port given");
+//!     3000
+//! });
+//! assert_eq!(port, 3000);
+//! ```
+//!
+//! [`ErrorChainExt`] goes the other way, for looking inside an error rather
+//! than raising one: it walks an error's chain of sources, finds a source of a
+//! given type anywhere in it, or gets to the root cause.
+//!
+//! ```
+//! # use futility::error::ErrorChainExt;
+//! # use std::{error::Error, fs, io};
+//! fn read_config() -> Result<String, Box<dyn Error>> {
+//!     Ok(fs::read_to_string("/does/not/exist/config.toml")?)
+//! }
+//!
+//! let err = read_config().unwrap_err();
+//! let io_err = err.find_source::<io::Error>().unwrap();
+//! assert_eq!(io_err.kind(), io::ErrorKind::NotFound);
+//! assert_eq!(err.chain().count(), 1);
+//! assert_eq!(err.root_cause().to_string(), err.to_string());
+//! ```
+//!
+//! [`Adhoc`] is an error type for programs that don't need their own, without
+//! pulling in `anyhow` or `eyre`. It can be created from a message or from any
+//! error, keeps the error it was created from as its source, and
+//! [`context`](Adhoc::context) adds a message on top of it. It works as the
+//! catch type of a `try_!` block, and as the error of a `Terminate`, whose
+//! report lists the message followed by everything that caused it.
+//!
+//! ```
+//! # use futility::{bail, error::{Adhoc, ContextExt, ErrorChainExt}};
+//! # use std::fs;
+//! fn read_config(path: &str) -> Result<String, Adhoc> {
+//!     let config = fs::read_to_string(path).context("failed to read the config")?;
+//!     if config.is_empty() {
+//!         bail!("{path} is empty");
+//!     }
+//!     Ok(config)
+//! }
+//!
+//! let err = read_config("/does/not/exist/config.toml").unwrap_err();
+//! assert_eq!(err.to_string(), "failed to read the config");
+//! assert!(format!("{err:#}").starts_with("failed to read the config: "));
+//! assert!(err.find_source::<std::io::Error>().is_some());
+//! ```
+//!
+//! For programs that do want their own error types, deriving
+//! [`ContextError`] for a struct wrapping another error writes its
+//! `Display`, `Error`, and `From` impls, so each layer of context is a type
+//! that a `try_!` block can catch.
+//!
+#![cfg_attr(not(feature = "try-catch"), doc = "```ignore")]
+#![cfg_attr(feature = "try-catch", doc = "```")]
+//! # use futility::{error::{ContextError, ErrorChainExt}, try_};
+//! # use std::{fs, io};
+//! #[derive(Debug, ContextError)]
+//! #[context("failed to read the config")]
+//! struct ReadConfig(io::Error);
+//!
+//! try_!({
+//!     fs::read_to_string("/does/not/exist/config.toml")?;
+//! } catch ReadConfig as err {
+//!     assert_eq!(err.to_string(), "failed to read the config");
+//!     assert_eq!(err.root_cause().to_string(), err.0.to_string());
+//! });
+//! ```
+
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+};
+use core::{error::Error, fmt};
+#[cfg(feature = "try-catch")]
+pub use futility_try_catch::ContextError;
+
+/// Walking an error's chain of sources
+///
+/// This is implemented for every error type as well as `dyn Error`, so it can
+/// be used directly on a `Box<dyn Error>`. The chain starts with the error
+/// itself.
+pub trait ErrorChainExt {
+    /// The error followed by each of its sources in turn
+    fn chain(&self) -> Chain<'_>;
+
+    /// The first error in the chain that is a `T`
+    fn find_source<T: Error + 'static>(&self) -> Option<&T> {
+        self.chain().find_map(|err| err.downcast_ref())
+    }
+
+    /// The last error in the chain, which has no source
+    fn root_cause(&self) -> &(dyn Error + 'static) {
+        self.chain()
+            .last()
+            .expect("the chain starts with the error itself")
+    }
+}
+
+impl<E: Error + 'static> ErrorChainExt for E {
+    fn chain(&self) -> Chain<'_> {
+        Chain { next: Some(self) }
+    }
+}
+
+impl ErrorChainExt for dyn Error + 'static {
+    fn chain(&self) -> Chain<'_> {
+        Chain { next: Some(self) }
+    }
+}
+
+impl ErrorChainExt for dyn Error + Send + 'static {
+    fn chain(&self) -> Chain<'_> {
+        Chain { next: Some(self) }
+    }
+}
+
+impl ErrorChainExt for dyn Error + Send + Sync + 'static {
+    fn chain(&self) -> Chain<'_> {
+        Chain { next: Some(self) }
+    }
+}
+
+/// An iterator over an error and its sources, from
+/// [`ErrorChainExt::chain`]
+#[derive(Clone, Debug)]
+pub struct Chain<'a> {
+    next: Option<&'a (dyn Error + 'static)>,
+}
+
+impl<'a> Iterator for Chain<'a> {
+    type Item = &'a (dyn Error + 'static);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next?;
+        self.next = current.source();
+        Some(current)
+    }
+}
+
+impl core::iter::FusedIterator for Chain<'_> {}
+
+/// An error made from a message or any other error, with context added on top
+///
+/// `Adhoc` can be created with `From` from any error as well as from a
+/// `String` or `&str`, so `?` and [`bail!`](crate::bail) both work with it.
+/// It doesn't implement [`Error`] itself, which is what allows that, but
+/// [`as_error`](Adhoc::as_error) and [`into_boxed`](Adhoc::into_boxed) give
+/// it as one where it's needed.
+///
+/// Displaying it shows the outermost message, and the alternate form `{:#}`
+/// shows every message in the chain separated by `: `. Its `Debug` output
+/// lists the message followed by its causes, which is what `Terminate`
+/// prints when a program fails.
+pub struct Adhoc(Box<dyn Error + Send + Sync + 'static>);
+
+impl Adhoc {
+    /// An error wrapping `err`, which stays reachable as its source
+    pub fn new(err: impl Error + Send + Sync + 'static) -> Self {
+        Self(Box::new(err))
+    }
+
+    /// An error with a message and no source
+    pub fn msg(message: impl fmt::Display) -> Self {
+        Self::from(message.to_string())
+    }
+
+    /// Add a message on top of the error, which becomes its source
+    pub fn context(self, context: impl fmt::Display) -> Self {
+        Self::new(Context {
+            context: context.to_string(),
+            source: self.0,
+        })
+    }
+
+    /// The first error in the chain that is a `T`
+    pub fn downcast_ref<T: Error + 'static>(&self) -> Option<&T> {
+        self.find_source()
+    }
+
+    /// The error as a trait object
+    pub fn as_error(&self) -> &(dyn Error + Send + Sync + 'static) {
+        &*self.0
+    }
+
+    /// The error as a boxed trait object
+    pub fn into_boxed(self) -> Box<dyn Error + Send + Sync + 'static> {
+        self.0
+    }
+}
+
+impl<E> From<E> for Adhoc
+where
+    E: Into<Box<dyn Error + Send + Sync + 'static>>,
+{
+    fn from(err: E) -> Self {
+        Self(err.into())
+    }
+}
+
+impl ErrorChainExt for Adhoc {
+    fn chain(&self) -> Chain<'_> {
+        Chain {
+            next: Some(self.as_error()),
+        }
+    }
+}
+
+impl fmt::Display for Adhoc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() {
+            return fmt::Display::fmt(&self.0, f);
+        }
+        for (i, err) in self.chain().enumerate() {
+            if i > 0 {
+                f.write_str(": ")?;
+            }
+            write!(f, "{err}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Adhoc {
+    /// The message followed by each of its causes, or with `{:#?}` the
+    /// `Debug` output of the error it holds
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt::Debug::fmt(&self.0, f);
+        }
+        write!(f, "{}", self.0)?;
+        let mut causes = self.chain().skip(1).peekable();
+        if causes.peek().is_some() {
+            f.write_str("\n\nCaused by:")?;
+            for (i, cause) in causes.enumerate() {
+                write!(f, "\n    {i}: {cause}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A message added on top of an error by [`Adhoc::context`]
+#[derive(Debug)]
+struct Context {
+    context: String,
+    source: Box<dyn Error + Send + Sync + 'static>,
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.context)
+    }
+}
+
+impl Error for Context {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+/// Adding context to the error of a `Result`, turning it into an [`Adhoc`]
+pub trait ContextExt<T> {
+    /// Turn the error, if there is one, into an [`Adhoc`] with `context`
+    /// added on top
+    fn context(self, context: impl fmt::Display) -> Result<T, Adhoc>;
+
+    /// Turn the error, if there is one, into an [`Adhoc`] with the context
+    /// returned by `f` added on top, where `f` is only called if there's an
+    /// error
+    fn with_context<C: fmt::Display>(self, f: impl FnOnce() -> C) -> Result<T, Adhoc>;
+}
+
+impl<T, E: Into<Adhoc>> ContextExt<T> for Result<T, E> {
+    fn context(self, context: impl fmt::Display) -> Result<T, Adhoc> {
+        self.map_err(|err| err.into().context(context))
+    }
+
+    fn with_context<C: fmt::Display>(self, f: impl FnOnce() -> C) -> Result<T, Adhoc> {
+        self.map_err(|err| err.into().context(f()))
+    }
+}
+
+/// Return early with an error
+///
+/// With a format string and arguments, like [`format!`], the formatted message
+/// is turned into the error with `From<String>`. With any other expression
+/// the value is turned into the error with `From`, the same as `?` does.
+///
+/// ```
+/// # use futility::bail;
+/// # use std::{error::Error, io};
+/// fn check(attempts: u32) -> Result<(), Box<dyn Error>> {
+///     match attempts {
+///         0 => bail!("never tried"),
+///         1..=3 => Ok(()),
+///         _ => bail!(io::Error::other(format!("gave up after {attempts} attempts"))),
+///     }
+/// }
+/// assert_eq!(check(0).unwrap_err().to_string(), "never tried");
+/// assert_eq!(check(4).unwrap_err().to_string(), "gave up after 4 attempts");
+/// ```
+#[macro_export]
+macro_rules! bail {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        return ::core::result::Result::Err(::core::convert::From::from(
+            $crate::__private::format!($fmt $(, $arg)*)
+        ))
+    };
+    ($err:expr $(,)?) => {
+        return ::core::result::Result::Err(::core::convert::From::from($err))
+    };
+}
+
+/// Return early with an error if a condition isn't true
+///
+/// Without an error the message is the condition that failed. Otherwise the
+/// error is created the same way as with [`bail!`](crate::bail).
+///
+/// ```
+/// # use futility::ensure;
+/// # use core::error::Error;
+/// fn check(len: usize) -> Result<(), Box<dyn Error>> {
+///     ensure!(len > 0);
+///     ensure!(len <= 8, "{len} is longer than 8");
+///     Ok(())
+/// }
+/// assert_eq!(check(0).unwrap_err().to_string(), "condition failed: `len > 0`");
+/// assert_eq!(check(9).unwrap_err().to_string(), "9 is longer than 8");
+/// ```
+#[macro_export]
+macro_rules! ensure {
+    ($cond:expr $(,)?) => {
+        if !$cond {
+            $crate::bail!("condition failed: `{}`", ::core::stringify!($cond));
+        }
+    };
+    ($cond:expr, $($err:tt)+) => {
+        if !$cond {
+            $crate::bail!($($err)+);
+        }
+    };
+}
+
+/// Return early with an error if two values aren't equal
+///
+/// This is [`assert_eq!`] for code that should report a broken invariant
+/// rather than panic. Without an error the message is the comparison that
+/// failed, and with a format string the message is followed by both values.
+/// Any other expression is turned into the error with `From` as is. Both
+/// values have to implement `Debug`.
+///
+/// ```
+/// # use futility::ensure_eq;
+/// # use core::error::Error;
+/// fn check(header: &[u8]) -> Result<(), Box<dyn Error>> {
+///     ensure_eq!(header.len(), 4);
+///     ensure_eq!(header[0], 0x7f, "bad magic byte");
+///     Ok(())
+/// }
+/// assert_eq!(
+///     check(b"ELF").unwrap_err().to_string(),
+///     "condition failed: `header.len() == 4` (left: `3`, right: `4`)"
+/// );
+/// assert_eq!(
+///     check(b"\x7eELF").unwrap_err().to_string(),
+///     "bad magic byte (left: `126`, right: `127`)"
+/// );
+/// ```
+#[macro_export]
+macro_rules! ensure_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::__ensure_cmp!($left, $right, ==, "condition failed: `{} == {}`", ::core::stringify!($left), ::core::stringify!($right))
+    };
+    ($left:expr, $right:expr, $fmt:literal $(, $arg:expr)* $(,)?) => {
+        $crate::__ensure_cmp!($left, $right, ==, $fmt $(, $arg)*)
+    };
+    ($left:expr, $right:expr, $err:expr $(,)?) => {
+        if $left != $right {
+            $crate::bail!($err);
+        }
+    };
+}
+
+/// Return early with an error if two values are equal
+///
+/// The opposite of [`ensure_eq!`](crate::ensure_eq), with the same messages.
+///
+/// ```
+/// # use futility::ensure_ne;
+/// # use core::error::Error;
+/// fn rename(from: &str, to: &str) -> Result<(), Box<dyn Error>> {
+///     ensure_ne!(from, to, "can't rename a file to itself");
+///     Ok(())
+/// }
+/// assert_eq!(
+///     rename("a.txt", "a.txt").unwrap_err().to_string(),
+///     "can't rename a file to itself (left: `\"a.txt\"`, right: `\"a.txt\"`)"
+/// );
+/// ```
+#[macro_export]
+macro_rules! ensure_ne {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::__ensure_cmp!($left, $right, !=, "condition failed: `{} != {}`", ::core::stringify!($left), ::core::stringify!($right))
+    };
+    ($left:expr, $right:expr, $fmt:literal $(, $arg:expr)* $(,)?) => {
+        $crate::__ensure_cmp!($left, $right, !=, $fmt $(, $arg)*)
+    };
+    ($left:expr, $right:expr, $err:expr $(,)?) => {
+        if $left == $right {
+            $crate::bail!($err);
+        }
+    };
+}
+
+/// Return early with an error if a value doesn't match a pattern
+///
+/// This is [`matches!`] for code that should report a broken invariant,
+/// with the same messages as [`ensure_eq!`](crate::ensure_eq) except that
+/// only the value is given. The value has to implement `Debug`.
+///
+/// ```
+/// # use futility::ensure_matches;
+/// # use core::error::Error;
+/// fn check(version: Option<u32>) -> Result<(), Box<dyn Error>> {
+///     ensure_matches!(version, Some(1..=3));
+///     Ok(())
+/// }
+/// assert!(check(Some(2)).is_ok());
+/// assert_eq!(
+///     check(None).unwrap_err().to_string(),
+///     "condition failed: `version` doesn't match `Some(1..=3)` (value: `None`)"
+/// );
+/// ```
+#[macro_export]
+macro_rules! ensure_matches {
+    ($value:expr, $pat:pat $(if $guard:expr)? $(,)?) => {
+        match $value {
+            $pat $(if $guard)? => {}
+            ref value => $crate::bail!(
+                "condition failed: `{}` doesn't match `{}` (value: `{:?}`)",
+                ::core::stringify!($value),
+                ::core::stringify!($pat $(if $guard)?),
+                value,
+            ),
+        }
+    };
+    ($value:expr, $pat:pat $(if $guard:expr)?, $fmt:literal $(, $arg:expr)* $(,)?) => {
+        match $value {
+            $pat $(if $guard)? => {}
+            ref value => $crate::bail!(
+                "{} (value: `{:?}`)",
+                ::core::format_args!($fmt $(, $arg)*),
+                value,
+            ),
+        }
+    };
+    ($value:expr, $pat:pat $(if $guard:expr)?, $err:expr $(,)?) => {
+        match $value {
+            $pat $(if $guard)? => {}
+            _ => $crate::bail!($err),
+        }
+    };
+}
+
+/// Compare two values for [`ensure_eq!`](crate::ensure_eq) and
+/// [`ensure_ne!`](crate::ensure_ne), failing with the message followed by
+/// both values
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_cmp {
+    ($left:expr, $right:expr, $op:tt, $fmt:literal $(, $arg:expr)*) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left $op *right) {
+                    $crate::bail!(
+                        "{} (left: `{:?}`, right: `{:?}`)",
+                        ::core::format_args!($fmt $(, $arg)*),
+                        left,
+                        right,
+                    );
+                }
+            }
+        }
+    };
+}
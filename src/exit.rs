@@ -0,0 +1,183 @@
+//! Exit codes for errors
+//!
+//! Scripts and service managers can only tell why a program failed from its
+//! exit code, so programs should agree on what the codes mean rather than
+//! exiting with `1` for everything. This module has the codes from BSD's
+//! `sysexits.h`, which most Unix programs that use specific codes follow, and
+//! the [`ExitCoded`] trait for errors that know which code they should exit
+//! with.
+//!
+//! [`ExitCoded`] can be derived for error enums, with the code for each
+//! variant given by an `#[exit_code(...)]` attribute and a default for the
+//! variants without one on the enum itself:
+//!
+//! ```
+//! # use futility::exit::{ExitCoded, EX_CONFIG, EX_SOFTWARE};
+//! #[derive(Debug, ExitCoded)]
+//! #[exit_code(EX_SOFTWARE)]
+//! enum AppError {
+//!     #[exit_code(64)]
+//!     Usage(String),
+//!     #[exit_code(EX_CONFIG)]
+//!     MissingConfig { key: String },
+//!     Bug,
+//! }
+//!
+//! assert_eq!(AppError::Usage("no input".into()).code(), 64);
+//! assert_eq!(AppError::Bug.code(), EX_SOFTWARE);
+//! ```
+//!
+//! [`exit_with`] prints an error and exits with its code, for programs that
+//! don't use [`Terminate`](crate::terminate::Terminate). With `Terminate`,
+//! [`Terminate::exit_coded`](crate::terminate::Terminate::exit_coded) makes
+//! [`Terminate::run`](crate::terminate::Terminate::run) exit with the code of
+//! the error returned from the program.
+//!
+//! ```no_run
+//! # use futility::exit::{self, ExitCoded};
+//! # use std::fmt;
+//! #[derive(Debug)]
+//! enum CliError {
+//!     MissingArgument(&'static str),
+//!     Unreachable(String),
+//! }
+//!
+//! impl fmt::Display for CliError {
+//!     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+//!         match self {
+//!             Self::MissingArgument(arg) => write!(f, "missing argument {arg}"),
+//!             Self::Unreachable(host) => write!(f, "{host} is unreachable"),
+//!         }
+//!     }
+//! }
+//!
+//! impl ExitCoded for CliError {
+//!     fn code(&self) -> u8 {
+//!         match self {
+//!             Self::MissingArgument(_) => exit::EX_USAGE,
+//!             Self::Unreachable(_) => exit::EX_TEMPFAIL,
+//!         }
+//!     }
+//! }
+//!
+//! fn main() {
+//!     let Some(host) = std::env::args().nth(1) else {
+//!         exit::exit_with(CliError::MissingArgument("HOST"));
+//!     };
+//!     println!("connecting to {host}");
+//! }
+//! ```
+
+#[cfg(feature = "terminate")]
+use crate::terminate::{reporter, ChildFailed};
+use crate::tty;
+pub use futility_try_catch::ExitCoded;
+use std::{
+    fmt::Display,
+    io::{self, ErrorKind},
+    process::{self, ExitCode},
+};
+
+/// Successful termination
+pub const EX_OK: u8 = 0;
+/// The command was used incorrectly, such as with the wrong number of
+/// arguments or a bad flag
+pub const EX_USAGE: u8 = 64;
+/// The input data was incorrect in some way
+pub const EX_DATAERR: u8 = 65;
+/// An input file did not exist or was not readable
+pub const EX_NOINPUT: u8 = 66;
+/// The user specified did not exist
+pub const EX_NOUSER: u8 = 67;
+/// The host specified did not exist
+pub const EX_NOHOST: u8 = 68;
+/// A service is unavailable, such as a support program or file that's
+/// missing
+pub const EX_UNAVAILABLE: u8 = 69;
+/// An internal software error was detected
+pub const EX_SOFTWARE: u8 = 70;
+/// An operating system error was detected, such as being unable to fork
+pub const EX_OSERR: u8 = 71;
+/// A system file did not exist, could not be opened, or had an error
+pub const EX_OSFILE: u8 = 72;
+/// A user specified output file could not be created
+pub const EX_CANTCREAT: u8 = 73;
+/// An error occurred while doing I/O on a file
+pub const EX_IOERR: u8 = 74;
+/// A temporary failure, such as a connection that couldn't be made, where
+/// trying again later might work
+pub const EX_TEMPFAIL: u8 = 75;
+/// The remote system returned something that was not possible during a
+/// protocol exchange
+pub const EX_PROTOCOL: u8 = 76;
+/// The user did not have permission to do what was asked
+pub const EX_NOPERM: u8 = 77;
+/// Something was found in an unconfigured or misconfigured state
+pub const EX_CONFIG: u8 = 78;
+
+/// An error that knows which code the program should exit with when it fails
+/// with it
+pub trait ExitCoded {
+    /// The code to exit with, usually one of the `EX_` constants in this
+    /// module
+    fn code(&self) -> u8;
+
+    /// [`ExitCoded::code`] as an [`ExitCode`] to return from `main`
+    fn exit_code(&self) -> ExitCode {
+        ExitCode::from(self.code())
+    }
+}
+
+impl<T: ExitCoded + ?Sized> ExitCoded for &T {
+    fn code(&self) -> u8 {
+        (**self).code()
+    }
+}
+
+impl<T: ExitCoded + ?Sized> ExitCoded for Box<T> {
+    fn code(&self) -> u8 {
+        (**self).code()
+    }
+}
+
+impl ExitCoded for io::Error {
+    fn code(&self) -> u8 {
+        match self.kind() {
+            ErrorKind::NotFound => EX_NOINPUT,
+            ErrorKind::PermissionDenied => EX_NOPERM,
+            ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::TimedOut
+            | ErrorKind::Interrupted
+            | ErrorKind::WouldBlock => EX_TEMPFAIL,
+            ErrorKind::InvalidInput | ErrorKind::InvalidData => EX_DATAERR,
+            _ => EX_IOERR,
+        }
+    }
+}
+
+#[cfg(feature = "terminate")]
+impl ExitCoded for ChildFailed {
+    fn code(&self) -> u8 {
+        ChildFailed::code(self)
+    }
+}
+
+/// Print `err` to stderr the same way [`Terminate::run`] does and exit the
+/// process with its code
+///
+/// This exits with [`std::process::exit`], so destructors on the stack of
+/// this and other threads don't run.
+///
+/// [`Terminate::run`]: crate::terminate::Terminate::run
+pub fn exit_with<E>(err: E) -> !
+where
+    E: ExitCoded + Display,
+{
+    eprintln!("{}: {err}", tty::error_label());
+    #[cfg(feature = "terminate")]
+    reporter::print_context();
+    process::exit(err.code().into())
+}
@@ -0,0 +1,347 @@
+//! Temporary files and directories that clean up after themselves, and
+//! changing the working directory for a scope
+//!
+//! [`TempDirGuard`] and [`TempFileGuard`] create a path with a unique name in
+//! the system's temporary directory and remove it when they're dropped, or
+//! hand it over with `keep` if it turns out to be worth keeping.
+//!
+//! Dropping isn't enough when the program exits with [`std::process::exit`],
+//! aborts on a panic, or is forced to exit by a second shutdown signal, since
+//! none of those run destructors. A guard marked with
+//! [`remove_at_exit`](TempDirGuard::remove_at_exit) is remembered so that
+//! [`remove_pending`] can remove it on those paths too.
+#![cfg_attr(
+    feature = "terminate",
+    doc = r#"It's also removed by the [`at_exit!`](crate::at_exit) registry when
+[`Terminate`] exits, and `remove_pending` is meant to be given to
+[`Terminate::at_exit_critical`] so that it runs on those other paths.
+
+```
+# use futility::{fs::{self, TempDirGuard}, terminate::Terminate};
+# use std::io;
+Terminate::<io::Error>::new()
+    .at_exit_critical(fs::remove_pending)
+    .execute(|| {
+        let scratch = TempDirGuard::new("build-")?.remove_at_exit();
+        std::fs::write(scratch.path().join("main.o"), b"\x7fELF")?;
+        // ...
+        Ok(())
+    })
+    .unwrap();
+```
+
+[`Terminate`]: crate::terminate::Terminate
+[`Terminate::at_exit_critical`]: crate::terminate::Terminate::at_exit_critical"#
+)]
+//!
+//! [`ScopedCwd`] changes the working directory until it's dropped, for the
+//! parts of a program that need to run somewhere else rather than all of it.
+//! The working directory is shared by the whole process, so it changes for
+//! every thread while the guard is alive.
+#![cfg_attr(
+    feature = "terminate",
+    doc = "[`Terminate::working_dir`] changes it for all of the program.\n\n\
+           [`Terminate::working_dir`]: crate::terminate::Terminate::working_dir"
+)]
+//!
+//! ```
+//! # use futility::fs::{ScopedCwd, TempDirGuard};
+//! # use std::io;
+//! let scratch = TempDirGuard::new("build-")?;
+//! {
+//!     let _cwd = ScopedCwd::change(scratch.path())?;
+//!     std::fs::write("main.o", b"\x7fELF")?;
+//! }
+//! assert!(scratch.path().join("main.o").exists());
+//! # Ok::<_, io::Error>(())
+//! ```
+
+use crate::lock::Mutex;
+#[cfg(feature = "terminate")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{
+    collections::hash_map::RandomState,
+    env,
+    fs::{self, File, OpenOptions},
+    hash::{BuildHasher, Hasher},
+    io,
+    mem::{self, ManuallyDrop},
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Paths marked with `remove_at_exit` that haven't been removed or kept yet,
+/// along with how to remove them
+static PENDING: Mutex<Vec<(PathBuf, Remove)>> = Mutex::new(Vec::new());
+
+type Remove = fn(&Path) -> io::Result<()>;
+
+/// Whether [`remove_pending`] is in the `at_exit!` registry
+#[cfg(feature = "terminate")]
+static REGISTERED: AtomicBool = AtomicBool::new(false);
+
+/// A temporary directory that is removed along with everything in it when
+/// dropped
+#[derive(Debug)]
+#[must_use = "the directory is removed as soon as the guard is dropped"]
+pub struct TempDirGuard(TempPath);
+
+impl TempDirGuard {
+    /// Create a directory in [`env::temp_dir`] with a unique name starting
+    /// with `prefix`
+    pub fn new(prefix: &str) -> io::Result<Self> {
+        Self::new_in(env::temp_dir(), prefix)
+    }
+
+    /// Create a directory in `dir` with a unique name starting with `prefix`
+    pub fn new_in(dir: impl AsRef<Path>, prefix: &str) -> io::Result<Self> {
+        let path = create_unique(dir.as_ref(), prefix, |path| fs::create_dir(path))?;
+        Ok(Self(TempPath {
+            path,
+            remove: |path| fs::remove_dir_all(path),
+        }))
+    }
+
+    /// Also remove the directory when the program exits, see the
+    /// [module documentation](self)
+    pub fn remove_at_exit(self) -> Self {
+        self.0.remove_at_exit();
+        self
+    }
+
+    /// The path of the directory
+    pub fn path(&self) -> &Path {
+        &self.0.path
+    }
+
+    /// Stop the directory from being removed and return its path
+    pub fn keep(self) -> PathBuf {
+        self.0.keep()
+    }
+}
+
+impl AsRef<Path> for TempDirGuard {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
+
+/// A temporary file that is removed when dropped
+#[derive(Debug)]
+#[must_use = "the file is removed as soon as the guard is dropped"]
+pub struct TempFileGuard {
+    file: File,
+    path: TempPath,
+}
+
+impl TempFileGuard {
+    /// Create a file in [`env::temp_dir`] with a unique name starting with
+    /// `prefix`, open for reading and writing
+    pub fn new(prefix: &str) -> io::Result<Self> {
+        Self::new_in(env::temp_dir(), prefix)
+    }
+
+    /// Create a file in `dir` with a unique name starting with `prefix`, open
+    /// for reading and writing
+    pub fn new_in(dir: impl AsRef<Path>, prefix: &str) -> io::Result<Self> {
+        let mut file = None;
+        let path = create_unique(dir.as_ref(), prefix, |path| {
+            file = Some(
+                OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create_new(true)
+                    .open(path)?,
+            );
+            Ok(())
+        })?;
+        Ok(Self {
+            file: file.expect("the file was created"),
+            path: TempPath {
+                path,
+                remove: |path| fs::remove_file(path),
+            },
+        })
+    }
+
+    /// Also remove the file when the program exits, see the
+    /// [module documentation](self)
+    pub fn remove_at_exit(self) -> Self {
+        self.path.remove_at_exit();
+        self
+    }
+
+    /// The path of the file
+    pub fn path(&self) -> &Path {
+        &self.path.path
+    }
+
+    /// The open file
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    /// The open file, mutably for writing to it
+    pub fn file_mut(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// Stop the file from being removed and return its path along with the
+    /// open file
+    pub fn keep(self) -> (PathBuf, File) {
+        (self.path.keep(), self.file)
+    }
+}
+
+impl AsRef<Path> for TempFileGuard {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
+
+/// A working directory that is changed back to the previous one when dropped,
+/// including while a panic unwinds
+#[derive(Debug)]
+#[must_use = "the working directory is changed back as soon as the guard is dropped"]
+pub struct ScopedCwd {
+    previous: PathBuf,
+}
+
+impl ScopedCwd {
+    /// Change the working directory to `dir`
+    pub fn change(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        let previous = env::current_dir().map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!("couldn't find the current working directory: {err}"),
+            )
+        })?;
+        env::set_current_dir(dir).map_err(|err| {
+            io::Error::new(
+                err.kind(),
+                format!(
+                    "couldn't change the working directory to {}: {err}",
+                    dir.display()
+                ),
+            )
+        })?;
+        Ok(Self { previous })
+    }
+
+    /// The working directory that is changed back to
+    pub fn previous(&self) -> &Path {
+        &self.previous
+    }
+
+    /// Change back to the previous working directory, returning an error
+    /// naming it if it was removed or can't be changed to, rather than
+    /// printing the error as dropping the guard does
+    pub fn restore(self) -> io::Result<()> {
+        let mut this = ManuallyDrop::new(self);
+        let res = this.change_back();
+        drop(mem::take(&mut this.previous));
+        res
+    }
+
+    fn change_back(&self) -> io::Result<()> {
+        env::set_current_dir(&self.previous).map_err(|err| {
+            let reason = match self.previous.exists() {
+                true => err.to_string(),
+                false => "it no longer exists".into(),
+            };
+            io::Error::new(
+                err.kind(),
+                format!(
+                    "couldn't change back to the working directory {}: {reason}",
+                    self.previous.display()
+                ),
+            )
+        })
+    }
+}
+
+impl Drop for ScopedCwd {
+    fn drop(&mut self) {
+        if let Err(err) = self.change_back() {
+            eprintln!("{err}");
+        }
+    }
+}
+
+/// A path that is removed when dropped
+#[derive(Debug)]
+struct TempPath {
+    path: PathBuf,
+    remove: Remove,
+}
+
+impl TempPath {
+    /// Remember the path for [`remove_pending`], and have the `at_exit!`
+    /// registry remove it when `Terminate` exits
+    fn remove_at_exit(&self) {
+        PENDING.lock().push((self.path.clone(), self.remove));
+        // One registry entry removes every pending path, rather than one per
+        // path, so marking paths in a loop doesn't grow the registry. Running
+        // the registry drops the entry, so it's registered again after that.
+        #[cfg(feature = "terminate")]
+        if !REGISTERED.swap(true, Ordering::SeqCst) {
+            crate::terminate::registry::register(|| {
+                REGISTERED.store(false, Ordering::SeqCst);
+                remove_pending();
+            });
+        }
+    }
+
+    fn keep(self) -> PathBuf {
+        let path = mem::take(&mut ManuallyDrop::new(self).path);
+        unmark_pending(&path);
+        path
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        unmark_pending(&self.path);
+        let _ = (self.remove)(&self.path);
+    }
+}
+
+/// Remove every temporary path marked with `remove_at_exit` that is still
+/// around. This is safe to call more than once.
+#[cfg_attr(
+    feature = "terminate",
+    doc = "\nIt's a plain `fn()` so it can be given to \
+           [`Terminate::at_exit_critical`](crate::terminate::Terminate::at_exit_critical)."
+)]
+pub fn remove_pending() {
+    for (path, remove) in mem::take(&mut *PENDING.lock()) {
+        let _ = remove(&path);
+    }
+}
+
+/// Try names in `dir` starting with `prefix` until `create` makes one that
+/// didn't exist yet
+fn create_unique(
+    dir: &Path,
+    prefix: &str,
+    mut create: impl FnMut(&Path) -> io::Result<()>,
+) -> io::Result<PathBuf> {
+    let mut attempts = 0;
+    loop {
+        let random = RandomState::new().build_hasher().finish();
+        let path = dir.join(format!("{prefix}{}-{random:016x}", process::id()));
+        match create(&path) {
+            Ok(()) => return Ok(path),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempts < 16 => attempts += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Forget a pending path, returning how to remove it if it was pending
+fn unmark_pending(path: &Path) -> Option<Remove> {
+    let mut pending = PENDING.lock();
+    let index = pending.iter().position(|(pending, _)| pending == path)?;
+    Some(pending.swap_remove(index).1)
+}
@@ -0,0 +1,235 @@
+//! Cleanup attached to a scope
+//!
+//! A [`ScopeGuard`] owns a value and a function that is called with the value
+//! when the guard is dropped, whether the scope it lives in is left normally,
+//! with `?`, or by a panic unwinding through it. [`defer!`](crate::defer)
+//! attaches cleanup to the current scope without needing a value.
+//!
+//! Guards created with [`ScopeGuard::on_success`] or
+//! [`defer_on_success!`](crate::defer_on_success) only run when the scope is
+//! left normally, and those created with [`ScopeGuard::on_unwind`] or
+//! [`defer_on_unwind!`](crate::defer_on_unwind) only run when a panic is
+//! unwinding through it, which is checked with [`std::thread::panicking`].
+//! Together they give a "commit on success, roll back on panic" pattern.
+//! These need the `std` feature, as only `std` knows whether a panic is
+//! unwinding.
+//!
+//! ```
+//! # use futility::{defer, guard::ScopeGuard};
+//! # use std::cell::RefCell;
+//! let log = RefCell::new(Vec::new());
+//! {
+//!     defer! { log.borrow_mut().push("deferred"); }
+//!     let mut buffer = ScopeGuard::new(Vec::new(), |buffer| {
+//!         log.borrow_mut().push("flushed");
+//!         assert_eq!(buffer, ["data"]);
+//!     });
+//!     buffer.push("data");
+//!     log.borrow_mut().push("end of scope");
+//! }
+//! assert_eq!(*log.borrow(), ["end of scope", "flushed", "deferred"]);
+//! ```
+
+use core::{
+    fmt,
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+};
+#[cfg(feature = "std")]
+use std::thread;
+
+/// When the cleanup function of a guard runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum When {
+    Always,
+    #[cfg(feature = "std")]
+    OnSuccess,
+    #[cfg(feature = "std")]
+    OnUnwind,
+}
+
+/// Calls a function with the value it holds when it is dropped, unless it is
+/// dismissed first
+pub struct ScopeGuard<T, F>
+where
+    F: FnOnce(T),
+{
+    value: ManuallyDrop<T>,
+    cleanup: ManuallyDrop<F>,
+    when: When,
+}
+
+impl<T, F> ScopeGuard<T, F>
+where
+    F: FnOnce(T),
+{
+    /// Create a guard that calls `cleanup` with `value` when it is dropped
+    pub fn new(value: T, cleanup: F) -> Self {
+        Self::with(value, cleanup, When::Always)
+    }
+
+    /// Create a guard that calls `cleanup` with `value` when it is dropped,
+    /// but only if the thread isn't panicking
+    #[cfg(feature = "std")]
+    pub fn on_success(value: T, cleanup: F) -> Self {
+        Self::with(value, cleanup, When::OnSuccess)
+    }
+
+    /// Create a guard that calls `cleanup` with `value` when it is dropped,
+    /// but only if a panic is unwinding the thread
+    #[cfg(feature = "std")]
+    pub fn on_unwind(value: T, cleanup: F) -> Self {
+        Self::with(value, cleanup, When::OnUnwind)
+    }
+
+    fn with(value: T, cleanup: F, when: When) -> Self {
+        Self {
+            value: ManuallyDrop::new(value),
+            cleanup: ManuallyDrop::new(cleanup),
+            when,
+        }
+    }
+
+    /// Take the value back without calling the cleanup function, such as once
+    /// the work the cleanup would undo has been committed. This is called as
+    /// `ScopeGuard::dismiss(guard)` so that it doesn't shadow a method of the
+    /// same name on `T`.
+    pub fn dismiss(guard: Self) -> T {
+        let mut guard = ManuallyDrop::new(guard);
+        // SAFETY: The guard is never dropped, so the value and the cleanup
+        // function are each taken exactly once
+        unsafe {
+            ManuallyDrop::drop(&mut guard.cleanup);
+            ManuallyDrop::take(&mut guard.value)
+        }
+    }
+}
+
+impl<T, F> Deref for ScopeGuard<T, F>
+where
+    F: FnOnce(T),
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, F> DerefMut for ScopeGuard<T, F>
+where
+    F: FnOnce(T),
+{
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T, F> Drop for ScopeGuard<T, F>
+where
+    F: FnOnce(T),
+{
+    fn drop(&mut self) {
+        // SAFETY: Drop only runs once and nothing else takes these out of a
+        // guard that is being dropped
+        let (value, cleanup) = unsafe {
+            (
+                ManuallyDrop::take(&mut self.value),
+                ManuallyDrop::take(&mut self.cleanup),
+            )
+        };
+        let run = match self.when {
+            When::Always => true,
+            #[cfg(feature = "std")]
+            When::OnSuccess => !thread::panicking(),
+            #[cfg(feature = "std")]
+            When::OnUnwind => thread::panicking(),
+        };
+        if run {
+            cleanup(value);
+        }
+    }
+}
+
+impl<T, F> fmt::Debug for ScopeGuard<T, F>
+where
+    T: fmt::Debug,
+    F: FnOnce(T),
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScopeGuard")
+            .field("value", &*self.value)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Run the given statements when the current scope is left, including by a
+/// panic. Deferred blocks in the same scope run in the reverse order they were
+/// written in. See the [`guard`](crate::guard) module for more details.
+///
+/// ```
+/// # use futility::defer;
+/// # use std::cell::Cell;
+/// let count = Cell::new(0);
+/// {
+///     defer! { count.set(count.get() + 1); }
+///     assert_eq!(count.get(), 0);
+/// }
+/// assert_eq!(count.get(), 1);
+/// ```
+#[macro_export]
+macro_rules! defer {
+    ($($body:tt)*) => {
+        let _guard = $crate::guard::ScopeGuard::new((), |()| {
+            $($body)*
+        });
+    };
+}
+
+/// Run the given statements when the current scope is left normally, but not
+/// when a panic unwinds through it. See the [`guard`](crate::guard) module for
+/// more details.
+///
+/// ```
+/// # use futility::defer_on_success;
+/// # use std::{cell::Cell, panic::{self, AssertUnwindSafe}};
+/// let committed = Cell::new(false);
+/// let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+///     defer_on_success! { committed.set(true); }
+///     panic!("Oh no");
+/// }));
+/// assert!(!committed.get());
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! defer_on_success {
+    ($($body:tt)*) => {
+        let _guard = $crate::guard::ScopeGuard::on_success((), |()| {
+            $($body)*
+        });
+    };
+}
+
+/// Run the given statements only when a panic unwinds through the current
+/// scope, such as to roll back work that wasn't finished. See the
+/// [`guard`](crate::guard) module for more details.
+///
+/// ```
+/// # use futility::defer_on_unwind;
+/// # use std::{cell::Cell, panic::{self, AssertUnwindSafe}};
+/// let rolled_back = Cell::new(false);
+/// let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+///     defer_on_unwind! { rolled_back.set(true); }
+///     panic!("Oh no");
+/// }));
+/// assert!(rolled_back.get());
+/// ```
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! defer_on_unwind {
+    ($($body:tt)*) => {
+        let _guard = $crate::guard::ScopeGuard::on_unwind((), |()| {
+            $($body)*
+        });
+    };
+}
@@ -0,0 +1,150 @@
+//! I/O that carries on through interruptions
+//!
+//! A signal arriving while a thread is blocked in a system call makes the call
+//! fail with [`ErrorKind::Interrupted`], even though nothing went wrong and the
+//! call only has to be made again. Programs that handle signals, which this
+//! crate makes easy, see it much more often than others, and checking for it
+//! after every read and write is easy to forget. [`read_full`],
+//! [`write_full`], and [`retry_interrupted`] make the call again for as long
+//! as it's interrupted.
+//!
+//! ```
+//! # use futility::io;
+//! let mut input: &[u8] = b"GET / HTTP/1.1\r\n";
+//! let mut method = [0; 3];
+//! assert_eq!(io::read_full(&mut input, &mut method).unwrap(), 3);
+//! assert_eq!(&method, b"GET");
+//!
+//! let mut output = Vec::new();
+//! io::write_full(&mut output, b"HTTP/1.1 200 OK\r\n").unwrap();
+//! ```
+//!
+//! Errors that might go away if the call is made again a little later, like
+//! [`ErrorKind::WouldBlock`] from a non-blocking socket or
+//! [`ErrorKind::TimedOut`] from one with a timeout, are retried with a
+//! [`RetryPolicy`] by wrapping the reader or writer in a [`Retrying`].
+//!
+//! ```
+//! # use futility::{io::Retrying, retry::{ExponentialBackoff, RetryPolicy}};
+//! # use std::{io::Read, net::TcpStream, time::Duration};
+//! # fn connect() -> std::io::Result<()> {
+//! let stream = TcpStream::connect("127.0.0.1:8080")?;
+//! stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+//! let mut stream = Retrying::new(stream, ExponentialBackoff::default().max_attempts(3));
+//! let mut response = String::new();
+//! stream.read_to_string(&mut response)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::retry::{self, RetryPolicy};
+use std::io::{self, ErrorKind, Read, Write};
+
+/// Call `f` until it doesn't fail with [`ErrorKind::Interrupted`]
+pub fn retry_interrupted<T>(mut f: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    loop {
+        match f() {
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            res => return res,
+        }
+    }
+}
+
+/// Read from `reader` until `buf` is full or the end of the input is reached,
+/// returning how many bytes were read
+///
+/// Unlike [`Read::read_exact`] reaching the end early isn't an error, so this
+/// can read the last part of an input that isn't a whole number of buffers.
+pub fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match retry_interrupted(|| reader.read(&mut buf[filled..]))? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+/// Write all of `buf` to `writer` and flush it, failing with
+/// [`ErrorKind::WriteZero`] if the writer stops accepting bytes
+pub fn write_full(writer: &mut impl Write, buf: &[u8]) -> io::Result<()> {
+    let mut written = 0;
+    while written < buf.len() {
+        match retry_interrupted(|| writer.write(&buf[written..]))? {
+            0 => {
+                return Err(io::Error::new(
+                    ErrorKind::WriteZero,
+                    "failed to write the whole buffer",
+                ))
+            }
+            wrote => written += wrote,
+        }
+    }
+    retry_interrupted(|| writer.flush())
+}
+
+/// Whether an I/O error might go away if the call is made again: it was
+/// [`ErrorKind::Interrupted`], [`ErrorKind::WouldBlock`], or
+/// [`ErrorKind::TimedOut`]
+pub fn is_transient(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut
+    )
+}
+
+/// A reader or writer that retries each call failing with a
+/// [transient](is_transient) error until it succeeds or the policy gives up
+///
+/// Each call to `read`, `write`, or `flush` starts the policy over, so a limit
+/// like [`RetryPolicy::max_attempts`] applies to each call rather than to the
+/// reader or writer as a whole.
+#[derive(Debug)]
+pub struct Retrying<T, P> {
+    inner: T,
+    policy: P,
+}
+
+impl<T, P: RetryPolicy> Retrying<T, P> {
+    /// Wrap `inner`, retrying its calls with `policy`
+    pub fn new(inner: T, policy: P) -> Self {
+        Self { inner, policy }
+    }
+
+    /// The wrapped reader or writer
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// The wrapped reader or writer, mutably
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Take the wrapped reader or writer back out
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn retry<R>(&mut self, mut f: impl FnMut(&mut T) -> io::Result<R>) -> io::Result<R> {
+        let inner = &mut self.inner;
+        retry::retry_if(&mut self.policy, is_transient, || f(inner))
+    }
+}
+
+impl<T: Read, P: RetryPolicy> Read for Retrying<T, P> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.retry(|inner| inner.read(buf))
+    }
+}
+
+impl<T: Write, P: RetryPolicy> Write for Retrying<T, P> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.retry(|inner| inner.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.retry(|inner| inner.flush())
+    }
+}
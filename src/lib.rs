@@ -1,9 +1,133 @@
 #![doc = include_str!("../README.md")]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+extern crate alloc;
+
+#[cfg(feature = "abort")]
+pub mod abort;
+#[cfg(feature = "args")]
+pub mod args;
+#[cfg(feature = "budget")]
+pub mod budget;
+#[cfg(feature = "buildinfo")]
+pub mod buildinfo;
+#[cfg(feature = "config")]
+pub mod config;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+#[cfg(feature = "env")]
+pub mod env;
+pub mod error;
+#[cfg(feature = "exit")]
+pub mod exit;
+#[cfg(feature = "fs")]
+pub mod fs;
+#[cfg(feature = "guards")]
+pub mod guard;
+#[cfg(feature = "io")]
+pub mod io;
+#[cfg(feature = "lock")]
+pub mod lock;
+#[cfg(feature = "log")]
+pub mod log;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "once")]
+pub mod once;
+#[cfg(feature = "panic")]
+pub mod panic;
+#[cfg(feature = "pool")]
+pub mod pool;
+pub mod prelude;
+#[cfg(feature = "process")]
+pub mod process;
+#[cfg(feature = "rate")]
+pub mod rate;
+#[cfg(feature = "report")]
+pub mod report;
+#[cfg(feature = "result")]
+pub mod result;
+#[cfg(feature = "retry")]
+pub mod retry;
+#[cfg(feature = "shutdown")]
+pub mod shutdown;
+#[cfg(feature = "signals")]
+pub mod signal;
+#[cfg(feature = "supervisor")]
+pub mod supervisor;
+#[cfg(feature = "terminate")]
 pub mod terminate;
+#[cfg(feature = "test")]
+pub mod test;
+#[cfg(feature = "thread")]
+pub mod thread;
+#[cfg(feature = "time")]
+pub mod time;
+#[cfg(feature = "timeout")]
+pub mod timeout;
+#[cfg(feature = "trace")]
+pub mod trace;
+#[cfg(feature = "std")]
+pub mod tty;
+#[cfg(feature = "watchdog")]
+pub mod watchdog;
+#[cfg(feature = "test")]
+pub use futility_try_catch::fixture;
+#[cfg(feature = "terminate")]
+pub use futility_try_catch::main;
+#[cfg(all(feature = "terminate", feature = "test"))]
+pub use futility_try_catch::test;
+#[cfg(feature = "try-catch")]
 pub use futility_try_catch::try_;
 
-#[test]
+/// Items used by the crate's macros, which aren't part of its API
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::format;
+    use core::error::Error;
+
+    /// The source of a `#[derive(ContextError)]` error as a trait object,
+    /// which method call syntax finds through a `Box<dyn Error>` as well
+    pub trait AsDynError {
+        fn as_dyn_error(&self) -> &(dyn Error + 'static);
+    }
+
+    impl<E: Error + 'static> AsDynError for E {
+        fn as_dyn_error(&self) -> &(dyn Error + 'static) {
+            self
+        }
+    }
+
+    impl AsDynError for dyn Error + 'static {
+        fn as_dyn_error(&self) -> &(dyn Error + 'static) {
+            self
+        }
+    }
+
+    impl AsDynError for dyn Error + Send + 'static {
+        fn as_dyn_error(&self) -> &(dyn Error + 'static) {
+            self
+        }
+    }
+
+    impl AsDynError for dyn Error + Send + Sync + 'static {
+        fn as_dyn_error(&self) -> &(dyn Error + 'static) {
+            self
+        }
+    }
+
+    impl AsDynError for crate::error::Adhoc {
+        fn as_dyn_error(&self) -> &(dyn Error + 'static) {
+            self.as_error()
+        }
+    }
+}
+
+// `test` is re-exported above, so the built-in test attribute has to be named
+// by its full path in this file
+
+#[cfg(all(test, feature = "try-catch"))]
+#[std::prelude::v1::test]
 fn try_catch() {
     use std::error::Error;
     let mut errored: Option<Box<dyn Error>> = None;
@@ -20,7 +144,8 @@ fn try_catch() {
     }
 }
 
-#[test]
+#[cfg(all(test, feature = "try-catch"))]
+#[std::prelude::v1::test]
 fn try_catch_ret_val() {
     use std::error::Error;
     let mut errored: Option<Box<dyn Error>> = None;
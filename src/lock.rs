@@ -0,0 +1,213 @@
+//! Locks that don't make poisoning everyone's problem
+//!
+//! A std lock is poisoned when a thread panics while holding it, and from then
+//! on every `lock()` returns a [`PoisonError`](std::sync::PoisonError) that is
+//! almost always `.unwrap()`ed, turning one panic into a panic in every
+//! thread that touches the lock. With panics caught by
+//! [`panic::catch`](crate::panic::catch) or a worker being restarted that's
+//! the opposite of what's wanted.
+//!
+//! [`Mutex`] and [`RwLock`] wrap the std locks and decide what poisoning means
+//! at each call: [`Mutex::lock`] ignores it, [`Mutex::lock_checked`] turns it
+//! into a [`Poisoned`] error that can be returned with `?`, and
+//! [`Mutex::lock_or_recover`] repairs the data with a function before clearing
+//! the poison.
+//!
+//! ```
+//! # use futility::lock::Mutex;
+//! # use std::{panic, sync::Arc, thread};
+//! let totals = Arc::new(Mutex::new(vec![1, 2, 3]));
+//! let _ = thread::spawn({
+//!     let totals = totals.clone();
+//!     move || {
+//!         let mut totals = totals.lock();
+//!         totals.push(4);
+//!         panic!("failed halfway through an update");
+//!     }
+//! })
+//! .join();
+//!
+//! assert!(totals.lock_checked().is_err());
+//! let totals = totals.lock_or_recover(|totals| totals.truncate(3));
+//! assert_eq!(*totals, [1, 2, 3]);
+//! ```
+
+use std::{
+    fmt,
+    sync::{self, TryLockError},
+};
+use thiserror::Error;
+
+pub use std::sync::{MutexGuard, RwLockReadGuard, RwLockWriteGuard};
+
+/// The error returned when a lock was poisoned by a thread that panicked while
+/// holding it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+#[error("a thread panicked while holding the lock")]
+pub struct Poisoned;
+
+/// A [`std::sync::Mutex`] that doesn't panic when poisoned
+#[derive(Default)]
+pub struct Mutex<T: ?Sized>(sync::Mutex<T>);
+
+impl<T> Mutex<T> {
+    /// Create an unlocked mutex holding `value`
+    pub const fn new(value: T) -> Self {
+        Self(sync::Mutex::new(value))
+    }
+
+    /// Take the value out of the mutex, even if it's poisoned
+    pub fn into_inner(self) -> T {
+        self.0.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Lock the mutex, blocking until it's available, ignoring whether it's
+    /// poisoned
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Lock the mutex if it isn't already locked, ignoring whether it's
+    /// poisoned
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        match self.0.try_lock() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(e)) => Some(e.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
+    /// Lock the mutex, blocking until it's available, failing if it's
+    /// poisoned
+    pub fn lock_checked(&self) -> Result<MutexGuard<'_, T>, Poisoned> {
+        self.0.lock().map_err(|_| Poisoned)
+    }
+
+    /// Lock the mutex, blocking until it's available. If it's poisoned
+    /// `recover` is called to put the value back into a good state and the
+    /// poison is cleared.
+    pub fn lock_or_recover(&self, recover: impl FnOnce(&mut T)) -> MutexGuard<'_, T> {
+        match self.0.lock() {
+            Ok(guard) => guard,
+            Err(e) => {
+                let mut guard = e.into_inner();
+                recover(&mut guard);
+                self.0.clear_poison();
+                guard
+            }
+        }
+    }
+
+    /// Whether a thread panicked while holding the lock since it was last
+    /// recovered
+    pub fn is_poisoned(&self) -> bool {
+        self.0.is_poisoned()
+    }
+
+    /// Get the value without locking, since the mutex is borrowed mutably,
+    /// even if it's poisoned
+    pub fn get_mut(&mut self) -> &mut T {
+        self.0.get_mut().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> From<T> for Mutex<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Mutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => f.debug_tuple("Mutex").field(&&*guard).finish(),
+            None => f.write_str("Mutex(<locked>)"),
+        }
+    }
+}
+
+/// A [`std::sync::RwLock`] that doesn't panic when poisoned
+#[derive(Default)]
+pub struct RwLock<T: ?Sized>(sync::RwLock<T>);
+
+impl<T> RwLock<T> {
+    /// Create an unlocked lock holding `value`
+    pub const fn new(value: T) -> Self {
+        Self(sync::RwLock::new(value))
+    }
+
+    /// Take the value out of the lock, even if it's poisoned
+    pub fn into_inner(self) -> T {
+        self.0.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Lock for reading, blocking until no writer holds the lock, ignoring
+    /// whether it's poisoned
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.0.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Lock for writing, blocking until nothing else holds the lock,
+    /// ignoring whether it's poisoned
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.0.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Lock for reading, failing if the lock is poisoned
+    pub fn read_checked(&self) -> Result<RwLockReadGuard<'_, T>, Poisoned> {
+        self.0.read().map_err(|_| Poisoned)
+    }
+
+    /// Lock for writing, failing if the lock is poisoned
+    pub fn write_checked(&self) -> Result<RwLockWriteGuard<'_, T>, Poisoned> {
+        self.0.write().map_err(|_| Poisoned)
+    }
+
+    /// Lock for writing. If the lock is poisoned `recover` is called to put
+    /// the value back into a good state and the poison is cleared.
+    pub fn write_or_recover(&self, recover: impl FnOnce(&mut T)) -> RwLockWriteGuard<'_, T> {
+        match self.0.write() {
+            Ok(guard) => guard,
+            Err(e) => {
+                let mut guard = e.into_inner();
+                recover(&mut guard);
+                self.0.clear_poison();
+                guard
+            }
+        }
+    }
+
+    /// Whether a thread panicked while holding the lock for writing since it
+    /// was last recovered
+    pub fn is_poisoned(&self) -> bool {
+        self.0.is_poisoned()
+    }
+
+    /// Get the value without locking, since the lock is borrowed mutably,
+    /// even if it's poisoned
+    pub fn get_mut(&mut self) -> &mut T {
+        self.0.get_mut().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> From<T> for RwLock<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.try_read() {
+            Ok(guard) => f.debug_tuple("RwLock").field(&&*guard).finish(),
+            Err(TryLockError::Poisoned(e)) => {
+                f.debug_tuple("RwLock").field(&&*e.into_inner()).finish()
+            }
+            Err(TryLockError::WouldBlock) => f.write_str("RwLock(<locked>)"),
+        }
+    }
+}
@@ -0,0 +1,398 @@
+//! A small logger for programs that don't need the full `tracing` stack
+//!
+//! [`SimpleLogger`] writes each message on its own line as
+//! `LEVEL target: message`, to stderr with the level colored if stderr is
+//! colored, or to a file. Which messages are written is decided by a filter
+//! read from the `RUST_LOG` environment variable, which is either a level
+//! like `debug` or a comma separated list of levels and `target=level` pairs
+//! like `warn,my_program=trace`. The most specific target wins, and messages
+//! at `info` and above are written without a filter.
+//!
+//! Messages are logged with [`log!`](crate::log) from anywhere in the
+//! program once a logger is installed, and are thrown away before then.
+//! [`Terminate::install_simple_logger`] installs the default logger before
+//! the program runs and flushes it when it exits.
+//!
+//! With the `log-facade` feature, installing a [`SimpleLogger`] also makes it
+//! the logger of the `log` crate, so messages from its macros, including the
+//! ones in dependencies, are filtered and written the same way.
+//!
+//! ```
+//! # use futility::log::{Level, SimpleLogger};
+//! # use futility::log;
+//! SimpleLogger::new().level(Level::Debug).install().unwrap();
+//! log!(Level::Info, "listening on port {}", 8080);
+//! log!(Level::Trace, "not written");
+//! ```
+//!
+//! [`Terminate::install_simple_logger`]: crate::terminate::Terminate::install_simple_logger
+
+use crate::tty;
+use std::{
+    env,
+    fmt::{self, Arguments},
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    str::FromStr,
+    sync::Mutex,
+};
+use thiserror::Error;
+
+/// How important a message is, from most to least
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    /// Something failed
+    Error,
+    /// Something is likely to fail or was handled in a degraded way
+    Warn,
+    /// What the program is doing
+    Info,
+    /// Details that help when debugging
+    Debug,
+    /// Everything else
+    Trace,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+
+    /// The ANSI color the level is shown in
+    fn color(self) -> &'static str {
+        match self {
+            Level::Error => "31",
+            Level::Warn => "33",
+            Level::Info => "32",
+            Level::Debug => "34",
+            Level::Trace => "35",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+/// The error returned when parsing a [`Level`] or a filter fails
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("invalid log level {0:?}, expected error, warn, info, debug, or trace")]
+pub struct ParseLevelError(String);
+
+impl FromStr for Level {
+    type Err = ParseLevelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "error" => Ok(Level::Error),
+            "warn" | "warning" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            "trace" => Ok(Level::Trace),
+            _ => Err(ParseLevelError(s.into())),
+        }
+    }
+}
+
+/// Which messages are written, by target
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Filter {
+    default: Level,
+    targets: Vec<(String, Level)>,
+}
+
+impl Filter {
+    fn parse(spec: &str) -> Result<Self, ParseLevelError> {
+        let mut filter = Filter {
+            default: Level::Info,
+            targets: Vec::new(),
+        };
+        for part in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            match part.split_once('=') {
+                Some((target, level)) => {
+                    filter.targets.push((target.trim().into(), level.parse()?))
+                }
+                None => filter.default = part.parse()?,
+            }
+        }
+        // Check the most specific targets first
+        filter
+            .targets
+            .sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        Ok(filter)
+    }
+
+    /// The least important level any target writes
+    #[cfg(feature = "log-facade")]
+    fn max(&self) -> Level {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Level::max)
+    }
+
+    fn enabled(&self, level: Level, target: &str) -> bool {
+        let max = self
+            .targets
+            .iter()
+            .find(|(prefix, _)| {
+                target == prefix
+                    || target
+                        .strip_prefix(prefix.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            .map_or(self.default, |(_, level)| *level);
+        level <= max
+    }
+}
+
+enum Target {
+    Stderr { color: bool },
+    File(BufWriter<File>),
+}
+
+/// A logger writing to stderr or a file
+pub struct SimpleLogger {
+    filter: Result<Filter, ParseLevelError>,
+    color: Option<bool>,
+    file: Option<PathBuf>,
+}
+
+impl SimpleLogger {
+    /// A logger filtered by the `RUST_LOG` environment variable, writing to
+    /// stderr with color if stderr is colored
+    pub fn new() -> Self {
+        Self::default().env("RUST_LOG")
+    }
+
+    /// Read the filter from the environment variable `var` rather than
+    /// `RUST_LOG`. If it isn't set `info` and above are written.
+    pub fn env(mut self, var: &str) -> Self {
+        self.filter = Filter::parse(&env::var(var).unwrap_or_default());
+        self
+    }
+
+    /// Write messages at `level` and above from every target, ignoring the
+    /// environment
+    pub fn level(mut self, level: Level) -> Self {
+        self.filter = Ok(Filter {
+            default: level,
+            targets: Vec::new(),
+        });
+        self
+    }
+
+    /// Color the level or not, rather than deciding based on
+    /// [`output_style`](crate::tty::output_style)
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Append messages to the file at `path` rather than writing them to
+    /// stderr. Messages written to a file are never colored.
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.file = Some(path.into());
+        self
+    }
+
+    /// Make this the logger [`log!`](crate::log) writes to, replacing the
+    /// one installed before it after flushing it. With the `log-facade`
+    /// feature this is also the `log` crate's logger, which fails if a
+    /// different logger was already set with it.
+    pub fn install(self) -> Result<(), LogError> {
+        let filter = self.filter?;
+        #[cfg(feature = "log-facade")]
+        facade::install(filter.max())?;
+        let target = match self.file {
+            Some(path) => Target::File(BufWriter::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .map_err(|source| LogError::Open { path, source })?,
+            )),
+            None => Target::Stderr {
+                color: self
+                    .color
+                    .unwrap_or_else(|| tty::output_style().stderr_color),
+            },
+        };
+        flush();
+        *lock() = Some(Installed { filter, target });
+        Ok(())
+    }
+}
+
+impl Default for SimpleLogger {
+    /// A logger writing `info` and above to stderr
+    fn default() -> Self {
+        Self {
+            filter: Ok(Filter {
+                default: Level::Info,
+                targets: Vec::new(),
+            }),
+            color: None,
+            file: None,
+        }
+    }
+}
+
+/// The error returned when a [`SimpleLogger`] couldn't be installed
+#[derive(Debug, Error)]
+pub enum LogError {
+    /// The filter couldn't be parsed
+    #[error("invalid log filter: {0}")]
+    Filter(#[from] ParseLevelError),
+    /// The log file couldn't be opened
+    #[error("failed to open log file {}: {source}", .path.display())]
+    Open {
+        /// The log file
+        path: PathBuf,
+        /// The underlying error from the OS
+        #[source]
+        source: io::Error,
+    },
+    /// A different logger was already set for the `log` crate
+    #[cfg(feature = "log-facade")]
+    #[error("another logger is already set for the log crate")]
+    Facade(#[from] ::log::SetLoggerError),
+}
+
+struct Installed {
+    filter: Filter,
+    target: Target,
+}
+
+static LOGGER: Mutex<Option<Installed>> = Mutex::new(None);
+
+fn lock() -> std::sync::MutexGuard<'static, Option<Installed>> {
+    LOGGER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Whether a message at `level` from `target` would be written
+pub fn enabled(level: Level, target: &str) -> bool {
+    lock()
+        .as_ref()
+        .is_some_and(|installed| installed.filter.enabled(level, target))
+}
+
+/// Write a message, which is usually done with [`log!`](crate::log)
+pub fn log(level: Level, target: &str, message: Arguments<'_>) {
+    let mut logger = lock();
+    let Some(installed) = logger.as_mut().filter(|i| i.filter.enabled(level, target)) else {
+        return;
+    };
+    // There's nowhere to report a failure to log to
+    let _ = match &mut installed.target {
+        Target::Stderr { color: true } => writeln!(
+            io::stderr().lock(),
+            "\x1b[{}m{level:<5}\x1b[0m {target}: {message}",
+            level.color()
+        ),
+        Target::Stderr { color: false } => {
+            writeln!(io::stderr().lock(), "{level:<5} {target}: {message}")
+        }
+        Target::File(file) => writeln!(file, "{level:<5} {target}: {message}"),
+    };
+}
+
+/// Flush the installed logger's output
+pub fn flush() {
+    if let Some(Installed {
+        target: Target::File(file),
+        ..
+    }) = lock().as_mut()
+    {
+        let _ = file.flush();
+    }
+}
+
+#[cfg(feature = "log-facade")]
+mod facade {
+    use super::Level;
+    use ::log::{LevelFilter, Metadata, Record};
+    use std::sync::Mutex;
+
+    /// Whether [`Facade`] is the `log` crate's logger
+    static SET: Mutex<bool> = Mutex::new(false);
+
+    /// Forwards the `log` crate's messages to the installed logger
+    struct Facade;
+
+    impl ::log::Log for Facade {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            super::enabled(metadata.level().into(), metadata.target())
+        }
+
+        fn log(&self, record: &Record<'_>) {
+            super::log(record.level().into(), record.target(), *record.args());
+        }
+
+        fn flush(&self) {
+            super::flush();
+        }
+    }
+
+    impl From<::log::Level> for Level {
+        fn from(level: ::log::Level) -> Self {
+            match level {
+                ::log::Level::Error => Level::Error,
+                ::log::Level::Warn => Level::Warn,
+                ::log::Level::Info => Level::Info,
+                ::log::Level::Debug => Level::Debug,
+                ::log::Level::Trace => Level::Trace,
+            }
+        }
+    }
+
+    /// Set [`Facade`] as the `log` crate's logger the first time a logger is
+    /// installed, and let through messages up to `max` from then on
+    pub(super) fn install(max: Level) -> Result<(), ::log::SetLoggerError> {
+        let mut set = SET.lock().unwrap_or_else(|e| e.into_inner());
+        if !*set {
+            ::log::set_boxed_logger(Box::new(Facade))?;
+            *set = true;
+        }
+        ::log::set_max_level(match max {
+            Level::Error => LevelFilter::Error,
+            Level::Warn => LevelFilter::Warn,
+            Level::Info => LevelFilter::Info,
+            Level::Debug => LevelFilter::Debug,
+            Level::Trace => LevelFilter::Trace,
+        });
+        Ok(())
+    }
+}
+
+/// Log a message at a [`Level`](crate::log::Level) with the current module as
+/// its target, formatting the rest of the arguments like [`format!`]
+///
+/// ```
+/// # use futility::{log, log::Level};
+/// let port = 8080;
+/// log!(Level::Info, "listening on port {port}");
+/// log!(Level::Debug, target: "http", "accepted a connection");
+/// ```
+#[macro_export]
+macro_rules! log {
+    ($level:expr, target: $target:expr, $($arg:tt)+) => {
+        $crate::log::log($level, $target, ::std::format_args!($($arg)+))
+    };
+    ($level:expr, $($arg:tt)+) => {
+        $crate::log::log($level, ::std::module_path!(), ::std::format_args!($($arg)+))
+    };
+}
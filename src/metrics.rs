@@ -0,0 +1,267 @@
+//! Counting things over a run without a metrics stack
+//!
+//! Small tools often want a few numbers at the end of a run, like how many
+//! files were processed or how many requests failed, without setting up an
+//! exporter. [`counter!`](crate::counter) and [`gauge!`](crate::gauge) get a
+//! global [`Counter`] or [`Gauge`] by name, created the first time it's used
+//! and looked up only once per call site, so updating one is a single atomic
+//! operation.
+//!
+//! [`snapshot`] reads every metric at once, and
+//! [`Terminate::report_metrics`] prints it when the program exits. The
+//! snapshot is also in the [`ExitInfo`] given to `at_exit_with`.
+//!
+//! ```
+//! # use futility::{counter, gauge, metrics};
+//! for file in ["a.txt", "b.txt", "c.txt"] {
+//!     counter!("files").inc();
+//!     gauge!("queued").set(2);
+//! }
+//! counter!("bytes").add(1024);
+//!
+//! let snapshot = metrics::snapshot();
+//! assert_eq!(snapshot.counter("files"), Some(3));
+//! assert_eq!(snapshot.gauge("queued"), Some(2));
+//! assert_eq!(snapshot.to_string(), "bytes: 1024\nfiles: 3\nqueued: 2");
+//! ```
+//!
+//! [`Terminate::report_metrics`]: crate::terminate::Terminate::report_metrics
+//! [`ExitInfo`]: crate::terminate::ExitInfo
+
+use crate::lock::Mutex;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::atomic::{AtomicI64, AtomicU64, Ordering},
+};
+
+/// A count that only goes up
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    /// A counter starting at zero
+    pub const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    /// Add one to the count
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Add `n` to the count
+    pub fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// The current count
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A value that can go up and down, like the length of a queue
+#[derive(Debug, Default)]
+pub struct Gauge(AtomicI64);
+
+impl Gauge {
+    /// A gauge starting at zero
+    pub const fn new() -> Self {
+        Self(AtomicI64::new(0))
+    }
+
+    /// Set the value
+    pub fn set(&self, value: i64) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+
+    /// Add one to the value
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Take one from the value
+    pub fn dec(&self) {
+        self.add(-1);
+    }
+
+    /// Add `n` to the value, which can be negative
+    pub fn add(&self, n: i64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// The current value
+    pub fn get(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+struct Registry {
+    counters: BTreeMap<String, &'static Counter>,
+    gauges: BTreeMap<String, &'static Gauge>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    counters: BTreeMap::new(),
+    gauges: BTreeMap::new(),
+});
+
+/// The global counter called `name`, created if it doesn't exist yet.
+/// [`counter!`](crate::counter) only calls this once per call site.
+pub fn counter(name: &str) -> &'static Counter {
+    let mut registry = REGISTRY.lock();
+    match registry.counters.get(name) {
+        Some(counter) => counter,
+        // Metrics live for the rest of the program, so they are leaked to be
+        // handed out without a lock
+        None => registry
+            .counters
+            .entry(name.into())
+            .or_insert(Box::leak(Box::default())),
+    }
+}
+
+/// The global gauge called `name`, created if it doesn't exist yet.
+/// [`gauge!`](crate::gauge) only calls this once per call site.
+pub fn gauge(name: &str) -> &'static Gauge {
+    let mut registry = REGISTRY.lock();
+    match registry.gauges.get(name) {
+        Some(gauge) => gauge,
+        None => registry
+            .gauges
+            .entry(name.into())
+            .or_insert(Box::leak(Box::default())),
+    }
+}
+
+/// The value of every global counter and gauge at one point in time
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Snapshot {
+    /// Each counter and its count, sorted by name
+    pub counters: Vec<(String, u64)>,
+    /// Each gauge and its value, sorted by name
+    pub gauges: Vec<(String, i64)>,
+}
+
+impl Snapshot {
+    /// The count of the counter called `name`, if there is one
+    pub fn counter(&self, name: &str) -> Option<u64> {
+        lookup(&self.counters, name)
+    }
+
+    /// The value of the gauge called `name`, if there is one
+    pub fn gauge(&self, name: &str) -> Option<i64> {
+        lookup(&self.gauges, name)
+    }
+
+    /// Whether there are no metrics
+    pub fn is_empty(&self) -> bool {
+        self.counters.is_empty() && self.gauges.is_empty()
+    }
+}
+
+fn lookup<T: Copy>(metrics: &[(String, T)], name: &str) -> Option<T> {
+    metrics
+        .binary_search_by(|(metric, _)| metric.as_str().cmp(name))
+        .ok()
+        .map(|index| metrics[index].1)
+}
+
+impl fmt::Display for Snapshot {
+    /// Each metric as `name: value` on its own line, sorted by name
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counters = self
+            .counters
+            .iter()
+            .map(|(name, count)| (name, count as &dyn fmt::Display));
+        let gauges = self
+            .gauges
+            .iter()
+            .map(|(name, value)| (name, value as &dyn fmt::Display));
+        let mut metrics = counters.chain(gauges).collect::<Vec<_>>();
+        metrics.sort_by_key(|(name, _)| *name);
+        for (i, (name, value)) in metrics.into_iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{name}: {value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Read every global counter and gauge
+pub fn snapshot() -> Snapshot {
+    let registry = REGISTRY.lock();
+    Snapshot {
+        counters: registry
+            .counters
+            .iter()
+            .map(|(name, counter)| (name.clone(), counter.get()))
+            .collect(),
+        gauges: registry
+            .gauges
+            .iter()
+            .map(|(name, gauge)| (name.clone(), gauge.get()))
+            .collect(),
+    }
+}
+
+/// Set every global counter and gauge back to zero
+pub fn reset() {
+    let registry = REGISTRY.lock();
+    for counter in registry.counters.values() {
+        counter.0.store(0, Ordering::Relaxed);
+    }
+    for gauge in registry.gauges.values() {
+        gauge.set(0);
+    }
+}
+
+/// The global [`Counter`](crate::metrics::Counter) with a name, created the
+/// first time it's used. With a string literal for the name it's only looked
+/// up the first time this call site runs.
+///
+/// ```
+/// # use futility::counter;
+/// counter!("requests").inc();
+/// let route = "/health";
+/// counter!(format!("requests {route}")).inc();
+/// assert_eq!(counter!("requests").get(), 1);
+/// ```
+#[macro_export]
+macro_rules! counter {
+    ($name:literal) => {{
+        static COUNTER: ::std::sync::OnceLock<&'static $crate::metrics::Counter> =
+            ::std::sync::OnceLock::new();
+        *COUNTER.get_or_init(|| $crate::metrics::counter($name))
+    }};
+    ($name:expr) => {
+        $crate::metrics::counter(&$name)
+    };
+}
+
+/// The global [`Gauge`](crate::metrics::Gauge) with a name, created the first
+/// time it's used. With a string literal for the name it's only looked up the
+/// first time this call site runs.
+///
+/// ```
+/// # use futility::gauge;
+/// gauge!("connections").inc();
+/// gauge!("connections").inc();
+/// gauge!("connections").dec();
+/// assert_eq!(gauge!("connections").get(), 1);
+/// ```
+#[macro_export]
+macro_rules! gauge {
+    ($name:literal) => {{
+        static GAUGE: ::std::sync::OnceLock<&'static $crate::metrics::Gauge> =
+            ::std::sync::OnceLock::new();
+        *GAUGE.get_or_init(|| $crate::metrics::gauge($name))
+    }};
+    ($name:expr) => {
+        $crate::metrics::gauge(&$name)
+    };
+}
@@ -0,0 +1,134 @@
+//! Initializing a value once when initializing can fail
+//!
+//! [`OnceLock`] and [`LazyLock`](std::sync::LazyLock) expect initialization
+//! to succeed, so a fallible one has to panic or cache the error forever.
+//! [`OnceResult`] and [`TryLazy`] only keep the value once initialization
+//! succeeds. A failure is returned to whoever was initializing, and the next
+//! access tries again, which is what's wanted for something like a connection
+//! or a config file that might not be there yet.
+//!
+//! ```
+//! # use futility::once::TryLazy;
+//! # use std::{fs, io};
+//! static CONFIG: TryLazy<String, io::Error> =
+//!     TryLazy::new(|| fs::read_to_string("/does/not/exist/config.toml"));
+//!
+//! assert!(CONFIG.force().is_err());
+//! assert!(CONFIG.get().is_none());
+//! ```
+//!
+//! With the `retry` feature [`TryLazy::force_retry`] retries a failed
+//! initialization with a [`RetryPolicy`] before giving up on that access.
+
+use crate::lock::Mutex;
+#[cfg(feature = "retry")]
+use crate::retry::{self, RetryPolicy};
+use std::{fmt, marker::PhantomData, sync::OnceLock};
+
+/// A cell that is written to once, by an initialization that can fail
+pub struct OnceResult<T> {
+    value: OnceLock<T>,
+    /// Held while initializing so only one thread initializes at a time
+    init: Mutex<()>,
+}
+
+impl<T> OnceResult<T> {
+    /// An empty cell
+    pub const fn new() -> Self {
+        Self {
+            value: OnceLock::new(),
+            init: Mutex::new(()),
+        }
+    }
+
+    /// The value, if the cell has been initialized
+    pub fn get(&self) -> Option<&T> {
+        self.value.get()
+    }
+
+    /// The value, initializing the cell with `init` if it's empty. If `init`
+    /// fails the cell is left empty and the error returned. Other threads
+    /// trying to initialize the cell at the same time wait for this one to
+    /// finish, and if it failed try again themselves.
+    pub fn get_or_try_init<E>(&self, init: impl FnOnce() -> Result<T, E>) -> Result<&T, E> {
+        if let Some(value) = self.value.get() {
+            return Ok(value);
+        }
+        let _init = self.init.lock();
+        if let Some(value) = self.value.get() {
+            return Ok(value);
+        }
+        let value = init()?;
+        Ok(self.value.get_or_init(|| value))
+    }
+
+    /// Take the value out of the cell, if it has been initialized
+    pub fn into_inner(self) -> Option<T> {
+        self.value.into_inner()
+    }
+}
+
+impl<T> Default for OnceResult<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OnceResult<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get() {
+            Some(value) => f.debug_tuple("OnceResult").field(value).finish(),
+            None => f.write_str("OnceResult(<uninit>)"),
+        }
+    }
+}
+
+/// A value initialized on first access by a function that can fail, which is
+/// called again on the next access if it does
+pub struct TryLazy<T, E, F = fn() -> Result<T, E>> {
+    cell: OnceResult<T>,
+    init: F,
+    error: PhantomData<fn() -> E>,
+}
+
+impl<T, E, F> TryLazy<T, E, F>
+where
+    F: Fn() -> Result<T, E>,
+{
+    /// A value initialized by `init`
+    pub const fn new(init: F) -> Self {
+        Self {
+            cell: OnceResult::new(),
+            init,
+            error: PhantomData,
+        }
+    }
+
+    /// The value, initializing it if it hasn't been yet
+    pub fn force(&self) -> Result<&T, E> {
+        self.cell.get_or_try_init(&self.init)
+    }
+
+    /// The value, initializing it if it hasn't been yet and retrying a failed
+    /// initialization until it succeeds or `policy` gives up, the same as
+    /// [`retry`](crate::retry::retry) does
+    #[cfg(feature = "retry")]
+    pub fn force_retry(&self, policy: impl RetryPolicy) -> Result<&T, E> {
+        self.cell
+            .get_or_try_init(|| retry::retry(policy, &self.init))
+    }
+
+    /// The value, if it has been initialized
+    pub fn get(&self) -> Option<&T> {
+        self.cell.get()
+    }
+}
+
+impl<T: fmt::Debug, E, F> fmt::Debug for TryLazy<T, E, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.cell.get() {
+            Some(value) => f.debug_tuple("TryLazy").field(value).finish(),
+            None => f.write_str("TryLazy(<uninit>)"),
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Inspecting panics
+//!
+//! The payload of a panic is a `dyn Any` that is almost always a `&str` or a
+//! `String`, so anything that wants to report a panic ends up downcasting it.
+//! [`payload_as_str`] and [`panic_message`] do this once, and
+//! [`PanicDetails`] collects everything worth reporting about a panic into an
+//! owned value that can be kept after the hook returns, such as to send it
+//! somewhere or turn it into an error.
+//!
+//! [`catch`] is a panic boundary for code that shouldn't be able to take the
+//! rest of the program down with it, such as plugin callbacks or functions
+//! called over FFI. It turns a panic into an error created from its
+//! [`PanicDetails`] instead of printing it to stderr.
+//!
+//! ```
+//! # use futility::panic::{self, PanicDetails};
+//! fn call_plugin(plugin: fn(u32) -> u32, input: u32) -> Result<u32, PanicDetails> {
+//!     panic::catch(|| plugin(input))
+//! }
+//!
+//! assert_eq!(call_plugin(|input| input + 1, 1).unwrap(), 2);
+//! let err = call_plugin(|_| panic!("plugin bug"), 1).unwrap_err();
+//! assert_eq!(err.message, "plugin bug");
+//! ```
+//!
+//! [`scoped_hook`] sets the panic hook for panics on the current thread while
+//! a function runs, and goes back to the previous hook afterwards, even if
+//! the function panicked. Setting the process wide hook with
+//! [`std::panic::set_hook`] is fine for a program's `main` but not for a
+//! library or a test, which would change it for every other thread as well.
+//!
+//! ```
+//! # use futility::panic::{self, PanicDetails};
+//! # use std::sync::{Arc, Mutex};
+//! let caught = Arc::new(Mutex::new(Vec::new()));
+//! let hooked = caught.clone();
+//! let res = panic::scoped_hook(
+//!     move |info| hooked.lock().unwrap().push(PanicDetails::from_hook(info)),
+//!     || std::panic::catch_unwind(|| panic!("quietly")),
+//! );
+//! assert!(res.is_err());
+//! assert_eq!(caught.lock().unwrap()[0].message, "quietly");
+//! ```
+//!
+//! ```
+//! # use futility::panic::PanicDetails;
+//! # use std::{panic, sync::Mutex};
+//! static LAST: Mutex<Option<PanicDetails>> = Mutex::new(None);
+//!
+//! panic::set_hook(Box::new(|info| {
+//!     *LAST.lock().unwrap() = Some(PanicDetails::from_hook(info));
+//! }));
+//! let _ = panic::catch_unwind(|| panic!("oh no"));
+//! let _ = panic::take_hook();
+//!
+//! let details = LAST.lock().unwrap().take().unwrap();
+//! assert_eq!(details.message, "oh no");
+//! assert!(details.location.is_some());
+//! ```
+
+#[cfg(feature = "terminate")]
+use crate::terminate::PanicPayload;
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    cell::{Cell, RefCell},
+    error::Error,
+    fmt,
+    panic::{self, AssertUnwindSafe, PanicHookInfo, UnwindSafe},
+    rc::Rc,
+    sync::Once,
+    thread,
+};
+
+/// The message shown for a panic whose payload isn't a string
+pub const NON_STRING_PAYLOAD: &str = "Box<dyn Any>";
+
+/// The payload of a panic as a string, if it's a `&str` or a `String`, which
+/// it is for every panic created with [`panic!`]
+pub fn payload_as_str(payload: &dyn Any) -> Option<&str> {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+}
+
+/// The message of the panic a hook was called for, if its payload is a string
+pub fn panic_message<'a>(info: &'a PanicHookInfo<'_>) -> Option<&'a str> {
+    payload_as_str(info.payload())
+}
+
+/// Everything known about a panic, owned so it can outlive the hook or
+/// `catch_unwind` that saw it
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct PanicDetails {
+    /// The message the panic was created with, or [`NON_STRING_PAYLOAD`]
+    pub message: String,
+    /// Where the panic happened formatted as `file:line:column`, if known
+    pub location: Option<String>,
+    /// The name of the thread that panicked, if it had one
+    pub thread: Option<String>,
+    /// The backtrace of the panic, which is only captured when enabled with
+    /// `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` and is only meaningful when
+    /// captured from a hook
+    pub backtrace: Backtrace,
+}
+
+impl PanicDetails {
+    /// Collect the details of the panic a hook was called for on the current
+    /// thread
+    pub fn from_hook(info: &PanicHookInfo<'_>) -> Self {
+        Self {
+            message: panic_message(info).unwrap_or(NON_STRING_PAYLOAD).into(),
+            location: info.location().map(ToString::to_string),
+            thread: thread::current().name().map(Into::into),
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    /// Collect what can be known from the payload of a panic caught on the
+    /// current thread, which doesn't include where it happened
+    pub fn from_payload(payload: &dyn Any) -> Self {
+        Self {
+            message: payload_as_str(payload).unwrap_or(NON_STRING_PAYLOAD).into(),
+            location: None,
+            thread: thread::current().name().map(Into::into),
+            backtrace: Backtrace::disabled(),
+        }
+    }
+}
+
+impl From<&PanicHookInfo<'_>> for PanicDetails {
+    fn from(info: &PanicHookInfo<'_>) -> Self {
+        Self::from_hook(info)
+    }
+}
+
+#[cfg(feature = "terminate")]
+impl From<&PanicPayload<'_>> for PanicDetails {
+    fn from(panic: &PanicPayload<'_>) -> Self {
+        Self {
+            message: panic.message().unwrap_or(NON_STRING_PAYLOAD).into(),
+            location: panic.location().map(Into::into),
+            thread: panic.thread_name().map(Into::into),
+            backtrace: Backtrace::capture(),
+        }
+    }
+}
+
+impl fmt::Display for PanicDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("panicked")?;
+        if let Some(thread) = &self.thread {
+            write!(f, " in thread '{thread}'")?;
+        }
+        if let Some(location) = &self.location {
+            write!(f, " at {location}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl Error for PanicDetails {}
+
+/// Run `f`, turning a panic into an error created from its [`PanicDetails`]
+///
+/// While `f` runs, a panic on the current thread isn't passed on to the panic
+/// hook, so it isn't printed to stderr, and its details are captured for the
+/// error instead. This is done with a hook installed the first time this is
+/// called, on top of whatever hook is set at the time. If the hook is replaced
+/// afterwards with [`std::panic::set_hook`], panics are printed by the new
+/// hook and the error only has the panic's message.
+pub fn catch<T, E>(f: impl FnOnce() -> T + UnwindSafe) -> Result<T, E>
+where
+    E: From<PanicDetails>,
+{
+    catch_asserted(f)
+}
+
+/// [`catch`] for a function returning a `Result`, flattening the panic and the
+/// function's own error into one
+pub fn catch_result<T, E>(f: impl FnOnce() -> Result<T, E> + UnwindSafe) -> Result<T, E>
+where
+    E: From<PanicDetails>,
+{
+    catch(f).and_then(|result| result)
+}
+
+/// [`catch`] for a function that isn't [`UnwindSafe`], such as one that
+/// captures a `&mut` reference or a `RefCell`. Calling it asserts that
+/// anything `f` captures is either not used again after a panic, or is
+/// still in a state that's fine to use, the same as wrapping it in
+/// [`AssertUnwindSafe`].
+pub fn catch_asserted<T, E>(f: impl FnOnce() -> T) -> Result<T, E>
+where
+    E: From<PanicDetails>,
+{
+    quiet_hook();
+    let _catching = Catching::start();
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        let details = CAUGHT
+            .with(|caught| caught.borrow_mut().take())
+            .unwrap_or_else(|| PanicDetails::from_payload(&*payload));
+        E::from(details)
+    })
+}
+
+thread_local! {
+    static CATCHING: Cell<usize> = const { Cell::new(0) };
+    static CAUGHT: RefCell<Option<PanicDetails>> = const { RefCell::new(None) };
+}
+
+/// Marks the current thread as inside of [`catch`] until it's dropped
+///
+/// The details captured by the hook are scoped to one call, so a panic that
+/// `f` catches itself doesn't leave them behind for a later panic that never
+/// reaches the hook, such as one resumed with [`panic::resume_unwind`].
+struct Catching {
+    previous: Option<PanicDetails>,
+}
+
+impl Catching {
+    fn start() -> Self {
+        CATCHING.with(|catching| catching.set(catching.get() + 1));
+        Self {
+            previous: CAUGHT.with(|caught| caught.borrow_mut().take()),
+        }
+    }
+}
+
+impl Drop for Catching {
+    fn drop(&mut self) {
+        CATCHING.with(|catching| catching.set(catching.get() - 1));
+        let previous = self.previous.take();
+        CAUGHT.with(|caught| *caught.borrow_mut() = previous);
+    }
+}
+
+/// Install a panic hook, on top of whatever hook is currently set, that
+/// captures the details of panics on threads inside of [`catch`] instead of
+/// passing them on
+fn quiet_hook() {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let original_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| match CATCHING.with(Cell::get) {
+            0 => original_hook(info),
+            _ => CAUGHT.with(|caught| *caught.borrow_mut() = Some(PanicDetails::from_hook(info))),
+        }));
+    });
+}
+
+/// A panic hook set by [`scoped_hook`]
+type ScopedHook = Rc<dyn Fn(&PanicHookInfo<'_>)>;
+
+thread_local! {
+    static SCOPED: RefCell<Vec<ScopedHook>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Run `f` with `hook` as the panic hook for panics on the current thread,
+/// going back to the previous hook once `f` returns or panics
+///
+/// A panic in `f` is passed to `hook` before it unwinds out of here. Other
+/// threads, including ones spawned by `f`, keep using the process wide hook,
+/// and calls can be nested, with the innermost hook being used. Panics inside
+/// of [`catch`] are still captured by it rather than passed to `hook`.
+///
+/// This is done with a hook installed the first time this is called, on top
+/// of whatever hook is set at the time. If the hook is replaced afterwards
+/// with [`std::panic::set_hook`], the new hook is used instead of `hook`.
+pub fn scoped_hook<T>(hook: impl Fn(&PanicHookInfo<'_>) + 'static, f: impl FnOnce() -> T) -> T {
+    /// Takes the hook back off of the stack, including while unwinding
+    struct Pop;
+
+    impl Drop for Pop {
+        fn drop(&mut self) {
+            SCOPED.with(|scoped| scoped.borrow_mut().pop());
+        }
+    }
+
+    dispatch_hook();
+    SCOPED.with(|scoped| scoped.borrow_mut().push(Rc::new(hook)));
+    let _pop = Pop;
+    f()
+}
+
+/// Install a panic hook, on top of whatever hook is currently set, that
+/// passes panics on threads inside of [`scoped_hook`] to their scoped hook
+fn dispatch_hook() {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let original_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let scoped = match CATCHING.with(Cell::get) {
+                0 => SCOPED.with(|scoped| scoped.borrow().last().cloned()),
+                _ => None,
+            };
+            match scoped {
+                Some(hook) => hook(info),
+                None => original_hook(info),
+            }
+        }));
+    });
+}
@@ -0,0 +1,317 @@
+//! A thread pool that shuts down on a deadline and reports what went wrong
+//!
+//! Dropping most thread pools either blocks until every queued job has run or
+//! leaves the workers to finish in the background, and a job that panics or
+//! fails is only noticed if something waits for its result. A [`ThreadPool`]
+//! keeps the error of every job that panicked or failed, and
+//! [`ThreadPool::shutdown`] stops taking new jobs, runs the queued ones until
+//! its [`Deadline`], cancels any that are left, joins the workers, and returns
+//! everything that went wrong as a [`MultiError`] indexed by job.
+//!
+//! ```
+//! # use futility::{budget::Deadline, pool::{JobError, ThreadPool}};
+//! # use std::time::Duration;
+//! let pool = ThreadPool::new(2).unwrap();
+//! for n in 0..4 {
+//!     pool.try_execute(move || match n {
+//!         2 => Err(format!("job {n} failed")),
+//!         _ => Ok(()),
+//!     })
+//!     .unwrap();
+//! }
+//!
+//! let err = pool.shutdown(Deadline::after(Duration::from_secs(5))).unwrap_err();
+//! assert!(matches!(&err.errors[..], [(2, JobError::Failed(_))]));
+//! ```
+//!
+//! [`Terminate::thread_pool`] shuts a pool down when the program exits, along
+//! with any tracked workers.
+//!
+//! [`Terminate::thread_pool`]: crate::terminate::Terminate::thread_pool
+
+use crate::{
+    budget::Deadline,
+    lock::{Mutex, MutexGuard},
+    panic::{self, PanicDetails},
+    result::MultiError,
+};
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt, io,
+    sync::{Arc, Condvar},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use thiserror::Error;
+
+/// Why a job given to a [`ThreadPool`] didn't finish cleanly
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum JobError {
+    /// The job panicked
+    #[error("the job {0}")]
+    Panicked(PanicDetails),
+    /// The job returned an error
+    #[error("the job failed: {0}")]
+    Failed(Box<dyn Error + Send + Sync>),
+    /// The job was still queued when the pool shut down, so it never ran
+    #[error("the job was cancelled before it started")]
+    Cancelled,
+    /// The job was still running at the shutdown deadline, so its worker was
+    /// left running instead of being joined
+    #[error("the job was still running at the shutdown deadline")]
+    Unfinished,
+}
+
+impl From<PanicDetails> for JobError {
+    fn from(details: PanicDetails) -> Self {
+        Self::Panicked(details)
+    }
+}
+
+/// The error returned when giving a job to a [`ThreadPool`] that has shut
+/// down
+#[derive(Debug, Error)]
+#[error("the thread pool has shut down")]
+pub struct ShutDown;
+
+type Job = Box<dyn FnOnce() -> Result<(), JobError> + Send>;
+
+/// A fixed number of worker threads running jobs in the order they were
+/// given. Clones share the same pool.
+///
+/// If the last clone is dropped without calling [`ThreadPool::shutdown`] the
+/// workers run every queued job and then exit without being joined, and the
+/// errors of the jobs are lost.
+#[derive(Clone)]
+pub struct ThreadPool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    shared: Arc<Shared>,
+    workers: Mutex<Vec<JoinHandle<()>>>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// Notified when a job is queued or the pool stops taking jobs
+    queued: Condvar,
+    /// Notified when a worker finishes a job or exits
+    finished: Condvar,
+}
+
+struct State {
+    queue: VecDeque<(usize, Job)>,
+    /// The index of the job each worker is running
+    running: Vec<usize>,
+    /// The index the next job is given
+    next: usize,
+    errors: Vec<(usize, JobError)>,
+    open: bool,
+    /// How many workers haven't exited
+    workers: usize,
+}
+
+impl ThreadPool {
+    /// Start a pool of `threads` workers, named `futility-pool-{n}`
+    ///
+    /// # Panics
+    ///
+    /// If `threads` is zero.
+    pub fn new(threads: usize) -> io::Result<Self> {
+        assert!(threads > 0, "a thread pool needs at least one thread");
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                running: Vec::new(),
+                next: 0,
+                errors: Vec::new(),
+                open: true,
+                workers: 0,
+            }),
+            queued: Condvar::new(),
+            finished: Condvar::new(),
+        });
+        let pool = Self {
+            inner: Arc::new(Inner {
+                shared: Arc::clone(&shared),
+                workers: Mutex::new(Vec::with_capacity(threads)),
+            }),
+        };
+        for n in 0..threads {
+            let worker = Arc::clone(&shared);
+            let thread = thread::Builder::new()
+                .name(format!("futility-pool-{n}"))
+                .spawn(move || worker.work())?;
+            shared.state.lock().workers += 1;
+            pool.inner.workers.lock().push(thread);
+        }
+        Ok(pool)
+    }
+
+    /// Queue `job` to run on a worker, returning the index its error will
+    /// have if it panics
+    pub fn execute(&self, job: impl FnOnce() + Send + 'static) -> Result<usize, ShutDown> {
+        self.try_execute(move || {
+            job();
+            Ok::<_, JobError>(())
+        })
+    }
+
+    /// Queue `job` to run on a worker, returning the index its error will
+    /// have if it panics or fails
+    pub fn try_execute<E>(
+        &self,
+        job: impl FnOnce() -> Result<(), E> + Send + 'static,
+    ) -> Result<usize, ShutDown>
+    where
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        let job: Job = Box::new(move || {
+            panic::catch_asserted::<_, JobError>(job)?.map_err(|err| JobError::Failed(err.into()))
+        });
+        let mut state = self.inner.shared.state.lock();
+        if !state.open {
+            return Err(ShutDown);
+        }
+        let index = state.next;
+        state.next += 1;
+        state.queue.push_back((index, job));
+        self.inner.shared.queued.notify_one();
+        Ok(index)
+    }
+
+    /// How many jobs are waiting for a worker
+    pub fn queued(&self) -> usize {
+        self.inner.shared.state.lock().queue.len()
+    }
+
+    /// Whether the pool is still taking jobs
+    pub fn is_open(&self) -> bool {
+        self.inner.shared.state.lock().open
+    }
+
+    /// Stop taking jobs, run the queued ones until `deadline`, and join the
+    /// workers, returning the error of every job that panicked or failed
+    /// since the pool started
+    ///
+    /// Jobs still queued at the deadline are cancelled, and workers still
+    /// running a job at the deadline are left running instead of being
+    /// joined. Both are reported with the rest of the errors. Calling this
+    /// again, from any clone, only reports what went wrong since the last
+    /// call.
+    pub fn shutdown(&self, deadline: Deadline) -> Result<(), MultiError<JobError>> {
+        self.shutdown_with(deadline, false)
+    }
+
+    /// Stop taking jobs, cancel the queued ones, and wait until `deadline`
+    /// for the jobs that are already running, the same as
+    /// [`ThreadPool::shutdown`] otherwise
+    pub fn shutdown_now(&self, deadline: Deadline) -> Result<(), MultiError<JobError>> {
+        self.shutdown_with(deadline, true)
+    }
+
+    fn shutdown_with(&self, deadline: Deadline, cancel: bool) -> Result<(), MultiError<JobError>> {
+        let shared = &*self.inner.shared;
+        let mut state = shared.state.lock();
+        state.open = false;
+        shared.queued.notify_all();
+        if cancel {
+            state.cancel_queued();
+        }
+        while state.workers > 0 && !deadline.is_expired() {
+            state = wait(&shared.finished, state, Some(deadline.remaining()));
+        }
+        state.cancel_queued();
+        let running = std::mem::take(&mut state.running);
+        state.errors.extend(
+            running
+                .into_iter()
+                .map(|index| (index, JobError::Unfinished)),
+        );
+        let mut errors = std::mem::take(&mut state.errors);
+        let exited = state.workers == 0;
+        drop(state);
+
+        let workers = std::mem::take(&mut *self.inner.workers.lock());
+        for worker in workers {
+            if exited || worker.is_finished() {
+                let _ = worker.join();
+            }
+        }
+        errors.sort_by_key(|(index, _)| *index);
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(MultiError { errors }),
+        }
+    }
+}
+
+impl fmt::Debug for ThreadPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.inner.shared.state.lock();
+        f.debug_struct("ThreadPool")
+            .field("workers", &state.workers)
+            .field("queued", &state.queue.len())
+            .field("open", &state.open)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.shared.state.lock().open = false;
+        self.shared.queued.notify_all();
+    }
+}
+
+impl State {
+    fn cancel_queued(&mut self) {
+        let cancelled = self
+            .queue
+            .drain(..)
+            .map(|(index, _)| (index, JobError::Cancelled));
+        self.errors.extend(cancelled);
+    }
+}
+
+impl Shared {
+    fn work(&self) {
+        let mut state = self.state.lock();
+        loop {
+            if let Some((index, job)) = state.queue.pop_front() {
+                state.running.push(index);
+                drop(state);
+                let res = job();
+                state = self.state.lock();
+                state.running.retain(|&running| running != index);
+                if let Err(err) = res {
+                    state.errors.push((index, err));
+                }
+                self.finished.notify_all();
+            } else if state.open {
+                state = wait(&self.queued, state, None);
+            } else {
+                break;
+            }
+        }
+        state.workers -= 1;
+        self.finished.notify_all();
+    }
+}
+
+fn wait<'a>(
+    condvar: &Condvar,
+    state: MutexGuard<'a, State>,
+    timeout: Option<Duration>,
+) -> MutexGuard<'a, State> {
+    match timeout {
+        Some(timeout) => condvar
+            .wait_timeout(state, timeout)
+            .map(|(state, _)| state)
+            .unwrap_or_else(|e| e.into_inner().0),
+        None => condvar.wait(state).unwrap_or_else(|e| e.into_inner()),
+    }
+}
@@ -0,0 +1,15 @@
+//! The crate's traits, to be glob imported
+//!
+//! ```
+//! use futility::prelude::*;
+//! ```
+
+#[cfg(feature = "env")]
+pub use crate::env::FromEnv;
+pub use crate::error::{ContextExt, ErrorChainExt};
+#[cfg(feature = "exit")]
+pub use crate::exit::ExitCoded;
+#[cfg(feature = "result")]
+pub use crate::result::{FinallyExt, ResultExt, ResultIteratorExt};
+#[cfg(feature = "retry")]
+pub use crate::retry::{RetryIf, RetryPolicy};
@@ -0,0 +1,410 @@
+//! Making sure child processes don't outlive the code that started them
+//!
+//! A [`Child`] that is dropped keeps running, so a `?` or a panic between
+//! spawning a process and waiting on it leaks the process. [`ChildGuard`]
+//! wraps a [`Child`] and stops it when dropped: by killing it, or on Unix by
+//! sending it a signal such as [`Signal::TERM`] and only killing it if it
+//! hasn't exited within a grace period. Either way the child is then waited
+//! on so that it doesn't linger as a zombie.
+//!
+//! ```no_run
+//! # use futility::{process::ChildGuard, signal::Signal};
+//! # use std::{io, process::Command, time::Duration};
+//! # fn main() -> io::Result<()> {
+//! let server = ChildGuard::spawn(Command::new("my-server").arg("--port=8080"))?
+//!     .signal(Signal::TERM)
+//!     .grace(Duration::from_secs(2));
+//! run_integration_tests()?;
+//! // `my-server` is sent SIGTERM here, or if the tests fail
+//! # Ok(())
+//! # }
+//! # fn run_integration_tests() -> io::Result<()> { Ok(()) }
+//! ```
+//!
+//! [`ChildGuard::detach`] gives the [`Child`] back for when it should be left
+//! running after all.
+//!
+//! [`Supervisor`] keeps a command running, restarting it according to a
+//! [`RetryPolicy`] whenever it fails, until it exits successfully or a
+//! [`ShutdownToken`] is triggered. If the policy gives up the most recent
+//! failures are returned together in a [`SupervisorError`].
+//!
+//! ```no_run
+//! # use futility::{process::Supervisor, retry::{ExponentialBackoff, RetryPolicy}};
+//! # use std::{process::Command, time::Duration};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! Supervisor::new(
+//!     Command::new("my-worker"),
+//!     ExponentialBackoff::default().max_elapsed(Duration::from_secs(60)),
+//! )
+//! .run()?;
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(unix)]
+use crate::signal::Signal;
+use crate::{
+    exit::ExitCoded,
+    log::{self, Level},
+    retry::RetryPolicy,
+    shutdown::{self, ShutdownToken},
+    terminate::ChildFailed,
+};
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt, io,
+    ops::{Deref, DerefMut},
+    process::{Child, Command, ExitStatus},
+    thread,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+/// How often a child is checked for having exited
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How many of the most recent failures a [`SupervisorError`] keeps
+const KEPT_FAILURES: usize = 10;
+
+/// A child process that is stopped and waited on when dropped
+///
+/// The guard dereferences to the [`Child`], so its `id`, `stdin`, and so on
+/// can be used as usual.
+#[derive(Debug)]
+pub struct ChildGuard {
+    child: Option<Child>,
+    #[cfg(unix)]
+    signal: Option<Signal>,
+    grace: Duration,
+}
+
+impl ChildGuard {
+    /// Guard `child`, killing it when dropped
+    pub fn new(child: Child) -> Self {
+        Self {
+            child: Some(child),
+            #[cfg(unix)]
+            signal: None,
+            grace: Duration::from_secs(5),
+        }
+    }
+
+    /// Spawn `command` and guard the child, killing it when dropped
+    pub fn spawn(command: &mut Command) -> io::Result<Self> {
+        command.spawn().map(Self::new)
+    }
+
+    /// Send `signal` to the child when dropped rather than killing it right
+    /// away, killing it only if it doesn't exit within the grace period
+    #[cfg(unix)]
+    pub fn signal(mut self, signal: Signal) -> Self {
+        self.signal = Some(signal);
+        self
+    }
+
+    /// How long the child has to exit after being sent the signal set with
+    /// [`ChildGuard::signal`] before it is killed, which is five seconds by
+    /// default
+    pub fn grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    /// Stop guarding the child, leaving it running when this is dropped
+    pub fn detach(mut self) -> Child {
+        self.child.take().expect("the child is only taken on drop")
+    }
+
+    /// Wait for the child to exit for up to `timeout`, returning its status if
+    /// it did
+    pub fn wait_timeout(&mut self, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+        wait_timeout(self, timeout)
+    }
+
+    /// Stop the child the same way dropping the guard does, returning how it
+    /// exited
+    pub fn stop(mut self) -> io::Result<ExitStatus> {
+        let status = self.stop_child();
+        self.child = None;
+        status
+    }
+
+    fn stop_child(&mut self) -> io::Result<ExitStatus> {
+        let child = self
+            .child
+            .as_mut()
+            .expect("the child is only taken on drop");
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        #[cfg(unix)]
+        if let Some(signal) = self.signal {
+            send(child, signal)?;
+            if let Some(status) = wait_timeout(child, self.grace)? {
+                return Ok(status);
+            }
+        }
+        // The child may have exited since it was checked, which is fine
+        let _ = child.kill();
+        child.wait()
+    }
+}
+
+impl Deref for ChildGuard {
+    type Target = Child;
+
+    fn deref(&self) -> &Child {
+        self.child
+            .as_ref()
+            .expect("the child is only taken on drop")
+    }
+}
+
+impl DerefMut for ChildGuard {
+    fn deref_mut(&mut self) -> &mut Child {
+        self.child
+            .as_mut()
+            .expect("the child is only taken on drop")
+    }
+}
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        if self.child.is_some() {
+            if let Err(err) = self.stop_child() {
+                eprintln!("failed to stop child process {}: {err}", self.id());
+            }
+        }
+    }
+}
+
+/// Wait for `child` to exit for up to `timeout`, returning its status if it
+/// did. A timeout too large to add to the current time, such as
+/// `Duration::MAX`, waits for as long as it takes the child to exit.
+pub fn wait_timeout(child: &mut Child, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    let Some(deadline) = Instant::now().checked_add(timeout) else {
+        return child.wait().map(Some);
+    };
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(None);
+        }
+        thread::sleep(remaining.min(POLL_INTERVAL));
+    }
+}
+
+/// Send `signal` to `child`
+#[cfg(unix)]
+pub fn send(child: &Child, signal: Signal) -> io::Result<()> {
+    // SAFETY: kill has no memory safety requirements. The child hasn't been
+    // waited on, so its pid can't have been reused yet.
+    match unsafe { libc::kill(child.id() as libc::pid_t, signal.as_raw()) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Runs a command, restarting it whenever it fails
+#[derive(Debug)]
+pub struct Supervisor<P> {
+    command: Command,
+    policy: P,
+    shutdown: ShutdownToken,
+    grace: Duration,
+    reset_after: Duration,
+}
+
+impl<P: RetryPolicy> Supervisor<P> {
+    /// Supervise `command`, restarting it after each failure for as long as
+    /// `policy` allows. It's stopped when the process wide shutdown token
+    /// from [`Terminate::handle_signals`] is triggered.
+    ///
+    /// [`Terminate::handle_signals`]: crate::terminate::Terminate::handle_signals
+    pub fn new(command: Command, policy: P) -> Self {
+        Self {
+            command,
+            policy,
+            shutdown: shutdown::global(),
+            grace: Duration::from_secs(5),
+            reset_after: Duration::from_secs(60),
+        }
+    }
+
+    /// Start the policy over, forgetting earlier failures, once the command
+    /// has run for `reset_after` before failing. This is a minute by default,
+    /// so a command that fails now and then is only given up on if it fails
+    /// many times in a row.
+    pub fn reset_after(mut self, reset_after: Duration) -> Self {
+        self.reset_after = reset_after;
+        self
+    }
+
+    /// Stop the command once `shutdown` is triggered rather than the process
+    /// wide shutdown token
+    pub fn shutdown_token(mut self, shutdown: ShutdownToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// How long the command has to exit after being sent `SIGTERM` on
+    /// shutdown before it is killed, which is five seconds by default. On
+    /// Windows it is killed right away.
+    pub fn grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    /// Run the command until it exits successfully or the shutdown token is
+    /// triggered, returning the most recent failures if the policy gives up
+    /// first
+    pub fn run(self) -> Result<(), SupervisorError> {
+        let Self {
+            mut command,
+            policy,
+            shutdown,
+            grace,
+            reset_after,
+        } = self;
+        let program = command.get_program().to_string_lossy().into_owned();
+        let mut delays = policy.delays();
+        let mut failures = VecDeque::new();
+        let mut failed = 0;
+        loop {
+            if shutdown.is_triggered() {
+                return Ok(());
+            }
+            let started = Instant::now();
+            let failure = match run_once(&mut command, &shutdown, grace) {
+                Ok(None) => return Ok(()),
+                Ok(Some(status)) => Failure::Exited(ChildFailed {
+                    command: program.clone(),
+                    status,
+                }),
+                Err(err) => Failure::Spawn(err),
+            };
+            log::log(
+                Level::Warn,
+                module_path!(),
+                format_args!("supervised command {failure}"),
+            );
+            // The command was healthy for long enough that the failures
+            // before this run shouldn't count towards giving up on it
+            if started.elapsed() >= reset_after {
+                delays.reset();
+                failures.clear();
+                failed = 0;
+            }
+            if failures.len() == KEPT_FAILURES {
+                failures.pop_front();
+            }
+            failures.push_back(failure);
+            failed += 1;
+            match delays.next() {
+                Some(delay) if !shutdown.wait_timeout(delay) => {}
+                Some(_) => return Ok(()),
+                None => {
+                    return Err(SupervisorError {
+                        command: program,
+                        failed,
+                        failures: failures.into(),
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// Run `command` once, returning its status if it failed
+fn run_once(
+    command: &mut Command,
+    shutdown: &ShutdownToken,
+    grace: Duration,
+) -> io::Result<Option<ExitStatus>> {
+    let mut guard = ChildGuard::spawn(command)?.grace(grace);
+    #[cfg(unix)]
+    {
+        guard = guard.signal(Signal::TERM);
+    }
+    loop {
+        if let Some(status) = guard.wait_timeout(POLL_INTERVAL)? {
+            return Ok((!status.success()).then_some(status));
+        }
+        if shutdown.is_triggered() {
+            guard.stop()?;
+            return Ok(None);
+        }
+    }
+}
+
+/// One way a supervised command failed
+#[derive(Debug, Error)]
+pub enum Failure {
+    /// The command couldn't be started
+    #[error("failed to start: {0}")]
+    Spawn(#[source] io::Error),
+    /// The command exited unsuccessfully
+    #[error("exited with {}", .0.status)]
+    Exited(#[source] ChildFailed),
+}
+
+/// The error returned when a [`Supervisor`] gave up restarting a command
+#[derive(Debug)]
+pub struct SupervisorError {
+    /// The program that was run
+    pub command: String,
+    /// How many times the command failed in a row, since it last ran for as
+    /// long as [`Supervisor::reset_after`]
+    pub failed: u32,
+    /// The most recent failures, in order. Only the last ten are kept.
+    pub failures: Vec<Failure>,
+}
+
+impl fmt::Display for SupervisorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` failed {} times, giving up",
+            self.command, self.failed
+        )?;
+        if self.failures.len() < self.failed as usize {
+            write!(f, ", the last {} were", self.failures.len())?;
+        }
+        f.write_str(":")?;
+        for failure in &self.failures {
+            write!(f, "\n  {failure}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for SupervisorError {
+    /// The last failure's cause, so that a [`ChildFailed`] can be found by
+    /// [`Terminate::propagate_child_status`](crate::terminate::Terminate::propagate_child_status)
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self.failures.last()? {
+            Failure::Spawn(err) => Some(err),
+            Failure::Exited(err) => Some(err),
+        }
+    }
+}
+
+impl ExitCoded for SupervisorError {
+    /// The code of the last time the command exited, or `1` if it couldn't be
+    /// started
+    fn code(&self) -> u8 {
+        self.failures
+            .iter()
+            .rev()
+            .find_map(|failure| match failure {
+                Failure::Exited(err) => Some(err.code()),
+                Failure::Spawn(_) => None,
+            })
+            .unwrap_or(1)
+    }
+}
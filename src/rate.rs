@@ -0,0 +1,229 @@
+//! Limiting how often something happens
+//!
+//! A [`Limiter`] is a token bucket: it holds up to a number of tokens that
+//! refill at a steady rate, and each call takes one. Bursts up to the size of
+//! the bucket go through right away, after which calls are paced to the
+//! refill rate. [`Limiter::try_acquire`] says whether a token was free,
+//! [`Limiter::acquire`] blocks until one is, and [`Limiter::acquire_async`]
+//! waits for one in async code. This keeps retries against a shared service
+//! from piling up no matter how many callers are retrying at once.
+//!
+//! ```
+//! # use futility::{rate::Limiter, retry::{self, FixedDelay, RetryPolicy}};
+//! # use std::time::Duration;
+//! // At most 10 attempts a second, across every caller
+//! static ATTEMPTS: Limiter = Limiter::new(10, Duration::from_secs(1));
+//!
+//! let res: Result<(), &str> = retry::retry(FixedDelay::new(Duration::ZERO).max_attempts(3), || {
+//!     ATTEMPTS.acquire();
+//!     Err("service unavailable")
+//! });
+//! assert!(res.is_err());
+//! ```
+//!
+//! A [`Debouncer`] lets one call through and then holds back every call for
+//! an interval, counting how many it held back. It's for things that are
+//! fine to do now and then but not every time they're asked for, such as
+//! logging the same error from a hot loop or sending a heartbeat.
+//!
+//! ```
+//! # use futility::rate::Debouncer;
+//! # use std::time::Duration;
+//! let debouncer = Debouncer::new(Duration::from_secs(60));
+//! let mut logged = Vec::new();
+//! for _ in 0..100 {
+//!     debouncer.call(|suppressed| logged.push(format!("disk full ({suppressed} more)")));
+//! }
+//! assert_eq!(logged, ["disk full (0 more)"]);
+//! ```
+
+use crate::{
+    lock::Mutex,
+    retry::{Sleeper, ThreadSleeper},
+};
+use std::{
+    fmt, thread,
+    time::{Duration, Instant},
+};
+
+/// A token bucket that limits how often calls can go through
+pub struct Limiter {
+    /// How many tokens the bucket holds when full
+    capacity: u32,
+    /// How long it takes for one token to refill
+    interval: Duration,
+    state: Mutex<Bucket>,
+}
+
+struct Bucket {
+    /// Whole tokens available, as of `updated`
+    tokens: u32,
+    /// When `tokens` was last brought up to date, or `None` if the bucket
+    /// hasn't been used yet and is full
+    updated: Option<Instant>,
+}
+
+impl Limiter {
+    /// A limiter letting `tokens` calls through every `per`, all of which can
+    /// be used at once. Zero tokens is treated as one.
+    pub const fn new(tokens: u32, per: Duration) -> Self {
+        let tokens = if tokens == 0 { 1 } else { tokens };
+        Self {
+            capacity: tokens,
+            interval: per.checked_div(tokens).expect("tokens is not zero"),
+            state: Mutex::new(Bucket {
+                tokens,
+                updated: None,
+            }),
+        }
+    }
+
+    /// Take a token if one is available, returning whether one was
+    pub fn try_acquire(&self) -> bool {
+        self.reserve().is_none()
+    }
+
+    /// Take a token, blocking until one is available
+    pub fn acquire(&self) {
+        while let Some(wait) = self.reserve() {
+            thread::sleep(wait);
+        }
+    }
+
+    /// Take a token, waiting with the [`ThreadSleeper`] until one is
+    /// available
+    pub async fn acquire_async(&self) {
+        self.acquire_async_with(ThreadSleeper).await;
+    }
+
+    /// Take a token, waiting with `sleeper` until one is available
+    pub async fn acquire_async_with(&self, sleeper: impl Sleeper) {
+        while let Some(wait) = self.reserve() {
+            sleeper.sleep(wait).await;
+        }
+    }
+
+    /// How many tokens are available right now
+    pub fn available(&self) -> u32 {
+        let mut bucket = self.state.lock();
+        self.refill(&mut bucket, Instant::now());
+        bucket.tokens
+    }
+
+    /// Take a token, or return how long until the next one refills
+    fn reserve(&self) -> Option<Duration> {
+        let now = Instant::now();
+        let mut bucket = self.state.lock();
+        let updated = self.refill(&mut bucket, now);
+        match bucket.tokens {
+            0 => Some((updated + self.interval).saturating_duration_since(now)),
+            _ => {
+                bucket.tokens -= 1;
+                None
+            }
+        }
+    }
+
+    /// Add the tokens refilled since the bucket was last updated, returning
+    /// when the token that is partly refilled started refilling
+    fn refill(&self, bucket: &mut Bucket, now: Instant) -> Instant {
+        let updated = bucket.updated.unwrap_or(now);
+        let room = self.capacity - bucket.tokens;
+        let refilled = match self.interval.is_zero() {
+            true => u128::from(room),
+            false => now.duration_since(updated).as_nanos() / self.interval.as_nanos(),
+        };
+        // A full bucket doesn't keep refilling, so its time starts again now
+        let updated = match refilled >= u128::from(room) {
+            true => {
+                bucket.tokens = self.capacity;
+                now
+            }
+            false => {
+                let refilled = refilled as u32;
+                bucket.tokens += refilled;
+                updated + self.interval * refilled
+            }
+        };
+        bucket.updated = Some(updated);
+        updated
+    }
+}
+
+impl fmt::Debug for Limiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Limiter")
+            .field("capacity", &self.capacity)
+            .field("interval", &self.interval)
+            .field("available", &self.available())
+            .finish()
+    }
+}
+
+/// Lets a call through and then holds back calls for an interval
+#[derive(Debug)]
+pub struct Debouncer {
+    interval: Duration,
+    state: Mutex<Debounce>,
+}
+
+#[derive(Debug)]
+struct Debounce {
+    /// When a call was last let through
+    last: Option<Instant>,
+    /// How many calls have been held back since
+    suppressed: u64,
+}
+
+impl Debouncer {
+    /// A debouncer holding back calls for `interval` after each one it lets
+    /// through
+    pub const fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            state: Mutex::new(Debounce {
+                last: None,
+                suppressed: 0,
+            }),
+        }
+    }
+
+    /// Whether a call should go through now. If it should, it counts as the
+    /// call let through and the interval starts again.
+    pub fn ready(&self) -> bool {
+        self.check().is_some()
+    }
+
+    /// Call `f` if a call should go through now, passing it how many calls
+    /// were held back since the last one that went through. Returns whether
+    /// `f` was called.
+    pub fn call(&self, f: impl FnOnce(u64)) -> bool {
+        match self.check() {
+            Some(suppressed) => {
+                f(suppressed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// How many calls have been held back since the last one let through
+    pub fn suppressed(&self) -> u64 {
+        self.state.lock().suppressed
+    }
+
+    fn check(&self) -> Option<u64> {
+        let now = Instant::now();
+        let mut state = self.state.lock();
+        match state.last {
+            Some(last) if now.duration_since(last) < self.interval => {
+                state.suppressed += 1;
+                None
+            }
+            _ => {
+                state.last = Some(now);
+                Some(std::mem::take(&mut state.suppressed))
+            }
+        }
+    }
+}
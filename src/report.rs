@@ -0,0 +1,157 @@
+//! Printing an error along with what caused it
+//!
+//! An error's `Display` output usually only says what went wrong at the top
+//! level, like "failed to load config", with the reason why hidden in its
+//! chain of [`source`](Error::source)s. [`Report`] displays the whole chain,
+//! either as a numbered block:
+//!
+//! ```text
+//! failed to load config
+//!
+//! caused by:
+//!   1: failed to read config.toml
+//!   2: No such file or directory (os error 2)
+//! ```
+//!
+//! or, with [`Report::compact`], on a single line joined by `: ` for logs.
+//!
+//! ```
+//! # use futility::report::Report;
+//! # use std::{error::Error, fmt, io};
+//! #[derive(Debug)]
+//! struct LoadConfig(io::Error);
+//!
+//! impl fmt::Display for LoadConfig {
+//!     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+//!         f.write_str("failed to load config")
+//!     }
+//! }
+//!
+//! impl Error for LoadConfig {
+//!     fn source(&self) -> Option<&(dyn Error + 'static)> {
+//!         Some(&self.0)
+//!     }
+//! }
+//!
+//! let err = LoadConfig(io::Error::new(io::ErrorKind::NotFound, "config.toml is missing"));
+//! assert_eq!(
+//!     Report::new(&err).to_string(),
+//!     "failed to load config\n\ncaused by:\n  1: config.toml is missing"
+//! );
+//! assert_eq!(
+//!     Report::new(&err).compact().to_string(),
+//!     "failed to load config: config.toml is missing"
+//! );
+//! ```
+//!
+//! A boxed error can be reported with `Report::new(&*err)`, as `&dyn Error`
+//! implements [`Error`] while `Box<dyn Error>` doesn't.
+
+use crate::tty;
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display},
+};
+
+/// Displays an error and its chain of sources
+#[derive(Clone, Copy)]
+pub struct Report<E> {
+    error: E,
+    compact: bool,
+    color: bool,
+}
+
+impl<E: Error> Report<E> {
+    /// Report `error` as a numbered block, colored if stderr is colored
+    pub fn new(error: E) -> Self {
+        Self {
+            error,
+            compact: false,
+            color: tty::output_style().stderr_color,
+        }
+    }
+
+    /// Report the error and its sources on one line, joined by `: `
+    pub fn compact(mut self) -> Self {
+        self.compact = true;
+        self
+    }
+
+    /// Color the report or not, rather than deciding based on
+    /// [`output_style`](crate::tty::output_style)
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// The error being reported
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    /// Stop reporting the error and take it back
+    pub fn into_error(self) -> E {
+        self.error
+    }
+}
+
+impl<E: Error> From<E> for Report<E> {
+    fn from(error: E) -> Self {
+        Self::new(error)
+    }
+}
+
+/// Start and end `text` with an ANSI style if `color` is set
+struct Styled<'a, T> {
+    text: T,
+    style: &'a str,
+    color: bool,
+}
+
+impl<T: Display> Display for Styled<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.color {
+            true => write!(f, "\x1b[{}m{}\x1b[0m", self.style, self.text),
+            false => write!(f, "{}", self.text),
+        }
+    }
+}
+
+impl<E> Report<E> {
+    fn styled<'a, T: Display>(&self, text: T, style: &'a str) -> Styled<'a, T> {
+        Styled {
+            text,
+            style,
+            color: self.color,
+        }
+    }
+}
+
+impl<E: Error> Display for Report<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.styled(&self.error, "1"))?;
+        let sources = std::iter::successors(self.error.source(), |&err| err.source());
+        if self.compact {
+            for source in sources {
+                write!(f, ": {source}")?;
+            }
+            return Ok(());
+        }
+        for (i, source) in sources.enumerate() {
+            if i == 0 {
+                write!(f, "\n\n{}", self.styled("caused by:", "1;33"))?;
+            }
+            write!(f, "\n  {}: {source}", self.styled(i + 1, "2"))?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: Error> Debug for Report<E> {
+    /// The same as `Display`, so that returning a `Report` from `main`
+    /// prints the whole chain. `Report` doesn't implement [`Error`] itself so
+    /// that it can't end up in another error's chain and be printed twice.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
@@ -0,0 +1,251 @@
+//! Combinators for `Result` that tie in the rest of the crate
+//!
+//! [`ResultExt`] adds methods to every `Result` for the things that otherwise
+//! need a `match` in the middle of an expression: looking at an error on its
+//! way past, logging it with the installed [logger](crate::log), adding
+//! context to it, or retrying the operation that produced it with a
+//! [`RetryPolicy`]. It's in the [prelude](crate::prelude) along with the
+//! crate's other traits.
+//!
+//! ```
+//! # use futility::prelude::*;
+//! # use futility::retry::FixedDelay;
+//! # use std::{cell::Cell, error::Error, time::Duration};
+//! fn connect(attempts: &Cell<u32>) -> Result<&'static str, String> {
+//!     attempts.set(attempts.get() + 1);
+//!     match attempts.get() {
+//!         3 => Ok("connected"),
+//!         n => Err(format!("attempt {n} refused")),
+//!     }
+//! }
+//!
+//! let attempts = Cell::new(0);
+//! let conn = connect(&attempts)
+//!     .or_retry(FixedDelay::new(Duration::ZERO).max_attempts(3), || connect(&attempts))
+//!     .log_err("app::db")
+//!     .context_with::<Box<dyn Error>, _, _>(|| "failed to connect to the database");
+//! assert_eq!(conn.unwrap(), "connected");
+//! ```
+//!
+//! Collecting an iterator of results into `Result<Vec<T>, E>` stops at the
+//! first error, which is no good for reporting everything wrong with some
+//! input. [`ResultIteratorExt::collect_all_errors`] goes through the whole
+//! iterator instead and returns every error in a [`MultiError`].
+//!
+//! ```
+//! # use futility::prelude::*;
+//! let ports = ["80", "http", "443", ""]
+//!     .iter()
+//!     .map(|port| port.parse::<u16>())
+//!     .collect_all_errors::<Vec<_>>();
+//! let err = ports.unwrap_err();
+//! assert_eq!(err.errors.len(), 2);
+//! assert_eq!(err.errors[0].0, 1);
+//! assert_eq!(err.errors[1].0, 3);
+//! ```
+//!
+//! [`FinallyExt`] runs cleanup after a result is produced whether it's `Ok`
+//! or `Err`, for a chain of calls where a whole [`try_!`](crate::try_) block
+//! would be too much.
+//!
+//! ```
+//! # use futility::prelude::*;
+//! # use std::cell::Cell;
+//! let connections = Cell::new(1);
+//! let port = "http"
+//!     .parse::<u16>()
+//!     .finally(|| connections.set(connections.get() - 1))
+//!     .finally_with(|res| assert!(res.is_err()));
+//! assert!(port.is_err());
+//! assert_eq!(connections.get(), 0);
+//! ```
+
+use crate::{
+    log::{self, Level},
+    retry::{self, RetryPolicy},
+};
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display},
+    panic::Location,
+    time::Instant,
+};
+
+/// Extra methods for `Result`
+pub trait ResultExt<T, E>: Sized {
+    /// Call `f` with the error, if there is one, and return the result
+    /// unchanged
+    fn tap_err(self, f: impl FnOnce(&E)) -> Self;
+
+    /// Log the error, if there is one, at [`Level::Error`] with `target` and
+    /// return the result unchanged
+    fn log_err(self, target: &str) -> Self
+    where
+        E: Display;
+
+    /// Log the error, if there is one, at [`Level::Warn`] with the target
+    /// `futility::result` along with where this was called, and carry on
+    /// without it, for errors that shouldn't stop the program
+    #[track_caller]
+    fn warn_on_err(self) -> Option<T>
+    where
+        E: Display;
+
+    /// Replace the error, if there is one, with one made from the message
+    /// `"{context}: {error}"`, where the context is only created if there's
+    /// an error. The new error can be any type that can be created from a
+    /// `String`, such as `Box<dyn Error>`.
+    fn context_with<E2, C, F>(self, context: F) -> Result<T, E2>
+    where
+        E: Display,
+        E2: From<String>,
+        C: Display,
+        F: FnOnce() -> C;
+
+    /// If this is an error, keep calling `operation` until it succeeds or
+    /// `policy` gives up, the same as [`retry`](crate::retry::retry) does.
+    /// The attempt that produced this result counts as the first one.
+    fn or_retry(
+        self,
+        policy: impl RetryPolicy,
+        operation: impl FnMut() -> Result<T, E>,
+    ) -> Result<T, E>;
+}
+
+impl<T, E> ResultExt<T, E> for Result<T, E> {
+    fn tap_err(self, f: impl FnOnce(&E)) -> Self {
+        if let Err(err) = &self {
+            f(err);
+        }
+        self
+    }
+
+    fn log_err(self, target: &str) -> Self
+    where
+        E: Display,
+    {
+        self.tap_err(|err| log::log(Level::Error, target, format_args!("{err}")))
+    }
+
+    #[track_caller]
+    fn warn_on_err(self) -> Option<T>
+    where
+        E: Display,
+    {
+        match self {
+            Ok(value) => Some(value),
+            Err(err) => {
+                let location = Location::caller();
+                log::log(
+                    Level::Warn,
+                    module_path!(),
+                    format_args!("{location}: {err}"),
+                );
+                None
+            }
+        }
+    }
+
+    fn context_with<E2, C, F>(self, context: F) -> Result<T, E2>
+    where
+        E: Display,
+        E2: From<String>,
+        C: Display,
+        F: FnOnce() -> C,
+    {
+        self.map_err(|err| E2::from(format!("{}: {err}", context())))
+    }
+
+    fn or_retry(
+        self,
+        policy: impl RetryPolicy,
+        operation: impl FnMut() -> Result<T, E>,
+    ) -> Result<T, E> {
+        match self {
+            Ok(value) => Ok(value),
+            Err(err) => retry::retry_failed(policy, |_: &E| true, Instant::now(), err, operation),
+        }
+    }
+}
+
+/// Extra methods for iterators of `Result`s
+pub trait ResultIteratorExt<T, E>: Iterator<Item = Result<T, E>> + Sized {
+    /// Collect every `Ok` value if there were no errors, otherwise return
+    /// every error along with its index in the iterator. Unlike collecting
+    /// into a `Result`, the whole iterator is always consumed.
+    fn collect_all_errors<C>(self) -> Result<C, MultiError<E>>
+    where
+        C: FromIterator<T>;
+}
+
+impl<I, T, E> ResultIteratorExt<T, E> for I
+where
+    I: Iterator<Item = Result<T, E>>,
+{
+    fn collect_all_errors<C>(self) -> Result<C, MultiError<E>>
+    where
+        C: FromIterator<T>,
+    {
+        let mut errors = Vec::new();
+        let values = self
+            .enumerate()
+            .filter_map(|(index, res)| res.map_err(|err| errors.push((index, err))).ok())
+            .collect();
+        match errors.is_empty() {
+            true => Ok(values),
+            false => Err(MultiError { errors }),
+        }
+    }
+}
+
+/// Every error from an iterator, returned by
+/// [`collect_all_errors`](ResultIteratorExt::collect_all_errors)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultiError<E> {
+    /// Each error along with its index in the iterator, in order
+    pub errors: Vec<(usize, E)>,
+}
+
+impl<E> MultiError<E> {
+    /// The errors without their indices
+    pub fn into_errors(self) -> impl Iterator<Item = E> {
+        self.errors.into_iter().map(|(_, err)| err)
+    }
+}
+
+impl<E: Display> Display for MultiError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.errors.len() {
+            1 => f.write_str("1 error:")?,
+            n => write!(f, "{n} errors:")?,
+        }
+        for (index, err) in &self.errors {
+            write!(f, "\n  [{index}] {err}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: Debug + Display> Error for MultiError<E> {}
+
+/// `finally` for `Result`s
+pub trait FinallyExt<T, E>: Sized {
+    /// Call `f` whether this is `Ok` or `Err` and return the result unchanged
+    fn finally(self, f: impl FnOnce()) -> Self;
+
+    /// Call `f` with the result, whether it's `Ok` or `Err`, and return it
+    /// unchanged
+    fn finally_with(self, f: impl FnOnce(&Self)) -> Self;
+}
+
+impl<T, E> FinallyExt<T, E> for Result<T, E> {
+    fn finally(self, f: impl FnOnce()) -> Self {
+        f();
+        self
+    }
+
+    fn finally_with(self, f: impl FnOnce(&Self)) -> Self {
+        f(&self);
+        self
+    }
+}
@@ -0,0 +1,844 @@
+//! Retrying fallible operations
+//!
+//! [`retry`] calls an operation until it succeeds or the [`RetryPolicy`] gives
+//! up, sleeping between attempts for as long as the policy says to. Policies
+//! start from a delay strategy like [`FixedDelay`] or [`ExponentialBackoff`]
+//! and are limited or adjusted by chaining [`RetryPolicy::max_attempts`],
+//! [`RetryPolicy::max_elapsed`], [`RetryPolicy::deadline`], and
+//! [`RetryPolicy::jitter`]. Without a limit a policy retries forever.
+//!
+//! ```
+//! # use futility::retry::{retry, ExponentialBackoff, Jitter, RetryPolicy};
+//! # use std::{cell::Cell, time::Duration};
+//! let attempts = Cell::new(0);
+//! let res = retry(
+//!     ExponentialBackoff::new(Duration::from_millis(1))
+//!         .jitter(Jitter::Full)
+//!         .max_attempts(3),
+//!     || {
+//!         attempts.set(attempts.get() + 1);
+//!         match attempts.get() {
+//!             3 => Ok("fetched"),
+//!             _ => Err("connection reset"),
+//!         }
+//!     },
+//! );
+//! assert_eq!(res, Ok("fetched"));
+//! ```
+//!
+//! A policy is also an iterator of the delays it waits for, through
+//! [`Delays`], for loops that don't fit any of the functions here:
+//! `for delay in ExponentialBackoff::default().max_attempts(5)`.
+//!
+//! Not every error is worth retrying, so [`retry_if`] takes a [`RetryIf`]
+//! predicate and returns the first error it rejects right away.
+//!
+//! Waiting for something to become ready, rather than retrying something that
+//! failed, is done with [`loop_until`], which backs off between checks and
+//! stops early when a [`ShutdownToken`] is triggered.
+//!
+//! The same policies work in async code with [`retry_async`]. Waiting between
+//! attempts is done by a [`Sleeper`], which by default is [`ThreadSleeper`]
+//! as it works with any executor, at the cost of a thread per sleep. With
+//! [`retry_async_with`] the timer of the runtime in use should be passed
+//! instead: the `tokio`, `async-std`, and `smol` features add a sleeper for
+//! each, and any function returning a future, such as `tokio::time::sleep`,
+//! implements [`Sleeper`] too.
+//!
+//! ```ignore
+//! let body = retry_async_with(
+//!     ExponentialBackoff::default().max_attempts(5),
+//!     tokio::time::sleep,
+//!     |err: &reqwest::Error| err.is_timeout(),
+//!     || async { reqwest::get(url).await?.text().await },
+//! )
+//! .await?;
+//! ```
+
+use crate::{budget::Deadline, shutdown::ShutdownToken};
+#[cfg(feature = "futures-core")]
+use std::fmt;
+use std::{
+    collections::hash_map::RandomState,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    ops::ControlFlow,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+/// Decides how long to wait before each retry and when to give up
+pub trait RetryPolicy {
+    /// How long to wait before the next attempt, given how many attempts have
+    /// failed so far and how long it has been since the first one started, or
+    /// `None` to give up
+    fn next_delay(&mut self, attempts: u32, elapsed: Duration) -> Option<Duration>;
+
+    /// Give up once `attempts` attempts, including the first, have failed
+    fn max_attempts(self, attempts: u32) -> MaxAttempts<Self>
+    where
+        Self: Sized,
+    {
+        MaxAttempts {
+            policy: self,
+            attempts,
+        }
+    }
+
+    /// Give up rather than wait past `elapsed` since the first attempt started
+    fn max_elapsed(self, elapsed: Duration) -> MaxElapsed<Self>
+    where
+        Self: Sized,
+    {
+        MaxElapsed {
+            policy: self,
+            elapsed,
+        }
+    }
+
+    /// Give up rather than wait past `deadline`
+    fn deadline(self, deadline: Deadline) -> WithinDeadline<Self>
+    where
+        Self: Sized,
+    {
+        WithinDeadline {
+            policy: self,
+            deadline,
+        }
+    }
+
+    /// Randomize each delay so that many clients retrying at once don't all
+    /// retry at the same moment
+    fn jitter(self, jitter: Jitter) -> Jittered<Self>
+    where
+        Self: Sized,
+    {
+        Jittered {
+            policy: self,
+            jitter,
+        }
+    }
+
+    /// The policy's delays as an iterator, which ends when the policy gives
+    /// up, for driving a loop by hand. The time elapsed is counted from when
+    /// this is called.
+    fn delays(self) -> Delays<Self>
+    where
+        Self: Sized,
+    {
+        Delays::starting_at(self, Instant::now())
+    }
+}
+
+/// The delays of a [`RetryPolicy`], created with [`RetryPolicy::delays`] or by
+/// iterating over one of the policies in this module
+///
+/// Each item is the delay before the next attempt, with the first being the
+/// delay after the first attempt failed. [`retry`], [`loop_until`], and the
+/// rest of the crate go through a policy this way too, so a loop written by
+/// hand waits exactly as long as they would.
+///
+/// ```
+/// # use futility::retry::{ExponentialBackoff, RetryPolicy};
+/// # use std::time::Duration;
+/// let delays = ExponentialBackoff::new(Duration::from_millis(100))
+///     .max_attempts(4)
+///     .into_iter()
+///     .map(|delay| delay.as_millis())
+///     .collect::<Vec<_>>();
+/// assert_eq!(delays, [100, 200, 400]);
+///
+/// for delay in ExponentialBackoff::new(Duration::from_millis(1)).delays().take(3) {
+///     // try something, then
+///     std::thread::sleep(delay);
+/// }
+/// ```
+///
+/// In async code [`Delays::next_async`] waits out each delay before returning
+/// it. It takes `&mut self` rather than `self` so it can be used in a `while
+/// let` loop. With the `futures-core` feature [`Delays::into_stream`] turns
+/// the delays into a [`Stream`](futures_core::Stream) that does the same.
+#[derive(Clone, Debug)]
+pub struct Delays<P> {
+    policy: P,
+    attempts: u32,
+    start: Instant,
+}
+
+impl<P: RetryPolicy> Delays<P> {
+    /// The delays of `policy` with the time elapsed counted from `start`
+    pub(crate) fn starting_at(policy: P, start: Instant) -> Self {
+        Self {
+            policy,
+            attempts: 0,
+            start,
+        }
+    }
+
+    /// How many delays have been asked for, which is the number of attempts
+    /// that have failed if one is asked for after each failed attempt
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Start over as if no attempt had failed yet, counting the time elapsed
+    /// from now. This is for something retried over a long time that
+    /// recovers in between, such as a service that ran fine for an hour
+    /// before failing again.
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+        self.start = Instant::now();
+    }
+
+    /// Block the thread for the next delay and return it, or return `None`
+    /// right away if the policy gives up
+    pub fn sleep(&mut self) -> Option<Duration> {
+        let delay = self.next()?;
+        thread::sleep(delay);
+        Some(delay)
+    }
+
+    /// Wait for the next delay with the [`ThreadSleeper`] and return it, or
+    /// return `None` right away if the policy gives up
+    pub async fn next_async(&mut self) -> Option<Duration> {
+        self.next_async_with(&ThreadSleeper).await
+    }
+
+    /// Wait for the next delay with `sleeper` and return it, or return `None`
+    /// right away if the policy gives up
+    pub async fn next_async_with(&mut self, sleeper: &impl Sleeper) -> Option<Duration> {
+        let delay = self.next()?;
+        sleeper.sleep(delay).await;
+        Some(delay)
+    }
+
+    /// A [`Stream`](futures_core::Stream) that waits out each delay with
+    /// `sleeper` before yielding it, and ends when the policy gives up
+    ///
+    /// ```ignore
+    /// let mut delays = ExponentialBackoff::default()
+    ///     .max_attempts(5)
+    ///     .delays()
+    ///     .into_stream(tokio::time::sleep);
+    /// while let Some(delay) = delays.next().await {
+    ///     println!("waited {delay:?}, checking again");
+    /// }
+    /// ```
+    #[cfg(feature = "futures-core")]
+    pub fn into_stream<S: Sleeper>(self, sleeper: S) -> DelayStream<P, S> {
+        DelayStream {
+            delays: self,
+            sleeper,
+            sleeping: None,
+        }
+    }
+}
+
+/// The [`Stream`](futures_core::Stream) returned by [`Delays::into_stream`]
+#[cfg(feature = "futures-core")]
+#[must_use = "streams do nothing unless polled"]
+pub struct DelayStream<P, S: Sleeper> {
+    delays: Delays<P>,
+    sleeper: S,
+    sleeping: Option<(Duration, Pin<Box<S::Sleep>>)>,
+}
+
+#[cfg(feature = "futures-core")]
+impl<P: RetryPolicy + Unpin, S: Sleeper + Unpin> futures_core::Stream for DelayStream<P, S> {
+    type Item = Duration;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Duration>> {
+        let this = self.get_mut();
+        if this.sleeping.is_none() {
+            let Some(delay) = this.delays.next() else {
+                return Poll::Ready(None);
+            };
+            this.sleeping = Some((delay, Box::pin(this.sleeper.sleep(delay))));
+        }
+        let (delay, sleep) = this.sleeping.as_mut().expect("a sleep was just started");
+        let delay = *delay;
+        match sleep.as_mut().poll(cx) {
+            Poll::Ready(()) => {
+                this.sleeping = None;
+                Poll::Ready(Some(delay))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(feature = "futures-core")]
+impl<P: fmt::Debug, S: Sleeper + fmt::Debug> fmt::Debug for DelayStream<P, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DelayStream")
+            .field("delays", &self.delays)
+            .field("sleeper", &self.sleeper)
+            .field("sleeping", &self.sleeping.as_ref().map(|(delay, _)| delay))
+            .finish()
+    }
+}
+
+impl<P: RetryPolicy> Iterator for Delays<P> {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        self.attempts = self.attempts.saturating_add(1);
+        self.policy.next_delay(self.attempts, self.start.elapsed())
+    }
+}
+
+/// Iterating over a policy goes through its [`Delays`]
+macro_rules! delays_into_iter {
+    ($($policy:ident $(<$param:ident>)?),* $(,)?) => {
+        $(
+            impl$(<$param: RetryPolicy>)? IntoIterator for $policy$(<$param>)? {
+                type Item = Duration;
+                type IntoIter = Delays<Self>;
+
+                fn into_iter(self) -> Delays<Self> {
+                    self.delays()
+                }
+            }
+        )*
+    };
+}
+
+delays_into_iter!(
+    FixedDelay,
+    ExponentialBackoff,
+    MaxAttempts<P>,
+    MaxElapsed<P>,
+    WithinDeadline<P>,
+    Jittered<P>,
+);
+
+impl<P> RetryPolicy for &mut P
+where
+    P: RetryPolicy + ?Sized,
+{
+    fn next_delay(&mut self, attempts: u32, elapsed: Duration) -> Option<Duration> {
+        (**self).next_delay(attempts, elapsed)
+    }
+}
+
+/// Decides whether an error is worth retrying
+pub trait RetryIf<E> {
+    /// Whether to retry after `err`
+    fn should_retry(&mut self, err: &E) -> bool;
+}
+
+impl<E, F> RetryIf<E> for F
+where
+    F: FnMut(&E) -> bool,
+{
+    fn should_retry(&mut self, err: &E) -> bool {
+        self(err)
+    }
+}
+
+/// Call `operation` until it succeeds or `policy` gives up, returning the last
+/// error if it does
+pub fn retry<T, E>(
+    policy: impl RetryPolicy,
+    operation: impl FnMut() -> Result<T, E>,
+) -> Result<T, E> {
+    retry_if(policy, |_: &E| true, operation)
+}
+
+/// Call `operation` until it succeeds, `policy` gives up, or it fails with an
+/// error that `retry_if` doesn't want to retry, returning the last error
+pub fn retry_if<T, E>(
+    policy: impl RetryPolicy,
+    retry_if: impl RetryIf<E>,
+    mut operation: impl FnMut() -> Result<T, E>,
+) -> Result<T, E> {
+    let start = Instant::now();
+    match operation() {
+        Ok(value) => Ok(value),
+        Err(err) => retry_failed(policy, retry_if, start, err, operation),
+    }
+}
+
+/// Keep calling `operation` after its first attempt, started at `start`,
+/// failed with `err`
+pub(crate) fn retry_failed<T, E>(
+    policy: impl RetryPolicy,
+    mut retry_if: impl RetryIf<E>,
+    start: Instant,
+    mut err: E,
+    mut operation: impl FnMut() -> Result<T, E>,
+) -> Result<T, E> {
+    let mut delays = Delays::starting_at(policy, start);
+    loop {
+        if !retry_if.should_retry(&err) || delays.sleep().is_none() {
+            return Err(err);
+        }
+        err = match operation() {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+    }
+}
+
+/// Why [`loop_until`] stopped without a value
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum LoopStopped {
+    /// A shutdown was requested
+    #[error("stopped waiting because of a shutdown")]
+    Shutdown,
+    /// The policy gave up
+    #[error("gave up waiting after {attempts} attempts")]
+    GaveUp {
+        /// How many times the loop body ran
+        attempts: u32,
+    },
+}
+
+/// Call `poll` until it returns [`ControlFlow::Break`] with a value, backing
+/// off between calls for as long as `policy` says to. This is for waiting on
+/// something to become ready, like a port being opened or a file being
+/// created, rather than retrying an operation that failed.
+///
+/// The loop stops with [`LoopStopped::Shutdown`] as soon as `shutdown` is
+/// triggered, including while waiting between calls, and with
+/// [`LoopStopped::GaveUp`] if the policy gives up.
+///
+/// ```
+/// # use futility::retry::{self, FixedDelay, LoopStopped, RetryPolicy};
+/// # use futility::shutdown::ShutdownToken;
+/// # use std::{ops::ControlFlow, time::Duration};
+/// let policy = FixedDelay::new(Duration::from_millis(10)).max_attempts(5);
+/// let mut polls = 0;
+/// let ready = retry::loop_until(policy, &ShutdownToken::new(), || {
+///     polls += 1;
+///     match polls {
+///         3 => ControlFlow::Break("ready"),
+///         _ => ControlFlow::Continue(()),
+///     }
+/// });
+/// assert_eq!(ready, Ok("ready"));
+///
+/// let policy = FixedDelay::new(Duration::from_millis(10)).max_attempts(2);
+/// let never = retry::loop_until(policy, &ShutdownToken::new(), || {
+///     ControlFlow::<()>::Continue(())
+/// });
+/// assert_eq!(never, Err(LoopStopped::GaveUp { attempts: 2 }));
+/// ```
+pub fn loop_until<T>(
+    policy: impl RetryPolicy,
+    shutdown: &ShutdownToken,
+    mut poll: impl FnMut() -> ControlFlow<T>,
+) -> Result<T, LoopStopped> {
+    let mut delays = policy.delays();
+    loop {
+        if shutdown.is_triggered() {
+            return Err(LoopStopped::Shutdown);
+        }
+        if let ControlFlow::Break(value) = poll() {
+            return Ok(value);
+        }
+        let Some(delay) = delays.next() else {
+            return Err(LoopStopped::GaveUp {
+                attempts: delays.attempts(),
+            });
+        };
+        if shutdown.wait_timeout(delay) {
+            return Err(LoopStopped::Shutdown);
+        }
+    }
+}
+
+/// Call `operation` and await the future it returns until it succeeds or
+/// `policy` gives up, returning the last error if it does. Delays are waited
+/// out with the [`ThreadSleeper`].
+pub async fn retry_async<T, E, F>(
+    policy: impl RetryPolicy,
+    operation: impl FnMut() -> F,
+) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    retry_async_with(policy, ThreadSleeper, |_: &E| true, operation).await
+}
+
+/// Call `operation` and await the future it returns until it succeeds,
+/// `policy` gives up, or it fails with an error that `retry_if` doesn't want
+/// to retry, returning the last error. Delays are waited out with `sleeper`.
+pub async fn retry_async_with<T, E, F>(
+    policy: impl RetryPolicy,
+    sleeper: impl Sleeper,
+    mut retry_if: impl RetryIf<E>,
+    mut operation: impl FnMut() -> F,
+) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let mut delays = policy.delays();
+    loop {
+        let err = match operation().await {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        if !retry_if.should_retry(&err) || delays.next_async_with(&sleeper).await.is_none() {
+            return Err(err);
+        }
+    }
+}
+
+/// Waits out the delay between attempts in async code
+pub trait Sleeper {
+    /// The future returned by [`Sleeper::sleep`]
+    type Sleep: Future<Output = ()>;
+
+    /// Return a future that completes once `delay` has passed
+    fn sleep(&self, delay: Duration) -> Self::Sleep;
+}
+
+impl<F, S> Sleeper for F
+where
+    F: Fn(Duration) -> S,
+    S: Future<Output = ()>,
+{
+    type Sleep = S;
+
+    fn sleep(&self, delay: Duration) -> S {
+        self(delay)
+    }
+}
+
+/// A [`Sleeper`] that works with any executor by sleeping on a new thread and
+/// waking the task once it's done. Dropping the sleep before it's done wakes
+/// the thread up so it exits right away.
+///
+/// Spawning a thread for every sleep is far more expensive than a runtime's
+/// timer, so this is a last resort for when there is no runtime to lean on.
+/// Use `TokioSleeper`, `AsyncStdSleeper`, or `SmolSleeper` with their features
+/// on, or pass the runtime's sleep function, whenever possible.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreadSleeper;
+
+impl Sleeper for ThreadSleeper {
+    type Sleep = ThreadSleep;
+
+    fn sleep(&self, delay: Duration) -> ThreadSleep {
+        ThreadSleep { delay, state: None }
+    }
+}
+
+/// The future returned by [`ThreadSleeper`]
+#[derive(Debug)]
+pub struct ThreadSleep {
+    delay: Duration,
+    state: Option<Arc<Sleeping>>,
+}
+
+#[derive(Debug, Default)]
+struct Sleeping {
+    state: Mutex<SleepState>,
+    condvar: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct SleepState {
+    done: bool,
+    /// Set when the sleep is dropped before it's done, which wakes the
+    /// thread up early
+    cancelled: bool,
+    waker: Option<Waker>,
+}
+
+impl Sleeping {
+    fn lock(&self) -> MutexGuard<'_, SleepState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Future for ThreadSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let delay = self.delay;
+        let sleeping = self.state.get_or_insert_with(|| {
+            let sleeping = Arc::new(Sleeping::default());
+            let thread = Arc::clone(&sleeping);
+            thread::spawn(move || {
+                let state = thread.lock();
+                let (mut state, _) = thread
+                    .condvar
+                    .wait_timeout_while(state, delay, |state| !state.cancelled)
+                    .unwrap_or_else(|e| e.into_inner());
+                state.done = true;
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            });
+            sleeping
+        });
+        let mut state = sleeping.lock();
+        match state.done {
+            true => Poll::Ready(()),
+            false => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for ThreadSleep {
+    fn drop(&mut self) {
+        if let Some(sleeping) = &self.state {
+            sleeping.lock().cancelled = true;
+            sleeping.condvar.notify_one();
+        }
+    }
+}
+
+/// A [`Sleeper`] that uses [`tokio::time::sleep`], so it has to be used
+/// inside of a tokio runtime with the timer enabled
+#[cfg(feature = "tokio")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioSleeper;
+
+#[cfg(feature = "tokio")]
+impl Sleeper for TokioSleeper {
+    type Sleep = tokio::time::Sleep;
+
+    fn sleep(&self, delay: Duration) -> tokio::time::Sleep {
+        tokio::time::sleep(delay)
+    }
+}
+
+/// A [`Sleeper`] that uses [`async_std::task::sleep`]
+#[cfg(feature = "async-std")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AsyncStdSleeper;
+
+#[cfg(feature = "async-std")]
+impl Sleeper for AsyncStdSleeper {
+    type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+    fn sleep(&self, delay: Duration) -> Self::Sleep {
+        Box::pin(async_std::task::sleep(delay))
+    }
+}
+
+/// A [`Sleeper`] that uses a [`smol::Timer`]
+#[cfg(feature = "smol")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SmolSleeper;
+
+#[cfg(feature = "smol")]
+impl Sleeper for SmolSleeper {
+    type Sleep = SmolSleep;
+
+    fn sleep(&self, delay: Duration) -> SmolSleep {
+        SmolSleep(smol::Timer::after(delay))
+    }
+}
+
+/// The future returned by [`SmolSleeper`]
+#[cfg(feature = "smol")]
+#[derive(Debug)]
+pub struct SmolSleep(smol::Timer);
+
+#[cfg(feature = "smol")]
+impl Future for SmolSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        Pin::new(&mut self.0).poll(cx).map(|_| ())
+    }
+}
+
+/// Wait the same amount of time before every retry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedDelay {
+    delay: Duration,
+}
+
+impl FixedDelay {
+    /// Wait `delay` before every retry
+    pub fn new(delay: Duration) -> Self {
+        Self { delay }
+    }
+}
+
+impl RetryPolicy for FixedDelay {
+    fn next_delay(&mut self, _: u32, _: Duration) -> Option<Duration> {
+        Some(self.delay)
+    }
+}
+
+/// Multiply the delay by a factor after every retry, up to a maximum
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExponentialBackoff {
+    initial: Duration,
+    factor: f64,
+    max_delay: Duration,
+}
+
+impl ExponentialBackoff {
+    /// Wait `initial` before the first retry, doubling the delay after every
+    /// retry up to 30 seconds
+    pub fn new(initial: Duration) -> Self {
+        Self {
+            initial,
+            factor: 2.0,
+            max_delay: Duration::from_secs(30),
+        }
+    }
+
+    /// Multiply the delay by `factor` after every retry
+    pub fn factor(mut self, factor: f64) -> Self {
+        self.factor = factor;
+        self
+    }
+
+    /// Never wait longer than `max_delay` between attempts
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+}
+
+impl Default for ExponentialBackoff {
+    /// Start at 100 milliseconds
+    fn default() -> Self {
+        Self::new(Duration::from_millis(100))
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn next_delay(&mut self, attempts: u32, _: Duration) -> Option<Duration> {
+        let exponent = attempts.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = self.initial.as_secs_f64() * self.factor.powi(exponent);
+        Some(
+            Duration::try_from_secs_f64(delay)
+                .unwrap_or(self.max_delay)
+                .min(self.max_delay),
+        )
+    }
+}
+
+/// A policy that gives up after a number of attempts, created with
+/// [`RetryPolicy::max_attempts`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaxAttempts<P> {
+    policy: P,
+    attempts: u32,
+}
+
+impl<P: RetryPolicy> RetryPolicy for MaxAttempts<P> {
+    fn next_delay(&mut self, attempts: u32, elapsed: Duration) -> Option<Duration> {
+        match attempts < self.attempts {
+            true => self.policy.next_delay(attempts, elapsed),
+            false => None,
+        }
+    }
+}
+
+/// A policy that gives up after an amount of time, created with
+/// [`RetryPolicy::max_elapsed`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaxElapsed<P> {
+    policy: P,
+    elapsed: Duration,
+}
+
+impl<P: RetryPolicy> RetryPolicy for MaxElapsed<P> {
+    fn next_delay(&mut self, attempts: u32, elapsed: Duration) -> Option<Duration> {
+        self.policy.next_delay(attempts, elapsed).filter(|delay| {
+            elapsed
+                .checked_add(*delay)
+                .is_some_and(|t| t <= self.elapsed)
+        })
+    }
+}
+
+/// A policy that gives up at a [`Deadline`], created with
+/// [`RetryPolicy::deadline`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WithinDeadline<P> {
+    policy: P,
+    deadline: Deadline,
+}
+
+impl<P: RetryPolicy> RetryPolicy for WithinDeadline<P> {
+    fn next_delay(&mut self, attempts: u32, elapsed: Duration) -> Option<Duration> {
+        self.policy
+            .next_delay(attempts, elapsed)
+            .filter(|delay| *delay <= self.deadline.remaining())
+    }
+}
+
+/// How much of a delay is randomized
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Jitter {
+    /// Wait anywhere from nothing up to the full delay
+    Full,
+    /// Wait at least half of the delay, with the other half random
+    Equal,
+}
+
+/// A policy with randomized delays, created with [`RetryPolicy::jitter`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Jittered<P> {
+    policy: P,
+    jitter: Jitter,
+}
+
+impl<P: RetryPolicy> RetryPolicy for Jittered<P> {
+    fn next_delay(&mut self, attempts: u32, elapsed: Duration) -> Option<Duration> {
+        let delay = self.policy.next_delay(attempts, elapsed)?;
+        Some(match self.jitter {
+            Jitter::Full => delay.mul_f64(random()),
+            Jitter::Equal => delay / 2 + (delay / 2).mul_f64(random()),
+        })
+    }
+}
+
+/// A random number from 0 to 1. Every `RandomState` is seeded differently,
+/// which is plenty for spreading out retries without a dependency.
+fn random() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Retry a block of code with a [`RetryPolicy`] until it succeeds, evaluating
+/// to a `Result` with the value of the block or the last error. `?` can be used
+/// inside of the block to fail the current attempt. The error type can be
+/// given after the block with `as` if it can't be inferred from how the
+/// result is used.
+///
+/// ```
+/// # use futility::{retry, retry::{FixedDelay, RetryPolicy}};
+/// # use std::{cell::Cell, error::Error, time::Duration};
+/// # fn fetch(attempt: u32) -> Result<&'static str, Box<dyn Error>> {
+/// #     match attempt {
+/// #         3 => Ok("body"),
+/// #         _ => Err("connection reset".into()),
+/// #     }
+/// # }
+/// let attempt = Cell::new(0);
+/// let body = retry!(FixedDelay::new(Duration::ZERO).max_attempts(5), {
+///     attempt.set(attempt.get() + 1);
+///     fetch(attempt.get())?
+/// } as Box<dyn Error>);
+/// assert_eq!(body.unwrap(), "body");
+/// ```
+#[macro_export]
+macro_rules! retry {
+    ($policy:expr, $body:block as $err:ty $(,)?) => {
+        $crate::retry::retry($policy, || -> ::std::result::Result<_, $err> {
+            ::std::result::Result::Ok($body)
+        })
+    };
+    ($policy:expr, $body:block $(,)?) => {
+        $crate::retry::retry($policy, || ::std::result::Result::Ok($body))
+    };
+}
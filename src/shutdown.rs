@@ -0,0 +1,220 @@
+//! Cooperative cancellation
+//!
+//! A [`ShutdownToken`] is a cloneable flag that one part of a program triggers
+//! and every other part holding a clone of it can check, block on, or with the
+//! `async` feature await. Nothing is stopped by force: code doing work checks
+//! the token at points where it's safe to stop and returns early.
+//!
+//! Tokens can be arranged in a tree with [`ShutdownToken::child`]. Triggering
+//! a token triggers all of its children and their children, but not its
+//! parent, so a subsystem can be shut down on its own while shutting down the
+//! whole program still reaches it.
+//!
+//! ```
+//! # use futility::shutdown::ShutdownToken;
+//! # use std::{thread, time::Duration};
+//! let program = ShutdownToken::new();
+//! let server = program.child();
+//! let worker = thread::spawn({
+//!     let server = server.clone();
+//!     move || {
+//!         let mut handled = 0;
+//!         while !server.wait_timeout(Duration::from_millis(1)) {
+//!             handled += 1;
+//!         }
+//!         handled
+//!     }
+//! });
+//! program.trigger();
+//! worker.join().unwrap();
+//! assert!(server.is_triggered());
+//! ```
+//!
+//! [`Terminate::handle_signals`](crate::terminate::Terminate::handle_signals)
+//! triggers a process wide token when the program is asked to shut down,
+//! which is available from
+//! [`Handle::shutdown_token`](crate::terminate::Handle::shutdown_token). Each
+//! program run by [`Terminate`](crate::terminate::Terminate) gets a new one.
+
+#[cfg(feature = "async")]
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+use std::{
+    mem,
+    sync::{Arc, Condvar, Mutex, MutexGuard, Weak},
+    time::Duration,
+};
+
+/// A cloneable token used to request and observe a shutdown
+#[derive(Clone, Debug, Default)]
+pub struct ShutdownToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    triggered: bool,
+    children: Vec<Weak<Inner>>,
+    #[cfg(feature = "async")]
+    wakers: Vec<Waker>,
+}
+
+impl ShutdownToken {
+    /// Create a new token that has not been triggered
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a token that is triggered when this one is, but that can also
+    /// be triggered on its own without affecting this one
+    pub fn child(&self) -> Self {
+        let child = Self::new();
+        let mut state = self.inner.lock();
+        if state.triggered {
+            drop(state);
+            child.trigger();
+        } else {
+            state.children.retain(|child| child.strong_count() > 0);
+            state.children.push(Arc::downgrade(&child.inner));
+        }
+        child
+    }
+
+    /// Request a shutdown, waking up anything waiting on this token or any of
+    /// its children
+    pub fn trigger(&self) {
+        Inner::trigger(&self.inner);
+    }
+
+    /// Whether a shutdown has been requested
+    pub fn is_triggered(&self) -> bool {
+        self.inner.lock().triggered
+    }
+
+    /// Block until a shutdown is requested
+    pub fn wait(&self) {
+        let mut state = self.inner.lock();
+        while !state.triggered {
+            state = self
+                .inner
+                .condvar
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Block until a shutdown is requested or the timeout passes, returning
+    /// whether a shutdown was requested
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let state = self.inner.lock();
+        let (state, _) = self
+            .inner
+            .condvar
+            .wait_timeout_while(state, timeout, |state| !state.triggered)
+            .unwrap_or_else(|e| e.into_inner());
+        state.triggered
+    }
+
+    /// A future that completes once a shutdown is requested
+    ///
+    /// ```
+    /// # use futility::shutdown::ShutdownToken;
+    /// async fn serve(shutdown: ShutdownToken) {
+    ///     shutdown.cancelled().await;
+    ///     println!("shutting down");
+    /// }
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn cancelled(&self) -> Cancelled {
+        Cancelled {
+            token: self.clone(),
+        }
+    }
+}
+
+impl Inner {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn trigger(self: &Arc<Self>) {
+        // Children are triggered with a loop rather than recursion so that a
+        // deep tree can't overflow the stack
+        let mut pending = vec![Arc::clone(self)];
+        while let Some(inner) = pending.pop() {
+            let mut state = inner.lock();
+            if state.triggered {
+                continue;
+            }
+            state.triggered = true;
+            pending.extend(
+                mem::take(&mut state.children)
+                    .iter()
+                    .filter_map(Weak::upgrade),
+            );
+            #[cfg(feature = "async")]
+            let wakers = mem::take(&mut state.wakers);
+            drop(state);
+            inner.condvar.notify_all();
+            #[cfg(feature = "async")]
+            wakers.into_iter().for_each(Waker::wake);
+        }
+    }
+}
+
+/// The process wide token triggered by shutdown signals handled by
+/// [`Terminate::handle_signals`](crate::terminate::Terminate::handle_signals)
+#[cfg(feature = "terminate")]
+static GLOBAL: Mutex<Option<ShutdownToken>> = Mutex::new(None);
+
+/// The process wide token of the program being run by
+/// [`Terminate`](crate::terminate::Terminate)
+#[cfg(feature = "terminate")]
+pub(crate) fn global() -> ShutdownToken {
+    GLOBAL
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(ShutdownToken::new)
+        .clone()
+}
+
+/// Replace the process wide token with one that hasn't been triggered, so that
+/// a program doesn't start out shut down because the one before it was.
+/// Clones of the old token aren't affected.
+#[cfg(feature = "terminate")]
+pub(crate) fn reset_global() {
+    *GLOBAL.lock().unwrap_or_else(|e| e.into_inner()) = Some(ShutdownToken::new());
+}
+
+/// The future returned by [`ShutdownToken::cancelled`]
+#[cfg(feature = "async")]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Cancelled {
+    token: ShutdownToken,
+}
+
+#[cfg(feature = "async")]
+impl Future for Cancelled {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.token.inner.lock();
+        if state.triggered {
+            return Poll::Ready(());
+        }
+        if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
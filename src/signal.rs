@@ -0,0 +1,489 @@
+//! Receiving signals in regular Rust code
+//!
+//! Signal handlers can only do a very limited set of things, so rather than
+//! running code inside of one, [`Signals`] registers a handler for a set of
+//! signals that hands each received signal off to be delivered outside of the
+//! signal context, where it is free to allocate, take locks, and so on. A
+//! signal can be delivered to a callback with [`Signals::handle`], or queued
+//! up for a [`Listener`] from [`Signals::listen`] which can be read from as a
+//! blocking iterator or awaited in async code. [`Signals::iter`] is the
+//! shortest way to write a handling loop in a program without callbacks or
+//! async, and [`Listener::try_iter`] and [`Listener::recv_timeout`] check for
+//! signals in a loop that has other work to do.
+//!
+//! On Unix these are regular signals and any signal that can be caught can be
+//! handled. On Windows the console control events are delivered as signals:
+//! `Ctrl-C` as [`Signal::INT`], `Ctrl-Break` as [`Signal::BREAK`], and the
+//! console being closed, the user logging off, or the system shutting down as
+//! [`Signal::TERM`].
+//!
+//! Any number of handlers and listeners can be registered at once, including
+//! for the same signal, and each one gets every signal it registered for.
+//! [`Terminate`](crate::terminate::Terminate) handles signals this way too,
+//! so its handlers and the program's own don't get in each others way. When
+//! the last handler or listener for a signal is dropped the handler that was
+//! in place before is put back.
+//!
+//! ```no_run
+//! # use futility::signal::{Signal, Signals};
+//! # use std::io;
+//! # fn main() -> io::Result<()> {
+//! let _reload = Signals::new([Signal::HUP]).handle(|_| println!("reloading"))?;
+//!
+//! let listener = Signals::new([Signal::INT, Signal::TERM]).listen()?;
+//! for signal in listener.iter() {
+//!     println!("received {signal}, shutting down");
+//!     break;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ```no_run
+//! # use futility::signal::{Signal, Signals};
+//! # use std::{io, time::Duration};
+//! # fn poll_work() {}
+//! # fn main() -> io::Result<()> {
+//! let listener = Signals::new([Signal::USR1, Signal::TERM]).listen()?;
+//! loop {
+//!     match listener.recv_timeout(Duration::from_millis(100)) {
+//!         Some(Signal::TERM) => break,
+//!         Some(signal) => println!("ignoring {signal}"),
+//!         None => poll_work(),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(unix)]
+use unix as sys;
+#[cfg(windows)]
+use windows as sys;
+
+use std::{
+    cell::Cell,
+    fmt,
+    future::{self, Future},
+    io,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+/// A signal received by the process, identified by its number
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Signal(i32);
+
+#[cfg(unix)]
+impl Signal {
+    /// `SIGHUP`
+    pub const HUP: Self = Self(libc::SIGHUP);
+    /// `SIGINT`
+    pub const INT: Self = Self(libc::SIGINT);
+    /// `SIGQUIT`
+    pub const QUIT: Self = Self(libc::SIGQUIT);
+    /// `SIGTERM`
+    pub const TERM: Self = Self(libc::SIGTERM);
+    /// `SIGUSR1`
+    pub const USR1: Self = Self(libc::SIGUSR1);
+    /// `SIGUSR2`
+    pub const USR2: Self = Self(libc::SIGUSR2);
+    /// `SIGALRM`
+    pub const ALRM: Self = Self(libc::SIGALRM);
+    /// `SIGWINCH`
+    pub const WINCH: Self = Self(libc::SIGWINCH);
+}
+
+#[cfg(windows)]
+impl Signal {
+    /// `Ctrl-C` was pressed in the console, `SIGINT`
+    pub const INT: Self = Self(2);
+    /// The console was closed, the user logged off, or the system is shutting
+    /// down, `SIGTERM`
+    pub const TERM: Self = Self(15);
+    /// `Ctrl-Break` was pressed in the console, `SIGBREAK`
+    pub const BREAK: Self = Self(21);
+}
+
+impl Signal {
+    /// Create a signal from its raw number
+    pub fn from_raw(signal: i32) -> Self {
+        Self(signal)
+    }
+
+    /// The raw number of the signal
+    pub fn as_raw(self) -> i32 {
+        self.0
+    }
+
+    /// The name of the signal, such as `SIGTERM`, if it is one that is
+    /// commonly sent to a program to control it
+    pub fn name(self) -> Option<&'static str> {
+        #[cfg(unix)]
+        {
+            Some(match self.0 {
+                libc::SIGHUP => "SIGHUP",
+                libc::SIGINT => "SIGINT",
+                libc::SIGQUIT => "SIGQUIT",
+                libc::SIGTERM => "SIGTERM",
+                libc::SIGUSR1 => "SIGUSR1",
+                libc::SIGUSR2 => "SIGUSR2",
+                libc::SIGALRM => "SIGALRM",
+                libc::SIGWINCH => "SIGWINCH",
+                _ => return None,
+            })
+        }
+        #[cfg(windows)]
+        {
+            Some(match self {
+                Self::INT => "SIGINT",
+                Self::TERM => "SIGTERM",
+                Self::BREAK => "SIGBREAK",
+                _ => return None,
+            })
+        }
+        #[cfg(not(any(unix, windows)))]
+        {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "signal {}", self.0),
+        }
+    }
+}
+
+/// A set of signals to handle
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Signals {
+    signals: Vec<Signal>,
+}
+
+impl Signals {
+    /// Handle `signals`
+    pub fn new(signals: impl IntoIterator<Item = Signal>) -> Self {
+        signals.into_iter().fold(Self::default(), Self::signal)
+    }
+
+    /// Handle `signal` as well
+    pub fn signal(mut self, signal: Signal) -> Self {
+        if !self.signals.contains(&signal) {
+            self.signals.push(signal);
+        }
+        self
+    }
+
+    /// The signals that will be handled
+    pub fn signals(&self) -> &[Signal] {
+        &self.signals
+    }
+
+    /// Call `handler` with each of the signals that is received until the
+    /// returned [`Handler`] is dropped. The handler is called on a thread
+    /// shared by every handler and listener, so it should return quickly.
+    pub fn handle(self, handler: impl FnMut(Signal) + Send + 'static) -> io::Result<Handler> {
+        Ok(Handler {
+            _subscription: subscribe(self.signals, Box::new(handler))?,
+        })
+    }
+
+    /// A blocking iterator over each of the signals as they're received,
+    /// which never ends. This is [`listen`](Signals::listen) for a loop that
+    /// owns the listener.
+    ///
+    /// ```no_run
+    /// # use futility::signal::{Signal, Signals};
+    /// # fn main() -> std::io::Result<()> {
+    /// for signal in Signals::new([Signal::HUP, Signal::TERM]).iter()? {
+    ///     match signal {
+    ///         Signal::HUP => println!("reloading"),
+    ///         _ => break,
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter(self) -> io::Result<IntoIter> {
+        self.listen().map(Listener::into_iter)
+    }
+
+    /// Queue up each of the signals that is received for the returned
+    /// [`Listener`] to read until it is dropped
+    pub fn listen(self) -> io::Result<Listener> {
+        let (sender, receiver) = mpsc::channel();
+        let waker = Arc::new(Mutex::new(None::<Waker>));
+        let wake = Arc::clone(&waker);
+        let subscription = subscribe(
+            self.signals,
+            Box::new(move |signal| {
+                let _ = sender.send(signal);
+                if let Some(waker) = lock(&wake).take() {
+                    waker.wake();
+                }
+            }),
+        )?;
+        Ok(Listener {
+            receiver,
+            waker,
+            _subscription: subscription,
+        })
+    }
+}
+
+/// A callback registered with [`Signals::handle`]. Dropping it stops the
+/// callback from being called again, waiting for it to return if it's
+/// running.
+#[must_use = "dropping the handler stops it from handling signals"]
+pub struct Handler {
+    _subscription: Subscription,
+}
+
+impl Handler {
+    /// Stop handling signals, the same as dropping the handler
+    pub fn stop(self) {}
+}
+
+impl fmt::Debug for Handler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handler").finish_non_exhaustive()
+    }
+}
+
+/// The signals received since [`Signals::listen`] was called, in the order
+/// they were received
+#[must_use = "dropping the listener stops it from receiving signals"]
+pub struct Listener {
+    receiver: Receiver<Signal>,
+    waker: Arc<Mutex<Option<Waker>>>,
+    _subscription: Subscription,
+}
+
+impl Listener {
+    /// Block until a signal is received
+    pub fn recv(&self) -> Signal {
+        self.receiver
+            .recv()
+            .expect("the sender lives as long as the subscription")
+    }
+
+    /// The next signal if one has been received, without blocking
+    pub fn try_recv(&self) -> Option<Signal> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Block until a signal is received or `timeout` passes, whichever comes
+    /// first
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Signal> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+
+    /// A blocking iterator over the signals as they're received, which never
+    /// ends
+    pub fn iter(&self) -> impl Iterator<Item = Signal> + '_ {
+        std::iter::repeat_with(|| self.recv())
+    }
+
+    /// An iterator over the signals that have been received but not read yet,
+    /// which ends instead of blocking once there are none left
+    pub fn try_iter(&self) -> impl Iterator<Item = Signal> + '_ {
+        self.receiver.try_iter()
+    }
+
+    /// Wait for a signal to be received without blocking the thread
+    pub fn recv_async(&self) -> impl Future<Output = Signal> + '_ {
+        future::poll_fn(|cx| self.poll_recv(cx))
+    }
+
+    /// Poll for the next signal, registering the task in `cx` to be woken
+    /// when one is received if there isn't one yet. This is the same shape
+    /// as `Stream::poll_next`, so a listener can be turned into a stream for
+    /// any runtime.
+    pub fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Signal> {
+        if let Ok(signal) = self.receiver.try_recv() {
+            return Poll::Ready(signal);
+        }
+        *lock(&self.waker) = Some(cx.waker().clone());
+        // A signal could have been sent before the waker was stored
+        match self.receiver.try_recv() {
+            Ok(signal) => Poll::Ready(signal),
+            Err(_) => Poll::Pending,
+        }
+    }
+}
+
+impl fmt::Debug for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Listener").finish_non_exhaustive()
+    }
+}
+
+impl IntoIterator for Listener {
+    type Item = Signal;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter(self)
+    }
+}
+
+/// A blocking iterator over the signals a [`Listener`] receives, from
+/// [`Signals::iter`] or turning a listener into an iterator
+#[derive(Debug)]
+#[must_use = "dropping the iterator stops it from receiving signals"]
+pub struct IntoIter(Listener);
+
+impl IntoIter {
+    /// The listener the signals come from, for its non-blocking methods
+    pub fn listener(&self) -> &Listener {
+        &self.0
+    }
+}
+
+impl Iterator for IntoIter {
+    type Item = Signal;
+
+    fn next(&mut self) -> Option<Signal> {
+        Some(self.0.recv())
+    }
+}
+
+/// Deliver `signal` to every handler and listener registered for it as if it
+/// had been received by the process. Returns `false` if there aren't any.
+pub fn deliver(signal: Signal) -> bool {
+    let subscribed = lock(&REGISTRY)
+        .subscribers
+        .iter()
+        .any(|subscriber| subscriber.signals.contains(&signal));
+    subscribed && sys::raise(signal)
+}
+
+type Deliver = Arc<Mutex<Box<dyn FnMut(Signal) + Send>>>;
+
+struct Subscriber {
+    id: u64,
+    signals: Vec<Signal>,
+    deliver: Deliver,
+}
+
+/// Every handler and listener, and the handlers they replaced
+struct Registry {
+    next_id: u64,
+    subscribers: Vec<Subscriber>,
+    installed: Vec<(Signal, sys::Previous)>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    next_id: 0,
+    subscribers: Vec::new(),
+    installed: Vec::new(),
+});
+
+thread_local! {
+    /// Whether signals are being delivered on this thread
+    static DISPATCHING: Cell<bool> = const { Cell::new(false) };
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Removes a subscriber from the registry when dropped
+struct Subscription {
+    id: u64,
+}
+
+fn subscribe(
+    signals: Vec<Signal>,
+    deliver: Box<dyn FnMut(Signal) + Send>,
+) -> io::Result<Subscription> {
+    let mut registry = lock(&REGISTRY);
+    sys::start()?;
+    let mut installed = Vec::new();
+    for signal in &signals {
+        if registry.installed.iter().any(|(s, _)| s == signal) {
+            continue;
+        }
+        match sys::install(*signal) {
+            Ok(previous) => installed.push((*signal, previous)),
+            Err(err) => {
+                for (signal, previous) in installed {
+                    sys::restore(signal, previous);
+                }
+                return Err(err);
+            }
+        }
+    }
+    registry.installed.extend(installed);
+    let id = registry.next_id;
+    registry.next_id += 1;
+    registry.subscribers.push(Subscriber {
+        id,
+        signals,
+        deliver: Arc::new(Mutex::new(deliver)),
+    });
+    Ok(Subscription { id })
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let deliver = {
+            let mut registry = lock(&REGISTRY);
+            let Some(index) = registry.subscribers.iter().position(|s| s.id == self.id) else {
+                return;
+            };
+            let subscriber = registry.subscribers.remove(index);
+            let Registry {
+                subscribers,
+                installed,
+                ..
+            } = &mut *registry;
+            let (keep, restore) = installed.drain(..).partition::<Vec<_>, _>(|(signal, _)| {
+                subscribers.iter().any(|s| s.signals.contains(signal))
+            });
+            *installed = keep;
+            for (signal, previous) in restore {
+                sys::restore(signal, previous);
+            }
+            subscriber.deliver
+        };
+        // Wait for a delivery that's in progress to finish, unless this is
+        // being dropped by one
+        if !DISPATCHING.with(Cell::get) {
+            drop(lock(&deliver));
+        }
+    }
+}
+
+/// Deliver `signal` to every subscriber registered for it on the current
+/// thread. Returns `false` if there aren't any.
+fn dispatch(signal: Signal) -> bool {
+    let delivers = lock(&REGISTRY)
+        .subscribers
+        .iter()
+        .filter(|subscriber| subscriber.signals.contains(&signal))
+        .map(|subscriber| Arc::clone(&subscriber.deliver))
+        .collect::<Vec<_>>();
+    let dispatching = DISPATCHING.with(|dispatching| dispatching.replace(true));
+    for deliver in &delivers {
+        // A handler panicking shouldn't stop signals from being delivered to
+        // the others
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| (*lock(deliver))(signal)));
+    }
+    DISPATCHING.with(|cell| cell.set(dispatching));
+    !delivers.is_empty()
+}
@@ -0,0 +1,151 @@
+//! Unix signals, delivered through a pipe to a dispatch thread
+//!
+//! The handler installed for each signal only writes the signal number to a
+//! pipe. A dispatch thread started with the first handler or listener reads
+//! from the other end and delivers each signal outside of the signal context.
+
+use super::{dispatch, Signal};
+use libc::c_int;
+use std::{
+    io, mem, ptr,
+    sync::atomic::{AtomicI32, Ordering},
+    thread,
+};
+
+/// The handler that was installed for a signal before ours
+pub(super) type Previous = libc::sigaction;
+
+/// The write end of the pipe to the dispatch thread, or -1 if it is not running
+static PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// Start the dispatch thread if it isn't running yet. It keeps running for the
+/// rest of the program once started.
+pub(super) fn start() -> io::Result<()> {
+    if PIPE.load(Ordering::SeqCst) >= 0 {
+        return Ok(());
+    }
+    let mut pipe = [0; 2];
+    // SAFETY: pipe has room for the two file descriptors pipe writes
+    if unsafe { libc::pipe(pipe.as_mut_ptr()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    for fd in pipe {
+        // SAFETY: fcntl with F_SETFD has no memory safety requirements
+        unsafe {
+            libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        }
+    }
+    // A signal handler must never block, so signals received while the pipe
+    // is full are dropped
+    // SAFETY: fcntl with F_GETFL and F_SETFL has no memory safety requirements
+    unsafe {
+        let flags = libc::fcntl(pipe[1], libc::F_GETFL);
+        libc::fcntl(pipe[1], libc::F_SETFL, flags | libc::O_NONBLOCK);
+    }
+
+    let reader = pipe[0];
+    let thread = thread::Builder::new()
+        .name("futility-signals".into())
+        .spawn(move || loop {
+            let mut signal = 0u8;
+            // SAFETY: signal is valid for a one byte read
+            match unsafe { libc::read(reader, (&mut signal as *mut u8).cast(), 1) } {
+                1 => {
+                    dispatch(Signal::from_raw(c_int::from(signal)));
+                }
+                -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+                _ => break,
+            }
+        });
+    if let Err(err) = thread {
+        close(pipe);
+        return Err(err);
+    }
+    PIPE.store(pipe[1], Ordering::SeqCst);
+    Ok(())
+}
+
+/// Install our handler for `signal`, returning the one it replaced
+pub(super) fn install(signal: Signal) -> io::Result<Previous> {
+    // SAFETY: The action is fully initialized before being passed to
+    // sigaction and the previous action is written into memory we own
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = on_signal as extern "C" fn(c_int) as usize;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        let mut previous: libc::sigaction = mem::zeroed();
+        match libc::sigaction(signal.as_raw(), &action, &mut previous) {
+            0 => Ok(previous),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+}
+
+/// Put back the handler for `signal` that was replaced by ours
+pub(super) fn restore(signal: Signal, previous: Previous) {
+    // SAFETY: previous was filled in by sigaction when we installed our
+    // handler
+    unsafe {
+        libc::sigaction(signal.as_raw(), &previous, ptr::null_mut());
+    }
+}
+
+/// Deliver `signal` through the dispatch thread as if it had been received
+pub(super) fn raise(signal: Signal) -> bool {
+    send(signal.as_raw() as u8)
+}
+
+fn send(byte: u8) -> bool {
+    let fd = PIPE.load(Ordering::SeqCst);
+    // SAFETY: byte is valid for a one byte write
+    fd >= 0 && unsafe { libc::write(fd, (&byte as *const u8).cast(), 1) == 1 }
+}
+
+extern "C" fn on_signal(signal: c_int) {
+    // write is async-signal-safe, but it can change errno which the code that
+    // was interrupted might be about to read
+    let errno = io::Error::last_os_error().raw_os_error();
+    send(signal as u8);
+    if let Some(errno) = errno {
+        set_errno(errno);
+    }
+}
+
+fn set_errno(errno: c_int) {
+    // SAFETY: The errno location is always valid for the current thread
+    unsafe {
+        #[cfg(any(target_os = "linux", target_os = "android", target_os = "emscripten"))]
+        {
+            *libc::__errno_location() = errno;
+        }
+        #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+        {
+            *libc::__error() = errno;
+        }
+        #[cfg(any(target_os = "openbsd", target_os = "netbsd"))]
+        {
+            *libc::__errno() = errno;
+        }
+        #[cfg(not(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "emscripten",
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "netbsd"
+        )))]
+        let _ = errno;
+    }
+}
+
+fn close(pipe: [c_int; 2]) {
+    for fd in pipe {
+        // SAFETY: We own both ends of the pipe
+        unsafe {
+            libc::close(fd);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! Windows console control events, delivered as signals
+//!
+//! Windows calls console control handlers on a thread it creates for each
+//! event, so signals are delivered directly from the handler.
+
+use super::{dispatch, Signal};
+use std::{
+    io,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+const CTRL_C_EVENT: u32 = 0;
+const CTRL_BREAK_EVENT: u32 = 1;
+const CTRL_CLOSE_EVENT: u32 = 2;
+const CTRL_LOGOFF_EVENT: u32 = 5;
+const CTRL_SHUTDOWN_EVENT: u32 = 6;
+
+type HandlerRoutine = unsafe extern "system" fn(ctrl_type: u32) -> i32;
+
+extern "system" {
+    fn SetConsoleCtrlHandler(handler: Option<HandlerRoutine>, add: i32) -> i32;
+}
+
+/// Nothing needs to be put back for a single event, the handler is removed
+/// once no events are handled
+pub(super) struct Previous;
+
+/// How many events are being handled
+static INSTALLED: AtomicUsize = AtomicUsize::new(0);
+
+/// Events are delivered on threads created by Windows, so there's nothing to
+/// start
+pub(super) fn start() -> io::Result<()> {
+    Ok(())
+}
+
+/// Start handling `signal`, adding the console control handler if it's the
+/// first
+pub(super) fn install(signal: Signal) -> io::Result<Previous> {
+    if ![Signal::INT, Signal::TERM, Signal::BREAK].contains(&signal) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{signal} can't be handled on Windows"),
+        ));
+    }
+    if INSTALLED.fetch_add(1, Ordering::SeqCst) == 0 {
+        // SAFETY: on_event has the signature of a HandlerRoutine
+        if unsafe { SetConsoleCtrlHandler(Some(on_event), 1) } == 0 {
+            INSTALLED.fetch_sub(1, Ordering::SeqCst);
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(Previous)
+}
+
+/// Stop handling `signal`, removing the console control handler if it was the
+/// last
+pub(super) fn restore(_: Signal, _: Previous) {
+    if INSTALLED.fetch_sub(1, Ordering::SeqCst) == 1 {
+        // SAFETY: on_event was added as a handler by install
+        unsafe {
+            SetConsoleCtrlHandler(Some(on_event), 0);
+        }
+    }
+}
+
+/// Deliver `signal` on the current thread as if the event had happened
+pub(super) fn raise(signal: Signal) -> bool {
+    dispatch(signal)
+}
+
+unsafe extern "system" fn on_event(ctrl_type: u32) -> i32 {
+    let signal = match ctrl_type {
+        CTRL_C_EVENT => Signal::INT,
+        CTRL_BREAK_EVENT => Signal::BREAK,
+        CTRL_CLOSE_EVENT | CTRL_LOGOFF_EVENT | CTRL_SHUTDOWN_EVENT => Signal::TERM,
+        _ => return 0,
+    };
+    i32::from(dispatch(signal))
+}
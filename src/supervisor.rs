@@ -0,0 +1,533 @@
+//! Running a program made of long running parts
+//!
+//! [`Terminate`](crate::terminate::Terminate) runs a program from start to
+//! finish. Many programs are instead a handful of parts running side by side
+//! until they're told to stop: a listener, a queue consumer, a cache that is
+//! refreshed in the background. Each part implements [`Service`], and a
+//! [`Supervisor`] runs each one on its own thread, restarting it when it
+//! fails for as long as its [`RetryPolicy`] allows. A service that fails more
+//! often than that is escalated: every service is stopped and
+//! [`Supervisor::run`] returns the failures.
+//!
+//! Services can depend on each other. They're started with dependencies
+//! before the services that depend on them and stopped in the reverse order,
+//! each one waited on before its dependencies are told to stop, so nothing is
+//! left using a part of the program that is already gone.
+//!
+//! ```
+//! # use futility::{
+//! #     retry::{FixedDelay, RetryPolicy},
+//! #     shutdown::ShutdownToken,
+//! #     supervisor::{Restart, Supervised, Supervisor},
+//! # };
+//! # use std::{io, time::Duration};
+//! let shutdown = ShutdownToken::new();
+//! let res = Supervisor::new()
+//!     .shutdown_token(shutdown.clone())
+//!     .service(Supervised::new("database", |shutdown: ShutdownToken| {
+//!         shutdown.wait();
+//!         Ok::<_, io::Error>(())
+//!     }))
+//!     .service(
+//!         Supervised::new("api", move |_: ShutdownToken| {
+//!             // Serve requests until the server is told to stop
+//!             shutdown.trigger();
+//!             Ok::<_, io::Error>(())
+//!         })
+//!         .depends_on("database")
+//!         .restart(Restart::OnFailure)
+//!         .policy(FixedDelay::new(Duration::from_millis(10)).max_attempts(3)),
+//!     )
+//!     .run();
+//! assert!(res.is_ok());
+//! ```
+//!
+//! By default the supervisor stops once the process wide shutdown token from
+//! [`Terminate::handle_signals`] is triggered, so services run inside of
+//! [`Terminate::execute`] are stopped in order on `Ctrl-C`.
+//!
+//! [`Terminate::handle_signals`]: crate::terminate::Terminate::handle_signals
+//! [`Terminate::execute`]: crate::terminate::Terminate::execute
+
+use crate::{
+    log::{self, Level},
+    panic::{self, PanicDetails},
+    retry::{ExponentialBackoff, RetryPolicy},
+    shutdown::{self, ShutdownToken},
+};
+use std::{
+    error::Error,
+    fmt, io,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+/// How often the supervisor checks its shutdown token while waiting for
+/// services
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long a service has to run for before its policy starts over
+const RESET_AFTER: Duration = Duration::from_secs(60);
+
+/// A long running part of a program, run by a [`Supervisor`]
+///
+/// Any `FnMut(ShutdownToken) -> Result<(), E>` is a service.
+pub trait Service: Send + 'static {
+    /// The error the service fails with
+    type Error: Into<Box<dyn Error + Send + Sync>>;
+
+    /// Run until `shutdown` is triggered or the service has nothing left to
+    /// do. This is called again each time the service is restarted.
+    fn run(&mut self, shutdown: ShutdownToken) -> Result<(), Self::Error>;
+}
+
+impl<F, E> Service for F
+where
+    F: FnMut(ShutdownToken) -> Result<(), E> + Send + 'static,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    type Error = E;
+
+    fn run(&mut self, shutdown: ShutdownToken) -> Result<(), E> {
+        self(shutdown)
+    }
+}
+
+/// When a [`Supervisor`] restarts a service that returned
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Restart {
+    /// Never restart it, escalating if it fails
+    Never,
+    /// Restart it if it fails, leaving it stopped once it returns `Ok`
+    #[default]
+    OnFailure,
+    /// Restart it whenever it returns before the supervisor stops it. Returning
+    /// `Ok` restarts it right away without going through its policy.
+    Always,
+}
+
+type Run = Box<dyn FnMut(ShutdownToken) -> Result<(), Box<dyn Error + Send + Sync>> + Send>;
+
+/// A [`Service`] along with how a [`Supervisor`] runs it
+pub struct Supervised {
+    name: String,
+    run: Run,
+    restart: Restart,
+    policy: Box<dyn RetryPolicy + Send>,
+    reset_after: Duration,
+    dependencies: Vec<String>,
+}
+
+impl Supervised {
+    /// Supervise `service` under `name`, which is also the name of its
+    /// thread. It's restarted on failure with an [`ExponentialBackoff`] and
+    /// escalated on its fifth failure unless set otherwise.
+    pub fn new(name: impl Into<String>, mut service: impl Service) -> Self {
+        Self {
+            name: name.into(),
+            run: Box::new(move |shutdown| service.run(shutdown).map_err(Into::into)),
+            restart: Restart::default(),
+            policy: Box::new(ExponentialBackoff::default().max_attempts(5)),
+            reset_after: RESET_AFTER,
+            dependencies: Vec::new(),
+        }
+    }
+
+    /// Set when the service is restarted after it returns
+    pub fn restart(mut self, restart: Restart) -> Self {
+        self.restart = restart;
+        self
+    }
+
+    /// Wait between restarts after a failure for as long as `policy` says
+    /// to, and escalate once it gives up. The policy counts every failure
+    /// since the service last ran for as long as [`Supervised::reset_after`].
+    pub fn policy(mut self, policy: impl RetryPolicy + Send + 'static) -> Self {
+        self.policy = Box::new(policy);
+        self
+    }
+
+    /// Start the policy over, forgetting earlier failures, once the service
+    /// has run for `reset_after` before returning. This is a minute by
+    /// default, so a service that fails now and then is only escalated if it
+    /// fails many times in a row.
+    pub fn reset_after(mut self, reset_after: Duration) -> Self {
+        self.reset_after = reset_after;
+        self
+    }
+
+    /// Start the service after the service called `name` and stop it before.
+    /// This can be called multiple times for a service with several
+    /// dependencies.
+    pub fn depends_on(mut self, name: impl Into<String>) -> Self {
+        self.dependencies.push(name.into());
+        self
+    }
+
+    /// The name the service is supervised under
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Debug for Supervised {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Supervised")
+            .field("name", &self.name)
+            .field("restart", &self.restart)
+            .field("reset_after", &self.reset_after)
+            .field("dependencies", &self.dependencies)
+            .finish_non_exhaustive()
+    }
+}
+
+/// One way a supervised service failed
+#[derive(Debug, Error)]
+pub enum Failure {
+    /// The service returned an error
+    #[error("failed: {0}")]
+    Failed(Box<dyn Error + Send + Sync>),
+    /// The service panicked
+    #[error("{0}")]
+    Panicked(PanicDetails),
+}
+
+impl From<PanicDetails> for Failure {
+    fn from(details: PanicDetails) -> Self {
+        Self::Panicked(details)
+    }
+}
+
+/// Why [`Supervisor::run`] stopped without being told to
+#[derive(Debug, Error)]
+pub enum SupervisorError {
+    /// Two services were given the same name
+    #[error("more than one service is named `{service}`")]
+    DuplicateService {
+        /// The name that was given twice
+        service: String,
+    },
+    /// A service depends on a service that isn't supervised
+    #[error("service `{service}` depends on `{dependency}`, which isn't supervised")]
+    UnknownDependency {
+        /// The service with the dependency
+        service: String,
+        /// The name of the missing dependency
+        dependency: String,
+    },
+    /// A service depends on itself through its dependencies
+    #[error("service `{service}` depends on itself")]
+    DependencyCycle {
+        /// A service in the cycle
+        service: String,
+    },
+    /// A service's thread couldn't be spawned. The services that were already
+    /// started are stopped again.
+    #[error("failed to start service `{service}`: {source}")]
+    Spawn {
+        /// The service that couldn't be started
+        service: String,
+        /// Why its thread couldn't be spawned
+        source: io::Error,
+    },
+    /// A service's policy gave up on restarting it, so every service was
+    /// stopped
+    #[error("service `{service}` was restarted too many times, giving up")]
+    Escalated {
+        /// The service that was given up on
+        service: String,
+        /// Every time it failed since it last ran for as long as
+        /// [`Supervised::reset_after`], in order
+        failures: Vec<Failure>,
+    },
+}
+
+/// Runs [`Service`]s on their own threads, restarting them when they fail
+#[derive(Debug)]
+pub struct Supervisor {
+    services: Vec<Supervised>,
+    shutdown: ShutdownToken,
+    grace: Duration,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Supervisor {
+    /// A supervisor without any services that stops when the process wide
+    /// shutdown token from [`Terminate::handle_signals`] is triggered
+    ///
+    /// [`Terminate::handle_signals`]: crate::terminate::Terminate::handle_signals
+    pub fn new() -> Self {
+        Self {
+            services: Vec::new(),
+            shutdown: shutdown::global(),
+            grace: Duration::from_secs(5),
+        }
+    }
+
+    /// Run `service` along with the others
+    pub fn service(mut self, service: Supervised) -> Self {
+        self.services.push(service);
+        self
+    }
+
+    /// Stop once `shutdown` is triggered rather than the process wide
+    /// shutdown token
+    pub fn shutdown_token(mut self, shutdown: ShutdownToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// How long each service has to return once it's told to stop before the
+    /// supervisor moves on to its dependencies, which is five seconds by
+    /// default. A service that takes longer is logged and left running.
+    pub fn grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    /// Start every service, dependencies first, and run them until the
+    /// shutdown token is triggered or every service has stopped on its own,
+    /// then stop them in the reverse order
+    ///
+    /// Returns an error without starting anything if the dependencies can't
+    /// be ordered, or once every service has been stopped if one of them was
+    /// escalated.
+    pub fn run(self) -> Result<(), SupervisorError> {
+        let order = self.start_order()?;
+        let Self {
+            services,
+            shutdown,
+            grace,
+        } = self;
+        let names = services.iter().map(|s| s.name.clone()).collect::<Vec<_>>();
+        let mut running = Running {
+            names: &names,
+            threads: (0..services.len()).map(|_| None).collect(),
+            events: None,
+            escalated: None,
+        };
+
+        let (sender, events) = mpsc::channel();
+        let mut services = services.into_iter().map(Some).collect::<Vec<_>>();
+        for &index in &order {
+            let service = services[index]
+                .take()
+                .expect("each service is started once");
+            let token = ShutdownToken::new();
+            match spawn(index, service, token.clone(), sender.clone()) {
+                Ok(thread) => running.threads[index] = Some((token, thread)),
+                Err(source) => {
+                    running.events = Some(events);
+                    running.stop(&order, grace);
+                    return Err(SupervisorError::Spawn {
+                        service: names[index].clone(),
+                        source,
+                    });
+                }
+            }
+        }
+        drop(sender);
+        running.events = Some(events);
+
+        while running.escalated.is_none() && !shutdown.is_triggered() {
+            if !running.wait_for_event(POLL_INTERVAL) {
+                break;
+            }
+        }
+        running.stop(&order, grace);
+        match running.escalated {
+            Some((service, failures)) => Err(SupervisorError::Escalated {
+                service: names[service].clone(),
+                failures,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// The indices of the services with each one after its dependencies
+    fn start_order(&self) -> Result<Vec<usize>, SupervisorError> {
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        enum Mark {
+            Visiting,
+            Done,
+        }
+
+        fn visit(
+            services: &[Supervised],
+            index: usize,
+            marks: &mut [Option<Mark>],
+            order: &mut Vec<usize>,
+        ) -> Result<(), SupervisorError> {
+            let service = &services[index];
+            match marks[index] {
+                Some(Mark::Done) => return Ok(()),
+                Some(Mark::Visiting) => {
+                    return Err(SupervisorError::DependencyCycle {
+                        service: service.name.clone(),
+                    })
+                }
+                None => marks[index] = Some(Mark::Visiting),
+            }
+            for dependency in &service.dependencies {
+                let Some(dependency) = services.iter().position(|s| s.name == *dependency) else {
+                    return Err(SupervisorError::UnknownDependency {
+                        service: service.name.clone(),
+                        dependency: dependency.clone(),
+                    });
+                };
+                visit(services, dependency, marks, order)?;
+            }
+            marks[index] = Some(Mark::Done);
+            order.push(index);
+            Ok(())
+        }
+
+        for (i, service) in self.services.iter().enumerate() {
+            if self.services[..i].iter().any(|s| s.name == service.name) {
+                return Err(SupervisorError::DuplicateService {
+                    service: service.name.clone(),
+                });
+            }
+        }
+        let mut marks = vec![None; self.services.len()];
+        let mut order = Vec::with_capacity(self.services.len());
+        for index in 0..self.services.len() {
+            visit(&self.services, index, &mut marks, &mut order)?;
+        }
+        Ok(order)
+    }
+}
+
+/// How a service's thread ended
+enum Exit {
+    /// It returned and wasn't restarted, or was stopped
+    Stopped,
+    /// Its policy gave up on restarting it
+    GaveUp(Vec<Failure>),
+}
+
+/// The services started by [`Supervisor::run`]
+struct Running<'a> {
+    names: &'a [String],
+    /// The token and thread of each service that hasn't exited, by index
+    threads: Vec<Option<(ShutdownToken, JoinHandle<()>)>>,
+    events: Option<Receiver<(usize, Exit)>>,
+    /// The first service to be escalated and its failures
+    escalated: Option<(usize, Vec<Failure>)>,
+}
+
+impl Running<'_> {
+    /// Wait up to `timeout` for a service to exit, returning whether any
+    /// service could still exit
+    fn wait_for_event(&mut self, timeout: Duration) -> bool {
+        let Some(events) = &self.events else {
+            return false;
+        };
+        let (index, exit) = match events.recv_timeout(timeout) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => return true,
+            Err(RecvTimeoutError::Disconnected) => return false,
+        };
+        if let Some((_, thread)) = self.threads[index].take() {
+            let _ = thread.join();
+        }
+        if let Exit::GaveUp(failures) = exit {
+            self.escalated.get_or_insert((index, failures));
+        }
+        true
+    }
+
+    /// Stop every service still running in the reverse of `order`, waiting up
+    /// to `grace` for each
+    fn stop(&mut self, order: &[usize], grace: Duration) {
+        for &index in order.iter().rev() {
+            let Some((token, _)) = &self.threads[index] else {
+                continue;
+            };
+            token.trigger();
+            // A grace period too large to add to now waits without a deadline,
+            // which receiving with that same grace period as its timeout does
+            let deadline = Instant::now().checked_add(grace);
+            while self.threads[index].is_some() {
+                let remaining = deadline.map_or(grace, |deadline| {
+                    deadline.saturating_duration_since(Instant::now())
+                });
+                if remaining.is_zero() || !self.wait_for_event(remaining) {
+                    break;
+                }
+            }
+            if self.threads[index].take().is_some() {
+                log::log(
+                    Level::Warn,
+                    module_path!(),
+                    format_args!(
+                        "service `{}` didn't stop within {grace:?}, leaving it running",
+                        self.names[index]
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// Spawn the thread that runs and restarts `service`, sending how it ended on
+/// `exited`
+fn spawn(
+    index: usize,
+    service: Supervised,
+    token: ShutdownToken,
+    exited: Sender<(usize, Exit)>,
+) -> io::Result<JoinHandle<()>> {
+    thread::Builder::new()
+        .name(service.name.clone())
+        .spawn(move || {
+            let exit = supervise(service, token);
+            let _ = exited.send((index, exit));
+        })
+}
+
+fn supervise(mut service: Supervised, token: ShutdownToken) -> Exit {
+    let mut delays = (&mut *service.policy).delays();
+    let mut failures = Vec::new();
+    loop {
+        let started = Instant::now();
+        let res = panic::catch_asserted::<_, Failure>(|| (service.run)(token.clone()))
+            .and_then(|res| res.map_err(Failure::Failed));
+        if token.is_triggered() {
+            return Exit::Stopped;
+        }
+        // The service was healthy for long enough that the failures before
+        // this run shouldn't count towards giving up on it
+        if started.elapsed() >= service.reset_after {
+            delays.reset();
+            failures.clear();
+        }
+        match res {
+            Ok(()) if service.restart == Restart::Always => continue,
+            Ok(()) => return Exit::Stopped,
+            Err(failure) => {
+                log::log(
+                    Level::Warn,
+                    module_path!(),
+                    format_args!("service `{}` {failure}", service.name),
+                );
+                failures.push(failure);
+                if service.restart == Restart::Never {
+                    return Exit::GaveUp(failures);
+                }
+            }
+        }
+        match delays.next() {
+            Some(delay) if token.wait_timeout(delay) => return Exit::Stopped,
+            Some(_) => {}
+            None => return Exit::GaveUp(failures),
+        }
+    }
+}
@@ -1,10 +1,96 @@
 //! Types and functions associated with exiting a program
 
+use crate::exit::ExitCoded;
+#[cfg(feature = "log")]
+use crate::log::{LogError, SimpleLogger};
+use harness::Hook;
+use lifecycle::PhaseOutcome;
+use outcome::AtExit;
+#[cfg(feature = "runtime")]
+use std::future::Future;
 use std::{
+    cell::RefCell,
+    ffi::OsString,
     fmt::{Debug, Display},
+    io,
     marker::PhantomData,
-    panic::{self, PanicInfo},
+    panic::{AssertUnwindSafe, PanicHookInfo},
+    path::PathBuf,
+    process::ExitCode,
+    rc::Rc,
+    sync::{Condvar, Mutex},
+    time::{Duration, Instant},
 };
+use thiserror::Error;
+
+#[cfg(all(feature = "atexit", any(unix, windows)))]
+mod atexit;
+pub mod builder;
+pub mod child;
+#[cfg(feature = "crash-reports")]
+pub mod crash;
+#[cfg(all(unix, feature = "crash-marker"))]
+pub mod crash_marker;
+pub mod enrich;
+pub mod environment;
+pub mod handle;
+pub mod harness;
+mod heartbeat;
+#[cfg(unix)]
+pub mod instance;
+mod lifecycle;
+pub mod memory;
+pub mod outcome;
+pub mod panic_hook;
+pub mod plan;
+pub mod preflight;
+pub mod program;
+#[cfg(unix)]
+pub mod redirect;
+#[cfg(unix)]
+mod reexec;
+pub mod registry;
+pub mod reporter;
+#[cfg(all(unix, feature = "rlimit"))]
+pub mod rlimit;
+#[cfg(feature = "runtime")]
+pub mod runtime;
+pub mod scoped;
+pub mod shutdown_policy;
+#[cfg(unix)]
+pub mod signal_action;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+pub mod worker;
+
+pub use crate::shutdown::ShutdownToken;
+pub use crate::tty::{self, OutputStyle};
+pub use builder::Builder;
+pub use child::{ChildFailed, ChildStatus};
+#[cfg(feature = "crash-reports")]
+pub use crash::CrashReports;
+pub use enrich::ErrorContext;
+pub use handle::{handle, Handle};
+#[cfg(unix)]
+pub use instance::{InstanceError, InstanceLock};
+pub use outcome::{ExitInfo, ExitReason, LifecycleTimings, Outcome, Signal};
+pub use panic_hook::{PanicPayload, PanicPolicy};
+pub use plan::{ConfigError, Plan};
+pub use preflight::{Preflight, PreflightError};
+pub use program::{Program, ProgramContext};
+#[cfg(unix)]
+pub use redirect::OutputTarget;
+pub use registry::ExitPriority;
+pub use reporter::ErrorReporter;
+#[cfg(all(unix, feature = "rlimit"))]
+pub use rlimit::{Limit, Resource, RlimitError};
+#[cfg(feature = "runtime")]
+pub use runtime::RuntimeBuilder;
+pub use scoped::Scoped;
+pub use shutdown_policy::{ShutdownPolicy, GRACE_PERIOD_EXIT_CODE};
+#[cfg(feature = "otel")]
+pub use telemetry::FlushTelemetry;
+pub use worker::{WorkerError, WorkerErrors};
 
 /// The `Terminate` type is used to setup the execution of program from start to
 /// finish and what to do when the program errors, what panic hooks to use, what
@@ -14,12 +100,184 @@ pub struct Terminate<E>
 where
     E: Display + Debug,
 {
-    at_exit: Option<fn()>,
+    at_exit: Option<AtExit>,
     on_error: Option<fn(E) -> E>,
-    install: Option<fn() -> Result<(), E>>,
+    on_install_error: Option<fn(E) -> E>,
+    install: Option<Install<E>>,
+    panic_hooks: Vec<Box<dyn FnOnce()>>,
+    panic_to_error: Option<fn(&PanicPayload<'_>) -> E>,
+    panic_policy: PanicPolicy,
+    error_style: ErrorStyle,
+    reporter: Option<Box<dyn ErrorReporter<E>>>,
+    child_status: Option<fn(&E) -> Option<ExitCode>>,
+    exit_code: Option<fn(&E) -> u8>,
+    error_context: Option<ErrorContext>,
+    report_memory: bool,
+    #[cfg(feature = "metrics")]
+    report_metrics: bool,
+    report_runtime: bool,
+    #[cfg(feature = "trace")]
+    report_trace: bool,
+    worker_timeout: Duration,
+    worker_error: Option<fn(WorkerErrors) -> E>,
+    already_executing_error: Option<fn(AlreadyExecuting) -> E>,
+    #[cfg(feature = "pool")]
+    pools: Vec<(crate::pool::ThreadPool, Duration)>,
+    heartbeats: Vec<heartbeat::Heartbeat>,
+    detect_tty: bool,
+    critical: Vec<fn()>,
+    exit_hooks: Vec<(ExitPriority, registry::Cleanup)>,
+    #[cfg(all(feature = "atexit", any(unix, windows)))]
+    atexit_error: Option<fn(io::Error) -> E>,
+    env: Vec<(OsString, OsString)>,
+    working_dir: Option<PathBuf>,
+    working_dir_error: Option<fn(io::Error) -> E>,
+    preflight: Option<Preflight>,
+    preflight_error: Option<fn(PreflightError) -> E>,
+    #[cfg(unix)]
+    signals: signal_action::SignalConfig,
+    #[cfg(unix)]
+    signal_error: Option<fn(io::Error) -> E>,
+    #[cfg(unix)]
+    reexec: Option<ExitReason>,
+    stages: Vec<(&'static str, Stage<E>)>,
+    recorder: Option<harness::Recorder>,
+    #[cfg(feature = "runtime")]
+    runtime: Option<RuntimeBuilder>,
+    #[cfg(feature = "otel")]
+    flushes: Vec<telemetry::Flush>,
     error: PhantomData<E>,
 }
 
+/// The entry point to a program run by [`Terminate`]
+pub type Main<E> = fn() -> Result<(), E>;
+
+/// The function run to install anything needed before program execution
+enum Install<E> {
+    /// An install function that shares the program's error type
+    Direct(fn() -> Result<(), E>),
+    /// An install function with its own error type that is mapped to the
+    /// program's error type
+    Mapped(Box<dyn FnOnce() -> Result<(), E>>),
+}
+
+/// A [`Terminate`] with an install function whose error type is different from
+/// the program's, waiting on a way to convert between them. See
+/// [`Terminate::install_with_error`].
+pub struct MapInstallError<E, I>
+where
+    E: Display + Debug,
+{
+    terminate: Terminate<E>,
+    install: fn() -> Result<(), I>,
+}
+
+impl<E, I> MapInstallError<E, I>
+where
+    E: Display + Debug + 'static,
+    I: 'static,
+{
+    /// Convert any error from the install function into the program's error
+    /// type with `map`
+    pub fn map_install_error(mut self, map: fn(I) -> E) -> Terminate<E> {
+        let install = self.install;
+        self.terminate.install = Some(Install::Mapped(Box::new(move || install().map_err(map))));
+        self.terminate
+    }
+}
+
+/// Whether a [`Terminate`] is currently executing a program in this process
+static RUNNING: Mutex<bool> = Mutex::new(false);
+
+/// Notified whenever a [`Terminate`] finishes executing
+static FINISHED: Condvar = Condvar::new();
+
+thread_local! {
+    /// Whether the [`Terminate`] that is executing was started on this thread
+    static EXECUTING: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// The error returned when a [`Terminate`] is executed from inside of a
+/// program that is already being run by one. See
+/// [`Terminate::already_executing_error`].
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+#[error(
+    "a Terminate was executed from inside of a program already run by one, only one program \
+     can be run at a time as the panic hooks, signal handlers, and exit hooks it manages are \
+     process wide"
+)]
+#[non_exhaustive]
+pub struct AlreadyExecuting;
+
+/// Marks a [`Terminate`] as executing until dropped, so that a second one
+/// can't install its own panic hooks and signal handlers or run the exit hooks
+/// of the first
+struct Running;
+
+impl Running {
+    /// Wait for any [`Terminate`] executing on another thread to finish. One
+    /// started on this thread can't finish until the one being started does,
+    /// so that fails instead.
+    fn acquire() -> Result<Self, AlreadyExecuting> {
+        if EXECUTING.with(|executing| executing.get()) {
+            return Err(AlreadyExecuting);
+        }
+        let mut running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+        while *running {
+            running = FINISHED.wait(running).unwrap_or_else(|e| e.into_inner());
+        }
+        *running = true;
+        EXECUTING.with(|executing| executing.set(true));
+        Ok(Running)
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        EXECUTING.with(|executing| executing.set(false));
+        *RUNNING.lock().unwrap_or_else(|e| e.into_inner()) = false;
+        FINISHED.notify_one();
+    }
+}
+
+/// Everything known about a program once [`Terminate`] has finished running it
+struct Finished<E> {
+    result: Result<(), E>,
+    timings: LifecycleTimings,
+    reason: ExitReason,
+    panicked: bool,
+    workers_failed: bool,
+}
+
+/// Setup that runs after `install` for options that need it, which can hand
+/// back a [`Teardown`] to be run when the program exits
+type Stage<E> = Box<dyn FnOnce() -> Result<Option<Teardown>, E>>;
+
+/// Cleanup for a [`Stage`] that runs after `at_exit`
+type Teardown = Box<dyn FnOnce()>;
+
+/// The error returned by [`Terminate::execute_subcommand`] when there is no
+/// subcommand with the given name
+#[derive(Debug, Error)]
+#[error("unknown subcommand `{name}`, expected one of: {}", available.join(", "))]
+pub struct UnknownSubcommand {
+    /// The name of the subcommand that was requested
+    pub name: String,
+    /// The names of every subcommand that was available
+    pub available: Vec<String>,
+}
+
+/// How [`Terminate::run`] presents an error that made it out of the program
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorStyle {
+    /// Print `Error: {Debug}` to stderr, the same as returning `Err` from
+    /// `main`
+    #[default]
+    Debug,
+    /// Print a single `error: {Display}` line to stderr
+    Plain,
+}
+
 impl<E> Terminate<E>
 where
     E: Display + Debug,
@@ -28,81 +286,1622 @@ where
     pub fn new() -> Self {
         Self {
             on_error: None,
+            on_install_error: None,
             at_exit: None,
             install: None,
+            panic_hooks: Vec::new(),
+            panic_to_error: None,
+            panic_policy: PanicPolicy::Unwind,
+            error_style: ErrorStyle::Debug,
+            reporter: None,
+            child_status: None,
+            exit_code: None,
+            error_context: None,
+            report_memory: false,
+            #[cfg(feature = "metrics")]
+            report_metrics: false,
+            report_runtime: false,
+            #[cfg(feature = "trace")]
+            report_trace: false,
+            worker_timeout: worker::DEFAULT_TIMEOUT,
+            worker_error: None,
+            already_executing_error: None,
+            #[cfg(feature = "pool")]
+            pools: Vec::new(),
+            heartbeats: Vec::new(),
+            detect_tty: false,
+            critical: Vec::new(),
+            exit_hooks: Vec::new(),
+            #[cfg(all(feature = "atexit", any(unix, windows)))]
+            atexit_error: None,
+            env: Vec::new(),
+            working_dir: None,
+            working_dir_error: None,
+            preflight: None,
+            preflight_error: None,
+            #[cfg(unix)]
+            signals: signal_action::SignalConfig::default(),
+            #[cfg(unix)]
+            signal_error: None,
+            #[cfg(unix)]
+            reexec: None,
+            stages: Vec::new(),
+            recorder: None,
+            #[cfg(feature = "runtime")]
+            runtime: None,
+            #[cfg(feature = "otel")]
+            flushes: Vec::new(),
             error: PhantomData,
         }
     }
 
+    /// Create a new typestate [`Builder`] which only allows each stage to be
+    /// configured once and only allows execution once the required stages are
+    /// configured
+    pub fn builder() -> Builder<E> {
+        Builder::new()
+    }
+
+    /// Set the environment variables in `vars` before install, restoring their
+    /// previous values once the program exits. This can be called multiple
+    /// times with the variables set in the order they were given. See the
+    /// [`environment`] module for more details.
+    ///
+    /// ```
+    /// # use futility::terminate::Terminate;
+    /// # use std::{env, io};
+    /// Terminate::<io::Error>::new()
+    ///     .scoped_env([("APP_LOG", "debug")])
+    ///     .execute(|| {
+    ///         assert_eq!(env::var("APP_LOG").unwrap(), "debug");
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    /// assert!(env::var_os("APP_LOG").is_none());
+    /// ```
+    pub fn scoped_env<K, V>(mut self, vars: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<OsString>,
+        V: Into<OsString>,
+    {
+        self.env.extend(
+            vars.into_iter()
+                .map(|(key, value)| (key.into(), value.into())),
+        );
+        self
+    }
+
+    /// Work out whether stdout and stderr are terminals and should be colored
+    /// before install, after any `scoped_env` variables are set, so that
+    /// install, the error reporters, and the panic hooks all agree on it. The
+    /// result is available from [`tty::output_style`]. See the [`tty`] module
+    /// for the environment variables that are taken into account.
+    ///
+    /// ```
+    /// # use futility::terminate::{tty, Terminate};
+    /// # use std::io;
+    /// Terminate::<io::Error>::new()
+    ///     .detect_tty()
+    ///     .install(|| {
+    ///         let color = tty::output_style().stderr_color;
+    ///         println!("Logging with color: {color}");
+    ///         Ok(())
+    ///     })
+    ///     .execute(|| Ok(()))
+    ///     .unwrap();
+    /// ```
+    pub fn detect_tty(mut self) -> Self {
+        self.detect_tty = true;
+        self
+    }
+
+    /// Change the working directory to `dir` before install, after any
+    /// `scoped_env` variables are set, and change back to the original
+    /// directory once the program exits. The original directory is available
+    /// from [`environment::original_dir`] while the program runs.
+    ///
+    /// ```
+    /// # use futility::terminate::{environment, Terminate};
+    /// # use std::{env, io};
+    /// let original = env::current_dir().unwrap();
+    /// Terminate::<io::Error>::new()
+    ///     .working_dir(env::temp_dir())
+    ///     .execute(|| {
+    ///         assert_eq!(env::current_dir()?, env::temp_dir().canonicalize()?);
+    ///         assert!(environment::original_dir().is_some());
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(env::current_dir().unwrap(), original);
+    /// ```
+    pub fn working_dir(mut self, dir: impl Into<PathBuf>) -> Self
+    where
+        E: From<io::Error>,
+    {
+        self.working_dir = Some(dir.into());
+        self.working_dir_error = Some(E::from);
+        self
+    }
+
+    /// Run every check in `preflight` before install, failing with a
+    /// [`PreflightError`] listing every check that failed if any did. See the
+    /// [`preflight`] module for more details.
+    pub fn preflight(mut self, preflight: Preflight) -> Self
+    where
+        E: From<PreflightError>,
+    {
+        self.preflight = Some(preflight);
+        self.preflight_error = Some(E::from);
+        self
+    }
+
     /// Install anything that needs to be installed before program execution
     /// like `tracing`
     pub fn install(mut self, install: fn() -> Result<(), E>) -> Self {
-        self.install = Some(install);
+        self.install = Some(Install::Direct(install));
         self
     }
 
-    /// Set a panic for the program that replaces the original panic hook
-    pub fn replace_panic(self, panic: impl Fn(&PanicInfo<'_>) + Send + Sync + 'static) -> Self {
-        panic::set_hook(Box::new(panic));
+    /// Install anything that needs to be installed before program execution,
+    /// using a function that fails with a different error type `I` than the
+    /// program. The error type is converted by following this with a call to
+    /// [`MapInstallError::map_install_error`].
+    ///
+    /// ```
+    /// # use futility::terminate::Terminate;
+    /// # use std::{error::Error, io};
+    /// fn setup() -> Result<(), io::Error> {
+    ///     Ok(())
+    /// }
+    ///
+    /// Terminate::new()
+    ///     .install_with_error(setup)
+    ///     .map_install_error(|err| -> Box<dyn Error> { format!("setup failed: {err}").into() })
+    ///     .execute(|| Ok(()))
+    ///     .unwrap();
+    /// ```
+    pub fn install_with_error<I>(self, install: fn() -> Result<(), I>) -> MapInstallError<E, I> {
+        MapInstallError {
+            terminate: self,
+            install,
+        }
+    }
+
+    /// Set a panic for the program that replaces the original panic hook. The
+    /// hook is installed when the program is executed.
+    pub fn replace_panic(
+        mut self,
+        panic: impl Fn(&PanicHookInfo<'_>) + Send + Sync + 'static,
+    ) -> Self {
+        self.panic_hooks
+            .push(Box::new(move || std::panic::set_hook(Box::new(panic))));
         self
     }
 
     /// Set a panic for the program that is invoked first followed by the
-    /// original panic hook
-    pub fn panic_with(self, panic: fn(&PanicInfo<'_>)) -> Self {
-        let original_hook = panic::take_hook();
-        panic::set_hook(Box::new(move |panic_info| {
-            panic(&panic_info);
-            original_hook(&panic_info);
+    /// original panic hook. The hook is installed when the program is
+    /// executed.
+    pub fn panic_with(mut self, panic: fn(&PanicHookInfo<'_>)) -> Self {
+        self.panic_hooks.push(Box::new(move || {
+            let original_hook = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |panic_info| {
+                panic(panic_info);
+                original_hook(panic_info);
+            }));
+        }));
+        self
+    }
+
+    /// Like [`Terminate::replace_panic`], but the hook is given a
+    /// [`PanicPayload`] that has already found the message of the panic
+    ///
+    /// ```
+    /// # use futility::terminate::Terminate;
+    /// # use std::error::Error;
+    /// Terminate::<Box<dyn Error>>::new()
+    ///     .replace_panic_payload(|panic| {
+    ///         eprintln!("crashed: {}", panic.message().unwrap_or("unknown"));
+    ///     })
+    ///     .execute(|| Ok(()))
+    ///     .unwrap();
+    /// ```
+    pub fn replace_panic_payload(
+        self,
+        panic: impl Fn(&PanicPayload<'_>) + Send + Sync + 'static,
+    ) -> Self {
+        self.replace_panic(move |panic_info| panic(&panic_info.into()))
+    }
+
+    /// Like [`Terminate::panic_with`], but the hook is given a
+    /// [`PanicPayload`] that has already found the message of the panic
+    pub fn panic_with_payload(mut self, panic: fn(&PanicPayload<'_>)) -> Self {
+        self.panic_hooks.push(Box::new(move || {
+            let original_hook = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |panic_info| {
+                panic(&panic_info.into());
+                original_hook(panic_info);
+            }));
         }));
         self
     }
 
+    /// Forward every panic to `tracing::error!` with the `futility::panic`
+    /// target, so that panics end up wherever the program's logs are
+    /// collected. The event's message is the panic message, and the
+    /// `location`, `thread`, and `backtrace` are recorded as fields. This is
+    /// invoked first followed by the original panic hook. The backtrace is
+    /// captured following the usual `RUST_BACKTRACE` rules. The hook is
+    /// installed when the program is executed.
+    #[cfg(feature = "tracing")]
+    pub fn log_panics(mut self) -> Self {
+        self.panic_hooks.push(Box::new(|| {
+            let original_hook = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |panic_info| {
+                let panic = PanicPayload::from(panic_info);
+                tracing::error!(
+                    target: "futility::panic",
+                    location = panic.location().unwrap_or("unknown"),
+                    thread = panic.thread_name().unwrap_or("<unnamed>"),
+                    backtrace = %std::backtrace::Backtrace::capture(),
+                    "{}",
+                    panic.message().unwrap_or(crate::panic::NON_STRING_PAYLOAD)
+                );
+                original_hook(panic_info);
+            }));
+        }));
+        self
+    }
+
+    /// Set what happens once a panic has been reported by the panic hooks. With
+    /// [`PanicPolicy::Abort`] a hook is added during install, after every
+    /// other panic hook, that runs the `at_exit_critical` functions and aborts
+    /// the process rather than unwinding.
+    pub fn on_panic(mut self, policy: PanicPolicy) -> Self {
+        self.panic_policy = policy;
+        self
+    }
+
+    /// Catch any panic in the program and turn it into an error with
+    /// `panic_to_error`, so that it is handled by `on_error` like any other
+    /// error. The panic hook still runs when the panic happens.
+    ///
+    /// ```
+    /// # use futility::terminate::Terminate;
+    /// # use std::error::Error;
+    /// let err = Terminate::<Box<dyn Error>>::new()
+    ///     .panic_to_error(|panic| {
+    ///         format!("panicked: {}", panic.message().unwrap_or("unknown")).into()
+    ///     })
+    ///     .execute(|| panic!("Oh no"))
+    ///     .unwrap_err();
+    /// assert_eq!(err.to_string(), "panicked: Oh no");
+    /// ```
+    pub fn panic_to_error(mut self, panic_to_error: fn(&PanicPayload<'_>) -> E) -> Self {
+        self.panic_to_error = Some(panic_to_error);
+        self
+    }
+
+    /// Replace the panic hook with one that writes a crash report file to the
+    /// configured directory and asks the user to submit it. See the [`crash`]
+    /// module for more details.
+    ///
+    /// ```no_run
+    /// # use futility::terminate::{CrashReports, Terminate};
+    /// # use std::error::Error;
+    /// Terminate::<Box<dyn Error>>::new()
+    ///     .crash_reports(CrashReports::new("/tmp").version(env!("CARGO_PKG_VERSION")))
+    ///     .execute(|| panic!("Oh no"))
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "crash-reports")]
+    pub fn crash_reports(self, crash_reports: impl Into<CrashReports>) -> Self {
+        let crash_reports = crash_reports.into();
+        self.replace_panic_payload(move |panic| crash_reports.report(panic))
+    }
+
+    /// Install a native crash handler during install that writes a crash
+    /// marker to the directory at `path` if the program crashes with a signal
+    /// such as `SIGSEGV`. The marker records the signal, its code, the faulting
+    /// address, and the process id, not the threads or stack of the program.
+    /// The handler is removed again after `at_exit` runs. See the
+    /// [`crash_marker`] module for more details.
+    #[cfg(all(unix, feature = "crash-marker"))]
+    pub fn capture_crash_markers(mut self, path: impl Into<PathBuf>) -> Self
+    where
+        E: From<io::Error>,
+    {
+        let path = path.into();
+        self.stages.push((
+            "capture crash markers",
+            Box::new(move || {
+                let client = crash_marker::MarkerClient::install(path)?;
+                Ok(Some(Box::new(move || client.finish())))
+            }),
+        ));
+        self
+    }
+
+    /// Redirect stdout and stderr to the given [`OutputTarget`] after install.
+    /// Both are flushed and restored after `at_exit` runs, regardless of how
+    /// the program exits. See the [`redirect`] module for more details.
+    ///
+    /// ```no_run
+    /// # use futility::terminate::{OutputTarget, Terminate};
+    /// # use std::io;
+    /// Terminate::<io::Error>::new()
+    ///     .redirect_output(OutputTarget::Tee("job.log".into()))
+    ///     .execute(|| {
+    ///         println!("This is written to both stdout and job.log");
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    /// ```
+    #[cfg(unix)]
+    pub fn redirect_output(mut self, target: OutputTarget) -> Self
+    where
+        E: From<io::Error>,
+    {
+        self.stages.push((
+            "redirect stdout and stderr",
+            Box::new(move || {
+                let redirection = redirect::Redirection::start(&target)?;
+                Ok(Some(Box::new(move || redirection.finish())))
+            }),
+        ));
+        self
+    }
+
+    /// Raise the soft limit on the number of open files to at least `limit`
+    /// during install. See [`rlimit::raise_nofile`] for more details.
+    ///
+    /// ```no_run
+    /// # use futility::terminate::{RlimitError, Terminate};
+    /// Terminate::<RlimitError>::new()
+    ///     .raise_nofile_limit(65536)
+    ///     .execute(|| Ok(()))
+    ///     .unwrap();
+    /// ```
+    #[cfg(all(unix, feature = "rlimit"))]
+    pub fn raise_nofile_limit(mut self, limit: u64) -> Self
+    where
+        E: From<RlimitError>,
+    {
+        self.stages.push((
+            "raise the open file limit",
+            Box::new(move || {
+                rlimit::raise_nofile(limit)?;
+                Ok(None)
+            }),
+        ));
+        self
+    }
+
+    /// Set the limits of each given [`Resource`] in order during install,
+    /// stopping at the first that fails
+    #[cfg(all(unix, feature = "rlimit"))]
+    pub fn set_rlimits(mut self, limits: impl IntoIterator<Item = (Resource, Limit)>) -> Self
+    where
+        E: From<RlimitError>,
+    {
+        let limits = limits.into_iter().collect::<Vec<_>>();
+        self.stages.push((
+            "set resource limits",
+            Box::new(move || {
+                for (resource, limit) in limits {
+                    rlimit::set(resource, limit)?;
+                }
+                Ok(None)
+            }),
+        ));
+        self
+    }
+
+    /// Make sure only one instance of the program runs at a time by locking
+    /// `lock_path` during install. If another instance holds the lock an
+    /// [`InstanceError::AlreadyRunning`] with its process id is handled by
+    /// `on_error`. The lock is released and the file removed on exit. See the
+    /// [`instance`] module for more details.
+    ///
+    /// ```no_run
+    /// # use futility::terminate::{InstanceError, Terminate};
+    /// Terminate::<InstanceError>::new()
+    ///     .single_instance("/tmp/my-program.lock")
+    ///     .execute(|| Ok(()))
+    ///     .unwrap();
+    /// ```
+    #[cfg(unix)]
+    pub fn single_instance(mut self, lock_path: impl Into<PathBuf>) -> Self
+    where
+        E: From<InstanceError>,
+    {
+        let lock_path = lock_path.into();
+        self.stages.push((
+            "lock the single instance file",
+            Box::new(move || {
+                let lock = InstanceLock::acquire(lock_path)?;
+                Ok(Some(Box::new(move || drop(lock))))
+            }),
+        ));
+        self
+    }
+
+    /// Install a [`SimpleLogger`](crate::log::SimpleLogger) filtered by
+    /// `RUST_LOG` before the program runs, and flush it when the program
+    /// exits. See the [`log`](crate::log) module for more details.
+    ///
+    /// ```
+    /// # use futility::{log, log::{Level, LogError}, terminate::Terminate};
+    /// Terminate::<LogError>::new()
+    ///     .install_simple_logger()
+    ///     .execute(|| {
+    ///         log!(Level::Info, "starting up");
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "log")]
+    pub fn install_simple_logger(mut self) -> Self
+    where
+        E: From<LogError>,
+    {
+        self.stages.push((
+            "install the simple logger",
+            Box::new(|| {
+                SimpleLogger::new().install()?;
+                Ok(Some(Box::new(crate::log::flush)))
+            }),
+        ));
+        self
+    }
+
+    /// Call `on_reload` whenever the program receives `SIGHUP` or
+    /// [`Handle::trigger_reload`] is called, such as to re-read configuration
+    /// without restarting. The function is called on a dedicated thread rather
+    /// than inside of the signal handler, so it is free to do anything.
+    ///
+    /// ```no_run
+    /// # use futility::terminate::Terminate;
+    /// # use std::io;
+    /// Terminate::<io::Error>::new()
+    ///     .on_reload(|| println!("Reloading configuration"))
+    ///     .execute(|| {
+    ///         futility::terminate::handle().trigger_reload();
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    /// ```
+    #[cfg(unix)]
+    pub fn on_reload(mut self, on_reload: fn()) -> Self
+    where
+        E: From<io::Error>,
+    {
+        self.signals.reload = Some(on_reload);
+        self.signal_error = Some(E::from);
+        self
+    }
+
+    /// Handle `SIGINT` and `SIGTERM` by triggering the [`ShutdownToken`]
+    /// available from [`Handle::shutdown_token`] rather than killing the
+    /// program. What happens when another signal arrives during shutdown is
+    /// decided by the [`ShutdownPolicy`]. See the [`shutdown_policy`] module
+    /// for more details.
+    #[cfg(unix)]
+    pub fn handle_signals(mut self, policy: ShutdownPolicy) -> Self
+    where
+        E: From<io::Error>,
+    {
+        self.signals.shutdown = Some(policy);
+        self.signal_error = Some(E::from);
+        self
+    }
+
+    /// Once the program has exited for `reason` and everything has been cleaned
+    /// up, replace it with a fresh copy of the binary it was started from,
+    /// using the same arguments. This allows a program to upgrade itself in
+    /// place by having its binary replaced and then being sent a signal. If
+    /// `reason` is [`ExitReason::Signal`] then receiving that signal triggers
+    /// the [`ShutdownToken`] so that the program can return from `main`.
+    ///
+    /// ```no_run
+    /// # use futility::terminate::{self, ExitReason, Signal, Terminate};
+    /// # use std::io;
+    /// Terminate::<io::Error>::new()
+    ///     .reexec_on(ExitReason::Signal(Signal::from_raw(libc::SIGUSR2)))
+    ///     .execute(|| {
+    ///         terminate::handle().shutdown_token().wait();
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    /// ```
+    #[cfg(unix)]
+    pub fn reexec_on(mut self, reason: ExitReason) -> Self
+    where
+        E: From<io::Error>,
+    {
+        if let ExitReason::Signal(signal) = reason {
+            self.signals.reexec = Some(signal.as_raw());
+            self.signal_error = Some(E::from);
+        }
+        self.reexec = Some(reason);
+        self
+    }
+
+    /// Handle `signal` with `action` rather than its default action, such as
+    /// calling a function to dump diagnostics on `SIGUSR1`. Setting an action
+    /// for the same signal again replaces the previous one. See the
+    /// [`signal_action`] module for more details.
+    #[cfg(unix)]
+    pub fn signal(mut self, signal: Signal, action: signal_action::Action) -> Self
+    where
+        E: From<io::Error>,
+    {
+        let signal = signal.as_raw();
+        self.signals
+            .actions
+            .retain(|(existing, _)| *existing != signal);
+        self.signals.actions.push((signal, action));
+        self.signal_error = Some(E::from);
+        self
+    }
+
+    /// Once a shutdown signal handled by [`Terminate::handle_signals`] is
+    /// received, give the program `grace` to return from `main`. If it hasn't
+    /// by then `at_exit` is called with [`ExitReason::Timeout`], given up to
+    /// `at_exit_timeout` to finish, followed by every `at_exit_critical`
+    /// function, and the program exits with [`GRACE_PERIOD_EXIT_CODE`]. Without
+    /// `handle_signals` the grace period never starts, which
+    /// [`Terminate::validate`] reports as an error.
+    #[cfg(unix)]
+    pub fn grace_period(mut self, grace: Duration, at_exit_timeout: Duration) -> Self
+    where
+        E: From<io::Error>,
+    {
+        self.signals.grace = Some(shutdown_policy::GracePeriod {
+            grace,
+            at_exit_timeout,
+        });
+        self.signal_error = Some(E::from);
+        self
+    }
+
+    /// Call `beat` every `interval` on its own thread from when `main` starts
+    /// until it returns, such as to tell an external monitor that a long
+    /// running job is still alive. The heartbeat is always stopped before
+    /// `on_error` and `at_exit` run. This can be called multiple times to run
+    /// several heartbeats, each on their own thread.
+    ///
+    /// ```
+    /// # use futility::terminate::Terminate;
+    /// # use std::{io, thread, time::Duration};
+    /// Terminate::<io::Error>::new()
+    ///     .heartbeat(Duration::from_millis(10), || println!("still alive"))
+    ///     .execute(|| {
+    ///         thread::sleep(Duration::from_millis(50));
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn heartbeat(mut self, interval: Duration, beat: impl FnMut() + Send + 'static) -> Self {
+        self.heartbeats.push(heartbeat::Heartbeat {
+            interval,
+            beat: Box::new(beat),
+        });
+        self
+    }
+
+    /// Start `watchdog` during install and stop it when the program exits,
+    /// after `at_exit` runs. `main` feeds it with the clone it keeps. See the
+    /// [`watchdog`](crate::watchdog) module for more details.
+    ///
+    /// ```
+    /// # use futility::{terminate::Terminate, watchdog::Watchdog};
+    /// # use std::{io, time::Duration};
+    /// let watchdog = Watchdog::new(Duration::from_secs(30));
+    /// Terminate::<io::Error>::new()
+    ///     .watchdog(&watchdog)
+    ///     .execute(|| {
+    ///         // Fed each time around the program's main loop
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    /// watchdog.feed();
+    /// ```
+    #[cfg(feature = "watchdog")]
+    pub fn watchdog(mut self, watchdog: &crate::watchdog::Watchdog) -> Self
+    where
+        E: From<io::Error>,
+    {
+        let watchdog = watchdog.clone();
+        self.stages.push((
+            "start the watchdog",
+            Box::new(move || {
+                let running = watchdog.start()?;
+                Ok(Some(Box::new(move || running.stop())))
+            }),
+        ));
+        self
+    }
+
+    /// Shut `pool` down when the program exits, right after any workers
+    /// spawned with [`Handle::spawn_tracked`] and before `at_exit` runs,
+    /// giving its queued jobs up to `timeout` to finish. The error of each
+    /// job that panicked, failed, or didn't finish in time is printed to
+    /// stderr. See the [`pool`](crate::pool) module for more details.
+    ///
+    /// ```
+    /// # use futility::{pool::ThreadPool, terminate::Terminate};
+    /// # use std::{io, time::Duration};
+    /// let pool = ThreadPool::new(4)?;
+    /// Terminate::<io::Error>::new()
+    ///     .thread_pool(&pool, Duration::from_secs(10))
+    ///     .execute(|| Ok(()))?;
+    /// assert!(!pool.is_open());
+    /// # Ok::<_, io::Error>(())
+    /// ```
+    #[cfg(feature = "pool")]
+    pub fn thread_pool(mut self, pool: &crate::pool::ThreadPool, timeout: Duration) -> Self {
+        self.pools.push((pool.clone(), timeout));
+        self
+    }
+
+    /// Add a hook that runs when the program exits in the `priority` tier,
+    /// alongside the hooks registered with [`at_exit!`](crate::at_exit). The
+    /// `at_exit` function runs before every tier. See the [`registry`] module
+    /// for the order hooks are run in.
+    ///
+    /// ```
+    /// # use futility::terminate::{ExitPriority, Terminate};
+    /// # use std::io;
+    /// Terminate::<io::Error>::new()
+    ///     .exit_hook(ExitPriority::Release, || println!("Closing files"))
+    ///     .exit_hook(ExitPriority::Flush, || println!("Flushing logs"))
+    ///     .exit_hook(ExitPriority::Report, || println!("Reporting errors"))
+    ///     .execute(|| Ok(()))
+    ///     .unwrap();
+    /// ```
+    pub fn exit_hook(
+        mut self,
+        priority: ExitPriority,
+        hook: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        self.exit_hooks.push((priority, Box::new(hook)));
+        self
+    }
+
+    /// Add a function that must run when the program exits, even if it is
+    /// forced to exit by a repeated shutdown signal. These are run in the order
+    /// they were added after `at_exit`, and should be kept short.
+    pub fn at_exit_critical(mut self, critical: fn()) -> Self {
+        self.critical.push(critical);
+        self
+    }
+
+    /// Register a function with the C runtime's `atexit` during install so
+    /// that the `at_exit_critical` functions still run if something calls
+    /// [`std::process::exit`] directly, which otherwise skips all of
+    /// `Terminate`'s exit steps. They are only run once, so this does nothing
+    /// if the program exits normally.
+    ///
+    /// When run this way the critical functions are called while the process
+    /// is exiting and other threads may still be running, possibly holding
+    /// locks. They should do as little as possible, avoid allocating, avoid
+    /// taking locks used elsewhere in the program, and must not call
+    /// `process::exit` themselves.
+    ///
+    /// ```no_run
+    /// # use futility::terminate::Terminate;
+    /// # use std::{io, process};
+    /// Terminate::<io::Error>::new()
+    ///     .at_exit_critical(|| println!("Removed the lock file"))
+    ///     .register_libc_atexit()
+    ///     .execute(|| process::exit(1))
+    ///     .unwrap();
+    /// ```
+    #[cfg(all(feature = "atexit", any(unix, windows)))]
+    pub fn register_libc_atexit(mut self) -> Self
+    where
+        E: From<io::Error>,
+    {
+        self.atexit_error = Some(E::from);
+        self
+    }
+
+    /// Flush and shut down a telemetry provider, such as an OpenTelemetry
+    /// tracer or meter provider, as the very last step of exiting. If it
+    /// takes longer than `timeout` the program exits without waiting for it.
+    /// Providers are flushed in the order they were added. See the
+    /// [`telemetry`] module for more details.
+    #[cfg(feature = "otel")]
+    pub fn flush_telemetry(mut self, provider: impl FlushTelemetry, timeout: Duration) -> Self {
+        self.flushes.push(telemetry::Flush {
+            provider: Box::new(provider),
+            timeout,
+        });
+        self
+    }
+
     /// When there is an error in the main program set what should happen
     pub fn on_error(mut self, on_error: fn(E) -> E) -> Self {
         self.on_error = Some(on_error);
         self
     }
 
+    /// When `install`, or any setup that other options need, fails set what
+    /// should happen instead of calling `on_error`. This lets startup failures
+    /// be treated differently from errors in the program itself, such as
+    /// exiting with a different code. If this is not set `on_error` handles
+    /// both.
+    ///
+    /// ```
+    /// # use futility::terminate::Terminate;
+    /// # use std::error::Error;
+    /// let err = Terminate::<Box<dyn Error>>::new()
+    ///     .install(|| Err("missing config".into()))
+    ///     .on_install_error(|err| format!("startup failed: {err}").into())
+    ///     .on_error(|err| format!("program failed: {err}").into())
+    ///     .execute(|| Ok(()))
+    ///     .unwrap_err();
+    /// assert_eq!(err.to_string(), "startup failed: missing config");
+    /// ```
+    pub fn on_install_error(mut self, on_install_error: fn(E) -> E) -> Self {
+        self.on_install_error = Some(on_install_error);
+        self
+    }
+
     /// When the program is going to exit, regardless of if there is an error or
     /// not, set what should be done
     pub fn at_exit(mut self, at_exit: fn()) -> Self {
-        self.at_exit = Some(at_exit);
+        self.at_exit = Some(AtExit::Plain(at_exit));
         self
     }
 
+    /// When the program is going to exit, regardless of if there is an error or
+    /// not, set what should be done with the [`ExitInfo`] gathered about the
+    /// program, such as the [`ExitReason`] it is exiting for. This replaces any
+    /// function set with `at_exit`.
+    pub fn at_exit_with(mut self, at_exit: fn(&ExitInfo)) -> Self {
+        self.at_exit = Some(AtExit::WithInfo(at_exit));
+        self
+    }
+
+    /// Print the peak memory usage of the program to stderr when it exits.
+    /// This is measured with [`memory::peak_rss`] and is also available to
+    /// the `at_exit_with` function regardless of if this is set.
+    pub fn report_memory(mut self) -> Self {
+        self.report_memory = true;
+        self
+    }
+
+    /// Print the value of every [`metrics`](crate::metrics) counter and gauge
+    /// to stderr when the program exits. The values are also available to the
+    /// `at_exit_with` function regardless of if this is set.
+    #[cfg(feature = "metrics")]
+    pub fn report_metrics(mut self) -> Self {
+        self.report_metrics = true;
+        self
+    }
+
+    /// Print how long the program ran for to stderr when it exits, measured
+    /// from the start of install, along with a summary of the times recorded
+    /// with `time::Sink::Record` if the `time` feature is on. This is also
+    /// available to the `at_exit_with` function regardless of if this is set.
+    pub fn report_runtime(mut self) -> Self {
+        self.report_runtime = true;
+        self
+    }
+
+    /// Print every [`span!`](crate::span) that has ended to stderr when the
+    /// program exits, as a tree of how long each one took. See the
+    /// [`trace`](crate::trace) module for more details.
+    #[cfg(feature = "trace")]
+    pub fn report_trace(mut self) -> Self {
+        self.report_trace = true;
+        self
+    }
+
+    /// Attach `context`, such as the program's version and selected
+    /// environment variables, to the error printed by [`Terminate::run`] and
+    /// to crash reports. It is available from [`enrich::current`] for custom
+    /// reporters and panic hooks. See the [`enrich`] module for more details.
+    pub fn enrich_errors(mut self, context: ErrorContext) -> Self {
+        self.error_context = Some(context);
+        self
+    }
+
+    /// Set how [`Terminate::run`] presents an error that made it out of the
+    /// program and which [`ExitCode`] it returns, replacing the
+    /// [`ErrorStyle`]. See the [`reporter`] module for more details.
+    pub fn reporter(mut self, reporter: impl ErrorReporter<E> + 'static) -> Self {
+        self.reporter = Some(Box::new(reporter));
+        self
+    }
+
+    /// When the error returned from the program was caused by a child process
+    /// failing, make [`Terminate::run`] exit with the child's exit code rather
+    /// than the usual one. See the [`child`] module for more details.
+    pub fn propagate_child_status(mut self) -> Self
+    where
+        E: ChildStatus,
+    {
+        self.child_status = Some(|err| err.child_failed().map(ChildFailed::exit_code));
+        self
+    }
+
+    /// Make [`Terminate::run`] exit with the code of the error returned from
+    /// the program rather than the one from the [`ErrorReporter`]. A child's
+    /// status from [`Terminate::propagate_child_status`] still takes
+    /// precedence. See the [`exit`](crate::exit) module for more details.
+    pub fn exit_coded(mut self) -> Self
+    where
+        E: ExitCoded,
+    {
+        self.exit_code = Some(E::code);
+        self
+    }
+
+    /// Fail with [`AlreadyExecuting`] rather than panicking if this is
+    /// executed from inside of a program that is already being run by a
+    /// `Terminate`. The error is returned as is, without calling `on_error` or
+    /// anything else, as those belong to the program already running.
+    ///
+    /// ```
+    /// # use futility::terminate::Terminate;
+    /// # use std::error::Error;
+    /// Terminate::<Box<dyn Error>>::new()
+    ///     .execute(|| {
+    ///         let err = Terminate::<Box<dyn Error>>::new()
+    ///             .already_executing_error()
+    ///             .execute(|| Ok(()))
+    ///             .unwrap_err();
+    ///         assert!(err.to_string().starts_with("a Terminate was executed"));
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn already_executing_error(mut self) -> Self
+    where
+        E: From<AlreadyExecuting>,
+    {
+        self.already_executing_error = Some(E::from);
+        self
+    }
+
+    /// Set how long to wait for workers spawned with
+    /// [`Handle::spawn_tracked`] to stop when the program exits. This is five
+    /// seconds by default.
+    pub fn worker_timeout(mut self, timeout: Duration) -> Self {
+        self.worker_timeout = timeout;
+        self
+    }
+
+    /// Fail the program with the [`WorkerErrors`] of any workers spawned with
+    /// [`Handle::spawn_tracked`] that panicked, failed, or didn't stop in
+    /// time, if it would otherwise have succeeded. The error is handled by
+    /// `on_error` like any other. Without this they are only printed to
+    /// stderr.
+    ///
+    /// ```
+    /// # use futility::terminate::{self, Terminate};
+    /// # use std::{error::Error, io};
+    /// let err = Terminate::<Box<dyn Error>>::new()
+    ///     .propagate_worker_errors()
+    ///     .execute(|| {
+    ///         terminate::handle().spawn_tracked("flusher", |_| -> io::Result<()> {
+    ///             panic!("Oh no")
+    ///         })?;
+    ///         Ok(())
+    ///     })
+    ///     .unwrap_err();
+    /// assert_eq!(err.to_string(), "worker `flusher` panicked: Oh no");
+    /// ```
+    pub fn propagate_worker_errors(mut self) -> Self
+    where
+        E: From<WorkerErrors>,
+    {
+        self.worker_error = Some(E::from);
+        self
+    }
+
+    /// Set how [`Terminate::run`] prints an error that made it out of the
+    /// program
+    pub fn error_style(mut self, error_style: ErrorStyle) -> Self {
+        self.error_style = error_style;
+        self
+    }
+
+    /// Check the configuration for mistakes, such as a signal being given two
+    /// different jobs, and describe what would run when executing the program
+    /// without running anything. See the [`plan`] module for more details.
+    pub fn validate(self) -> Result<Plan, ConfigError> {
+        let can_fail_install = self.install.is_some()
+            || self.preflight.is_some()
+            || !self.stages.is_empty()
+            || self.signal_started();
+        if self.on_install_error.is_some() && !can_fail_install {
+            return Err(ConfigError::UnusedHook {
+                hook: "on_install_error",
+                reason: "nothing runs before the program that can fail",
+            });
+        }
+        if self.panic_to_error.is_some() && self.panic_policy == PanicPolicy::Abort {
+            return Err(ConfigError::UnusedHook {
+                hook: "panic_to_error",
+                reason: "panics abort the program with PanicPolicy::Abort",
+            });
+        }
+        #[cfg(unix)]
+        {
+            if self.signals.grace.is_some() && self.signals.shutdown.is_none() {
+                return Err(ConfigError::GraceWithoutSignals);
+            }
+            let mut claimed = Vec::new();
+            if self.signals.reload.is_some() {
+                claimed.push((libc::SIGHUP, "on_reload"));
+            }
+            if self.signals.shutdown.is_some() {
+                claimed.extend([
+                    (libc::SIGINT, "handle_signals"),
+                    (libc::SIGTERM, "handle_signals"),
+                ]);
+            }
+            if let Some(reexec) = self.signals.reexec {
+                claimed.push((reexec, "reexec_on"));
+            }
+            claimed.extend(
+                self.signals
+                    .actions
+                    .iter()
+                    .map(|(signal, _)| (*signal, "signal")),
+            );
+            for (i, (signal, second)) in claimed.iter().enumerate() {
+                if let Some((_, first)) = claimed[..i].iter().find(|(other, _)| other == signal) {
+                    return Err(ConfigError::ConflictingSignal {
+                        signal: Signal::from_raw(*signal),
+                        first,
+                        second,
+                    });
+                }
+            }
+        }
+
+        let mut plan = Plan::default();
+        if !self.env.is_empty() {
+            let vars = self.env.iter().map(|(key, _)| key.to_string_lossy());
+            plan.step(format!(
+                "set environment variables: {}",
+                vars.collect::<Vec<_>>().join(", ")
+            ));
+        }
+        if self.detect_tty {
+            plan.step("detect whether output goes to a terminal");
+        }
+        if let Some(dir) = &self.working_dir {
+            plan.step(format!("change the working directory to {}", dir.display()));
+        }
+        if let Some(preflight) = &self.preflight {
+            plan.step(format!(
+                "run preflight checks: {}",
+                preflight.names().collect::<Vec<_>>().join(", ")
+            ));
+        }
+        if self.install.is_some() {
+            plan.step("run install");
+        }
+        for (stage, _) in &self.stages {
+            plan.step(*stage);
+        }
+        #[cfg(unix)]
+        {
+            if self.signals.reload.is_some() {
+                plan.step("handle SIGHUP by calling on_reload");
+            }
+            if let Some(policy) = self.signals.shutdown {
+                plan.step(format!(
+                    "handle SIGINT and SIGTERM by shutting down with {policy:?}"
+                ));
+            }
+            if let Some(grace) = self.signals.grace {
+                plan.step(format!(
+                    "force exit if main doesn't return within {:?} of a shutdown signal",
+                    grace.grace
+                ));
+            }
+            if let Some(reexec) = self.signals.reexec {
+                plan.step(format!(
+                    "handle {} by shutting down",
+                    Signal::from_raw(reexec)
+                ));
+            }
+            for (signal, action) in &self.signals.actions {
+                plan.step(format!("handle {} by {action}", Signal::from_raw(*signal)));
+            }
+        }
+        if self.panic_policy == PanicPolicy::Abort {
+            plan.step("abort the process after reporting a panic");
+        }
+        for heartbeat in &self.heartbeats {
+            plan.step(format!(
+                "run a heartbeat every {:?} while main runs",
+                heartbeat.interval
+            ));
+        }
+        plan.step(match self.panic_to_error {
+            Some(_) => "run main, turning panics into errors",
+            None => "run main",
+        });
+        match (self.on_install_error, self.on_error) {
+            (Some(_), Some(_)) => {
+                plan.step("call on_install_error if install fails");
+                plan.step("call on_error if main fails");
+            }
+            (Some(_), None) => plan.step("call on_install_error if install fails"),
+            (None, Some(_)) => plan.step("call on_error on failure"),
+            (None, None) => {}
+        }
+        if self.report_runtime {
+            plan.step("report the runtime");
+        }
+        if self.report_memory {
+            plan.step("report the peak memory usage");
+        }
+        #[cfg(feature = "metrics")]
+        if self.report_metrics {
+            plan.step("report the metrics");
+        }
+        #[cfg(feature = "trace")]
+        if self.report_trace {
+            plan.step("report the trace");
+        }
+        #[cfg(feature = "pool")]
+        if !self.pools.is_empty() {
+            plan.step(format!("shut down {} thread pool(s)", self.pools.len()));
+        }
+        if let Some(at_exit) = self.at_exit {
+            plan.step(match at_exit {
+                AtExit::Plain(_) => "run at_exit",
+                AtExit::WithInfo(_) => "run at_exit_with",
+            });
+        }
+        if !self.exit_hooks.is_empty() {
+            plan.step(format!(
+                "run {} exit hook(s) in priority order",
+                self.exit_hooks.len()
+            ));
+        }
+        #[cfg(all(feature = "atexit", any(unix, windows)))]
+        if self.atexit_error.is_some() {
+            plan.step("run the at_exit_critical functions if the process exits early");
+        }
+        if !self.critical.is_empty() {
+            plan.step(format!(
+                "run {} at_exit_critical function(s)",
+                self.critical.len()
+            ));
+        }
+        #[cfg(feature = "otel")]
+        if !self.flushes.is_empty() {
+            plan.step(format!(
+                "flush {} telemetry provider(s)",
+                self.flushes.len()
+            ));
+        }
+        #[cfg(unix)]
+        if let Some(reason) = self.reexec {
+            plan.step(format!(
+                "re-execute the program after exiting with reason `{reason}`"
+            ));
+        }
+        Ok(plan)
+    }
+
+    /// Whether a signal handler is registered during install
+    fn signal_started(&self) -> bool {
+        #[cfg(unix)]
+        {
+            self.signal_error.is_some()
+        }
+        #[cfg(not(unix))]
+        {
+            false
+        }
+    }
+
     /// Execute your program with the given function. This will:
     ///
-    /// 1. Call the provided `install` function.
-    /// 2. If there was an error it will call the `on_error` function if it exists
-    /// 3. If there was an error then the `at_exit` function is called if it
-    ///    exists
-    /// 4. Call the provided the function to `execute`
-    /// 5. If there was an error it will call the `on_error` function if it exists
-    /// 6. If there was an error then the `at_exit` function is called if it
-    ///    exists
+    /// 1. Set any environment variables given to `scoped_env`, change to the
+    ///    `working_dir`, run the `preflight` checks, and call the provided
+    ///    `install` function.
+    /// 2. Run any setup that other options on `Terminate` need, such as
+    ///    installing a crash handler
+    /// 3. If there were no errors call the provided function to `execute`
+    /// 4. Stop and join any workers spawned with [`Handle::spawn_tracked`],
+    ///    failing the program with their errors if `propagate_worker_errors`
+    ///    is set and it would otherwise have succeeded
+    /// 5. If there was an error at any point it will call the `on_error`
+    ///    function if it exists, or the `on_install_error` function instead
+    ///    if the error happened in step 1 or 2 and it exists
+    /// 6. Print any reports that were asked for, such as `report_runtime`
+    /// 7. Shut down any pools given to `thread_pool`, then call the `at_exit`
+    ///    function if it exists, anything registered with
+    ///    [`at_exit!`](crate::at_exit), and every `at_exit_critical` function
+    /// 8. Tear down anything that was setup in step 2 in reverse order
+    /// 9. Flush any telemetry providers given to `flush_telemetry`
+    /// 10. Replace the program with a fresh copy of itself if it exited for
+    ///     the reason given to `reexec_on`
+    ///
+    /// With the `tracing` feature enabled steps 1 and 2 run in a `lifecycle`
+    /// span with `phase = "install"`, step 3 with `phase = "main"`, step 5
+    /// with `phase = "on_error"`, and steps 7 through 9 with
+    /// `phase = "at_exit"`. An event with the `duration_ms` and `outcome` of
+    /// each phase is emitted when it finishes, all with the
+    /// `futility::lifecycle` target.
+    ///
+    /// Only one `Terminate` can execute at a time in a process. Executing one
+    /// while another is running on a different thread waits for that one to
+    /// finish first. Executing one from inside of the program fails with
+    /// [`AlreadyExecuting`] if `already_executing_error` is set, and panics
+    /// otherwise.
     pub fn execute(self, main: fn() -> Result<(), E>) -> Result<(), E> {
-        if let Some(install) = self.install {
-            let mut res = install();
-            res = match (self.on_error, res) {
-                (Some(on_error), Err(err)) => Err(on_error(err)),
-                (_, res) => res,
+        self.execute_with(main).result
+    }
+
+    /// Execute your program like [`Terminate::execute`], also returning how
+    /// long each phase of the program took
+    ///
+    /// ```
+    /// # use futility::terminate::Terminate;
+    /// # use std::error::Error;
+    /// let (res, timings) = Terminate::<Box<dyn Error>>::new().execute_timed(|| Ok(()));
+    /// res.unwrap();
+    /// println!("install took {:?}", timings.install);
+    /// ```
+    pub fn execute_timed(self, main: fn() -> Result<(), E>) -> (Result<(), E>, LifecycleTimings) {
+        let finished = self.execute_with(main);
+        (finished.result, finished.timings)
+    }
+
+    /// Execute your program like [`Terminate::execute`], returning an
+    /// [`Outcome`] with why it exited, how long each phase took, and whether
+    /// it panicked along with any error. This is meant for test harnesses and
+    /// other programs that embed a program run by `Terminate`.
+    ///
+    /// ```
+    /// # use futility::terminate::{ExitReason, Terminate};
+    /// # use std::error::Error;
+    /// let outcome = Terminate::<Box<dyn Error>>::new()
+    ///     .panic_to_error(|_| "panicked".into())
+    ///     .execute_outcome(|| panic!("Oh no"));
+    /// assert_eq!(outcome.reason, ExitReason::Panic);
+    /// assert!(outcome.panicked);
+    /// assert_eq!(outcome.error.unwrap().to_string(), "panicked");
+    /// ```
+    pub fn execute_outcome(self, main: fn() -> Result<(), E>) -> Outcome<E> {
+        let finished = self.execute_with(main);
+        Outcome {
+            reason: finished.reason,
+            error: finished.result.err(),
+            timings: finished.timings,
+            panicked: finished.panicked,
+            workers_failed: finished.workers_failed,
+        }
+    }
+
+    /// Configure how the runtime used by [`Terminate::execute_async`] is built.
+    /// See the [`runtime`] module for more details.
+    #[cfg(feature = "runtime")]
+    pub fn runtime(mut self, configure: impl FnOnce(RuntimeBuilder) -> RuntimeBuilder) -> Self {
+        self.runtime = Some(configure(self.runtime.take().unwrap_or_default()));
+        self
+    }
+
+    /// Execute an async program like [`Terminate::execute`]. The tokio
+    /// runtime configured with [`Terminate::runtime`] is built after every
+    /// other part of install, the program is run on it, and it is shut down
+    /// after `at_exit`.
+    #[cfg(feature = "runtime")]
+    pub fn execute_async<F>(mut self, main: impl FnOnce() -> F) -> Result<(), E>
+    where
+        F: Future<Output = Result<(), E>>,
+        E: From<io::Error> + 'static,
+    {
+        let builder = self.runtime.take().unwrap_or_default();
+        let runtime = Rc::new(RefCell::new(None));
+        let slot = Rc::clone(&runtime);
+        self.stages.push((
+            "build the async runtime",
+            Box::new(move || {
+                *slot.borrow_mut() = Some(builder.build()?);
+                Ok(Some(Box::new(move || {
+                    if let Some(runtime) = slot.borrow_mut().take() {
+                        runtime.shutdown();
+                    }
+                })))
+            }),
+        ));
+        self.execute_with(|| {
+            let runtime = runtime.borrow();
+            let runtime = runtime
+                .as_ref()
+                .expect("the runtime is built during install");
+            runtime.block_on(main())
+        })
+        .result
+    }
+
+    /// Execute a [`Program`] like [`Terminate::execute`]. The program's
+    /// `install` runs after every other part of install and its `shutdown`
+    /// runs after `at_exit`. See the [`program`] module for more details.
+    pub fn run_program<P>(mut self, program: P) -> Result<(), E>
+    where
+        P: Program<Error = E> + 'static,
+        E: 'static,
+    {
+        let program = Rc::new(RefCell::new(program));
+        let installed = Rc::clone(&program);
+        self.stages.push((
+            "run the program's install",
+            Box::new(move || {
+                installed.borrow_mut().install()?;
+                Ok(Some(Box::new(move || installed.borrow_mut().shutdown())))
+            }),
+        ));
+        self.execute_with(|| program.borrow_mut().run(&mut ProgramContext::new()))
+            .result
+    }
+
+    /// Execute one of several entry points to your program chosen by `name`,
+    /// such as the subcommand a CLI was invoked with. Every subcommand shares
+    /// the same configuration and is executed the same way as
+    /// [`Terminate::execute`]. If `name` does not match any of the
+    /// `subcommands` then an [`UnknownSubcommand`] error is passed through
+    /// `on_error` instead.
+    ///
+    /// ```
+    /// # use futility::terminate::{Terminate, UnknownSubcommand};
+    /// # use std::error::Error;
+    /// fn build() -> Result<(), Box<dyn Error>> {
+    ///     println!("Building");
+    ///     Ok(())
+    /// }
+    /// fn clean() -> Result<(), Box<dyn Error>> {
+    ///     println!("Cleaning");
+    ///     Ok(())
+    /// }
+    ///
+    /// Terminate::new()
+    ///     .at_exit(|| println!("Exiting"))
+    ///     .execute_subcommand("build", &[("build", build), ("clean", clean)])
+    ///     .unwrap();
+    /// ```
+    pub fn execute_subcommand(self, name: &str, subcommands: &[(&str, Main<E>)]) -> Result<(), E>
+    where
+        E: From<UnknownSubcommand>,
+    {
+        let main = subcommands
+            .iter()
+            .find(|(subcommand, _)| *subcommand == name)
+            .map(|(_, main)| *main);
+        self.execute_with(|| match main {
+            Some(main) => main(),
+            None => Err(UnknownSubcommand {
+                name: name.into(),
+                available: subcommands.iter().map(|(name, _)| (*name).into()).collect(),
+            }
+            .into()),
+        })
+        .result
+    }
+
+    fn execute_with(mut self, main: impl FnOnce() -> Result<(), E>) -> Finished<E> {
+        // A harness::Harness only records what would happen, so it can run
+        // alongside a real program
+        let _running = match self.recorder.is_none().then(Running::acquire) {
+            Some(Err(err)) => {
+                let Some(into_error) = self.already_executing_error else {
+                    panic!("{err}");
+                };
+                return Finished {
+                    result: Err(into_error(err)),
+                    timings: LifecycleTimings::default(),
+                    reason: ExitReason::InstallError,
+                    panicked: false,
+                    workers_failed: false,
+                };
+            }
+            running => running,
+        };
+        if self.recorder.is_none() {
+            crate::shutdown::reset_global();
+        }
+        let start = Instant::now();
+        let mut timings = LifecycleTimings::default();
+        let mut teardowns = Vec::new();
+        let recorder = self.recorder.clone();
+        let (on_error, on_install_error) = (self.on_error, self.on_install_error);
+        let handle_error = |install: bool, err: E| {
+            let handler = match install {
+                true => on_install_error.or(on_error),
+                false => on_error,
             };
-            if let Some(at_exit) = self.at_exit {
-                at_exit()
+            match handler {
+                Some(handler) => {
+                    harness::record(&recorder, || match on_install_error {
+                        Some(_) if install => Hook::OnInstallError {
+                            error: err.to_string(),
+                        },
+                        _ => Hook::OnError {
+                            error: err.to_string(),
+                        },
+                    });
+                    lifecycle::phase("on_error", || handler(err), |_| PhaseOutcome::Ok)
+                }
+                None => err,
+            }
+        };
+        let started = lifecycle::phase("install", || self.start(&mut teardowns), PhaseOutcome::of);
+        timings.install = start.elapsed();
+        let (res, reason, installed) = match started {
+            Ok(()) => {
+                let main_start = Instant::now();
+                let panic_to_error = self.panic_to_error;
+                harness::record(&recorder, || Hook::Main);
+                let heartbeats = self
+                    .heartbeats
+                    .drain(..)
+                    .filter_map(heartbeat::Heartbeat::start)
+                    .collect::<Vec<_>>();
+                shutdown_policy::main_started();
+                let (res, reason) = lifecycle::phase(
+                    "main",
+                    || match panic_to_error {
+                        Some(panic_to_error) => {
+                            panic_hook::record_locations();
+                            match std::panic::catch_unwind(AssertUnwindSafe(main)) {
+                                Ok(res) => (res, None),
+                                Err(payload) => (
+                                    Err(panic_to_error(&PanicPayload::caught(&*payload))),
+                                    Some(ExitReason::Panic),
+                                ),
+                            }
+                        }
+                        None => (main(), None),
+                    },
+                    |(res, reason)| match reason {
+                        Some(_) => PhaseOutcome::Panic,
+                        None => PhaseOutcome::of(res),
+                    },
+                );
+                shutdown_policy::main_finished();
+                drop(heartbeats);
+                timings.main = main_start.elapsed();
+                let reason = reason.unwrap_or(match res {
+                    Ok(()) => ExitReason::Success,
+                    Err(_) => ExitReason::Error,
+                });
+                (res, reason, true)
             }
-            res?;
+            Err(err) => (Err(err), ExitReason::InstallError, false),
+        };
+        let worker_errors = worker::join_all(self.worker_timeout);
+        // Panicked pool jobs are counted in at_exit
+        #[cfg_attr(not(feature = "pool"), allow(unused_mut))]
+        let mut panicked = reason == ExitReason::Panic
+            || worker_errors
+                .iter()
+                .any(|err| matches!(err, WorkerError::Panicked { .. }));
+        let workers_failed = !worker_errors.is_empty();
+        let (res, reason) = match (res, self.worker_error) {
+            (Ok(()), Some(into_error)) if workers_failed => (
+                Err(into_error(WorkerErrors {
+                    errors: worker_errors,
+                })),
+                ExitReason::Error,
+            ),
+            (res, _) => {
+                for err in worker_errors {
+                    eprintln!("{}: {err}", tty::error_label());
+                }
+                (res, reason)
+            }
+        };
+        let res = res.map_err(|err| handle_error(!installed, err));
+        #[cfg(unix)]
+        let reason = match (reason, signal_action::shutdown_signal()) {
+            (ExitReason::Success | ExitReason::Error, Some(signal)) => ExitReason::Signal(signal),
+            (reason, _) => reason,
+        };
+        let runtime = start.elapsed();
+        timings.shutdown = runtime - timings.install - timings.main;
+        let info = ExitInfo {
+            peak_memory: memory::peak_rss(),
+            runtime,
+            reason,
+            timings,
+            #[cfg(feature = "metrics")]
+            metrics: crate::metrics::snapshot(),
+        };
+        if self.report_runtime {
+            eprintln!("runtime: {:.2?}", info.runtime);
+            #[cfg(feature = "time")]
+            crate::time::print_summary();
+        }
+        if self.report_memory {
+            match info.peak_memory {
+                Some(peak) => eprintln!("peak memory usage: {}", memory::Bytes(peak)),
+                None => eprintln!("peak memory usage: unavailable"),
+            }
+        }
+        #[cfg(feature = "metrics")]
+        if self.report_metrics && !info.metrics.is_empty() {
+            crate::tty::print_report("metrics", &info.metrics);
+        }
+        #[cfg(feature = "trace")]
+        if self.report_trace {
+            crate::trace::dump();
         }
+        lifecycle::phase(
+            "at_exit",
+            || {
+                #[cfg(feature = "pool")]
+                for (pool, timeout) in self.pools.drain(..) {
+                    let deadline = crate::budget::Deadline::after(timeout);
+                    for (index, err) in pool
+                        .shutdown(deadline)
+                        .err()
+                        .into_iter()
+                        .flat_map(|err| err.errors)
+                    {
+                        panicked |= matches!(err, crate::pool::JobError::Panicked(_));
+                        eprintln!("{}: thread pool job {index}: {err}", tty::error_label());
+                    }
+                }
+                if let Some(at_exit) = self.at_exit {
+                    harness::record(&recorder, || Hook::AtExit {
+                        reason: info.reason,
+                    });
+                    at_exit.call(&info);
+                }
+                registry::run_all();
+                #[cfg(all(feature = "atexit", any(unix, windows)))]
+                atexit::disarm();
+                for critical in &self.critical {
+                    harness::record(&recorder, || Hook::AtExitCritical);
+                    critical();
+                }
+                for teardown in teardowns.into_iter().rev() {
+                    teardown();
+                }
+                #[cfg(feature = "otel")]
+                for flush in self.flushes {
+                    flush.run();
+                }
+            },
+            |()| PhaseOutcome::Ok,
+        );
+        #[cfg(unix)]
+        if self.reexec == Some(reason) {
+            eprintln!("failed to re-execute the program: {}", reexec::exec());
+        }
+        timings.shutdown = start.elapsed() - timings.install - timings.main;
 
-        let mut res = main();
-        res = match (self.on_error, res) {
-            (Some(on_error), Err(err)) => Err(on_error(err)),
-            (_, res) => res,
-        };
-        if let Some(at_exit) = self.at_exit {
-            at_exit()
+        Finished {
+            result: res,
+            timings,
+            reason,
+            panicked,
+            workers_failed,
         }
+    }
 
-        res
+    /// Set any scoped environment variables, change the working directory, run
+    /// the preflight checks and the `install` function, and then every stage
+    /// in order, collecting the teardowns of the stages that ran successfully
+    fn start(&mut self, teardowns: &mut Vec<Teardown>) -> Result<(), E> {
+        for install in self.panic_hooks.drain(..) {
+            install();
+        }
+        environment::capture();
+        enrich::set(self.error_context.clone());
+        for (priority, hook) in self.exit_hooks.drain(..) {
+            registry::register_with(priority, hook);
+        }
+        if !self.env.is_empty() {
+            let env = environment::ScopedEnv::set(&self.env);
+            teardowns.push(Box::new(move || env.restore()));
+        }
+        if self.detect_tty {
+            tty::detect();
+        }
+        if let (Some(dir), Some(into_error)) = (&self.working_dir, self.working_dir_error) {
+            let dir = environment::ScopedDir::set(dir).map_err(into_error)?;
+            teardowns.push(Box::new(move || dir.restore()));
+        }
+        if let (Some(preflight), Some(into_error)) = (self.preflight.take(), self.preflight_error) {
+            harness::record(&self.recorder, || Hook::Preflight);
+            preflight.run().map_err(into_error)?;
+        }
+        if self.install.is_some() {
+            harness::record(&self.recorder, || Hook::Install);
+        }
+        match self.install.take() {
+            Some(Install::Direct(install)) => install()?,
+            Some(Install::Mapped(install)) => install()?,
+            None => {}
+        }
+        if self.panic_policy == PanicPolicy::Abort {
+            teardowns.push(Box::new(panic_hook::abort_after_hook(
+                self.critical.clone(),
+            )));
+        }
+        #[cfg(all(feature = "atexit", any(unix, windows)))]
+        if let Some(into_error) = self.atexit_error {
+            atexit::register(&self.critical).map_err(into_error)?;
+        }
+        for (_, stage) in self.stages.drain(..) {
+            if let Some(teardown) = stage()? {
+                teardowns.push(teardown);
+            }
+        }
+        #[cfg(unix)]
+        if let Some(into_error) = self.signal_error {
+            let handler = std::mem::take(&mut self.signals)
+                .start(self.at_exit, self.critical.clone())
+                .map_err(into_error)?;
+            teardowns.push(Box::new(move || signal_action::stop(handler)));
+        }
+        Ok(())
+    }
+
+    /// Execute your program like [`Terminate::execute`], but rather than
+    /// handing the error back to `main` print it according to the configured
+    /// [`ErrorStyle`], or with the [`ErrorReporter`] if one is set, and return
+    /// the [`ExitCode`] to exit the program with.
+    ///
+    /// ```no_run
+    /// # use futility::terminate::{ErrorStyle, Terminate};
+    /// # use std::{error::Error, process::ExitCode};
+    /// fn main() -> ExitCode {
+    ///     Terminate::new()
+    ///         .error_style(ErrorStyle::Plain)
+    ///         .run(|| -> Result<(), Box<dyn Error>> { Err("Always fails".into()) })
+    /// }
+    /// ```
+    pub fn run(mut self, main: fn() -> Result<(), E>) -> ExitCode {
+        let error_style = self.error_style;
+        let reporter = self.reporter.take();
+        let child_status = self.child_status;
+        let exit_code = self.exit_code;
+        let finished = self.execute_with(main);
+        let err = match finished.result {
+            // Workers failing without `propagate_worker_errors` have already
+            // been printed
+            Ok(()) if finished.workers_failed => return ExitCode::FAILURE,
+            Ok(()) => return ExitCode::SUCCESS,
+            Err(err) => err,
+        };
+        let code = match reporter {
+            Some(reporter) => reporter.report(&err, finished.reason),
+            None => {
+                match error_style {
+                    ErrorStyle::Debug => eprintln!("Error: {err:?}"),
+                    ErrorStyle::Plain => eprintln!("{}: {err}", tty::error_label()),
+                }
+                reporter::print_context();
+                ExitCode::FAILURE
+            }
+        };
+        child_status
+            .and_then(|child_status| child_status(&err))
+            .or_else(|| exit_code.map(|exit_code| ExitCode::from(exit_code(&err))))
+            .unwrap_or(code)
+    }
+}
+
+impl<E> Default for Terminate<E>
+where
+    E: Display + Debug,
+{
+    fn default() -> Self {
+        Self::new()
     }
 }
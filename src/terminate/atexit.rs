@@ -0,0 +1,66 @@
+//! Running `at_exit_critical` functions when the process exits without
+//! returning from [`Terminate::execute`](super::Terminate::execute)
+//!
+//! Calling [`std::process::exit`] skips every destructor and hook that
+//! `Terminate` would normally run. Registering a function with the C runtime's
+//! `atexit` lets the `at_exit_critical` functions run anyway, since
+//! `process::exit` still calls those on the way out. Only the critical
+//! functions are run this way as they run inside the C runtime's exit
+//! handling, where other threads may still be running and may hold locks, so
+//! they should not allocate heavily, take locks that the rest of the program
+//! uses, or call `process::exit` themselves.
+
+use std::{
+    io,
+    sync::{Mutex, Once},
+};
+
+/// The functions run when the process exits, cleared once they have run or
+/// `Terminate` has run them itself
+static CRITICAL: Mutex<Vec<fn()>> = Mutex::new(Vec::new());
+
+/// The C runtime has no way to unregister a function, so it is only
+/// registered once no matter how many times `Terminate` executes
+static REGISTER: Once = Once::new();
+
+#[cfg(unix)]
+use libc::atexit;
+
+#[cfg(windows)]
+extern "C" {
+    fn atexit(callback: extern "C" fn()) -> std::os::raw::c_int;
+}
+
+/// Run `critical` when the process exits unless [`disarm`] is called first
+pub(crate) fn register(critical: &[fn()]) -> io::Result<()> {
+    *lock() = critical.to_vec();
+    let mut res = Ok(());
+    REGISTER.call_once(|| {
+        // SAFETY: run is safe to call at any point during exit
+        if unsafe { atexit(run) } != 0 {
+            res = Err(io::Error::other("could not register an atexit function"));
+        }
+    });
+    res
+}
+
+/// Stop the registered functions from running when the process exits,
+/// because they are about to be run some other way
+pub(crate) fn disarm() {
+    lock().clear();
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Vec<fn()>> {
+    CRITICAL.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+extern "C" fn run() {
+    // A panic can't unwind out of an extern "C" function, and there is nothing
+    // useful to do with one this late anyway
+    let _ = std::panic::catch_unwind(|| {
+        let critical = std::mem::take(&mut *lock());
+        for critical in critical {
+            critical();
+        }
+    });
+}
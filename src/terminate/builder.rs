@@ -0,0 +1,226 @@
+//! A typestate variant of [`Terminate`] that rejects misconfiguration at
+//! compile time
+//!
+//! The regular [`Terminate`] type will happily let you call `on_error` twice
+//! with the second call silently overwriting the first, or replace a panic hook
+//! that was just chained onto. [`Builder`] tracks which stages have been
+//! configured in its type so that each stage can only be set once and
+//! `execute` only exists once the required stages have been configured.
+//!
+//! Currently the only required stage is `on_error`, so that errors returned
+//! from the program are always handled by something.
+//!
+//! ```
+//! # use futility::terminate::Terminate;
+//! # use std::error::Error;
+//! Terminate::builder()
+//!     .at_exit(|| println!("Exiting"))
+//!     .on_error(|err: Box<dyn Error>| err)
+//!     .execute(|| Ok(()))
+//!     .unwrap();
+//! ```
+//!
+//! Configuring the same stage twice fails to compile:
+//!
+//! ```compile_fail
+//! # use futility::terminate::Terminate;
+//! # use std::error::Error;
+//! Terminate::builder()
+//!     .on_error(|err: Box<dyn Error>| err)
+//!     .on_error(|err: Box<dyn Error>| err)
+//!     .execute(|| Ok(()));
+//! ```
+//!
+//! As does executing without the required stages:
+//!
+//! ```compile_fail
+//! # use futility::terminate::Terminate;
+//! # use std::error::Error;
+//! Terminate::<Box<dyn Error>>::builder()
+//!     .at_exit(|| println!("Exiting"))
+//!     .execute(|| Ok(()));
+//! ```
+
+use super::{ErrorReporter, ErrorStyle, ExitInfo, PanicPayload, Terminate};
+use std::{
+    fmt::{Debug, Display},
+    marker::PhantomData,
+    panic::PanicHookInfo,
+    process::ExitCode,
+};
+
+/// Marker type for a stage that has been configured
+pub struct Set;
+
+/// Marker type for a stage that has not been configured yet
+pub struct Unset;
+
+/// A typestate builder for [`Terminate`]. Each type parameter after the error
+/// type tracks whether the `install`, `on_error`, `at_exit`, and panic hook
+/// stages have been configured yet.
+pub struct Builder<E, Install = Unset, OnError = Unset, AtExit = Unset, Panic = Unset>
+where
+    E: Display + Debug,
+{
+    terminate: Terminate<E>,
+    state: PhantomData<(Install, OnError, AtExit, Panic)>,
+}
+
+impl<E> Builder<E>
+where
+    E: Display + Debug,
+{
+    /// Create a new Builder with no stages configured
+    pub fn new() -> Self {
+        Self {
+            terminate: Terminate::new(),
+            state: PhantomData,
+        }
+    }
+}
+
+impl<E> Default for Builder<E>
+where
+    E: Display + Debug,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E, I, O, A, P> Builder<E, I, O, A, P>
+where
+    E: Display + Debug,
+{
+    fn transition<I2, O2, A2, P2>(terminate: Terminate<E>) -> Builder<E, I2, O2, A2, P2> {
+        Builder {
+            terminate,
+            state: PhantomData,
+        }
+    }
+
+    /// Set a panic for the program that is invoked first followed by the
+    /// previously set panic hook. This can be called multiple times, but once
+    /// called the hook can no longer be replaced with `replace_panic`.
+    pub fn panic_with(self, panic: fn(&PanicHookInfo<'_>)) -> Builder<E, I, O, A, Set> {
+        Self::transition(self.terminate.panic_with(panic))
+    }
+
+    /// Like [`Builder::panic_with`], but the hook is given a [`PanicPayload`]
+    /// that has already found the message of the panic
+    pub fn panic_with_payload(self, panic: fn(&PanicPayload<'_>)) -> Builder<E, I, O, A, Set> {
+        Self::transition(self.terminate.panic_with_payload(panic))
+    }
+
+    /// When install fails set what should happen instead of calling `on_error`
+    pub fn on_install_error(self, on_install_error: fn(E) -> E) -> Self {
+        Self::transition(self.terminate.on_install_error(on_install_error))
+    }
+
+    /// Print the peak memory usage of the program to stderr when it exits
+    pub fn report_memory(self) -> Self {
+        Self::transition(self.terminate.report_memory())
+    }
+
+    /// Print how long the program ran for to stderr when it exits
+    pub fn report_runtime(self) -> Self {
+        Self::transition(self.terminate.report_runtime())
+    }
+
+    /// Set how `run` presents an error that made it out of the program and
+    /// which exit code it returns, replacing the error style
+    pub fn reporter(self, reporter: impl ErrorReporter<E> + 'static) -> Self {
+        Self::transition(self.terminate.reporter(reporter))
+    }
+
+    /// Set how `run` prints an error that made it out of the program
+    pub fn error_style(self, error_style: ErrorStyle) -> Self {
+        Self::transition(self.terminate.error_style(error_style))
+    }
+}
+
+impl<E, O, A, P> Builder<E, Unset, O, A, P>
+where
+    E: Display + Debug,
+{
+    /// Install anything that needs to be installed before program execution
+    /// like `tracing`
+    pub fn install(self, install: fn() -> Result<(), E>) -> Builder<E, Set, O, A, P> {
+        Self::transition(self.terminate.install(install))
+    }
+}
+
+impl<E, I, A, P> Builder<E, I, Unset, A, P>
+where
+    E: Display + Debug,
+{
+    /// When there is an error in the main program set what should happen
+    pub fn on_error(self, on_error: fn(E) -> E) -> Builder<E, I, Set, A, P> {
+        Self::transition(self.terminate.on_error(on_error))
+    }
+}
+
+impl<E, I, O, P> Builder<E, I, O, Unset, P>
+where
+    E: Display + Debug,
+{
+    /// When the program is going to exit, regardless of if there is an error or
+    /// not, set what should be done
+    pub fn at_exit(self, at_exit: fn()) -> Builder<E, I, O, Set, P> {
+        Self::transition(self.terminate.at_exit(at_exit))
+    }
+
+    /// When the program is going to exit, regardless of if there is an error or
+    /// not, set what should be done with the [`ExitInfo`] gathered about the
+    /// program
+    pub fn at_exit_with(self, at_exit: fn(&ExitInfo)) -> Builder<E, I, O, Set, P> {
+        Self::transition(self.terminate.at_exit_with(at_exit))
+    }
+}
+
+impl<E, I, O, A> Builder<E, I, O, A, Unset>
+where
+    E: Display + Debug,
+{
+    /// Set a panic for the program that replaces the original panic hook. This
+    /// is only available if no other panic hook has been configured.
+    pub fn replace_panic(
+        self,
+        panic: impl Fn(&PanicHookInfo<'_>) + Send + Sync + 'static,
+    ) -> Builder<E, I, O, A, Set> {
+        Self::transition(self.terminate.replace_panic(panic))
+    }
+
+    /// Like [`Builder::replace_panic`], but the hook is given a
+    /// [`PanicPayload`] that has already found the message of the panic. This
+    /// is only available if no other panic hook has been configured.
+    pub fn replace_panic_payload(
+        self,
+        panic: impl Fn(&PanicPayload<'_>) + Send + Sync + 'static,
+    ) -> Builder<E, I, O, A, Set> {
+        Self::transition(self.terminate.replace_panic_payload(panic))
+    }
+}
+
+impl<E, I, A, P> Builder<E, I, Set, A, P>
+where
+    E: Display + Debug,
+{
+    /// Turn the builder into a regular [`Terminate`] now that the required
+    /// stages are configured
+    pub fn build(self) -> Terminate<E> {
+        self.terminate
+    }
+
+    /// Execute your program with the given function. See
+    /// [`Terminate::execute`] for the order in which stages are run.
+    pub fn execute(self, main: fn() -> Result<(), E>) -> Result<(), E> {
+        self.terminate.execute(main)
+    }
+
+    /// Execute your program with the given function and print any error. See
+    /// [`Terminate::run`] for more details.
+    pub fn run(self, main: fn() -> Result<(), E>) -> ExitCode {
+        self.terminate.run(main)
+    }
+}
@@ -0,0 +1,108 @@
+//! Passing the exit status of a child process through as the program's own
+//!
+//! Wrappers and launchers should exit with whatever code the program they ran
+//! exited with rather than a generic `1`. Returning a [`ChildFailed`] error,
+//! either directly or as the source of another error, and calling
+//! [`Terminate::propagate_child_status`](super::Terminate::propagate_child_status)
+//! makes [`Terminate::run`](super::Terminate::run) exit with the child's code.
+//! A child that was killed by signal `N` is exited with as `128 + N`, like a
+//! shell does.
+//!
+//! ```no_run
+//! # use futility::terminate::{child::ChildFailed, Terminate};
+//! # use std::{error::Error, process::{Command, ExitCode}};
+//! fn main() -> ExitCode {
+//!     Terminate::<Box<dyn Error>>::new()
+//!         .propagate_child_status()
+//!         .run(|| {
+//!             let status = Command::new("make").status()?;
+//!             ChildFailed::check("make", status)?;
+//!             Ok(())
+//!         })
+//! }
+//! ```
+
+use crate::error::ErrorChainExt;
+use std::{error::Error, fmt, process::ExitCode, process::ExitStatus};
+
+/// A child process that exited unsuccessfully
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChildFailed {
+    /// The command that was run
+    pub command: String,
+    /// How it exited
+    pub status: ExitStatus,
+}
+
+impl ChildFailed {
+    /// Turn the `status` of `command` into an error if it wasn't successful
+    pub fn check(command: impl Into<String>, status: ExitStatus) -> Result<(), ChildFailed> {
+        if status.success() {
+            Ok(())
+        } else {
+            Err(ChildFailed {
+                command: command.into(),
+                status,
+            })
+        }
+    }
+
+    /// The code the program should exit with to match the child. Codes that
+    /// don't fit in a `u8`, which Windows allows, are clamped to `1..=255`
+    /// rather than truncated so that a failure never becomes a success.
+    pub fn code(&self) -> u8 {
+        if let Some(code) = self.status.code() {
+            return code.clamp(1, u8::MAX.into()) as u8;
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if let Some(signal) = self.status.signal() {
+                return (128 + signal) as u8;
+            }
+        }
+        1
+    }
+
+    /// The [`ExitCode`] the program should exit with to match the child
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(self.code())
+    }
+}
+
+impl fmt::Display for ChildFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` failed: {}", self.command, self.status)
+    }
+}
+
+impl Error for ChildFailed {}
+
+/// An error that might have been caused by a [`ChildFailed`]
+pub trait ChildStatus {
+    /// The child process failure that caused this error, if any
+    fn child_failed(&self) -> Option<&ChildFailed>;
+}
+
+impl ChildStatus for ChildFailed {
+    fn child_failed(&self) -> Option<&ChildFailed> {
+        Some(self)
+    }
+}
+
+impl ChildStatus for Box<dyn Error> {
+    fn child_failed(&self) -> Option<&ChildFailed> {
+        find(&**self)
+    }
+}
+
+impl ChildStatus for Box<dyn Error + Send + Sync> {
+    fn child_failed(&self) -> Option<&ChildFailed> {
+        find(&**self)
+    }
+}
+
+/// Find a [`ChildFailed`] in `err` or its chain of sources
+pub fn find<'a>(err: &'a (dyn Error + 'static)) -> Option<&'a ChildFailed> {
+    err.find_source()
+}
@@ -0,0 +1,229 @@
+//! Crash report files written when the program panics
+//!
+//! When enabled with [`Terminate::crash_reports`](super::Terminate::crash_reports)
+//! a panic will no longer print the usual `thread 'main' panicked at` message.
+//! Instead a report containing the program version, operating system,
+//! arguments, panic message, and backtrace is written to a file and the user is
+//! asked to submit it in a friendly message like so:
+//!
+//! ```text
+//! Well, this is embarrassing.
+//!
+//! my-program had a problem and crashed. To help us diagnose the problem you
+//! can send us a crash report.
+//!
+//! We have generated a report file at "/tmp/my-program-3124-1665849600.txt".
+//! Please submit an issue with the report attached.
+//! ```
+//!
+//! With the `diagnostics` feature the report also describes the machine, as
+//! collected by [`diagnostics::collect`](crate::diagnostics::collect).
+
+use super::{enrich, tty, PanicPayload};
+use std::{
+    backtrace::Backtrace,
+    env,
+    fmt::Write as _,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Configuration for where crash reports get written and what program they
+/// describe
+#[derive(Clone, Debug)]
+pub struct CrashReports {
+    dir: PathBuf,
+    name: Option<String>,
+    version: Option<String>,
+    #[cfg(feature = "buildinfo")]
+    build: Option<crate::buildinfo::BuildInfo>,
+}
+
+impl CrashReports {
+    /// Create a new configuration that writes crash reports to `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            name: None,
+            version: None,
+            #[cfg(feature = "buildinfo")]
+            build: None,
+        }
+    }
+
+    /// Set the name of the program used in the report. Defaults to the file
+    /// name of the current executable.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set the version of the program used in the report. This is usually
+    /// `env!("CARGO_PKG_VERSION")`.
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Set the version of the program used in the report from `build`, and
+    /// include its git commit, target, and profile
+    ///
+    /// ```
+    /// # use futility::{build_info, terminate::crash::CrashReports};
+    /// let crash_reports = CrashReports::new("/tmp").build_info(build_info!());
+    /// ```
+    #[cfg(feature = "buildinfo")]
+    pub fn build_info(mut self, build: crate::buildinfo::BuildInfo) -> Self {
+        self.version = Some(build.version.into());
+        self.build = Some(build);
+        self
+    }
+
+    /// Write a crash report for the given panic and let the user know where
+    /// to find it
+    pub(crate) fn report(&self, panic: &PanicPayload<'_>) {
+        let name = self.name.clone().unwrap_or_else(program_name);
+        let report = self.render(&name, panic);
+        let path = self.write(&name, &report);
+
+        let mut stderr = io::stderr().lock();
+        let _ = writeln!(stderr, "{}\n", tty::bold("Well, this is embarrassing."));
+        let _ = writeln!(
+            stderr,
+            "{name} had a problem and crashed. To help us diagnose the problem you\n\
+             can send us a crash report.\n"
+        );
+        match path {
+            Ok(path) => {
+                let _ = writeln!(
+                    stderr,
+                    "We have generated a report file at {path:?}.\n\
+                     Please submit an issue with the report attached."
+                );
+            }
+            Err(err) => {
+                let _ = writeln!(
+                    stderr,
+                    "We were unable to write a report file ({err}), so here it is instead:\n\n\
+                     {report}"
+                );
+            }
+        }
+    }
+
+    fn render(&self, name: &str, panic: &PanicPayload<'_>) -> String {
+        let context = enrich::current();
+        let mut report = String::new();
+        let _ = writeln!(report, "name = {name:?}");
+        let version = self.version.as_deref().or(context
+            .as_ref()
+            .and_then(|context| context.version.as_deref()));
+        let _ = writeln!(report, "version = {:?}", version.unwrap_or("unknown"));
+        #[cfg(feature = "buildinfo")]
+        let build_sha = self.build.and_then(|build| build.git_sha);
+        #[cfg(not(feature = "buildinfo"))]
+        let build_sha = None;
+        if let Some(git_sha) = build_sha.or(context
+            .as_ref()
+            .and_then(|context| context.git_sha.as_deref()))
+        {
+            let _ = writeln!(report, "git_sha = {git_sha:?}");
+        }
+        #[cfg(feature = "buildinfo")]
+        if let Some(build) = self.build {
+            if let Some(target) = build.target {
+                let _ = writeln!(report, "target = {target:?}");
+            }
+            let _ = writeln!(report, "profile = {:?}", build.profile);
+        }
+        let _ = writeln!(
+            report,
+            "operating_system = \"{} {}\"",
+            env::consts::OS,
+            env::consts::ARCH
+        );
+        let _ = writeln!(report, "arguments = {:?}", env::args().collect::<Vec<_>>());
+        if let Some(context) = &context {
+            let vars = context
+                .vars()
+                .into_iter()
+                .map(|(key, value)| format!("{key:?} = {value:?}"))
+                .collect::<Vec<_>>();
+            let _ = writeln!(report, "environment = {{ {} }}", vars.join(", "));
+        }
+        let _ = writeln!(
+            report,
+            "message = {:?}",
+            panic.message().unwrap_or(crate::panic::NON_STRING_PAYLOAD)
+        );
+        if let Some(location) = panic.location() {
+            let _ = writeln!(report, "location = \"{location}\"");
+        }
+        #[cfg(feature = "diagnostics")]
+        {
+            // The directory isn't created until the report is written
+            let dir = self.dir.ancestors().find(|dir| dir.exists());
+            let paths = env::current_dir()
+                .into_iter()
+                .chain([env::temp_dir()])
+                .chain(dir.map(Path::to_path_buf));
+            let _ = writeln!(
+                report,
+                "\n[diagnostics]\n{}",
+                crate::diagnostics::collect_for(paths)
+            );
+        }
+        let _ = writeln!(report, "\n{}", Backtrace::force_capture());
+        report
+    }
+
+    fn write(&self, name: &str, report: &str) -> io::Result<PathBuf> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+        fs::create_dir_all(&self.dir)?;
+        let path = self
+            .dir
+            .join(format!("{name}-{}-{timestamp}.txt", process::id()));
+        fs::write(&path, report)?;
+        Ok(path)
+    }
+}
+
+impl From<PathBuf> for CrashReports {
+    fn from(dir: PathBuf) -> Self {
+        Self::new(dir)
+    }
+}
+
+impl From<&Path> for CrashReports {
+    fn from(dir: &Path) -> Self {
+        Self::new(dir)
+    }
+}
+
+impl From<String> for CrashReports {
+    fn from(dir: String) -> Self {
+        Self::new(dir)
+    }
+}
+
+impl From<&str> for CrashReports {
+    fn from(dir: &str) -> Self {
+        Self::new(dir)
+    }
+}
+
+fn program_name() -> String {
+    env::current_exe()
+        .ok()
+        .and_then(|exe| {
+            exe.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "program".into())
+}
@@ -0,0 +1,223 @@
+//! Markers left behind by native crashes that a panic hook will never see
+//!
+//! Segmentation faults, bus errors, illegal instructions, floating point
+//! exceptions, and aborts kill the program without unwinding, which means none
+//! of the panic hooks or `at_exit` functions run. When enabled with
+//! [`Terminate::capture_crash_markers`] a native crash handler is installed
+//! that writes a crash marker saying how the program crashed before handing
+//! the signal back to whatever handler was installed before it.
+//!
+//! A crash marker is not a minidump or a core dump. It has to be written from
+//! inside of a signal handler where allocating or taking locks is not allowed,
+//! so it only records the signal and where it came from, not the threads,
+//! registers, or stack of the program. It is a plain text file with a `.crash`
+//! extension and the following contents:
+//!
+//! ```text
+//! signal = 11
+//! code = 1
+//! address = 0x0
+//! pid = 3124
+//! ```
+//!
+//! The marker is opened ahead of time when the handler is installed and is
+//! removed again when the program exits normally without a crash.
+//!
+//! [`Terminate::capture_crash_markers`]: super::Terminate::capture_crash_markers
+
+use libc::{c_int, c_void, sigaction, siginfo_t, SA_ONSTACK, SA_SIGINFO};
+use std::{
+    fs::{self, File},
+    io,
+    os::unix::io::IntoRawFd,
+    path::PathBuf,
+    process, ptr,
+    sync::atomic::{AtomicI32, AtomicPtr, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The signals that are considered a native crash
+const SIGNALS: [c_int; 5] = [
+    libc::SIGSEGV,
+    libc::SIGBUS,
+    libc::SIGILL,
+    libc::SIGFPE,
+    libc::SIGABRT,
+];
+
+/// The signature of a `SA_SIGINFO` signal handler
+type Handler = extern "C" fn(c_int, *mut siginfo_t, *mut c_void);
+
+/// The file descriptor of the crash marker or -1 if it is not open
+static MARKER_FD: AtomicI32 = AtomicI32::new(-1);
+
+/// The handlers that were installed before ours for each of [`SIGNALS`]
+static PREVIOUS: [AtomicPtr<sigaction>; 5] = [
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+];
+
+/// A handle to the installed crash handler that uninstalls it and cleans up
+/// the crash marker when finished
+pub(crate) struct MarkerClient {
+    path: PathBuf,
+}
+
+impl MarkerClient {
+    /// Open a new crash marker in `dir` and install the crash handler
+    pub(crate) fn install(dir: PathBuf) -> io::Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}-{timestamp}.crash", process::id()));
+        let fd = File::create(&path)?.into_raw_fd();
+        close(MARKER_FD.swap(fd, Ordering::SeqCst));
+        let client = Self { path };
+
+        for (signal, previous) in SIGNALS.iter().zip(&PREVIOUS) {
+            // SAFETY: We fully initialize the action before passing it to
+            // sigaction and the previous action is written into memory we own
+            unsafe {
+                let mut action: sigaction = std::mem::zeroed();
+                action.sa_sigaction = handle_crash as Handler as usize;
+                action.sa_flags = SA_SIGINFO | SA_ONSTACK;
+                libc::sigemptyset(&mut action.sa_mask);
+                let old = Box::into_raw(Box::new(std::mem::zeroed::<sigaction>()));
+                if libc::sigaction(*signal, &action, old) != 0 {
+                    let err = io::Error::last_os_error();
+                    drop(Box::from_raw(old));
+                    // Put back the handlers replaced so far and close the
+                    // crash marker rather than leaving half a crash handler
+                    client.finish();
+                    return Err(err);
+                }
+                let old = previous.swap(old, Ordering::SeqCst);
+                if !old.is_null() {
+                    drop(Box::from_raw(old));
+                }
+            }
+        }
+
+        Ok(client)
+    }
+
+    /// Restore the previous crash handlers, close the crash marker, and remove
+    /// it if nothing was written to it
+    pub(crate) fn finish(self) {
+        for (signal, previous) in SIGNALS.iter().zip(&PREVIOUS) {
+            let old = previous.swap(ptr::null_mut(), Ordering::SeqCst);
+            if !old.is_null() {
+                // SAFETY: The pointer came from Box::into_raw in install and
+                // has been taken out of PREVIOUS so nothing else can use it
+                unsafe {
+                    libc::sigaction(*signal, old, ptr::null_mut());
+                    drop(Box::from_raw(old));
+                }
+            }
+        }
+        close(MARKER_FD.swap(-1, Ordering::SeqCst));
+        if fs::metadata(&self.path).is_ok_and(|meta| meta.len() == 0) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn close(fd: c_int) {
+    if fd >= 0 {
+        // SAFETY: We own the file descriptor as it came from into_raw_fd
+        unsafe {
+            libc::close(fd);
+        }
+    }
+}
+
+extern "C" fn handle_crash(signal: c_int, info: *mut siginfo_t, _: *mut c_void) {
+    // SAFETY: Only async-signal-safe functions are called in here and info is
+    // either null or valid for the duration of the handler
+    unsafe {
+        let (code, address) = if info.is_null() {
+            (0, 0)
+        } else {
+            ((*info).si_code, (*info).si_addr() as usize)
+        };
+
+        let fd = MARKER_FD.swap(-1, Ordering::SeqCst);
+        if fd >= 0 {
+            let mut buf = Buf::new();
+            buf.push(b"signal = ");
+            buf.push_num(signal as u64, 10);
+            buf.push(b"\ncode = ");
+            if code < 0 {
+                buf.push(b"-");
+            }
+            buf.push_num(code.unsigned_abs() as u64, 10);
+            buf.push(b"\naddress = 0x");
+            buf.push_num(address as u64, 16);
+            buf.push(b"\npid = ");
+            buf.push_num(libc::getpid() as u64, 10);
+            buf.push(b"\n");
+            libc::write(fd, buf.bytes.as_ptr().cast(), buf.len);
+            libc::fsync(fd);
+            libc::close(fd);
+        }
+
+        // Hand the signal back to whatever was installed before us. Faults
+        // will be raised again when the faulting instruction is retried, but
+        // signals sent by a process or abort need to be raised again manually
+        if let Some(index) = SIGNALS.iter().position(|sig| *sig == signal) {
+            let previous = PREVIOUS[index].load(Ordering::SeqCst);
+            if previous.is_null() {
+                libc::signal(signal, libc::SIG_DFL);
+            } else {
+                libc::sigaction(signal, previous, ptr::null_mut());
+            }
+        }
+        if signal == libc::SIGABRT || code <= 0 {
+            libc::raise(signal);
+        }
+    }
+}
+
+/// A fixed size buffer that can be written to without allocating
+struct Buf {
+    bytes: [u8; 128],
+    len: usize,
+}
+
+impl Buf {
+    fn new() -> Self {
+        Self {
+            bytes: [0; 128],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            if self.len < self.bytes.len() {
+                self.bytes[self.len] = *byte;
+                self.len += 1;
+            }
+        }
+    }
+
+    fn push_num(&mut self, mut num: u64, radix: u64) {
+        let mut digits = [0; 20];
+        let mut len = 0;
+        loop {
+            digits[len] = b"0123456789abcdef"[(num % radix) as usize];
+            len += 1;
+            num /= radix;
+            if num == 0 {
+                break;
+            }
+        }
+        digits[..len].reverse();
+        self.push(&digits[..len]);
+    }
+}
@@ -0,0 +1,200 @@
+//! Context attached to error and crash reports
+//!
+//! A bug report that only contains an error message usually needs a round trip
+//! to find out which version was running and how it was invoked.
+//! [`Terminate::enrich_errors`](super::Terminate::enrich_errors) attaches an
+//! [`ErrorContext`] to the final error printed by
+//! [`Terminate::run`](super::Terminate::run) and to crash reports, containing
+//! the program's arguments, its version and git commit if given, and the
+//! environment variables matching the given patterns.
+//!
+//! Patterns are matched against variable names ignoring case and can use `*`
+//! to match any number of characters, such as `APP_*`. The values of variables
+//! matching a redaction pattern are replaced with `<redacted>`. By default
+//! `*TOKEN*`, `*SECRET*`, `*PASSWORD*`, `*CREDENTIAL*`, and `*KEY*` are
+//! redacted.
+//!
+//! ```no_run
+//! # use futility::terminate::{enrich::ErrorContext, reporter::Plain, Terminate};
+//! # use std::{error::Error, process::ExitCode};
+//! fn main() -> ExitCode {
+//!     Terminate::new()
+//!         .enrich_errors(
+//!             ErrorContext::new()
+//!                 .version(env!("CARGO_PKG_VERSION"))
+//!                 .git_sha("3f2a9c1")
+//!                 .env("RUST_LOG")
+//!                 .env("APP_*"),
+//!         )
+//!         .reporter(Plain)
+//!         .run(|| -> Result<(), Box<dyn Error>> { Err("Always fails".into()) })
+//! }
+//! ```
+//!
+//! Which prints something like:
+//!
+//! ```text
+//! error: Always fails
+//!
+//! version: 0.1.0 (3f2a9c1)
+//! arguments: ["my-program", "--verbose"]
+//! environment:
+//!   APP_API_KEY=<redacted>
+//!   RUST_LOG=debug
+//! ```
+
+use std::{env, fmt, sync::Mutex};
+
+/// The value shown in place of a redacted environment variable
+pub const REDACTED: &str = "<redacted>";
+
+/// What to include in error and crash reports
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorContext {
+    pub(crate) version: Option<String>,
+    pub(crate) git_sha: Option<String>,
+    env: Vec<String>,
+    redact: Vec<String>,
+}
+
+impl ErrorContext {
+    /// Create a context that includes the program's arguments, with the
+    /// default redaction patterns
+    pub fn new() -> Self {
+        Self {
+            version: None,
+            git_sha: None,
+            env: Vec::new(),
+            redact: ["*TOKEN*", "*SECRET*", "*PASSWORD*", "*CREDENTIAL*", "*KEY*"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+
+    /// Include the program's version, usually `env!("CARGO_PKG_VERSION")`
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Include the git commit the program was built from
+    pub fn git_sha(mut self, git_sha: impl Into<String>) -> Self {
+        self.git_sha = Some(git_sha.into());
+        self
+    }
+
+    /// Include the program's version and git commit from `build`
+    ///
+    /// ```
+    /// # use futility::{build_info, terminate::ErrorContext};
+    /// let context = ErrorContext::new().build_info(build_info!());
+    /// ```
+    #[cfg(feature = "buildinfo")]
+    pub fn build_info(mut self, build: crate::buildinfo::BuildInfo) -> Self {
+        self.version = Some(build.version.into());
+        self.git_sha = build.short_sha().map(Into::into);
+        self
+    }
+
+    /// Include the environment variables whose names match `pattern`
+    pub fn env(mut self, pattern: impl Into<String>) -> Self {
+        self.env.push(pattern.into());
+        self
+    }
+
+    /// Redact the values of included environment variables whose names match
+    /// `pattern`, in addition to the defaults
+    pub fn redact(mut self, pattern: impl Into<String>) -> Self {
+        self.redact.push(pattern.into());
+        self
+    }
+
+    /// The arguments the program was run with
+    pub fn args(&self) -> Vec<String> {
+        env::args_os()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// The included environment variables sorted by name, with the values of
+    /// those matching a redaction pattern replaced with [`REDACTED`]
+    pub fn vars(&self) -> Vec<(String, String)> {
+        let mut vars = env::vars_os()
+            .map(|(key, value)| (key.to_string_lossy().into_owned(), value))
+            .filter(|(key, _)| self.env.iter().any(|pattern| matches(pattern, key)))
+            .map(|(key, value)| {
+                let value = match self.redact.iter().any(|pattern| matches(pattern, &key)) {
+                    true => REDACTED.into(),
+                    false => value.to_string_lossy().into_owned(),
+                };
+                (key, value)
+            })
+            .collect::<Vec<_>>();
+        vars.sort();
+        vars
+    }
+}
+
+impl Default for ErrorContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.version, &self.git_sha) {
+            (Some(version), Some(git_sha)) => writeln!(f, "version: {version} ({git_sha})")?,
+            (Some(version), None) => writeln!(f, "version: {version}")?,
+            (None, Some(git_sha)) => writeln!(f, "version: unknown ({git_sha})")?,
+            (None, None) => {}
+        }
+        write!(f, "arguments: {:?}", self.args())?;
+        let vars = self.vars();
+        if !vars.is_empty() {
+            f.write_str("\nenvironment:")?;
+            for (key, value) in vars {
+                write!(f, "\n  {key}={value}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `name` matches `pattern`, ignoring case, where `*` in the pattern
+/// matches any number of characters
+fn matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_ascii_uppercase();
+    let name = name.to_ascii_uppercase();
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts = parts.collect::<Vec<_>>();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` in the pattern
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+static CURRENT: Mutex<Option<ErrorContext>> = Mutex::new(None);
+
+/// The context set with
+/// [`Terminate::enrich_errors`](super::Terminate::enrich_errors) for the
+/// running program, if any
+pub fn current() -> Option<ErrorContext> {
+    CURRENT.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Make `context` the one returned by [`current`]
+pub(crate) fn set(context: Option<ErrorContext>) {
+    *CURRENT.lock().unwrap_or_else(|e| e.into_inner()) = context;
+}
@@ -0,0 +1,104 @@
+//! Environment variables set for the lifetime of the program
+//!
+//! [`Terminate::scoped_env`](super::Terminate::scoped_env) sets environment
+//! variables before install, such as `RUST_LOG` for a logger that reads it, and
+//! puts back whatever was there before once the program exits. The environment
+//! as it was when the program started, before any of these were set, is kept
+//! so that it can be included in error reports with [`starting_environment`].
+//!
+//! [`Terminate::working_dir`](super::Terminate::working_dir) does the same for
+//! the working directory, changing to the given directory before install and
+//! changing back once the program exits. The directory the program was started
+//! from is available with [`original_dir`] while it runs.
+
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    io,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+static STARTING: OnceLock<Vec<(OsString, OsString)>> = OnceLock::new();
+
+/// The environment variables of the process as they were when
+/// [`Terminate`](super::Terminate) first started executing a program, or
+/// `None` if it hasn't yet
+pub fn starting_environment() -> Option<&'static [(OsString, OsString)]> {
+    STARTING.get().map(Vec::as_slice)
+}
+
+/// Remember the current environment if it hasn't been already
+pub(crate) fn capture() {
+    STARTING.get_or_init(|| env::vars_os().collect());
+}
+
+/// Environment variables that were replaced and their previous values
+pub(crate) struct ScopedEnv {
+    previous: Vec<(OsString, Option<OsString>)>,
+}
+
+impl ScopedEnv {
+    /// Set every variable in `vars` in order
+    pub(crate) fn set(vars: &[(OsString, OsString)]) -> Self {
+        let previous = vars
+            .iter()
+            .map(|(key, value)| {
+                let previous = env::var_os(key);
+                env::set_var(key, value);
+                (key.clone(), previous)
+            })
+            .collect();
+        Self { previous }
+    }
+
+    /// Put back the previous value of every variable, removing those that
+    /// weren't set before
+    pub(crate) fn restore(self) {
+        for (key, previous) in self.previous.into_iter().rev() {
+            match previous {
+                Some(previous) => env::set_var(&key, previous),
+                None => env::remove_var::<&OsStr>(&key),
+            }
+        }
+    }
+}
+
+/// The working directory from before `working_dir` changed it
+static ORIGINAL_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// The working directory the program was started from, if
+/// [`Terminate::working_dir`](super::Terminate::working_dir) changed it and
+/// the program is still running
+pub fn original_dir() -> Option<PathBuf> {
+    ORIGINAL_DIR
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// A working directory that was changed and the directory it was changed from
+pub(crate) struct ScopedDir {
+    previous: PathBuf,
+}
+
+impl ScopedDir {
+    /// Change the working directory to `dir`
+    pub(crate) fn set(dir: &Path) -> io::Result<Self> {
+        let previous = env::current_dir()?;
+        env::set_current_dir(dir)?;
+        *ORIGINAL_DIR.lock().unwrap_or_else(|e| e.into_inner()) = Some(previous.clone());
+        Ok(Self { previous })
+    }
+
+    /// Change back to the previous working directory
+    pub(crate) fn restore(self) {
+        if let Err(err) = env::set_current_dir(&self.previous) {
+            eprintln!(
+                "Failed to restore the working directory to {}: {err}",
+                self.previous.display()
+            );
+        }
+        *ORIGINAL_DIR.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
@@ -0,0 +1,62 @@
+//! A handle to the running program for use from inside of it
+//!
+//! The program passed to [`Terminate::execute`](super::Terminate::execute) is
+//! a plain function, so rather than being passed a handle it can get one at any
+//! time with [`handle`].
+
+#[cfg(unix)]
+use super::signal_action;
+use super::{environment, worker};
+use crate::shutdown::{self, ShutdownToken};
+use std::{error::Error, io, path::PathBuf};
+
+/// A handle to the running [`Terminate`](super::Terminate) that can be used to
+/// interact with it from inside of the program
+#[derive(Clone, Copy, Debug)]
+pub struct Handle {
+    _private: (),
+}
+
+/// Get a [`Handle`] to the running [`Terminate`](super::Terminate)
+pub fn handle() -> Handle {
+    Handle { _private: () }
+}
+
+impl Handle {
+    /// Get the process wide [`ShutdownToken`] which is triggered when a
+    /// shutdown signal is received
+    pub fn shutdown_token(&self) -> ShutdownToken {
+        shutdown::global()
+    }
+
+    /// Spawn a worker thread called `name` that is tracked by the running
+    /// program. The worker is given a child of the [`ShutdownToken`] and
+    /// should return once it is triggered. When `main` returns the token is
+    /// triggered and the worker is joined before `on_error` and `at_exit` run,
+    /// with any panic or error reported as a
+    /// [`WorkerError`](super::WorkerError). See the [`worker`](super::worker)
+    /// module for more details.
+    pub fn spawn_tracked<F, E>(&self, name: &str, worker: F) -> io::Result<()>
+    where
+        F: FnOnce(ShutdownToken) -> Result<(), E> + Send + 'static,
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        worker::spawn(name, worker)
+    }
+
+    /// The working directory the program was started from if it was changed
+    /// with [`Terminate::working_dir`](super::Terminate::working_dir)
+    pub fn original_dir(&self) -> Option<PathBuf> {
+        environment::original_dir()
+    }
+
+    /// Run the function set with
+    /// [`Terminate::on_reload`](super::Terminate::on_reload) on the calling
+    /// thread as if the program had received `SIGHUP`, without raising the
+    /// signal for anything else listening for it. Returns `false` if there is
+    /// no reload function set or the program is not running.
+    #[cfg(unix)]
+    pub fn trigger_reload(&self) -> bool {
+        signal_action::reload()
+    }
+}
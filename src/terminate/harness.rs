@@ -0,0 +1,245 @@
+//! Lifecycle aware tests
+//!
+//! Integration tests need the same setup and teardown discipline as `main`.
+#![cfg_attr(
+    feature = "test",
+    doc = r#"[`TestCase`] is a small [`Terminate`](super::Terminate) for a single test
+that runs an install function (either before every test or once per test
+binary), converts panics in the test into a failure with a readable report,
+and always runs the `at_exit` function, even if the test failed.
+
+It's usually used through the `#[futility::test]` attribute, which takes the
+same arguments as the methods on [`TestCase`]:
+
+```
+fn setup() -> Result<(), std::io::Error> {
+    Ok(())
+}
+
+fn cleanup() {}
+
+#[futility::test(install_once = setup, at_exit = cleanup)]
+fn it_works() {
+    assert_eq!(2 + 2, 4);
+}
+```
+
+Tests that need a value set up for them and torn down afterwards, like a
+scratch directory or a database, can use
+[`test::fixture`](crate::test::fixture) or the `#[futility::fixture]`
+attribute instead.
+"#
+)]
+#![cfg_attr(
+    not(feature = "test"),
+    doc = "`TestCase` and the `#[futility::test]` attribute, which run a single \
+           test with setup and guaranteed teardown, need the `test` feature.\n"
+)]
+//!
+//! The lifecycle wiring of a [`Terminate`](super::Terminate) itself can be
+//! tested with a [`Harness`], which runs it with a fake `main` and records
+//! which hooks fired and in what order:
+//!
+//! ```
+//! # use futility::terminate::{harness::{Harness, Hook}, ExitReason, Terminate};
+//! # use std::error::Error;
+//! let terminate = Terminate::<Box<dyn Error>>::new()
+//!     .on_error(|err| err)
+//!     .at_exit(|| {});
+//! let recording = Harness::new(terminate).run(|| Err("Oh no".into()));
+//! assert_eq!(
+//!     recording.hooks,
+//!     [
+//!         Hook::Main,
+//!         Hook::OnError { error: "Oh no".into() },
+//!         Hook::AtExit { reason: ExitReason::Error },
+//!     ]
+//! );
+//! ```
+
+#[cfg(feature = "test")]
+use super::panic_hook::{self, PanicPayload};
+use super::{ExitReason, Terminate};
+#[cfg(feature = "test")]
+pub use crate::test::{TestFailure, TestOutput};
+use std::{
+    cell::RefCell,
+    fmt::{Debug, Display},
+    rc::Rc,
+};
+#[cfg(feature = "test")]
+use std::{
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    sync::Mutex,
+};
+
+#[cfg(feature = "test")]
+type Install = Box<dyn Fn() -> Result<(), String>>;
+
+/// A single test run with setup and guaranteed teardown
+#[cfg(feature = "test")]
+#[derive(Default)]
+pub struct TestCase {
+    install: Option<(usize, bool, Install)>,
+    at_exit: Option<fn()>,
+}
+
+#[cfg(feature = "test")]
+impl TestCase {
+    /// Create a new TestCase
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `install` before the test
+    pub fn install<E: Debug + 'static>(mut self, install: fn() -> Result<(), E>) -> Self {
+        self.install = Some((install as usize, false, wrap(install)));
+        self
+    }
+
+    /// Run `install` before the first test that uses it in this test binary.
+    /// Every other test using the same function waits for it to finish and
+    /// fails if it failed.
+    pub fn install_once<E: Debug + 'static>(mut self, install: fn() -> Result<(), E>) -> Self {
+        self.install = Some((install as usize, true, wrap(install)));
+        self
+    }
+
+    /// Run `at_exit` after the test, regardless of if it passed or failed
+    pub fn at_exit(mut self, at_exit: fn()) -> Self {
+        self.at_exit = Some(at_exit);
+        self
+    }
+
+    /// Run the test, returning a [`TestFailure`] if the install function
+    /// failed, the test panicked, or the test returned an error
+    pub fn run<T: TestOutput>(self, test: fn() -> T) -> Result<(), TestFailure> {
+        let res = match self.install {
+            Some((key, true, install)) => install_once(key, install),
+            Some((_, false, install)) => install(),
+            None => Ok(()),
+        }
+        .map_err(|err| format!("install failed: {err}"))
+        .and_then(|()| catch(test));
+
+        if let Some(at_exit) = self.at_exit {
+            at_exit();
+        }
+
+        res.map_err(|report| TestFailure { report })
+    }
+}
+
+#[cfg(feature = "test")]
+fn wrap<E: Debug + 'static>(install: fn() -> Result<(), E>) -> Install {
+    Box::new(move || install().map_err(|err| format!("{err:?}")))
+}
+
+#[cfg(feature = "test")]
+fn install_once(key: usize, install: Install) -> Result<(), String> {
+    static INSTALLED: Mutex<Option<HashMap<usize, Result<(), String>>>> = Mutex::new(None);
+    // Holding the lock while installing makes every other test wait for it
+    let mut installed = INSTALLED.lock().unwrap_or_else(|e| e.into_inner());
+    installed
+        .get_or_insert_with(HashMap::new)
+        .entry(key)
+        .or_insert_with(install)
+        .clone()
+}
+
+/// Run the test, turning a panic into a report
+#[cfg(feature = "test")]
+fn catch<T: TestOutput>(test: fn() -> T) -> Result<(), String> {
+    panic_hook::record_locations();
+    catch_panic("test", test).and_then(TestOutput::into_result)
+}
+
+/// Call `f`, turning a panic into a report saying that `what` panicked
+#[cfg(feature = "test")]
+fn catch_panic<R>(what: &str, f: impl FnOnce() -> R) -> Result<R, String> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        let panic = PanicPayload::caught(&*payload);
+        let message = panic.message().unwrap_or(crate::panic::NON_STRING_PAYLOAD);
+        match panic.location() {
+            Some(location) => format!("{what} panicked at {location}:\n{message}"),
+            None => format!("{what} panicked:\n{message}"),
+        }
+    })
+}
+
+/// A hook run by [`Terminate`] while being run by a [`Harness`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Hook {
+    /// The preflight checks were run
+    Preflight,
+    /// The `install` function was called
+    Install,
+    /// The program itself was called
+    Main,
+    /// `on_install_error` was called with an error that displays as `error`
+    OnInstallError {
+        /// The error passed to the hook, formatted with `Display`
+        error: String,
+    },
+    /// `on_error` was called with an error that displays as `error`
+    OnError {
+        /// The error passed to the hook, formatted with `Display`
+        error: String,
+    },
+    /// The `at_exit` function was called when exiting for `reason`
+    AtExit {
+        /// Why the program was exiting
+        reason: ExitReason,
+    },
+    /// An `at_exit_critical` function was called
+    AtExitCritical,
+}
+
+/// Where a [`Terminate`] run by a [`Harness`] records its hooks
+pub(crate) type Recorder = Rc<RefCell<Vec<Hook>>>;
+
+/// Record a hook if there is a recorder
+pub(crate) fn record(recorder: &Option<Recorder>, hook: impl FnOnce() -> Hook) {
+    if let Some(recorder) = recorder {
+        recorder.borrow_mut().push(hook());
+    }
+}
+
+/// Runs a [`Terminate`] with a fake program and records the hooks it calls
+pub struct Harness<E>
+where
+    E: Display + Debug,
+{
+    terminate: Terminate<E>,
+}
+
+/// What happened when a [`Harness`] was run
+#[derive(Debug)]
+pub struct Recording<E> {
+    /// The result `Terminate` returned
+    pub result: Result<(), E>,
+    /// Every hook that was called in the order they were called
+    pub hooks: Vec<Hook>,
+}
+
+impl<E> Harness<E>
+where
+    E: Display + Debug,
+{
+    /// Create a harness for `terminate`
+    pub fn new(terminate: Terminate<E>) -> Self {
+        Self { terminate }
+    }
+
+    /// Execute the `Terminate` with `main` in place of the real program,
+    /// recording every hook that is called
+    pub fn run(mut self, main: impl FnOnce() -> Result<(), E>) -> Recording<E> {
+        let recorder = Recorder::default();
+        self.terminate.recorder = Some(Rc::clone(&recorder));
+        let result = self.terminate.execute_with(main).result;
+        let hooks = recorder.take();
+        Recording { result, hooks }
+    }
+}
@@ -0,0 +1,60 @@
+//! A callback run periodically while the program runs
+//!
+//! Long running jobs often need to tell an external monitor that they are
+//! still alive. [`Terminate::heartbeat`](super::Terminate::heartbeat) runs a
+//! callback on its own thread at a fixed interval from when `main` starts
+//! until it returns, so the heartbeat is always stopped before `on_error` and
+//! `at_exit` run.
+
+use std::{
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// A callback and how often to call it
+pub(crate) struct Heartbeat {
+    pub(crate) interval: Duration,
+    pub(crate) beat: Box<dyn FnMut() + Send>,
+}
+
+impl Heartbeat {
+    /// Start calling the callback every interval on a new thread until the
+    /// returned [`Running`] is dropped
+    pub(crate) fn start(self) -> Option<Running> {
+        let Self { interval, mut beat } = self;
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = thread::Builder::new()
+            .name("futility-heartbeat".into())
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    beat();
+                }
+            });
+        match thread {
+            Ok(thread) => Some(Running {
+                stop,
+                thread: Some(thread),
+            }),
+            Err(err) => {
+                eprintln!("failed to start the heartbeat: {err}");
+                None
+            }
+        }
+    }
+}
+
+/// A heartbeat thread that is stopped and joined when this is dropped
+pub(crate) struct Running {
+    stop: Sender<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        let _ = self.stop.send(());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
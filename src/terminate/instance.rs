@@ -0,0 +1,137 @@
+//! Making sure only one instance of a program runs at a time
+//!
+//! [`Terminate::single_instance`](super::Terminate::single_instance) takes an
+//! advisory lock on a file during install and writes the process id into it.
+//! A second instance fails to take the lock and reports an
+//! [`InstanceError::AlreadyRunning`] with the process id it found through
+//! `on_error`. The lock is released and the file removed when the program
+//! exits, including when it panics. If the process is killed the OS releases
+//! the lock, so a file left behind doesn't stop the next instance from
+//! starting.
+
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, Write},
+    os::unix::{fs::MetadataExt, io::AsRawFd},
+    path::{Path, PathBuf},
+    process,
+};
+use thiserror::Error;
+
+/// The error returned when the instance lock could not be taken
+#[derive(Debug, Error)]
+pub enum InstanceError {
+    /// Another instance of the program holds the lock
+    #[error("already running ({}), locked by {}", Pid(*.pid), .path.display())]
+    AlreadyRunning {
+        /// The lock file
+        path: PathBuf,
+        /// The process id written to the lock file, if it could be read
+        pid: Option<u32>,
+    },
+    /// The lock file could not be opened, locked, or written to
+    #[error("failed to lock {}: {source}", .path.display())]
+    Io {
+        /// The lock file
+        path: PathBuf,
+        /// The underlying error from the OS
+        #[source]
+        source: io::Error,
+    },
+}
+
+struct Pid(Option<u32>);
+
+impl fmt::Display for Pid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(pid) => write!(f, "pid {pid}"),
+            None => f.write_str("unknown pid"),
+        }
+    }
+}
+
+/// An exclusive lock on a file held for as long as this is alive. Dropping it
+/// removes the file and releases the lock.
+#[derive(Debug)]
+pub struct InstanceLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    /// Take the lock on `path`, creating it if needed, and write the id of
+    /// this process into it
+    pub fn acquire(path: impl AsRef<Path>) -> Result<Self, InstanceError> {
+        let path = path.as_ref().to_path_buf();
+        let io_error = |source| InstanceError::Io {
+            path: path.clone(),
+            source,
+        };
+        let mut file = loop {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)
+                .map_err(io_error)?;
+            // SAFETY: flock has no memory safety requirements
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::WouldBlock {
+                    return Err(io_error(err));
+                }
+                let mut contents = String::new();
+                let pid = file
+                    .read_to_string(&mut contents)
+                    .ok()
+                    .and_then(|_| contents.trim().parse().ok());
+                return Err(InstanceError::AlreadyRunning { path, pid });
+            }
+            // The instance that held the lock may have removed the file after
+            // it was opened here but before it was locked, leaving this lock
+            // on a file no other instance will ever open. Start over unless
+            // the locked file is still the one at `path`.
+            if is_same_file(&file, &path).map_err(io_error)? {
+                break file;
+            }
+        };
+        file.set_len(0)
+            .and_then(|()| file.rewind())
+            .and_then(|()| writeln!(file, "{}", process::id()))
+            .map_err(io_error)?;
+        Ok(Self { file, path })
+    }
+
+    /// The locked file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Whether `file` is the file at `path`, rather than one that was removed or
+/// replaced since it was opened
+fn is_same_file(file: &File, path: &Path) -> io::Result<bool> {
+    let opened = file.metadata()?;
+    match fs::metadata(path) {
+        Ok(current) => Ok(opened.dev() == current.dev() && opened.ino() == current.ino()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        // Remove the file before releasing the lock so it's never left on
+        // disk unlocked by a program that has already exited. An instance
+        // that opened it in the meantime notices it was removed once it gets
+        // the lock and tries again.
+        let _ = fs::remove_file(&self.path);
+        // SAFETY: flock has no memory safety requirements
+        unsafe {
+            libc::flock(self.file.as_raw_fd(), libc::LOCK_UN);
+        }
+    }
+}
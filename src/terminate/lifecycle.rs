@@ -0,0 +1,76 @@
+//! Tracing of each phase of a program's lifecycle
+//!
+//! With the `tracing` feature enabled each phase run by
+//! [`Terminate`](super::Terminate) is wrapped in a `lifecycle` span with the
+//! name of the phase (`install`, `main`, `on_error`, or `at_exit`), and an
+//! event with how long the phase took and its outcome is emitted when it
+//! finishes. Everything is emitted with the `futility::lifecycle` target.
+//! Without the feature this compiles down to calling each phase directly.
+
+/// How a lifecycle phase finished
+#[derive(Clone, Copy)]
+pub(crate) enum PhaseOutcome {
+    Ok,
+    Error,
+    Panic,
+}
+
+impl PhaseOutcome {
+    pub(crate) fn of<T, E>(res: &Result<T, E>) -> Self {
+        match res {
+            Ok(_) => PhaseOutcome::Ok,
+            Err(_) => PhaseOutcome::Error,
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    fn as_str(self) -> &'static str {
+        match self {
+            PhaseOutcome::Ok => "ok",
+            PhaseOutcome::Error => "error",
+            PhaseOutcome::Panic => "panic",
+        }
+    }
+}
+
+/// Run the phase `name`, using `outcome` to decide how it finished
+#[cfg(feature = "tracing")]
+pub(crate) fn phase<T>(
+    name: &'static str,
+    run: impl FnOnce() -> T,
+    outcome: impl FnOnce(&T) -> PhaseOutcome,
+) -> T {
+    let span = tracing::info_span!(target: "futility::lifecycle", "lifecycle", phase = name);
+    let _entered = span.enter();
+    let start = std::time::Instant::now();
+    let res = run();
+    let duration = start.elapsed();
+    let duration_ms = duration.as_secs_f64() * 1000.0;
+    match outcome(&res) {
+        PhaseOutcome::Ok => tracing::info!(
+            target: "futility::lifecycle",
+            phase = name,
+            duration_ms,
+            outcome = PhaseOutcome::Ok.as_str(),
+            "{name} finished"
+        ),
+        outcome => tracing::warn!(
+            target: "futility::lifecycle",
+            phase = name,
+            duration_ms,
+            outcome = outcome.as_str(),
+            "{name} finished"
+        ),
+    }
+    res
+}
+
+/// Run the phase `name`, using `outcome` to decide how it finished
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn phase<T>(
+    _name: &'static str,
+    run: impl FnOnce() -> T,
+    _outcome: impl FnOnce(&T) -> PhaseOutcome,
+) -> T {
+    run()
+}
@@ -0,0 +1,48 @@
+//! Memory usage statistics for the process
+
+use std::fmt;
+
+/// Get the peak resident set size of the process in bytes. This returns
+/// `None` on platforms where it is not available.
+pub fn peak_rss() -> Option<u64> {
+    #[cfg(unix)]
+    {
+        // SAFETY: rusage is plain old data that getrusage fills in
+        let mut usage = unsafe { std::mem::zeroed::<libc::rusage>() };
+        // SAFETY: usage is valid for getrusage to write into
+        if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+            return None;
+        }
+        let max_rss = u64::try_from(usage.ru_maxrss).ok()?;
+        // macOS reports this in bytes while everything else uses kilobytes
+        if cfg!(any(target_os = "macos", target_os = "ios")) {
+            Some(max_rss)
+        } else {
+            Some(max_rss * 1024)
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+/// Displays a number of bytes in the largest binary unit that fits
+pub(crate) struct Bytes(pub(crate) u64);
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+        let mut size = self.0 as f64;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < UNITS.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            write!(f, "{} {}", self.0, UNITS[0])
+        } else {
+            write!(f, "{size:.1} {}", UNITS[unit])
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Information about the program that is handed to `at_exit` when exiting
+
+#[cfg(feature = "metrics")]
+use crate::metrics::Snapshot;
+pub use crate::signal::Signal;
+use std::{fmt, time::Duration};
+
+/// What is known about the program when it is exiting. This is passed to the
+/// function set with [`Terminate::at_exit_with`](super::Terminate::at_exit_with).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ExitInfo {
+    /// The peak resident memory usage of the process in bytes, if it is
+    /// available on this platform
+    pub peak_memory: Option<u64>,
+    /// How long the program ran for, measured from the start of install
+    pub runtime: Duration,
+    /// Why the program is exiting
+    pub reason: ExitReason,
+    /// How long each phase of the program took. As this is gathered before
+    /// `at_exit` runs the shutdown duration only covers the time spent so
+    /// far, such as in `on_error`.
+    pub timings: LifecycleTimings,
+    /// The value of every [`metrics`](crate::metrics) counter and gauge
+    #[cfg(feature = "metrics")]
+    pub metrics: Snapshot,
+}
+
+/// How long each phase of a program run by
+/// [`Terminate`](super::Terminate) took. The complete timings are returned by
+/// [`Terminate::execute_timed`](super::Terminate::execute_timed).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LifecycleTimings {
+    /// The time spent in `install` and any setup needed by other options
+    pub install: Duration,
+    /// The time spent in the program itself, which is zero if install failed
+    pub main: Duration,
+    /// The time spent from the program returning until everything has been
+    /// cleaned up, including `on_error`, `at_exit`, and any teardown
+    pub shutdown: Duration,
+}
+
+/// Everything known about a program once [`Terminate`](super::Terminate) has
+/// finished running it, returned by
+/// [`Terminate::execute_outcome`](super::Terminate::execute_outcome)
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Outcome<E> {
+    /// Why the program exited
+    pub reason: ExitReason,
+    /// The error the program failed with, after `on_error`, if it failed
+    pub error: Option<E>,
+    /// How long each phase of the program took
+    pub timings: LifecycleTimings,
+    /// Whether any worker spawned with
+    /// [`Handle::spawn_tracked`](super::Handle::spawn_tracked) failed or
+    /// panicked, even if its errors weren't turned into `error` by
+    /// [`Terminate::propagate_worker_errors`](super::Terminate::propagate_worker_errors)
+    pub workers_failed: bool,
+    /// Whether the program panicked, either in `main` with the panic turned
+    /// into an error by `panic_to_error`, or in a tracked worker thread
+    pub panicked: bool,
+}
+
+impl<E> Outcome<E> {
+    /// Whether the program finished without an error and every tracked
+    /// worker stopped cleanly
+    pub fn is_success(&self) -> bool {
+        self.error.is_none() && !self.workers_failed
+    }
+
+    /// Turn the outcome into the result [`Terminate::execute`] would have
+    /// returned
+    ///
+    /// [`Terminate::execute`]: super::Terminate::execute
+    pub fn into_result(self) -> Result<(), E> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Why the program is exiting
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExitReason {
+    /// The program ran to completion without an error
+    #[default]
+    Success,
+    /// The program returned an error
+    Error,
+    /// `install`, or setup needed by another option, failed before the
+    /// program could run
+    InstallError,
+    /// The program panicked and the panic was caught with
+    /// [`Terminate::panic_to_error`](super::Terminate::panic_to_error)
+    Panic,
+    /// The program was asked to shut down by a signal and exited, with or
+    /// without an error
+    Signal(Signal),
+    /// The program ran past its deadline
+    Timeout,
+}
+
+impl fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitReason::Success => f.write_str("success"),
+            ExitReason::Error => f.write_str("error"),
+            ExitReason::InstallError => f.write_str("install error"),
+            ExitReason::Panic => f.write_str("panic"),
+            ExitReason::Signal(signal) => write!(f, "received {signal}"),
+            ExitReason::Timeout => f.write_str("timeout"),
+        }
+    }
+}
+
+/// The function set to run when exiting
+#[derive(Clone, Copy)]
+pub(crate) enum AtExit {
+    Plain(fn()),
+    WithInfo(fn(&ExitInfo)),
+}
+
+impl AtExit {
+    pub(crate) fn call(self, info: &ExitInfo) {
+        match self {
+            AtExit::Plain(at_exit) => at_exit(),
+            AtExit::WithInfo(at_exit) => at_exit(info),
+        }
+    }
+}
+
+/// Run the `at_exit_critical` functions when exiting without going through the
+/// rest of `Terminate`'s exit steps
+pub(crate) fn run_critical(critical: &[fn()]) {
+    #[cfg(all(feature = "atexit", any(unix, windows)))]
+    super::atexit::disarm();
+    for critical in critical {
+        critical();
+    }
+}
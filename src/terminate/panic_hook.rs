@@ -0,0 +1,131 @@
+//! Information about a panic handed to panic hooks
+//!
+//! The payload of a panic is a `dyn Any` that is almost always a `&str` or a
+//! `String`. Rather than making every panic hook downcast it to find the
+//! message, `panic_to_error` and hooks set with `panic_with_payload` or
+//! `replace_panic_payload` are given a [`PanicPayload`] that does this for
+//! them, which can be turned into an owned
+//! [`PanicDetails`](crate::panic::PanicDetails) to keep.
+
+use super::outcome;
+use std::{
+    any::Any,
+    cell::RefCell,
+    panic::{self, PanicHookInfo},
+    process,
+    sync::{Arc, Mutex, Weak},
+    thread,
+};
+
+/// What happens once a panic has been reported by the panic hooks
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// Unwind the stack as usual, which lets `panic_to_error` catch the
+    /// panic and `at_exit` run afterwards
+    #[default]
+    Unwind,
+    /// Run the `at_exit_critical` functions and then abort the process
+    /// without unwinding, the same as a binary built with `panic = "abort"`.
+    /// Neither `panic_to_error` nor `at_exit` run.
+    Abort,
+}
+
+/// A panic that is being handled, either by a panic hook or after it was
+/// caught
+pub struct PanicPayload<'a> {
+    payload: &'a (dyn Any + Send),
+    location: Option<String>,
+    thread_name: Option<String>,
+}
+
+impl<'a> PanicPayload<'a> {
+    /// Wrap the payload of a panic that was caught on the current thread. The
+    /// location is only known if the panic happened while
+    /// [`Terminate::panic_to_error`](super::Terminate::panic_to_error) was
+    /// active.
+    pub fn caught(payload: &'a (dyn Any + Send)) -> Self {
+        let location = LOCATION.with(|location| location.borrow_mut().take());
+        Self {
+            payload,
+            location,
+            thread_name: thread::current().name().map(Into::into),
+        }
+    }
+
+    /// The message the panic was created with if it was a string
+    pub fn message(&self) -> Option<&str> {
+        crate::panic::payload_as_str(self.payload)
+    }
+
+    /// Downcast the payload to a concrete type, such as one given to
+    /// [`std::panic::panic_any`]
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.payload.downcast_ref()
+    }
+
+    /// Where the panic happened formatted as `file:line:column`, if known
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    /// The name of the thread that panicked, if it had one
+    pub fn thread_name(&self) -> Option<&str> {
+        self.thread_name.as_deref()
+    }
+}
+
+impl<'a> From<&'a PanicHookInfo<'a>> for PanicPayload<'a> {
+    fn from(panic_info: &'a PanicHookInfo<'a>) -> Self {
+        Self {
+            payload: panic_info.payload(),
+            location: panic_info.location().map(ToString::to_string),
+            thread_name: thread::current().name().map(Into::into),
+        }
+    }
+}
+
+thread_local! {
+    static LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Held by the location hook while it's set, so that a later call can tell
+/// whether it's still part of the panic hook chain
+static LOCATION_HOOK: Mutex<Weak<()>> = Mutex::new(Weak::new());
+
+/// Install a panic hook, on top of whatever hook is currently set, that
+/// remembers where the last panic on each thread happened so that
+/// [`PanicPayload::caught`] can report it. The hook is only installed again
+/// if one set since then, such as one installed by `install`, replaced it
+/// rather than chaining onto it.
+pub(crate) fn record_locations() {
+    let mut installed = LOCATION_HOOK.lock().unwrap_or_else(|e| e.into_inner());
+    if installed.strong_count() > 0 {
+        return;
+    }
+    let marker = Arc::new(());
+    *installed = Arc::downgrade(&marker);
+    let original_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |panic_info| {
+        let _marker = &marker;
+        let location = panic_info.location().map(ToString::to_string);
+        LOCATION.with(|cell| *cell.borrow_mut() = location);
+        original_hook(panic_info);
+    }));
+}
+
+/// Chain a hook onto the current panic hook that runs `critical` and aborts the
+/// process once the panic has been reported. The returned function puts the
+/// previous hook back.
+pub(crate) fn abort_after_hook(critical: Vec<fn()>) -> impl FnOnce() {
+    let previous: Arc<dyn Fn(&PanicHookInfo<'_>) + Send + Sync> = Arc::from(panic::take_hook());
+    let hook = Arc::clone(&previous);
+    panic::set_hook(Box::new(move |panic_info| {
+        hook(panic_info);
+        outcome::run_critical(&critical);
+        process::abort();
+    }));
+    move || {
+        let _ = panic::take_hook();
+        panic::set_hook(Box::new(move |panic_info| previous(panic_info)));
+    }
+}
@@ -0,0 +1,76 @@
+//! A description of what [`Terminate`](super::Terminate) would do
+//!
+//! [`Terminate::validate`](super::Terminate::validate) checks the
+//! configuration for mistakes and describes every step that would run without
+//! running any of them, which is handy for a `--check-config` flag.
+//!
+//! ```
+//! # use futility::terminate::Terminate;
+//! # use std::io;
+//! let plan = Terminate::<io::Error>::new()
+//!     .install(|| Ok(()))
+//!     .at_exit(|| println!("Exiting"))
+//!     .validate()
+//!     .unwrap();
+//! assert_eq!(plan.steps(), ["run install", "run main", "run at_exit"]);
+//! ```
+
+use std::fmt;
+use thiserror::Error;
+
+/// The steps [`Terminate`](super::Terminate) would run, in order
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Plan {
+    steps: Vec<String>,
+}
+
+impl Plan {
+    /// A description of each step
+    pub fn steps(&self) -> &[String] {
+        &self.steps
+    }
+
+    pub(crate) fn step(&mut self, step: impl Into<String>) {
+        self.steps.push(step.into());
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}. {step}", i + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// A mistake in how [`Terminate`](super::Terminate) was configured
+#[derive(Debug, Error, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// A hook was set that can never be called
+    #[error("`{hook}` is set but can never be called: {reason}")]
+    UnusedHook {
+        /// The method the hook was set with
+        hook: &'static str,
+        /// Why it can never be called
+        reason: &'static str,
+    },
+    /// The same signal was given more than one job
+    #[error("{signal} is used by both `{first}` and `{second}`")]
+    ConflictingSignal {
+        /// The signal that was given more than one job
+        signal: super::Signal,
+        /// The first method that uses the signal
+        first: &'static str,
+        /// The second method that uses the signal
+        second: &'static str,
+    },
+    /// A grace period was set without handling the shutdown signals that
+    /// start it
+    #[error("`grace_period` is set but `handle_signals` isn't, so it never starts")]
+    GraceWithoutSignals,
+}
@@ -0,0 +1,89 @@
+//! Checks that the program's environment is usable before it starts
+//!
+//! A program that fails at startup because a port is taken is usually also
+//! missing a writable directory or two. Rather than stopping at the first
+//! problem, every check given to
+//! [`Terminate::preflight`](super::Terminate::preflight) is run before install
+//! and all of the failures are reported together in a single
+//! [`PreflightError`].
+//!
+//! ```
+//! # use futility::terminate::{preflight::{Preflight, PreflightError}, Terminate};
+//! # use std::{fs, net::TcpListener};
+//! let err = Terminate::<PreflightError>::new()
+//!     .preflight(
+//!         Preflight::new()
+//!             .check("port 8080 is available", || TcpListener::bind("127.0.0.1:8080").map(drop))
+//!             .check("config file exists", || fs::metadata("/does/not/exist").map(drop)),
+//!     )
+//!     .execute(|| Ok(()))
+//!     .unwrap_err();
+//! assert!(err.to_string().contains("config file exists"));
+//! ```
+
+use std::{error::Error, fmt};
+
+type Check = Box<dyn FnOnce() -> Result<(), Box<dyn Error + Send + Sync>>>;
+
+/// A list of named checks to run before install
+#[derive(Default)]
+pub struct Preflight {
+    checks: Vec<(String, Check)>,
+}
+
+impl Preflight {
+    /// Create an empty list of checks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a check described by `name`, such as "port 8080 is available"
+    pub fn check<F, E>(mut self, name: impl Into<String>, check: F) -> Self
+    where
+        F: FnOnce() -> Result<(), E> + 'static,
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        self.checks
+            .push((name.into(), Box::new(move || check().map_err(Into::into))));
+        self
+    }
+
+    /// The name of every check in the order they were added
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.checks.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Run every check, returning all of the failures if there were any
+    pub(crate) fn run(self) -> Result<(), PreflightError> {
+        let failures = self
+            .checks
+            .into_iter()
+            .filter_map(|(name, check)| check().err().map(|err| (name, err)))
+            .collect::<Vec<_>>();
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(PreflightError { failures })
+        }
+    }
+}
+
+/// Every preflight check that failed
+#[derive(Debug)]
+pub struct PreflightError {
+    /// The name of each check that failed along with why it failed, in the
+    /// order the checks were added
+    pub failures: Vec<(String, Box<dyn Error + Send + Sync>)>,
+}
+
+impl fmt::Display for PreflightError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} preflight check(s) failed:", self.failures.len())?;
+        for (name, err) in &self.failures {
+            write!(f, "\n  - {name}: {err}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for PreflightError {}
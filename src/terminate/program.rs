@@ -0,0 +1,92 @@
+//! Programs written as a type rather than a set of functions
+//!
+//! The functions given to [`Terminate`](super::Terminate) are plain `fn`
+//! pointers, which makes sharing state between `install`, the program, and
+//! cleanup awkward. A type implementing [`Program`] holds its own state and is
+//! run with [`Terminate::run_program`](super::Terminate::run_program).
+//!
+//! ```
+//! # use futility::terminate::{program::{Program, ProgramContext}, Terminate};
+//! # use std::io;
+//! struct Server {
+//!     requests: usize,
+//! }
+//!
+//! impl Program for Server {
+//!     type Error = io::Error;
+//!
+//!     fn install(&mut self) -> Result<(), io::Error> {
+//!         println!("Binding to port 8080");
+//!         Ok(())
+//!     }
+//!
+//!     fn run(&mut self, ctx: &mut ProgramContext) -> Result<(), io::Error> {
+//!         while !ctx.is_shutting_down() && self.requests < 3 {
+//!             self.requests += 1;
+//!         }
+//!         Ok(())
+//!     }
+//!
+//!     fn shutdown(&mut self) {
+//!         println!("Served {} requests", self.requests);
+//!     }
+//! }
+//!
+//! Terminate::new().run_program(Server { requests: 0 }).unwrap();
+//! ```
+
+use super::environment;
+use crate::shutdown::{self, ShutdownToken};
+use std::path::PathBuf;
+
+/// A program with its own state that is run by
+/// [`Terminate::run_program`](super::Terminate::run_program)
+pub trait Program {
+    /// The error the program fails with
+    type Error;
+
+    /// Set up anything the program needs. This runs after every other part
+    /// of install.
+    fn install(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Run the program
+    fn run(&mut self, ctx: &mut ProgramContext) -> Result<(), Self::Error>;
+
+    /// Clean up after the program once it has finished running, regardless of
+    /// if it failed. This runs after `at_exit`, and only if `install`
+    /// succeeded.
+    fn shutdown(&mut self) {}
+}
+
+/// What a [`Program`] has access to while running
+#[derive(Debug)]
+pub struct ProgramContext {
+    shutdown: ShutdownToken,
+}
+
+impl ProgramContext {
+    pub(crate) fn new() -> Self {
+        Self {
+            shutdown: shutdown::global(),
+        }
+    }
+
+    /// The process wide [`ShutdownToken`], which is triggered when the
+    /// program is asked to shut down
+    pub fn shutdown_token(&self) -> &ShutdownToken {
+        &self.shutdown
+    }
+
+    /// Whether the program has been asked to shut down
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.is_triggered()
+    }
+
+    /// The working directory the program was started from if it was changed
+    /// with [`Terminate::working_dir`](super::Terminate::working_dir)
+    pub fn original_dir(&self) -> Option<PathBuf> {
+        environment::original_dir()
+    }
+}
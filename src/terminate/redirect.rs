@@ -0,0 +1,184 @@
+//! Redirection of the process' stdout and stderr for the lifetime of the
+//! program
+//!
+//! Redirection happens at the file descriptor level, which means that output
+//! from anything writing to stdout or stderr, including C libraries and child
+//! processes that inherit them, ends up in the target. When the program exits
+//! both are flushed and the original stdout and stderr are restored.
+//!
+//! Note that when using [`OutputTarget::Tee`] any child processes that are
+//! still running and holding onto the redirected output will delay the exit of
+//! the program until they exit, as the output is copied until every writer is
+//! gone.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    os::unix::io::{AsRawFd, FromRawFd, RawFd},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+};
+
+/// Where to send stdout and stderr while the program runs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OutputTarget {
+    /// Append all output to the file at the given path instead of stdout and
+    /// stderr
+    File(PathBuf),
+    /// Append all output to the file at the given path as well as writing it
+    /// to the original stdout and stderr
+    Tee(PathBuf),
+}
+
+/// The state needed to undo a redirection
+pub(crate) struct Redirection {
+    saved: [RawFd; 2],
+    file: Arc<Mutex<File>>,
+    tees: Vec<JoinHandle<()>>,
+}
+
+const FDS: [RawFd; 2] = [libc::STDOUT_FILENO, libc::STDERR_FILENO];
+
+impl Redirection {
+    /// Redirect stdout and stderr to the given target
+    pub(crate) fn start(target: &OutputTarget) -> io::Result<Self> {
+        let (path, tee) = match target {
+            OutputTarget::File(path) => (path, false),
+            OutputTarget::Tee(path) => (path, true),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        flush();
+
+        let saved = [dup(FDS[0])?, dup(FDS[1])?];
+        let mut redirection = Self {
+            saved,
+            file: Arc::new(Mutex::new(file)),
+            tees: Vec::new(),
+        };
+        match redirection.redirect(tee) {
+            Ok(()) => Ok(redirection),
+            Err(err) => {
+                redirection.finish();
+                Err(err)
+            }
+        }
+    }
+
+    fn redirect(&mut self, tee: bool) -> io::Result<()> {
+        for (fd, saved) in FDS.into_iter().zip(self.saved) {
+            if tee {
+                let (reader, writer) = pipe()?;
+                let res = dup2(writer, fd);
+                close(writer);
+                res?;
+                let file = Arc::clone(&self.file);
+                self.tees
+                    .push(thread::spawn(move || copy(reader, saved, &file)));
+            } else {
+                let file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+                dup2(file.as_raw_fd(), fd)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flush any output and restore the original stdout and stderr
+    pub(crate) fn finish(self) {
+        flush();
+        for (fd, saved) in FDS.into_iter().zip(self.saved) {
+            let _ = dup2(saved, fd);
+        }
+        for tee in self.tees {
+            let _ = tee.join();
+        }
+        for saved in self.saved {
+            close(saved);
+        }
+        let _ = self
+            .file
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .sync_all();
+    }
+}
+
+/// Copy everything from `reader` to both the original output and the file
+/// until every writer has been closed
+fn copy(reader: RawFd, original: RawFd, file: &Mutex<File>) {
+    // SAFETY: The read end of the pipe is owned by this thread alone
+    let mut reader = unsafe { File::from_raw_fd(reader) };
+    let mut buf = [0; 8192];
+    loop {
+        let len = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        write_all(original, &buf[..len]);
+        let _ = file
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .write_all(&buf[..len]);
+    }
+}
+
+fn flush() {
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+}
+
+fn write_all(fd: RawFd, mut buf: &[u8]) {
+    while !buf.is_empty() {
+        // SAFETY: buf is valid for buf.len() bytes
+        let written = unsafe { libc::write(fd, buf.as_ptr().cast(), buf.len()) };
+        if written < 0 {
+            if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return;
+        }
+        buf = &buf[written as usize..];
+    }
+}
+
+/// Duplicate `fd` so that it is not inherited by child processes
+fn dup(fd: RawFd) -> io::Result<RawFd> {
+    // SAFETY: fcntl with F_DUPFD_CLOEXEC has no memory safety requirements
+    match unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) } {
+        -1 => Err(io::Error::last_os_error()),
+        fd => Ok(fd),
+    }
+}
+
+fn dup2(from: RawFd, to: RawFd) -> io::Result<()> {
+    // SAFETY: dup2 has no memory safety requirements
+    match unsafe { libc::dup2(from, to) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Create a pipe whose ends are not inherited by child processes
+fn pipe() -> io::Result<(RawFd, RawFd)> {
+    let mut fds = [0; 2];
+    // SAFETY: fds has room for the two file descriptors pipe writes
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    for fd in fds {
+        // SAFETY: fcntl with F_SETFD has no memory safety requirements
+        unsafe {
+            libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        }
+    }
+    Ok((fds[0], fds[1]))
+}
+
+fn close(fd: RawFd) {
+    // SAFETY: Every caller owns the file descriptor it closes
+    unsafe {
+        libc::close(fd);
+    }
+}
@@ -0,0 +1,34 @@
+//! Replacing the running program with a fresh copy of its binary
+
+use std::{
+    env,
+    ffi::OsString,
+    io::{self, Write},
+    os::unix::{ffi::OsStringExt, process::CommandExt},
+    path::PathBuf,
+    process::Command,
+};
+
+/// Execute the binary the program was started from with the arguments it was
+/// started with, replacing the current process. This only returns if the
+/// `exec` failed.
+pub(crate) fn exec() -> io::Error {
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+    let exe = match env::current_exe() {
+        Ok(exe) => strip_deleted(exe),
+        Err(err) => return err,
+    };
+    Command::new(exe).args(env::args_os().skip(1)).exec()
+}
+
+/// On Linux the path of a binary that has been replaced since the program
+/// started ends in ` (deleted)`. Upgrading in place is the point of
+/// re-executing, so use the path of the new binary instead.
+fn strip_deleted(exe: PathBuf) -> PathBuf {
+    let mut bytes = exe.into_os_string().into_vec();
+    if bytes.ends_with(b" (deleted)") {
+        bytes.truncate(bytes.len() - b" (deleted)".len());
+    }
+    PathBuf::from(OsString::from_vec(bytes))
+}
@@ -0,0 +1,116 @@
+//! Cleanup registered from anywhere in the program
+//!
+//! Library code deep in the call graph has no way to reach the `at_exit`
+//! function given to [`Terminate`](super::Terminate), so the
+//! [`at_exit!`](crate::at_exit) macro registers cleanup in a process wide
+//! registry instead. Everything registered is run right after the `at_exit`
+//! function when the program exits. Nothing registered runs if the program
+//! isn't run by `Terminate`.
+//!
+//! Cleanup is run in tiers given by its [`ExitPriority`] so that, for
+//! example, logs are always flushed after an error has been reported and
+//! before the files they are written to are closed. Every `Report` hook runs
+//! first, then every `Flush` hook, then every `Release` hook, with the hooks in
+//! each tier run in reverse order of registration. `at_exit!` without a
+//! priority and [`register`] use `Release`.
+//!
+//! ```
+//! # use futility::terminate::Terminate;
+//! # use std::{fs, io};
+//! Terminate::<io::Error>::new()
+//!     .execute(|| {
+//!         let tmp = std::env::temp_dir().join("futility-registry-example");
+//!         fs::write(&tmp, "scratch")?;
+//!         futility::at_exit!(move || {
+//!             let _ = fs::remove_file(tmp);
+//!         });
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//! ```
+
+use std::sync::Mutex;
+
+/// When a hook runs relative to other hooks while the program exits. Tiers
+/// run in the order they are listed here.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ExitPriority {
+    /// Report what happened, such as sending an error to a tracker
+    Report,
+    /// Flush buffered output, such as logs and metrics
+    Flush,
+    /// Release resources, such as removing temporary files or closing file
+    /// descriptors
+    #[default]
+    Release,
+}
+
+pub(crate) type Cleanup = Box<dyn FnOnce() + Send>;
+
+static REGISTRY: Mutex<Vec<(ExitPriority, Cleanup)>> = Mutex::new(Vec::new());
+
+/// Register `cleanup` to run when the program exits with
+/// [`ExitPriority::Release`]. This is what [`at_exit!`](crate::at_exit)
+/// expands to when it isn't given a priority.
+pub fn register(cleanup: impl FnOnce() + Send + 'static) {
+    register_with(ExitPriority::Release, cleanup);
+}
+
+/// Register `cleanup` to run when the program exits in the `priority` tier
+pub fn register_with(priority: ExitPriority, cleanup: impl FnOnce() + Send + 'static) {
+    REGISTRY
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push((priority, Box::new(cleanup)));
+}
+
+/// Run everything registered so far a tier at a time, most recently
+/// registered first within a tier. Cleanup registered while this runs is run
+/// as well, even if it is in a tier that has already run.
+pub(crate) fn run_all() {
+    loop {
+        // Don't hold the lock while running the cleanup so it can register more
+        let cleanup = {
+            let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+            registry
+                .iter()
+                .enumerate()
+                .rev()
+                .min_by_key(|(_, (priority, _))| *priority)
+                .map(|(index, _)| index)
+                .map(|index| registry.remove(index).1)
+        };
+        match cleanup {
+            Some(cleanup) => cleanup(),
+            None => break,
+        }
+    }
+}
+
+/// Register a closure to run when the program exits, after the `at_exit`
+/// function given to [`Terminate`](crate::terminate::Terminate). An
+/// [`ExitPriority`](crate::terminate::registry::ExitPriority) can be given
+/// first to pick the tier it runs in, otherwise it is `Release`. Closures in
+/// the same tier are run in the reverse order they were registered in. See the
+/// [`registry`](crate::terminate::registry) module for more details.
+///
+/// ```
+/// # use futility::{at_exit, terminate::{registry::ExitPriority, Terminate}};
+/// # use std::io;
+/// Terminate::<io::Error>::new()
+///     .execute(|| {
+///         at_exit!(|| println!("Runs last"));
+///         at_exit!(ExitPriority::Flush, || println!("Runs first"));
+///         Ok(())
+///     })
+///     .unwrap();
+/// ```
+#[macro_export]
+macro_rules! at_exit {
+    ($priority:expr, $cleanup:expr $(,)?) => {
+        $crate::terminate::registry::register_with($priority, $cleanup)
+    };
+    ($cleanup:expr $(,)?) => {
+        $crate::terminate::registry::register($cleanup)
+    };
+}
@@ -0,0 +1,143 @@
+//! How [`Terminate::run`](super::Terminate::run) presents an error
+//!
+//! By default `run` prints the error according to the
+//! [`ErrorStyle`](super::ErrorStyle), but anything implementing
+//! [`ErrorReporter`] can be set with
+//! [`Terminate::reporter`](super::Terminate::reporter) to take over both how the
+//! error is presented and which [`ExitCode`] the program exits with. The
+//! built-in reporters exit with `128 + N` when the program shut down because of
+//! signal `N`, following the shell convention, and with `1` otherwise. The
+//! `Plain` and `Pretty` reporters color their output according to
+//! [`tty::output_style`], and every built-in reporter includes the
+//! [`enrich::current`] context if there is one.
+//!
+//! ```no_run
+//! # use futility::terminate::{reporter::Json, Terminate};
+//! # use std::{error::Error, process::ExitCode};
+//! fn main() -> ExitCode {
+//!     Terminate::new()
+//!         .reporter(Json)
+//!         .run(|| -> Result<(), Box<dyn Error>> { Err("Always fails".into()) })
+//! }
+//! ```
+
+use super::{enrich, tty, ExitReason};
+use std::{
+    fmt::{Debug, Display, Write},
+    process::ExitCode,
+};
+
+/// Presents an error that made it out of the program
+pub trait ErrorReporter<E> {
+    /// Report `err`, which ended the program for `reason`, and return the code
+    /// to exit with
+    fn report(&self, err: &E, reason: ExitReason) -> ExitCode;
+}
+
+/// Print a single `error: {Display}` line to stderr
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Plain;
+
+impl<E: Display> ErrorReporter<E> for Plain {
+    fn report(&self, err: &E, reason: ExitReason) -> ExitCode {
+        eprintln!("{}: {err}", tty::error_label());
+        print_context();
+        exit_code(reason)
+    }
+}
+
+/// Print the error along with why the program exited and its pretty printed
+/// `Debug` output to stderr
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Pretty;
+
+impl<E: Display + Debug> ErrorReporter<E> for Pretty {
+    fn report(&self, err: &E, reason: ExitReason) -> ExitCode {
+        eprintln!(
+            "{}: {err}\n\nexited because of: {reason}\n\ndetails:\n{err:#?}",
+            tty::error_label()
+        );
+        print_context();
+        exit_code(reason)
+    }
+}
+
+/// Print a single line JSON object with the `error` and `reason` to stderr,
+/// for programs whose output is collected by a log aggregator
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json;
+
+impl<E: Display> ErrorReporter<E> for Json {
+    fn report(&self, err: &E, reason: ExitReason) -> ExitCode {
+        let context = enrich::current()
+            .map(|context| format!(",\"context\":{}", json_context(&context)))
+            .unwrap_or_default();
+        eprintln!(
+            "{{\"error\":{},\"reason\":{}{context}}}",
+            json_string(&err.to_string()),
+            json_string(&reason.to_string())
+        );
+        exit_code(reason)
+    }
+}
+
+/// Print the current error context after the error, if there is one
+pub(crate) fn print_context() {
+    if let Some(context) = enrich::current() {
+        eprintln!("\n{context}");
+    }
+}
+
+/// The error context as a JSON object
+fn json_context(context: &enrich::ErrorContext) -> String {
+    let optional = |value: &Option<String>| match value {
+        Some(value) => json_string(value),
+        None => "null".into(),
+    };
+    let args = context
+        .args()
+        .iter()
+        .map(|arg| json_string(arg))
+        .collect::<Vec<_>>();
+    let vars = context
+        .vars()
+        .iter()
+        .map(|(key, value)| format!("{}:{}", json_string(key), json_string(value)))
+        .collect::<Vec<_>>();
+    format!(
+        "{{\"version\":{},\"git_sha\":{},\"arguments\":[{}],\"environment\":{{{}}}}}",
+        optional(&context.version),
+        optional(&context.git_sha),
+        args.join(","),
+        vars.join(",")
+    )
+}
+
+/// The exit code used by the built-in reporters
+fn exit_code(reason: ExitReason) -> ExitCode {
+    match reason {
+        ExitReason::Signal(signal) => ExitCode::from((128 + signal.as_raw()) as u8),
+        _ => ExitCode::FAILURE,
+    }
+}
+
+/// Quote and escape `s` as a JSON string
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
@@ -0,0 +1,152 @@
+//! Resource limit setup for the process
+//!
+//! Servers routinely need limits like the number of open files raised at
+//! startup. These are set as part of install with
+//! [`Terminate::raise_nofile_limit`](super::Terminate::raise_nofile_limit) and
+//! [`Terminate::set_rlimits`](super::Terminate::set_rlimits), with any failure
+//! reported as an [`RlimitError`] through `on_error`.
+
+// rlim_t is a u64 on most, but not all, platforms
+#![allow(clippy::unnecessary_cast)]
+
+use libc::c_int;
+use std::{fmt, io};
+use thiserror::Error;
+
+/// A resource whose usage can be limited
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Resource {
+    /// The maximum size of a core dump in bytes (`RLIMIT_CORE`)
+    Core,
+    /// The maximum amount of CPU time in seconds (`RLIMIT_CPU`)
+    Cpu,
+    /// The maximum size of the data segment in bytes (`RLIMIT_DATA`)
+    Data,
+    /// The maximum size of a file that can be created in bytes
+    /// (`RLIMIT_FSIZE`)
+    FileSize,
+    /// The maximum number of open file descriptors (`RLIMIT_NOFILE`)
+    NoFile,
+    /// The maximum size of the stack in bytes (`RLIMIT_STACK`)
+    Stack,
+    /// The maximum size of the virtual address space in bytes (`RLIMIT_AS`)
+    AddressSpace,
+}
+
+impl Resource {
+    fn raw(self) -> c_int {
+        (match self {
+            Resource::Core => libc::RLIMIT_CORE,
+            Resource::Cpu => libc::RLIMIT_CPU,
+            Resource::Data => libc::RLIMIT_DATA,
+            Resource::FileSize => libc::RLIMIT_FSIZE,
+            Resource::NoFile => libc::RLIMIT_NOFILE,
+            Resource::Stack => libc::RLIMIT_STACK,
+            Resource::AddressSpace => libc::RLIMIT_AS,
+        }) as c_int
+    }
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Resource::Core => "RLIMIT_CORE",
+            Resource::Cpu => "RLIMIT_CPU",
+            Resource::Data => "RLIMIT_DATA",
+            Resource::FileSize => "RLIMIT_FSIZE",
+            Resource::NoFile => "RLIMIT_NOFILE",
+            Resource::Stack => "RLIMIT_STACK",
+            Resource::AddressSpace => "RLIMIT_AS",
+        })
+    }
+}
+
+/// The soft and hard limit of a [`Resource`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limit {
+    /// The limit enforced by the kernel, which can be raised up to `hard`
+    pub soft: u64,
+    /// The ceiling for `soft`, which can only be raised by a privileged
+    /// process
+    pub hard: u64,
+}
+
+impl Limit {
+    /// The value used for a limit with no upper bound
+    pub const INFINITY: u64 = libc::RLIM_INFINITY as u64;
+
+    /// Create a limit with the same soft and hard value
+    pub fn new(limit: u64) -> Self {
+        Self {
+            soft: limit,
+            hard: limit,
+        }
+    }
+}
+
+/// The error returned when a resource limit could not be read or changed
+#[derive(Debug, Error)]
+#[error("failed to set {resource} to {limit:?}: {source}")]
+pub struct RlimitError {
+    /// The resource that was being changed
+    pub resource: Resource,
+    /// The limit that was being set
+    pub limit: Limit,
+    /// The underlying error from the OS
+    #[source]
+    pub source: io::Error,
+}
+
+/// Get the current limit of `resource`
+pub fn get(resource: Resource) -> io::Result<Limit> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: limit is a valid rlimit for getrlimit to write into
+    match unsafe { libc::getrlimit(resource.raw() as _, &mut limit) } {
+        0 => Ok(Limit {
+            soft: limit.rlim_cur as u64,
+            hard: limit.rlim_max as u64,
+        }),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Set the limit of `resource`
+pub fn set(resource: Resource, limit: Limit) -> Result<(), RlimitError> {
+    let raw = libc::rlimit {
+        rlim_cur: limit.soft as libc::rlim_t,
+        rlim_max: limit.hard as libc::rlim_t,
+    };
+    // SAFETY: raw is a valid rlimit for setrlimit to read
+    match unsafe { libc::setrlimit(resource.raw() as _, &raw) } {
+        0 => Ok(()),
+        _ => Err(RlimitError {
+            resource,
+            limit,
+            source: io::Error::last_os_error(),
+        }),
+    }
+}
+
+/// Raise the soft limit on open files to at least `limit`, raising the hard
+/// limit as well if needed. Nothing is changed if the soft limit is already
+/// high enough.
+pub fn raise_nofile(limit: u64) -> Result<(), RlimitError> {
+    let current = get(Resource::NoFile).map_err(|source| RlimitError {
+        resource: Resource::NoFile,
+        limit: Limit::new(limit),
+        source,
+    })?;
+    if current.soft >= limit {
+        return Ok(());
+    }
+    set(
+        Resource::NoFile,
+        Limit {
+            soft: limit,
+            hard: current.hard.max(limit),
+        },
+    )
+}
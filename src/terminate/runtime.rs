@@ -0,0 +1,133 @@
+//! A tokio runtime whose lifetime is managed by the program
+//!
+//! Building and tearing down an async runtime is as much a part of a program's
+//! lifecycle as installing a logger, so
+//! [`Terminate::execute_async`](super::Terminate::execute_async) builds a
+//! multi-threaded [`tokio`] runtime after install, runs the async program on
+//! it, and shuts it down after `at_exit` with
+//! [`Runtime::shutdown_timeout`](tokio::runtime::Runtime::shutdown_timeout).
+//! Tasks still running at that point are cancelled, and shutdown waits up to
+//! the timeout for any that are in the middle of being polled or blocking. How
+//! the runtime is built is configured with
+//! [`Terminate::runtime`](super::Terminate::runtime).
+//!
+//! The runtime has its IO and timer drivers enabled, so the program can use
+//! `tokio` directly. [`spawn`] spawns a task onto it from anywhere in the
+//! program, including threads that aren't part of the runtime.
+//!
+//! ```
+//! # use futility::terminate::{runtime, Terminate};
+//! # use std::{io, sync::mpsc};
+//! Terminate::<io::Error>::new()
+//!     .runtime(|builder| builder.worker_threads(4).thread_name("app"))
+//!     .execute_async(|| async {
+//!         let (send, recv) = mpsc::channel();
+//!         runtime::spawn(async move {
+//!             send.send("Hello from a task").unwrap();
+//!         });
+//!         println!("{}", recv.recv().unwrap());
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//! ```
+
+use std::{future::Future, io, sync::Mutex, thread, time::Duration};
+use tokio::runtime::{Builder, Handle};
+
+/// How the runtime used by
+/// [`Terminate::execute_async`](super::Terminate::execute_async) is built
+#[derive(Clone, Debug)]
+pub struct RuntimeBuilder {
+    worker_threads: usize,
+    thread_name: String,
+    shutdown_timeout: Duration,
+}
+
+impl RuntimeBuilder {
+    /// Create a builder with one worker thread per CPU, workers named
+    /// `futility-runtime`, and a five second shutdown timeout
+    pub fn new() -> Self {
+        Self {
+            worker_threads: thread::available_parallelism().map_or(1, usize::from),
+            thread_name: "futility-runtime".into(),
+            shutdown_timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Set how many threads run spawned tasks. This is at least one.
+    pub fn worker_threads(mut self, worker_threads: usize) -> Self {
+        self.worker_threads = worker_threads.max(1);
+        self
+    }
+
+    /// Set the name of the worker threads
+    pub fn thread_name(mut self, thread_name: impl Into<String>) -> Self {
+        self.thread_name = thread_name.into();
+        self
+    }
+
+    /// Set how long to wait for tasks that are still being polled or blocking
+    /// when the runtime is shut down
+    pub fn shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.shutdown_timeout = shutdown_timeout;
+        self
+    }
+
+    /// Build the runtime, making it the one [`spawn`] uses until it is shut
+    /// down
+    pub(crate) fn build(self) -> io::Result<Runtime> {
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(self.worker_threads)
+            .thread_name(self.thread_name)
+            .enable_all()
+            .build()?;
+        *CURRENT.lock().unwrap_or_else(|e| e.into_inner()) = Some(runtime.handle().clone());
+        Ok(Runtime {
+            runtime,
+            shutdown_timeout: self.shutdown_timeout,
+        })
+    }
+}
+
+impl Default for RuntimeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The runtime of the running program, if there is one
+static CURRENT: Mutex<Option<Handle>> = Mutex::new(None);
+
+/// Spawn `task` onto the runtime of the running program. Returns `false` if
+/// there is no runtime running, in which case the task is dropped.
+pub fn spawn(task: impl Future<Output = ()> + Send + 'static) -> bool {
+    let handle = CURRENT.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    match handle {
+        Some(handle) => {
+            handle.spawn(task);
+            true
+        }
+        None => false,
+    }
+}
+
+/// A running runtime
+pub(crate) struct Runtime {
+    runtime: tokio::runtime::Runtime,
+    shutdown_timeout: Duration,
+}
+
+impl Runtime {
+    /// Run `future` to completion on the current thread
+    pub(crate) fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Stop accepting new tasks, cancel the spawned ones, and wait up to the
+    /// shutdown timeout for the ones still running to stop
+    pub(crate) fn shutdown(self) {
+        // Only one program runs at a time, so the current runtime is this one
+        *CURRENT.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.runtime.shutdown_timeout(self.shutdown_timeout);
+    }
+}
@@ -0,0 +1,105 @@
+//! The lifecycle of [`Terminate`](super::Terminate) for part of a program
+//!
+//! [`Scoped`] applies the same install, error handling, and cleanup discipline
+//! as `Terminate` to a subsystem or request handler inside of a running
+//! program. Unlike `Terminate` it never touches anything process wide such as
+//! panic hooks or signal handlers, so any number of them can run at once.
+//!
+//! ```
+//! # use futility::terminate::Scoped;
+//! # use std::error::Error;
+//! let users = Scoped::<Box<dyn Error>>::new()
+//!     .install(|| Ok(()))
+//!     .on_error(|err| format!("loading users failed: {err}").into())
+//!     .at_exit(|| println!("Closing the connection"))
+//!     .run(|| Ok(vec!["ferris"]))
+//!     .unwrap();
+//! assert_eq!(users, ["ferris"]);
+//! ```
+
+use std::{
+    fmt::{Debug, Display},
+    panic::{self, AssertUnwindSafe},
+};
+
+/// A scope that runs a closure with its own install, `on_error`, and
+/// `at_exit` functions
+pub struct Scoped<E>
+where
+    E: Display + Debug,
+{
+    install: Option<fn() -> Result<(), E>>,
+    on_error: Option<fn(E) -> E>,
+    at_exit: Option<fn()>,
+}
+
+impl<E> Scoped<E>
+where
+    E: Display + Debug,
+{
+    /// Create a new Scoped
+    pub fn new() -> Self {
+        Self {
+            install: None,
+            on_error: None,
+            at_exit: None,
+        }
+    }
+
+    /// Set up anything the scope needs before it runs
+    pub fn install(mut self, install: fn() -> Result<(), E>) -> Self {
+        self.install = Some(install);
+        self
+    }
+
+    /// When there is an error in install or the scope set what should happen
+    pub fn on_error(mut self, on_error: fn(E) -> E) -> Self {
+        self.on_error = Some(on_error);
+        self
+    }
+
+    /// When the scope finishes, regardless of if there is an error or a panic,
+    /// set what should be done
+    pub fn at_exit(mut self, at_exit: fn()) -> Self {
+        self.at_exit = Some(at_exit);
+        self
+    }
+
+    /// Run `install` followed by `scope` if it succeeded, passing any error
+    /// through `on_error`, and finally run `at_exit`. If `scope` panics then
+    /// `at_exit` is run before the panic continues.
+    pub fn run<T>(self, scope: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            if let Some(install) = self.install {
+                install()?;
+            }
+            scope()
+        }));
+        let res = match res {
+            Ok(res) => res,
+            Err(payload) => {
+                if let Some(at_exit) = self.at_exit {
+                    at_exit();
+                }
+                panic::resume_unwind(payload);
+            }
+        };
+        let res = match (self.on_error, res) {
+            (Some(on_error), Err(err)) => Err(on_error(err)),
+            (_, res) => res,
+        };
+        if let Some(at_exit) = self.at_exit {
+            at_exit();
+        }
+        res
+    }
+}
+
+impl<E> Default for Scoped<E>
+where
+    E: Display + Debug,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
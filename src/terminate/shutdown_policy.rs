@@ -0,0 +1,141 @@
+//! Cooperative shutdown of the running program
+//!
+//! When signal handling is configured with
+//! [`Terminate::handle_signals`](super::Terminate::handle_signals) receiving
+//! `SIGINT` or `SIGTERM` does not kill the program. Instead the process wide
+//! [`ShutdownToken`](crate::shutdown::ShutdownToken) is triggered and it is
+//! up to the program to notice and return from `main` so that everything can
+//! be cleaned up properly.
+//!
+//! ```no_run
+//! # use futility::terminate::{self, ShutdownPolicy, Terminate};
+//! # use std::{io, time::Duration};
+//! Terminate::<io::Error>::new()
+//!     .handle_signals(ShutdownPolicy::Escalate)
+//!     .execute(|| {
+//!         let shutdown = terminate::handle().shutdown_token();
+//!         while !shutdown.wait_timeout(Duration::from_secs(1)) {
+//!             println!("Doing work");
+//!         }
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//! ```
+
+#[cfg(unix)]
+use super::{
+    memory,
+    outcome::{self, AtExit, ExitInfo, ExitReason},
+};
+#[cfg(unix)]
+use std::{process, sync::mpsc, time::Instant};
+use std::{
+    sync::atomic::{AtomicU8, Ordering},
+    thread,
+    time::Duration,
+};
+
+/// The code the program exits with when it does not return from `main` within
+/// the grace period set with
+/// [`Terminate::grace_period`](super::Terminate::grace_period)
+pub const GRACE_PERIOD_EXIT_CODE: i32 = 124;
+
+/// What to do when a shutdown signal is received while the program is
+/// already shutting down
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShutdownPolicy {
+    /// Every signal only requests a graceful shutdown
+    Graceful,
+    /// The first signal requests a graceful shutdown and the second forces the
+    /// program to exit after running the `at_exit_critical` functions
+    Escalate,
+    /// Like [`ShutdownPolicy::Escalate`], but signals received within the
+    /// given grace period of the first one are ignored rather than forcing an
+    /// exit
+    EscalateAfter(Duration),
+}
+
+/// `main` is running or hasn't started yet
+const RUNNING: u8 = 0;
+/// `main` returned before the grace period ran out
+const FINISHED: u8 = 1;
+/// The grace period ran out and the program is being forced to exit
+const FORCED: u8 = 2;
+
+static MAIN: AtomicU8 = AtomicU8::new(RUNNING);
+
+/// Mark `main` as about to run
+pub(crate) fn main_started() {
+    MAIN.store(RUNNING, Ordering::SeqCst);
+}
+
+/// Mark `main` as having returned. If the program is already being forced to
+/// exit this never returns, as the process is about to exit and the normal
+/// exit path must not run `at_exit` a second time.
+pub(crate) fn main_finished() {
+    if MAIN
+        .compare_exchange(RUNNING, FINISHED, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        loop {
+            thread::park();
+        }
+    }
+}
+
+/// How long `main` has to return once a shutdown is requested, and how long
+/// `at_exit` has to run if it doesn't
+#[cfg(unix)]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct GracePeriod {
+    pub(crate) grace: Duration,
+    pub(crate) at_exit_timeout: Duration,
+}
+
+#[cfg(unix)]
+impl GracePeriod {
+    /// Start timing the grace period, forcing the program to exit if `main`
+    /// hasn't returned by the end of it
+    pub(crate) fn start(self, at_exit: Option<AtExit>, critical: Vec<fn()>, started: Instant) {
+        let watchdog = thread::Builder::new()
+            .name("futility-grace-period".into())
+            .spawn(move || {
+                thread::sleep(self.grace);
+                if MAIN
+                    .compare_exchange(RUNNING, FORCED, Ordering::SeqCst, Ordering::SeqCst)
+                    .is_err()
+                {
+                    return;
+                }
+                eprintln!(
+                    "The program did not shut down within {:?}, forcing exit",
+                    self.grace
+                );
+                let info = ExitInfo {
+                    peak_memory: memory::peak_rss(),
+                    runtime: started.elapsed(),
+                    reason: ExitReason::Timeout,
+                    #[cfg(feature = "metrics")]
+                    metrics: crate::metrics::snapshot(),
+                    ..ExitInfo::default()
+                };
+                let (done, finished) = mpsc::channel();
+                let _ = thread::Builder::new()
+                    .name("futility-at-exit".into())
+                    .spawn(move || {
+                        if let Some(at_exit) = at_exit {
+                            at_exit.call(&info);
+                        }
+                        let _ = done.send(());
+                    });
+                if finished.recv_timeout(self.at_exit_timeout).is_err() {
+                    eprintln!("at_exit did not finish within {:?}", self.at_exit_timeout);
+                }
+                outcome::run_critical(&critical);
+                process::exit(GRACE_PERIOD_EXIT_CODE);
+            });
+        if let Err(err) = watchdog {
+            eprintln!("failed to start the shutdown grace period: {err}");
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! How [`Terminate`](super::Terminate) handles Unix signals
+//!
+//! Signals are received with a [`Handler`] from the [`signal`](crate::signal)
+//! module, so they're handled outside of the signal context and alongside any
+//! handlers the program registers itself.
+//!
+//! Besides the signals handled by options like
+//! [`Terminate::handle_signals`](super::Terminate::handle_signals), any signal
+//! can be given an [`Action`] with
+//! [`Terminate::signal`](super::Terminate::signal), such as dumping
+//! diagnostics on `SIGUSR1` or reopening log files on `SIGUSR2`.
+//!
+//! ```no_run
+//! # use futility::terminate::{signal_action::Action, Signal, Terminate};
+//! # use std::io;
+//! fn dump_stats() {
+//!     eprintln!("requests served: 42");
+//! }
+//!
+//! Terminate::<io::Error>::new()
+//!     .signal(Signal::USR1, Action::Custom(dump_stats))
+//!     .signal(Signal::QUIT, Action::ForceExit)
+//!     .execute(|| Ok(()))
+//!     .unwrap();
+//! ```
+
+use super::{
+    outcome::{self, AtExit, Signal},
+    shutdown_policy::{GracePeriod, ShutdownPolicy},
+};
+use crate::signal::{Handler, Signals};
+use libc::c_int;
+use std::{
+    fmt, io, process,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Mutex,
+    },
+    time::Instant,
+};
+
+/// What to do when a signal given to
+/// [`Terminate::signal`](super::Terminate::signal) is received
+#[derive(Clone, Copy, Debug)]
+pub enum Action {
+    /// Trigger the [`ShutdownToken`](super::ShutdownToken) so that the program
+    /// can shut down gracefully, with the signal as the exit reason
+    Shutdown,
+    /// Run the `at_exit_critical` functions and exit immediately with
+    /// `128 + signal`
+    ForceExit,
+    /// Call the function on the signal dispatch thread
+    Custom(fn()),
+    /// Do nothing, rather than the default action of the signal
+    Ignore,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Action::Shutdown => "shutting down",
+            Action::ForceExit => "forcing exit",
+            Action::Custom(_) => "calling a custom function",
+            Action::Ignore => "ignoring it",
+        })
+    }
+}
+
+/// Which signals [`Terminate`](super::Terminate) handles and how
+#[derive(Clone, Default)]
+pub(crate) struct SignalConfig {
+    pub(crate) reload: Option<fn()>,
+    pub(crate) shutdown: Option<ShutdownPolicy>,
+    pub(crate) reexec: Option<c_int>,
+    pub(crate) grace: Option<GracePeriod>,
+    pub(crate) actions: Vec<(c_int, Action)>,
+}
+
+impl SignalConfig {
+    /// Start a handler for the configured signals. The `critical`
+    /// functions are run before the program is forced to exit, along with
+    /// `at_exit` if it is forced to exit because the grace period ran out.
+    pub(crate) fn start(self, at_exit: Option<AtExit>, critical: Vec<fn()>) -> io::Result<Handler> {
+        let started = Instant::now();
+        let mut signals = Vec::new();
+        if self.reload.is_some() {
+            signals.push(libc::SIGHUP);
+        }
+        if self.shutdown.is_some() {
+            signals.extend([libc::SIGINT, libc::SIGTERM]);
+        }
+        if let Some(reexec) = self.reexec {
+            signals.push(reexec);
+        }
+        signals.extend(self.actions.iter().map(|(signal, _)| *signal));
+
+        SHUTDOWN_SIGNAL.store(0, Ordering::SeqCst);
+        *RELOAD.lock().unwrap_or_else(|e| e.into_inner()) = self.reload;
+        let mut first_shutdown: Option<Instant> = None;
+        let actions = self.actions;
+        let signals = Signals::new(signals.into_iter().map(Signal::from_raw));
+        signals.handle(move |signal| {
+            let signal = signal.as_raw();
+            if let Some((_, action)) = actions
+                .iter()
+                .find(|(action_signal, _)| *action_signal == signal)
+            {
+                match action {
+                    Action::Shutdown => {
+                        let _ = SHUTDOWN_SIGNAL.compare_exchange(
+                            0,
+                            signal,
+                            Ordering::SeqCst,
+                            Ordering::SeqCst,
+                        );
+                        crate::shutdown::global().trigger();
+                    }
+                    Action::ForceExit => {
+                        outcome::run_critical(&critical);
+                        process::exit(128 + signal);
+                    }
+                    Action::Custom(custom) => custom(),
+                    Action::Ignore => {}
+                }
+                return;
+            }
+            match signal {
+                signal if Some(signal) == self.reexec => {
+                    let _ = SHUTDOWN_SIGNAL.compare_exchange(
+                        0,
+                        signal,
+                        Ordering::SeqCst,
+                        Ordering::SeqCst,
+                    );
+                    crate::shutdown::global().trigger();
+                }
+                libc::SIGHUP => {
+                    if let Some(reload) = self.reload {
+                        reload();
+                    }
+                }
+                libc::SIGINT | libc::SIGTERM => {
+                    let Some(policy) = self.shutdown else {
+                        return;
+                    };
+                    let force = match (first_shutdown, policy) {
+                        (None, _) | (Some(_), ShutdownPolicy::Graceful) => false,
+                        (Some(_), ShutdownPolicy::Escalate) => true,
+                        (Some(first), ShutdownPolicy::EscalateAfter(grace)) => {
+                            first.elapsed() >= grace
+                        }
+                    };
+                    if force {
+                        eprintln!("Forcing exit");
+                        outcome::run_critical(&critical);
+                        process::exit(128 + signal);
+                    }
+                    if first_shutdown.is_none() {
+                        first_shutdown = Some(Instant::now());
+                        SHUTDOWN_SIGNAL.store(signal, Ordering::SeqCst);
+                        if policy != ShutdownPolicy::Graceful {
+                            eprintln!(
+                                "Shutting down gracefully, send the signal again to force exit"
+                            );
+                        }
+                        if let Some(grace) = self.grace {
+                            grace.start(at_exit, critical.clone(), started);
+                        }
+                    }
+                    crate::shutdown::global().trigger();
+                }
+                _ => {}
+            }
+        })
+    }
+}
+
+/// Stop a handler started with [`SignalConfig::start`]
+pub(crate) fn stop(handler: Handler) {
+    *RELOAD.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    handler.stop();
+}
+
+/// The `on_reload` function of the running program, if it has one
+static RELOAD: Mutex<Option<fn()>> = Mutex::new(None);
+
+/// Call the `on_reload` function of the running program, returning whether
+/// there was one to call
+pub(crate) fn reload() -> bool {
+    let reload = *RELOAD.lock().unwrap_or_else(|e| e.into_inner());
+    reload.map(|reload| reload()).is_some()
+}
+
+/// The signal that started a shutdown, or 0 if there hasn't been one
+static SHUTDOWN_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+/// The signal that started a shutdown of the program, if any
+pub(crate) fn shutdown_signal() -> Option<Signal> {
+    match SHUTDOWN_SIGNAL.load(Ordering::SeqCst) {
+        0 => None,
+        signal => Some(Signal::from_raw(signal)),
+    }
+}
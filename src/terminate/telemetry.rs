@@ -0,0 +1,95 @@
+//! Flushing of telemetry when the program exits
+//!
+//! Exporters such as OpenTelemetry's batch span processor buffer what is
+//! recorded and send it in the background, which means whatever was recorded
+//! right before the program exits is lost unless the provider is shut down
+//! first. [`Terminate::flush_telemetry`](super::Terminate::flush_telemetry)
+//! does this as the very last step of exiting, after every `at_exit` function
+//! and teardown has had the chance to record something.
+//!
+//! Shutting down a provider can block on the network, so each flush is given a
+//! timeout after which the program exits anyway.
+//!
+//! The OpenTelemetry SDK's tracer, meter, and logger providers can be flushed
+//! as they are. Anything else can be flushed with a closure returning a
+//! `Result`.
+//!
+//! ```no_run
+//! # use futility::terminate::Terminate;
+//! # use opentelemetry_sdk::trace::SdkTracerProvider;
+//! # use std::{io, time::Duration};
+//! let provider = SdkTracerProvider::builder().build();
+//! Terminate::<io::Error>::new()
+//!     .flush_telemetry(provider, Duration::from_secs(5))
+//!     .execute(|| Ok(()))
+//!     .unwrap();
+//! ```
+
+use opentelemetry_sdk::{
+    logs::SdkLoggerProvider, metrics::SdkMeterProvider, trace::SdkTracerProvider,
+};
+use std::{fmt::Debug, sync::mpsc, thread, time::Duration};
+
+/// A provider of telemetry that needs to be flushed before exiting
+pub trait FlushTelemetry: Send + 'static {
+    /// Flush anything buffered and shut the provider down
+    fn flush(self: Box<Self>);
+}
+
+impl<F, T, E> FlushTelemetry for F
+where
+    F: FnOnce() -> Result<T, E> + Send + 'static,
+    E: Debug,
+{
+    fn flush(self: Box<Self>) {
+        report(self());
+    }
+}
+
+impl FlushTelemetry for SdkTracerProvider {
+    fn flush(self: Box<Self>) {
+        report(self.shutdown());
+    }
+}
+
+impl FlushTelemetry for SdkMeterProvider {
+    fn flush(self: Box<Self>) {
+        report(self.shutdown());
+    }
+}
+
+impl FlushTelemetry for SdkLoggerProvider {
+    fn flush(self: Box<Self>) {
+        report(self.shutdown());
+    }
+}
+
+fn report<T, E: Debug>(result: Result<T, E>) {
+    if let Err(err) = result {
+        eprintln!("failed to flush telemetry: {err:?}");
+    }
+}
+
+/// A provider waiting to be flushed along with how long it may take
+pub(crate) struct Flush {
+    pub(crate) provider: Box<dyn FlushTelemetry>,
+    pub(crate) timeout: Duration,
+}
+
+impl Flush {
+    /// Flush the provider on another thread, giving up once the timeout has
+    /// passed
+    pub(crate) fn run(self) {
+        let (done, finished) = mpsc::channel();
+        let provider = self.provider;
+        let spawned = thread::Builder::new()
+            .name("futility-flush".into())
+            .spawn(move || {
+                provider.flush();
+                let _ = done.send(());
+            });
+        if spawned.is_ok() && finished.recv_timeout(self.timeout).is_err() {
+            eprintln!("timed out flushing telemetry after {:?}", self.timeout);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Worker threads that are shut down and joined when the program exits
+//!
+//! Threads spawned with [`Handle::spawn_tracked`](super::Handle::spawn_tracked)
+//! are handed a child of the process wide [`ShutdownToken`] and are expected
+//! to return once it is triggered. When `main` returns
+//! [`Terminate`](super::Terminate) triggers each worker's token and waits for
+//! every tracked worker to finish before running `on_error` and `at_exit`, so
+//! that nothing they were writing is lost. Workers that panic, fail, or do not
+//! stop within the timeout set with
+//! [`Terminate::worker_timeout`](super::Terminate::worker_timeout) are
+//! reported as a [`WorkerError`]. With
+//! [`Terminate::propagate_worker_errors`](super::Terminate::propagate_worker_errors)
+//! they fail the program with [`WorkerErrors`] if it would otherwise have
+//! succeeded, and otherwise they are printed to stderr.
+//!
+//! ```no_run
+//! # use futility::terminate::{self, Terminate};
+//! # use std::{error::Error, time::Duration};
+//! Terminate::<Box<dyn Error>>::new()
+//!     .propagate_worker_errors()
+//!     .execute(|| {
+//!         terminate::handle().spawn_tracked("flusher", |shutdown| {
+//!             while !shutdown.wait_timeout(Duration::from_secs(1)) {
+//!                 println!("Flushing");
+//!             }
+//!             Ok::<_, std::io::Error>(())
+//!         })?;
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//! ```
+
+use super::panic_hook::{self, PanicPayload};
+use crate::shutdown::{self, ShutdownToken};
+use std::{
+    error::Error,
+    fmt, io,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+/// How long to wait for tracked workers to stop if no timeout is set
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Why a tracked worker did not stop cleanly
+#[derive(Debug, Error)]
+pub enum WorkerError {
+    /// The worker panicked
+    #[error("worker `{name}` panicked: {message}")]
+    Panicked {
+        /// The name the worker was spawned with
+        name: String,
+        /// The message the worker panicked with
+        message: String,
+    },
+    /// The worker returned an error
+    #[error("worker `{name}` failed: {source}")]
+    Failed {
+        /// The name the worker was spawned with
+        name: String,
+        /// The error the worker returned
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// The worker was still running after the timeout passed
+    #[error("worker `{name}` did not stop within {timeout:?}")]
+    TimedOut {
+        /// The name the worker was spawned with
+        name: String,
+        /// How long the worker was waited on
+        timeout: Duration,
+    },
+}
+
+/// Every tracked worker that didn't stop cleanly, returned by
+/// [`Terminate::execute`](super::Terminate::execute) when
+/// [`Terminate::propagate_worker_errors`](super::Terminate::propagate_worker_errors)
+/// is set
+#[derive(Debug)]
+pub struct WorkerErrors {
+    /// The error of each worker, in the order they were spawned
+    pub errors: Vec<WorkerError>,
+}
+
+impl WorkerErrors {
+    /// Whether any of the workers panicked
+    pub fn panicked(&self) -> bool {
+        self.errors
+            .iter()
+            .any(|err| matches!(err, WorkerError::Panicked { .. }))
+    }
+}
+
+impl fmt::Display for WorkerErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.errors[..] {
+            [err] => write!(f, "{err}"),
+            errors => {
+                write!(f, "{} workers did not stop cleanly", errors.len())?;
+                for err in errors {
+                    write!(f, "\n  {err}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for WorkerErrors {}
+
+/// A running worker and the channel its result is sent on
+struct Worker {
+    name: String,
+    shutdown: ShutdownToken,
+    thread: JoinHandle<()>,
+    finished: Receiver<Result<(), WorkerError>>,
+}
+
+static WORKERS: Mutex<Vec<Worker>> = Mutex::new(Vec::new());
+
+/// Spawn a tracked worker. See [`Handle::spawn_tracked`](super::Handle::spawn_tracked).
+pub(crate) fn spawn<F, E>(name: &str, worker: F) -> io::Result<()>
+where
+    F: FnOnce(ShutdownToken) -> Result<(), E> + Send + 'static,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    panic_hook::record_locations();
+    let (done, finished) = mpsc::channel();
+    let shutdown = shutdown::global().child();
+    let token = shutdown.clone();
+    let worker_name = name.to_string();
+    let thread = thread::Builder::new().name(name.into()).spawn(move || {
+        let res = match panic::catch_unwind(AssertUnwindSafe(|| worker(token))) {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(err)) => Err(WorkerError::Failed {
+                name: worker_name,
+                source: err.into(),
+            }),
+            Err(payload) => Err(WorkerError::Panicked {
+                name: worker_name,
+                message: PanicPayload::caught(&*payload)
+                    .message()
+                    .unwrap_or(crate::panic::NON_STRING_PAYLOAD)
+                    .into(),
+            }),
+        };
+        let _ = done.send(res);
+    })?;
+    WORKERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(Worker {
+            name: name.into(),
+            shutdown,
+            thread,
+            finished,
+        });
+    Ok(())
+}
+
+/// Trigger the shutdown token of every tracked worker and wait for them to
+/// stop, all within `timeout`, returning the errors of those that did not stop
+/// cleanly. The process wide token isn't triggered.
+pub(crate) fn join_all(timeout: Duration) -> Vec<WorkerError> {
+    let workers = std::mem::take(&mut *WORKERS.lock().unwrap_or_else(|e| e.into_inner()));
+    for worker in &workers {
+        worker.shutdown.trigger();
+    }
+    // A timeout too large to add to now, such as `Duration::MAX`, waits forever
+    let deadline = Instant::now().checked_add(timeout);
+    let mut errors = Vec::new();
+    for worker in workers {
+        let finished = match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                worker.finished.recv_timeout(remaining).ok()
+            }
+            None => worker.finished.recv().ok(),
+        };
+        match finished {
+            Some(res) => {
+                let _ = worker.thread.join();
+                errors.extend(res.err());
+            }
+            None => errors.push(WorkerError::TimedOut {
+                name: worker.name,
+                timeout,
+            }),
+        }
+    }
+    errors
+}
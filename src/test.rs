@@ -0,0 +1,108 @@
+//! Tests with a value set up for them and torn down afterwards
+//!
+//! Tests that need something like a scratch directory or a database can use
+//! [`fixture`], or the `#[futility::fixture]` attribute built on it, which
+//! hands the value from a setup function to the test and then to a teardown
+//! function even if the test failed or panicked. Panics are turned into a
+//! [`TestFailure`] whose report says which of the three panicked and where.
+//!
+//! ```
+//! # use futility::test::fixture;
+//! let failure = fixture(
+//!     || vec![1, 2, 3],
+//!     |numbers| assert_eq!(numbers.len(), 4),
+//!     |numbers| numbers.push(4),
+//! );
+//! assert!(failure.is_ok());
+//! ```
+
+use crate::panic::{self, PanicDetails};
+use std::fmt::{self, Debug};
+
+/// The failure returned by a test that failed, panicked, or whose setup or
+/// teardown did. Its `Debug` output is the report itself so that the test
+/// harness prints it as is.
+pub struct TestFailure {
+    pub(crate) report: String,
+}
+
+impl TestFailure {
+    /// The report describing why the test failed
+    pub fn report(&self) -> &str {
+        &self.report
+    }
+}
+
+impl Debug for TestFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.report)
+    }
+}
+
+/// What a test can return
+pub trait TestOutput {
+    /// Turn the output into either success or a report of why the test failed
+    fn into_result(self) -> Result<(), String>;
+}
+
+impl TestOutput for () {
+    fn into_result(self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl<E: Debug> TestOutput for Result<(), E> {
+    fn into_result(self) -> Result<(), String> {
+        self.map_err(|err| format!("test returned an error: {err:?}"))
+    }
+}
+
+/// Run `body` with a value made by `setup`, then give the value to
+/// `teardown` whether `body` passed, failed, or panicked. A panic in any of
+/// them is caught and turned into a [`TestFailure`] with a readable report,
+/// and if both `body` and `teardown` fail the report has both failures.
+/// `teardown` isn't run if `setup` panics, as there's nothing to tear down.
+///
+/// This is usually used through the `#[futility::fixture]` attribute:
+///
+/// ```
+/// # use std::{fs, path::PathBuf};
+/// fn scratch_dir() -> PathBuf {
+///     let dir = std::env::temp_dir().join(format!("fixture-{}", std::process::id()));
+///     fs::create_dir_all(&dir).unwrap();
+///     dir
+/// }
+///
+/// fn remove(dir: PathBuf) {
+///     fs::remove_dir_all(dir).unwrap();
+/// }
+///
+/// #[futility::fixture(setup = scratch_dir, teardown = remove)]
+/// fn writes_file(dir: &mut PathBuf) -> std::io::Result<()> {
+///     fs::write(dir.join("out.txt"), "done")
+/// }
+/// ```
+pub fn fixture<S, T: TestOutput>(
+    setup: impl FnOnce() -> S,
+    teardown: impl FnOnce(S),
+    body: impl FnOnce(&mut S) -> T,
+) -> Result<(), TestFailure> {
+    let mut value = catch_panic("setup", setup).map_err(|report| TestFailure { report })?;
+    let res = catch_panic("test", || body(&mut value)).and_then(TestOutput::into_result);
+    let torn_down = catch_panic("teardown", || teardown(value));
+    match (res, torn_down) {
+        (Ok(()), Ok(())) => Ok(()),
+        (Err(report), Ok(())) | (Ok(()), Err(report)) => Err(TestFailure { report }),
+        (Err(test), Err(teardown)) => Err(TestFailure {
+            report: format!("{test}\n\n{teardown}"),
+        }),
+    }
+}
+
+/// Call `f`, turning a panic into a report saying that `what` panicked
+fn catch_panic<R>(what: &str, f: impl FnOnce() -> R) -> Result<R, String> {
+    panic::catch_asserted(f).map_err(|panic: PanicDetails| match panic.location {
+        Some(location) => format!("{what} panicked at {location}:\n{}", panic.message),
+        None => format!("{what} panicked:\n{}", panic.message),
+    })
+}
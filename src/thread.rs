@@ -0,0 +1,115 @@
+//! Scoped threads that return errors
+//!
+//! [`std::thread::scope`] joins every thread spawned in it, but only passes on
+//! a panic and leaves each thread's `Result` to be checked by hand.
+//! [`scope_try`] spawns threads that return a `Result`, turns a panic in any
+//! of them into an error with [`From<PanicDetails>`](PanicDetails), and
+//! returns either every value or every error in a [`MultiError`] along with
+//! the index of the thread it came from.
+//!
+//! ```
+//! # use futility::{panic::PanicDetails, thread};
+//! # use std::fmt;
+//! #[derive(Debug)]
+//! enum CheckError {
+//!     Failed(String),
+//!     Panicked(PanicDetails),
+//! }
+//!
+//! impl From<PanicDetails> for CheckError {
+//!     fn from(details: PanicDetails) -> Self {
+//!         Self::Panicked(details)
+//!     }
+//! }
+//!
+//! let hosts = ["alpha", "beta", "gamma"];
+//! let res = thread::scope_try(|s| {
+//!     for host in &hosts {
+//!         s.spawn_try(move || match *host {
+//!             "beta" => Err(CheckError::Failed(format!("{host} is down"))),
+//!             "gamma" => panic!("lost connection to {host}"),
+//!             _ => Ok(host.len()),
+//!         });
+//!     }
+//! });
+//!
+//! let err = res.unwrap_err();
+//! assert!(matches!(&err.errors[0], (1, CheckError::Failed(_))));
+//! assert!(matches!(&err.errors[1], (2, CheckError::Panicked(_))));
+//! ```
+
+use crate::{
+    panic::{self, PanicDetails},
+    result::{MultiError, ResultIteratorExt},
+};
+use std::{
+    cell::RefCell,
+    fmt,
+    thread::{self, ScopedJoinHandle},
+};
+
+/// Spawns threads for [`scope_try`]
+pub struct Scope<'scope, 'env: 'scope, T, E> {
+    scope: &'scope thread::Scope<'scope, 'env>,
+    handles: RefCell<Vec<ScopedJoinHandle<'scope, Result<T, E>>>>,
+}
+
+impl<'scope, 'env, T, E> Scope<'scope, 'env, T, E>
+where
+    T: Send + 'scope,
+    E: Send + From<PanicDetails> + 'scope,
+{
+    /// Spawn a thread running `f`, returning the index its value or error
+    /// will have once the scope ends. A panic in `f` becomes its error.
+    pub fn spawn_try<F>(&self, f: F) -> usize
+    where
+        F: FnOnce() -> Result<T, E> + Send + 'scope,
+    {
+        let handle = self
+            .scope
+            .spawn(|| panic::catch_asserted(f).and_then(|res| res));
+        let mut handles = self.handles.borrow_mut();
+        handles.push(handle);
+        handles.len() - 1
+    }
+}
+
+impl<T, E> fmt::Debug for Scope<'_, '_, T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scope")
+            .field("threads", &self.handles.borrow().len())
+            .finish()
+    }
+}
+
+/// Run `f` with a [`Scope`] to spawn threads in, then wait for every thread
+/// and return their values in the order they were spawned, or every error if
+/// any of them failed or panicked
+///
+/// Like with [`std::thread::scope`] the threads can borrow from outside of
+/// the scope, and if `f` itself panics the panic is passed on once every
+/// thread has finished.
+pub fn scope_try<'env, T, E, F>(f: F) -> Result<Vec<T>, MultiError<E>>
+where
+    T: Send + 'env,
+    E: Send + From<PanicDetails> + 'env,
+    F: for<'scope> FnOnce(&Scope<'scope, 'env, T, E>),
+{
+    thread::scope(|scope| {
+        let scope = Scope {
+            scope,
+            handles: RefCell::new(Vec::new()),
+        };
+        f(&scope);
+        scope
+            .handles
+            .into_inner()
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|payload| Err(PanicDetails::from_payload(&*payload).into()))
+            })
+            .collect_all_errors()
+    })
+}
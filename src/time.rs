@@ -0,0 +1,327 @@
+//! Timing code without the boilerplate
+//!
+//! [`Stopwatch`] measures elapsed time and can be paused, resumed, and read
+//! in laps. [`time_scope!`](crate::time_scope) times the rest of the current
+//! scope and reports how long it took when the scope is left, however it's
+//! left. By default the time is logged with [`log!`](crate::log) at
+//! [`Level::Debug`], but it can be sent anywhere with a [`Sink`]:
+//!
+//! ```
+//! # use futility::{time_scope, time::{self, Sink}};
+//! # use std::time::Duration;
+//! fn load_config() {
+//!     time_scope!("load config");
+//!     // ...
+//! }
+//!
+//! fn query(sql: &str) {
+//!     time_scope!("query", Sink::Record);
+//!     // ...
+//! }
+//!
+//! fn render() {
+//!     time_scope!("render", Sink::callback(|label, elapsed| {
+//!         assert!(elapsed < Duration::from_secs(60), "{label} took {elapsed:?}");
+//!     }));
+//!     // ...
+//! }
+//!
+//! load_config();
+//! query("SELECT 1");
+//! query("SELECT 2");
+//! render();
+//! let summary = time::summary();
+//! assert_eq!(summary[0].0, "query");
+//! assert_eq!(summary[0].1.count, 2);
+//! ```
+//!
+//! Times sent to [`Sink::Record`] are added up by label, and
+//! [`Terminate::report_runtime`] prints a summary of them along with the
+//! program's runtime when it exits.
+//!
+//! [`Terminate::report_runtime`]: crate::terminate::Terminate::report_runtime
+
+use crate::{
+    lock::Mutex,
+    log::{self, Level},
+};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Measures elapsed time, which can be paused and resumed
+#[derive(Clone, Copy, Debug)]
+pub struct Stopwatch {
+    /// When the stopwatch was last started, if it's running
+    started: Option<Instant>,
+    /// Time counted before it was last started
+    counted: Duration,
+    /// The elapsed time at the end of the last lap
+    lap: Duration,
+}
+
+impl Stopwatch {
+    /// A stopwatch that isn't running and reads zero
+    pub fn new() -> Self {
+        Self {
+            started: None,
+            counted: Duration::ZERO,
+            lap: Duration::ZERO,
+        }
+    }
+
+    /// A stopwatch that is running from now
+    pub fn start() -> Self {
+        let mut stopwatch = Self::new();
+        stopwatch.resume();
+        stopwatch
+    }
+
+    /// Whether the stopwatch is running
+    pub fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// The time counted so far
+    pub fn elapsed(&self) -> Duration {
+        self.counted
+            + self
+                .started
+                .map_or(Duration::ZERO, |started| started.elapsed())
+    }
+
+    /// Stop counting time, returning the time counted so far
+    pub fn pause(&mut self) -> Duration {
+        self.counted = self.elapsed();
+        self.started = None;
+        self.counted
+    }
+
+    /// Start counting time again, if it isn't already
+    pub fn resume(&mut self) {
+        self.started.get_or_insert_with(Instant::now);
+    }
+
+    /// The time counted since the last lap, or since the stopwatch started for
+    /// the first lap
+    pub fn lap(&mut self) -> Duration {
+        let elapsed = self.elapsed();
+        let lap = elapsed - self.lap;
+        self.lap = elapsed;
+        lap
+    }
+
+    /// Set the time counted back to zero, leaving the stopwatch running if it
+    /// was
+    pub fn reset(&mut self) {
+        let running = self.is_running();
+        *self = Self::new();
+        if running {
+            self.resume();
+        }
+    }
+}
+
+impl Default for Stopwatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where the time taken by a [`ScopeTimer`] is sent
+pub enum Sink {
+    /// Log it at a level, with the target the timer was created with
+    Log(Level),
+    /// Emit it as a `tracing` event at the debug level
+    #[cfg(feature = "tracing")]
+    Tracing,
+    /// Add it to the times recorded for its label, see [`summary`]
+    Record,
+    /// Pass it to a function along with the label
+    Callback(Callback),
+}
+
+/// A function passed the label and time taken by [`Sink::Callback`]
+pub type Callback = Box<dyn FnOnce(&str, Duration) + Send>;
+
+impl Sink {
+    /// Pass the time to `f` along with the label
+    pub fn callback(f: impl FnOnce(&str, Duration) + Send + 'static) -> Self {
+        Sink::Callback(Box::new(f))
+    }
+}
+
+impl fmt::Debug for Sink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sink::Log(level) => f.debug_tuple("Log").field(level).finish(),
+            #[cfg(feature = "tracing")]
+            Sink::Tracing => f.write_str("Tracing"),
+            Sink::Record => f.write_str("Record"),
+            Sink::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+/// Times a scope, sending the time to a [`Sink`] when dropped. This is
+/// usually created with [`time_scope!`](crate::time_scope).
+#[derive(Debug)]
+#[must_use = "the scope is timed until the timer is dropped"]
+pub struct ScopeTimer {
+    label: Cow<'static, str>,
+    target: &'static str,
+    stopwatch: Stopwatch,
+    sink: Option<Sink>,
+}
+
+impl ScopeTimer {
+    /// Start timing, logging the time at [`Level::Debug`] with `target` when
+    /// dropped
+    pub fn new(label: impl Into<Cow<'static, str>>, target: &'static str) -> Self {
+        Self {
+            label: label.into(),
+            target,
+            stopwatch: Stopwatch::start(),
+            sink: Some(Sink::Log(Level::Debug)),
+        }
+    }
+
+    /// Send the time to `sink` rather than logging it
+    pub fn sink(mut self, sink: Sink) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// The time taken so far
+    pub fn elapsed(&self) -> Duration {
+        self.stopwatch.elapsed()
+    }
+}
+
+impl Drop for ScopeTimer {
+    fn drop(&mut self) {
+        let elapsed = self.stopwatch.elapsed();
+        let label = &*self.label;
+        match self.sink.take() {
+            Some(Sink::Log(level)) => log::log(
+                level,
+                self.target,
+                format_args!("{label} took {elapsed:.2?}"),
+            ),
+            #[cfg(feature = "tracing")]
+            Some(Sink::Tracing) => tracing::debug!(label, ?elapsed, "{label} took {elapsed:.2?}"),
+            Some(Sink::Record) => record(label, elapsed),
+            Some(Sink::Callback(f)) => f(label, elapsed),
+            None => {}
+        }
+    }
+}
+
+/// Time the rest of the current scope, sending the time to a [`Sink`] when
+/// the scope is left. Without a sink the time is logged at
+/// [`Level::Debug`](crate::log::Level::Debug) with the current module as the
+/// target.
+///
+/// ```
+/// # use futility::{time_scope, time::Sink, log::Level};
+/// fn handle_request() {
+///     time_scope!("handle request");
+///     time_scope!("handle request (recorded)", Sink::Record);
+///     time_scope!(format!("handle request {}", 1), Sink::Log(Level::Info));
+/// }
+/// ```
+#[macro_export]
+macro_rules! time_scope {
+    ($label:expr $(,)?) => {
+        let _time_scope = $crate::time::ScopeTimer::new($label, ::std::module_path!());
+    };
+    ($label:expr, $sink:expr $(,)?) => {
+        let _time_scope = $crate::time::ScopeTimer::new($label, ::std::module_path!()).sink($sink);
+    };
+}
+
+/// The times recorded for one label
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Summary {
+    /// How many times were recorded
+    pub count: u64,
+    /// The sum of the times
+    pub total: Duration,
+    /// The shortest time
+    pub min: Duration,
+    /// The longest time
+    pub max: Duration,
+}
+
+impl Summary {
+    /// The average time
+    pub fn mean(&self) -> Duration {
+        match self.count {
+            0 => Duration::ZERO,
+            count => self.total.div_f64(count as f64),
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} calls, total {:.2?}, mean {:.2?}, min {:.2?}, max {:.2?}",
+            self.count,
+            self.total,
+            self.mean(),
+            self.min,
+            self.max
+        )
+    }
+}
+
+static RECORDED: Mutex<BTreeMap<String, Summary>> = Mutex::new(BTreeMap::new());
+
+/// Add `elapsed` to the times recorded for `label`
+pub fn record(label: &str, elapsed: Duration) {
+    let mut recorded = RECORDED.lock();
+    let summary = recorded.entry(label.into()).or_insert(Summary {
+        count: 0,
+        total: Duration::ZERO,
+        min: Duration::MAX,
+        max: Duration::ZERO,
+    });
+    summary.count += 1;
+    summary.total += elapsed;
+    summary.min = summary.min.min(elapsed);
+    summary.max = summary.max.max(elapsed);
+}
+
+/// The times recorded so far for each label, sorted by label
+pub fn summary() -> Vec<(String, Summary)> {
+    RECORDED
+        .lock()
+        .iter()
+        .map(|(label, summary)| (label.clone(), *summary))
+        .collect()
+}
+
+/// Forget every recorded time
+pub fn clear() {
+    RECORDED.lock().clear();
+}
+
+/// Print the recorded times to stderr, if there are any
+#[cfg(feature = "terminate")]
+pub(crate) fn print_summary() {
+    let summary = summary();
+    if summary.is_empty() {
+        return;
+    }
+    let lines = summary
+        .iter()
+        .map(|(label, summary)| format!("{label}: {summary}"))
+        .collect::<Vec<_>>();
+    crate::tty::print_report("timings", lines.join("\n"));
+}
@@ -0,0 +1,265 @@
+//! Bounding how long blocking code can run for
+//!
+//! Plenty of blocking APIs have no way to give up after a while, such as
+//! resolving a host name or reading from a file on a network share. [`run`]
+//! runs a function on a worker thread and stops waiting for it once the
+//! timeout is up, returning [`TimedOut`]. The worker can't be stopped from the
+//! outside, so it's left running in the background where it can be waited for
+//! with [`TimedOut::join_worker`], or is detached if the error is dropped. If
+//! the worker thread can't be spawned at all the [`io::Error`] is returned
+//! instead, both as a [`TimeoutError`].
+//!
+//! Code that can check in on its progress should use [`run_with_deadline`]
+//! instead, which passes it a [`Deadline`] to stop at so that the worker
+//! doesn't outlive the timeout. [`run_within`] does the same with a deadline
+//! that was set further up, see the [`budget`](crate::budget) module.
+//!
+//! ```
+//! # use futility::timeout::{self, TimedOut};
+//! # use std::{net::ToSocketAddrs, time::Duration};
+//! let addrs = timeout::run(Duration::from_secs(5), || {
+//!     ("localhost", 443).to_socket_addrs().map(|addrs| addrs.count())
+//! });
+//!
+//! let sum = timeout::run_with_deadline(Duration::from_millis(50), |deadline| {
+//!     let mut sum = 0u64;
+//!     for n in 0.. {
+//!         deadline.check()?;
+//!         sum += n;
+//!     }
+//!     Ok::<_, TimedOut>(sum)
+//! });
+//! // Either the deadline was checked or the caller stopped waiting first
+//! assert!(sum.map_or(true, |sum| sum.is_err()));
+//! ```
+//!
+//! Async code doesn't need a worker thread to be bounded, since a future can
+//! just stop being polled. [`timeout_async`] and [`deadline_at`] wrap a future
+//! so it resolves to [`Elapsed`] if it isn't done in time. Waiting is done by
+//! a [`Sleeper`], the same as between attempts in the [`retry`](crate::retry)
+//! module, which by default is a [`ThreadSleeper`] that works with any
+//! executor. [`timeout_async_with`] and [`deadline_at_with`] take the sleep
+//! function of the runtime in use instead, such as `tokio::time::sleep`.
+//!
+//! ```ignore
+//! let body = retry_async_with(
+//!     ExponentialBackoff::default().max_attempts(5),
+//!     tokio::time::sleep,
+//!     |err: &io::Error| err.kind() == io::ErrorKind::TimedOut,
+//!     || async {
+//!         timeout_async_with(tokio::time::sleep, Duration::from_secs(5), fetch(url))
+//!             .await?
+//!     },
+//! )
+//! .await?;
+//! ```
+
+use crate::retry::{Sleeper, ThreadSleeper};
+use std::{
+    future::{self, Future},
+    io,
+    panic::{self, AssertUnwindSafe},
+    pin::pin,
+    sync::mpsc::{self, RecvTimeoutError},
+    task::Poll,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+/// The error returned when a function didn't finish in time
+#[derive(Debug, Error)]
+#[error("timed out after {timeout:?}")]
+pub struct TimedOut {
+    /// How long the function was given
+    pub timeout: Duration,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl TimedOut {
+    /// Whether the worker thread that was running the function has finished
+    /// since this was returned. This is always `true` for the error returned
+    /// by [`Deadline::check`].
+    pub fn worker_finished(&self) -> bool {
+        self.worker.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// Wait for the worker thread that was running the function to finish,
+    /// throwing away what it returns. Returns `false` if it panicked.
+    pub fn join_worker(self) -> bool {
+        self.worker.is_none_or(|worker| worker.join().is_ok())
+    }
+}
+
+impl From<TimedOut> for io::Error {
+    fn from(err: TimedOut) -> Self {
+        io::Error::new(io::ErrorKind::TimedOut, err)
+    }
+}
+
+/// The error returned when a function run on a worker thread didn't finish
+#[derive(Debug, Error)]
+pub enum TimeoutError {
+    /// The function didn't finish in time
+    #[error(transparent)]
+    TimedOut(#[from] TimedOut),
+    /// The worker thread couldn't be spawned, such as when the process is at
+    /// its limit of threads
+    #[error("failed to spawn a timeout worker thread")]
+    Spawn(#[source] io::Error),
+}
+
+impl From<TimeoutError> for io::Error {
+    fn from(err: TimeoutError) -> Self {
+        match err {
+            TimeoutError::TimedOut(err) => err.into(),
+            TimeoutError::Spawn(err) => err,
+        }
+    }
+}
+
+/// The error a future wrapped by [`timeout_async`] or [`deadline_at`]
+/// resolves to if it isn't done in time
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+#[error("deadline elapsed")]
+pub struct Elapsed(());
+
+impl From<Elapsed> for io::Error {
+    fn from(err: Elapsed) -> Self {
+        io::Error::new(io::ErrorKind::TimedOut, err)
+    }
+}
+
+pub use crate::budget::Deadline;
+
+impl Deadline {
+    /// Return [`TimedOut`] if the deadline has passed, so that `?` can be used
+    /// to stop once it has. This is [`Deadline::checkpoint`] with the error
+    /// that [`run_with_deadline`] returns when it runs out of time.
+    pub fn check(&self) -> Result<(), TimedOut> {
+        match self.is_expired() {
+            true => Err(TimedOut {
+                timeout: self.budget(),
+                worker: None,
+            }),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Run `f` on a worker thread, returning what it returns or [`TimedOut`] if
+/// it takes longer than `timeout`. If `f` panics the panic is resumed on the
+/// calling thread.
+pub fn run<T, F>(timeout: Duration, f: F) -> Result<T, TimeoutError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    run_with_deadline(timeout, |_| f())
+}
+
+/// Run `f` on a worker thread like [`run`], passing it the [`Deadline`] it
+/// should finish by
+pub fn run_with_deadline<T, F>(timeout: Duration, f: F) -> Result<T, TimeoutError>
+where
+    T: Send + 'static,
+    F: FnOnce(Deadline) -> T + Send + 'static,
+{
+    run_within(Deadline::after(timeout), f)
+}
+
+/// Run `f` on a worker thread like [`run_with_deadline`], giving up at a
+/// [`Deadline`] that's already been set, such as one passed down from a
+/// caller
+pub fn run_within<T, F>(deadline: Deadline, f: F) -> Result<T, TimeoutError>
+where
+    T: Send + 'static,
+    F: FnOnce(Deadline) -> T + Send + 'static,
+{
+    let (sender, receiver) = mpsc::sync_channel(1);
+    let worker = thread::Builder::new()
+        .name("futility-timeout".into())
+        .spawn(move || {
+            let _ = sender.send(panic::catch_unwind(AssertUnwindSafe(|| f(deadline))));
+        })
+        .map_err(TimeoutError::Spawn)?;
+    match receiver.recv_timeout(deadline.remaining()) {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(payload)) => panic::resume_unwind(payload),
+        Err(RecvTimeoutError::Timeout) => Err(TimeoutError::TimedOut(TimedOut {
+            timeout: deadline.budget(),
+            worker: Some(worker),
+        })),
+        Err(RecvTimeoutError::Disconnected) => {
+            unreachable!("the worker always sends before exiting")
+        }
+    }
+}
+
+/// Wait for `future`, resolving to [`Elapsed`] if it takes longer than
+/// `timeout`
+pub fn timeout_async<F>(
+    timeout: Duration,
+    future: F,
+) -> impl Future<Output = Result<F::Output, Elapsed>>
+where
+    F: Future,
+{
+    timeout_async_with(ThreadSleeper, timeout, future)
+}
+
+/// [`timeout_async`] waiting with `sleeper`
+pub fn timeout_async_with<F>(
+    sleeper: impl Sleeper,
+    timeout: Duration,
+    future: F,
+) -> impl Future<Output = Result<F::Output, Elapsed>>
+where
+    F: Future,
+{
+    race(sleeper.sleep(timeout), future)
+}
+
+/// Wait for `future`, resolving to [`Elapsed`] if it isn't done by `deadline`.
+/// The wrapped future can be created ahead of time, as how long is left until
+/// `deadline` is measured when it's first polled.
+pub fn deadline_at<F>(
+    deadline: Instant,
+    future: F,
+) -> impl Future<Output = Result<F::Output, Elapsed>>
+where
+    F: Future,
+{
+    deadline_at_with(ThreadSleeper, deadline, future)
+}
+
+/// [`deadline_at`] waiting with `sleeper`
+pub async fn deadline_at_with<F>(
+    sleeper: impl Sleeper,
+    deadline: Instant,
+    future: F,
+) -> Result<F::Output, Elapsed>
+where
+    F: Future,
+{
+    // Being an `async fn`, how long is left is only worked out once this is
+    // first polled rather than when it was created
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    race(sleeper.sleep(remaining), future).await
+}
+
+/// Resolve to the output of `future` if it's done before `sleep` is
+async fn race<F>(sleep: impl Future<Output = ()>, future: F) -> Result<F::Output, Elapsed>
+where
+    F: Future,
+{
+    let mut sleep = pin!(sleep);
+    let mut future = pin!(future);
+    future::poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        sleep.as_mut().poll(cx).map(|()| Err(Elapsed(())))
+    })
+    .await
+}
@@ -0,0 +1,229 @@
+//! Timing nested sections of a program without a tracing subscriber
+//!
+//! [`time_scope!`](crate::time_scope) reports how long one scope took on its
+//! own. [`span!`](crate::span) does the same for scopes inside of each other,
+//! keeping each span's children so it's clear where the time inside of it
+//! went. Spans are collected on the thread they're entered on, and once the
+//! outermost one on a thread ends the whole tree is kept until it's read with
+//! [`take`] or printed with [`dump`]. [`Terminate::report_trace`] prints it
+//! when the program exits.
+//!
+//! ```
+//! # use futility::{span, trace};
+//! fn handle(request: u32) {
+//!     let _span = span!("request {request}");
+//!     {
+//!         let _span = span!("parse");
+//!         // ...
+//!     }
+//!     let _span = span!("respond");
+//!     // ...
+//! }
+//!
+//! handle(1);
+//! let trace = trace::take();
+//! assert_eq!(trace.spans[0].name, "request 1");
+//! assert_eq!(trace.spans[0].children[0].name, "parse");
+//! assert_eq!(trace.spans[0].children[1].name, "respond");
+//! ```
+//!
+//! This is for small programs where pulling in `tracing` isn't worth it. Each
+//! span takes the time when it starts and ends and nothing else, and the tree
+//! is printed the same way as the rest of what `Terminate` reports.
+//!
+//! [`Terminate::report_trace`]: crate::terminate::Terminate::report_trace
+
+use crate::lock::Mutex;
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fmt,
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
+
+/// A span that has ended, along with the spans entered inside of it
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Span {
+    /// The name the span was entered with
+    pub name: Cow<'static, str>,
+    /// How long the span lasted
+    pub duration: Duration,
+    /// The spans entered inside of this one, in the order they ended
+    pub children: Vec<Span>,
+}
+
+/// The outermost spans that have ended, from every thread, in the order they
+/// ended
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Trace {
+    /// Each outermost span, with the spans inside of it as its children
+    pub spans: Vec<Span>,
+}
+
+impl Trace {
+    /// Whether no spans have ended
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+}
+
+impl fmt::Display for Trace {
+    /// Each span as `name: duration` on its own line, with each level of
+    /// children indented by two more spaces than their parent
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_span(f: &mut fmt::Formatter<'_>, span: &Span, depth: usize) -> fmt::Result {
+            write!(
+                f,
+                "{:indent$}{}: {:.2?}",
+                "",
+                span.name,
+                span.duration,
+                indent = depth * 2
+            )?;
+            for child in &span.children {
+                f.write_str("\n")?;
+                write_span(f, child, depth + 1)?;
+            }
+            Ok(())
+        }
+
+        for (i, span) in self.spans.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write_span(f, span, 0)?;
+        }
+        Ok(())
+    }
+}
+
+/// A span that hasn't ended yet
+struct Open {
+    name: Cow<'static, str>,
+    start: Instant,
+    children: Vec<Span>,
+}
+
+thread_local! {
+    /// The spans entered on this thread that haven't ended, outermost first
+    static OPEN: RefCell<Vec<Open>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The outermost spans that have ended on any thread
+static FINISHED: Mutex<Vec<Span>> = Mutex::new(Vec::new());
+
+/// Ends its span when dropped, returned by [`enter`] and
+/// [`span!`](crate::span)
+///
+/// If a guard is dropped before a guard for a span entered inside of its
+/// span, which can only happen if the guards are moved around, the spans
+/// inside of it end along with it.
+#[must_use = "the span ends as soon as the guard is dropped"]
+pub struct SpanGuard {
+    /// How many spans were open on this thread when this one was entered
+    depth: usize,
+    /// The span belongs to the thread it was entered on
+    thread: PhantomData<*const ()>,
+}
+
+impl fmt::Debug for SpanGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpanGuard")
+            .field("depth", &self.depth)
+            .finish()
+    }
+}
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        let _ = OPEN.try_with(|open| {
+            let mut open = open.borrow_mut();
+            while open.len() > self.depth {
+                let Open {
+                    name,
+                    start,
+                    children,
+                } = open.pop().expect("the stack is longer than the depth");
+                let span = Span {
+                    name,
+                    duration: start.elapsed(),
+                    children,
+                };
+                match open.last_mut() {
+                    Some(parent) => parent.children.push(span),
+                    None => FINISHED.lock().push(span),
+                }
+            }
+        });
+    }
+}
+
+/// Enter a span called `name` on the current thread, which ends when the
+/// returned guard is dropped. [`span!`](crate::span) is the shorter way to
+/// call this.
+pub fn enter(name: impl Into<Cow<'static, str>>) -> SpanGuard {
+    let open = Open {
+        name: name.into(),
+        start: Instant::now(),
+        children: Vec::new(),
+    };
+    let depth = OPEN.with(|spans| {
+        let mut spans = spans.borrow_mut();
+        spans.push(open);
+        spans.len() - 1
+    });
+    SpanGuard {
+        depth,
+        thread: PhantomData,
+    }
+}
+
+/// Take every outermost span that has ended so far, leaving none behind
+pub fn take() -> Trace {
+    Trace {
+        spans: std::mem::take(&mut *FINISHED.lock()),
+    }
+}
+
+/// Take every outermost span that has ended so far and print them to stderr,
+/// the same way [`Terminate::report_trace`] does
+///
+/// [`Terminate::report_trace`]: crate::terminate::Terminate::report_trace
+pub fn dump() {
+    let trace = take();
+    if !trace.is_empty() {
+        crate::tty::print_report("trace", &trace);
+    }
+}
+
+/// Enter a span on the current thread, returning a [`SpanGuard`] that ends it
+/// when dropped
+///
+/// The name is either a string, or a format string and arguments like
+/// [`format!`].
+///
+/// ```
+/// # use futility::span;
+/// let path = "config.toml";
+/// let _load = span!("load");
+/// let _read = span!("read {path}");
+/// ```
+#[macro_export]
+macro_rules! span {
+    ($name:literal $(,)?) => {
+        // A name without anything to format isn't copied
+        match ::std::format_args!($name).as_str() {
+            ::std::option::Option::Some(name) => $crate::trace::enter(name),
+            ::std::option::Option::None => $crate::trace::enter(::std::format!($name)),
+        }
+    };
+    ($fmt:literal, $($arg:tt)+) => {
+        $crate::trace::enter(::std::format!($fmt, $($arg)+))
+    };
+    ($name:expr $(,)?) => {
+        $crate::trace::enter($name)
+    };
+}
@@ -0,0 +1,378 @@
+//! Whether output goes to a terminal and should be colored, and putting the
+//! terminal back the way it was found
+//!
+//! Everything that prints on behalf of the program, such as the error
+//! reporters and crash reports, should agree on whether to use color.
+//! [`Terminate::detect_tty`](crate::terminate::Terminate::detect_tty) works this out once
+//! before install and every part of [`Terminate`](crate::terminate::Terminate) that
+//! prints then uses [`output_style`], which install, custom reporters, and
+//! panic hooks can use as well, such as to configure a logger.
+//!
+//! Color is decided for each stream using these rules, with the first that
+//! applies winning:
+//!
+//! 1. `FORCE_COLOR` set to anything other than an empty string, `0`, or
+//!    `false` enables color, and `0` or `false` disables it
+//! 2. `NO_COLOR` set to anything other than an empty string disables color
+//! 3. `CLICOLOR_FORCE` set to anything other than an empty string or `0`
+//!    enables color
+//! 4. `CLICOLOR=0` disables color
+//! 5. Otherwise color is used if the stream is a terminal and `TERM` isn't
+//!    `dumb`
+//!
+//! # Terminal state
+//!
+//! A program that switches the terminal into raw mode or the alternate screen
+//! and then crashes leaves the user with a shell that doesn't echo what they
+//! type, or with their scrollback hidden. [`RawModeGuard`] and
+//! [`AltScreenGuard`] put the terminal back when they're dropped, including
+//! while a panic unwinds. A panic on the thread that created a guard puts the
+//! terminal back before the panic message is printed, so that the message
+//! isn't lost with the alternate screen. This is done with a panic hook
+//! installed when the first guard is created, on top of whatever hook is set
+//! at the time.
+//!
+//! ```no_run
+//! # use futility::tty::{AltScreenGuard, RawModeGuard};
+//! # use std::io;
+//! let _screen = AltScreenGuard::enter()?;
+//! let _raw = RawModeGuard::enable()?;
+//! // Draw the interface and read keys
+//! # Ok::<_, io::Error>(())
+//! ```
+//!
+//! As with temporary files, dropping isn't enough when the program exits
+//! with [`std::process::exit`] or is forced to exit by a second shutdown
+//! signal. Every guard that is still alive is also restored by the
+//! [`at_exit!`](crate::at_exit) registry when
+//! [`Terminate`](crate::terminate::Terminate) exits, and by
+//! [`restore_pending`] which is meant to be given to
+//! [`Terminate::at_exit_critical`](crate::terminate::Terminate::at_exit_critical)
+//! so that it runs on those other paths too.
+
+#[cfg(feature = "terminate")]
+use std::sync::atomic::AtomicBool;
+use std::{
+    env,
+    ffi::OsString,
+    io::{self, IsTerminal, Write},
+    mem, panic,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard, Once,
+    },
+    thread::{self, ThreadId},
+};
+
+/// Whether stdout and stderr are terminals and should be colored
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct OutputStyle {
+    /// stdout is a terminal
+    pub stdout_is_tty: bool,
+    /// stderr is a terminal
+    pub stderr_is_tty: bool,
+    /// Output to stdout should be colored
+    pub stdout_color: bool,
+    /// Output to stderr should be colored
+    pub stderr_color: bool,
+}
+
+impl OutputStyle {
+    /// Check stdout, stderr, and the environment
+    pub fn detect() -> Self {
+        Self::from_env(
+            io::stdout().is_terminal(),
+            io::stderr().is_terminal(),
+            |key| env::var_os(key),
+        )
+    }
+
+    /// Work out the style for streams that are or aren't terminals with
+    /// environment variables looked up with `var`
+    fn from_env(
+        stdout_is_tty: bool,
+        stderr_is_tty: bool,
+        var: impl Fn(&str) -> Option<OsString>,
+    ) -> Self {
+        let set = |key| var(key).filter(|value| !value.is_empty());
+        let forced = match set("FORCE_COLOR") {
+            Some(value) => Some(value != "0" && value != "false"),
+            None if set("NO_COLOR").is_some() => Some(false),
+            None if set("CLICOLOR_FORCE").is_some_and(|value| value != "0") => Some(true),
+            None if set("CLICOLOR").is_some_and(|value| value == "0") => Some(false),
+            None => None,
+        };
+        let dumb = set("TERM").is_some_and(|term| term == "dumb");
+        let color = |is_tty| forced.unwrap_or(is_tty && !dumb);
+        Self {
+            stdout_is_tty,
+            stderr_is_tty,
+            stdout_color: color(stdout_is_tty),
+            stderr_color: color(stderr_is_tty),
+        }
+    }
+}
+
+static STYLE: Mutex<Option<OutputStyle>> = Mutex::new(None);
+
+/// The style detected by
+/// [`Terminate::detect_tty`](crate::terminate::Terminate::detect_tty), or
+/// the default of no terminals and no color if it hasn't been used
+pub fn output_style() -> OutputStyle {
+    STYLE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .unwrap_or_default()
+}
+
+/// Detect the style and make it the one returned by [`output_style`]
+#[cfg(feature = "terminate")]
+pub(crate) fn detect() {
+    *STYLE.lock().unwrap_or_else(|e| e.into_inner()) = Some(OutputStyle::detect());
+}
+
+/// The `error` label printed before an error on stderr, in bold red if stderr
+/// is colored
+#[cfg(feature = "exit")]
+pub(crate) fn error_label() -> &'static str {
+    match output_style().stderr_color {
+        true => "\x1b[1;31merror\x1b[0m",
+        false => "error",
+    }
+}
+
+/// `text` in bold if stderr is colored
+#[cfg(feature = "crash-reports")]
+pub(crate) fn bold(text: &str) -> String {
+    match output_style().stderr_color {
+        true => format!("\x1b[1m{text}\x1b[0m"),
+        false => text.into(),
+    }
+}
+
+/// Print a section of what's reported when a program exits to stderr: the
+/// heading on its own line, followed by each line of `body` indented under it
+#[cfg(any(
+    feature = "trace",
+    all(feature = "terminate", any(feature = "metrics", feature = "time"))
+))]
+pub(crate) fn print_report(heading: &str, body: impl std::fmt::Display) {
+    let mut report = format!("{heading}:");
+    for line in body.to_string().lines() {
+        report.push_str("\n  ");
+        report.push_str(line);
+    }
+    eprintln!("{report}");
+}
+
+/// Changes made to the terminal by guards that haven't been dropped yet, in
+/// the order they were made
+static PENDING: Mutex<Vec<Pending>> = Mutex::new(Vec::new());
+
+/// Gives each guard an id to find its change in [`PENDING`] with
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A change made to the terminal by a guard
+struct Pending {
+    id: u64,
+    thread: ThreadId,
+    restore: Restore,
+}
+
+/// How to undo a change made to the terminal
+#[derive(Clone, Copy)]
+enum Restore {
+    /// Set the terminal attributes of stdin back to these
+    #[cfg(unix)]
+    Termios(libc::termios),
+    /// Switch back from the alternate screen to the main one
+    AltScreen,
+}
+
+impl Restore {
+    fn restore(self) -> io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            // SAFETY: termios was filled in by tcgetattr
+            Restore::Termios(termios) => {
+                match unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) } {
+                    0 => Ok(()),
+                    _ => Err(io::Error::last_os_error()),
+                }
+            }
+            Restore::AltScreen => {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(b"\x1b[?1049l")?;
+                stdout.flush()
+            }
+        }
+    }
+}
+
+/// Puts stdin's terminal into raw mode, where input is passed on a byte at a
+/// time without being echoed or turned into signals, and puts it back when
+/// dropped
+///
+/// Only Unix terminals are supported for now.
+#[derive(Debug)]
+#[must_use = "the terminal leaves raw mode as soon as the guard is dropped"]
+pub struct RawModeGuard(Guard);
+
+impl RawModeGuard {
+    /// Put the terminal into raw mode, failing if stdin isn't a terminal
+    #[cfg(unix)]
+    pub fn enable() -> io::Result<Self> {
+        let mut termios = mem::MaybeUninit::<libc::termios>::uninit();
+        // SAFETY: termios is valid for tcgetattr to write into
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: tcgetattr succeeded so it filled in termios
+        let original = unsafe { termios.assume_init() };
+        let mut raw = original;
+        // SAFETY: raw is a valid termios to modify
+        unsafe { libc::cfmakeraw(&mut raw) };
+        // SAFETY: raw is a valid termios
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self(Guard::new(Restore::Termios(original))))
+    }
+
+    /// Put the terminal into raw mode, which isn't supported on this platform
+    #[cfg(not(unix))]
+    pub fn enable() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "raw mode is only supported on Unix terminals",
+        ))
+    }
+
+    /// Put the terminal back and return any error doing so, rather than
+    /// ignoring it when dropped
+    pub fn restore(self) -> io::Result<()> {
+        self.0.restore()
+    }
+}
+
+/// Switches stdout's terminal to the alternate screen, and back to the main
+/// screen with its scrollback when dropped
+#[derive(Debug)]
+#[must_use = "the terminal leaves the alternate screen as soon as the guard is dropped"]
+pub struct AltScreenGuard(Guard);
+
+impl AltScreenGuard {
+    /// Switch to the alternate screen, failing if stdout isn't a terminal
+    pub fn enter() -> io::Result<Self> {
+        let mut stdout = io::stdout().lock();
+        if !stdout.is_terminal() {
+            return Err(io::Error::other("stdout isn't a terminal"));
+        }
+        stdout.write_all(b"\x1b[?1049h")?;
+        stdout.flush()?;
+        Ok(Self(Guard::new(Restore::AltScreen)))
+    }
+
+    /// Switch back to the main screen and return any error doing so, rather
+    /// than ignoring it when dropped
+    pub fn restore(self) -> io::Result<()> {
+        self.0.restore()
+    }
+}
+
+/// A change to the terminal that is undone when dropped
+#[derive(Debug)]
+struct Guard {
+    id: u64,
+}
+
+impl Guard {
+    /// Remember `restore` for [`restore_pending`], the panic hook, and the
+    /// `at_exit!` registry
+    fn new(restore: Restore) -> Self {
+        restore_hook();
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        pending().push(Pending {
+            id,
+            thread: thread::current().id(),
+            restore,
+        });
+        #[cfg(feature = "terminate")]
+        restore_at_exit();
+        Self { id }
+    }
+
+    fn restore(self) -> io::Result<()> {
+        let restore = unmark_pending(self.id);
+        mem::forget(self);
+        restore.map_or(Ok(()), Restore::restore)
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if let Some(restore) = unmark_pending(self.id) {
+            let _ = restore.restore();
+        }
+    }
+}
+
+/// Undo every change made to the terminal by guards that are still alive,
+/// most recent first. This is a plain `fn()` so it can be given to
+/// [`Terminate::at_exit_critical`](crate::terminate::Terminate::at_exit_critical),
+/// and is safe to call more than once.
+pub fn restore_pending() {
+    for pending in mem::take(&mut *pending()).into_iter().rev() {
+        let _ = pending.restore.restore();
+    }
+}
+
+fn pending() -> MutexGuard<'static, Vec<Pending>> {
+    PENDING.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Forget a pending change, returning how to undo it if it was pending
+fn unmark_pending(id: u64) -> Option<Restore> {
+    let mut pending = pending();
+    let index = pending.iter().position(|pending| pending.id == id)?;
+    Some(pending.remove(index).restore)
+}
+
+/// Put [`restore_pending`] in the `at_exit!` registry if it isn't already
+/// there. Running the registry drops it, so it's put back by the next guard.
+#[cfg(feature = "terminate")]
+fn restore_at_exit() {
+    static REGISTERED: AtomicBool = AtomicBool::new(false);
+    if !REGISTERED.swap(true, Ordering::SeqCst) {
+        crate::terminate::registry::register(|| {
+            REGISTERED.store(false, Ordering::SeqCst);
+            restore_pending();
+        });
+    }
+}
+
+/// Install a panic hook, on top of whatever hook is currently set, that undoes
+/// the changes made by guards on the panicking thread before passing the
+/// panic on
+fn restore_hook() {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let original_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let current = thread::current().id();
+            let restores = {
+                let mut pending = pending();
+                let (restores, others) = mem::take(&mut *pending)
+                    .into_iter()
+                    .partition::<Vec<_>, _>(|pending| pending.thread == current);
+                *pending = others;
+                restores
+            };
+            for pending in restores.into_iter().rev() {
+                let _ = pending.restore.restore();
+            }
+            original_hook(info);
+        }));
+    });
+}
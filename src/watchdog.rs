@@ -0,0 +1,243 @@
+//! Noticing when a loop stops making progress
+//!
+//! A main loop that gets stuck, waiting on a lock that is never released or a
+//! network call without a timeout, doesn't crash or return an error, it just
+//! stops doing anything. A [`Watchdog`] is fed by the loop each time around,
+//! and if it isn't fed within its timeout a thread watching it takes an
+//! [`Action`]: logging that the loop is stuck, triggering a
+//! [`ShutdownToken`], aborting the process so a supervisor restarts it, or
+//! calling a function.
+//!
+//! ```
+//! # use futility::{shutdown::ShutdownToken, watchdog::{Action, Watchdog}};
+//! # use std::time::Duration;
+//! let shutdown = ShutdownToken::new();
+//! let watchdog = Watchdog::new(Duration::from_millis(50))
+//!     .on_timeout(Action::Log)
+//!     .on_timeout(Action::Shutdown(shutdown.clone()));
+//! let _running = watchdog.start().unwrap();
+//!
+//! while !shutdown.is_triggered() {
+//!     watchdog.feed();
+//!     // A stuck iteration that never feeds the watchdog again
+//!     shutdown.wait();
+//! }
+//! ```
+//!
+//! [`Terminate::watchdog`](crate::terminate::Terminate::watchdog) starts the
+//! watchdog during install and stops it when the program exits.
+
+use crate::{
+    lock::{Mutex, MutexGuard},
+    log::{self, Level},
+    shutdown::ShutdownToken,
+};
+use std::{
+    fmt,
+    io::{self, Write},
+    process,
+    sync::{Arc, Condvar},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// What a [`Watchdog`] does when it isn't fed in time
+pub enum Action {
+    /// Log at [`Level::Error`] with the target `futility::watchdog`
+    Log,
+    /// Trigger the token, so the program can shut down
+    Shutdown(ShutdownToken),
+    /// Print a message to stderr and abort the process
+    Abort,
+    /// Call the function with how long it has been since the watchdog was
+    /// last fed
+    Call(Box<dyn Fn(Duration) + Send + Sync>),
+}
+
+impl Action {
+    fn take(&self, starved: Duration) {
+        match self {
+            Self::Log => log::log(
+                Level::Error,
+                "futility::watchdog",
+                format_args!("the watchdog hasn't been fed for {starved:?}"),
+            ),
+            Self::Shutdown(token) => token.trigger(),
+            Self::Abort => {
+                let _ = writeln!(
+                    io::stderr(),
+                    "aborting: the watchdog hasn't been fed for {starved:?}"
+                );
+                process::abort();
+            }
+            Self::Call(f) => f(starved),
+        }
+    }
+}
+
+impl fmt::Debug for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Log => f.write_str("Log"),
+            Self::Shutdown(token) => f.debug_tuple("Shutdown").field(token).finish(),
+            Self::Abort => f.write_str("Abort"),
+            Self::Call(_) => f.write_str("Call(..)"),
+        }
+    }
+}
+
+/// Takes an action if it isn't fed often enough. Clones share the same
+/// watchdog, so one can be fed from wherever the work happens.
+#[derive(Clone, Debug)]
+pub struct Watchdog {
+    shared: Arc<Shared>,
+    actions: Arc<Vec<Action>>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    timeout: Duration,
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+
+#[derive(Debug)]
+struct State {
+    fed: Instant,
+    /// Whether the actions have been taken since the watchdog was last fed
+    fired: bool,
+    stopped: bool,
+}
+
+impl Watchdog {
+    /// A watchdog that has to be fed at least once every `timeout` once it's
+    /// started
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                timeout,
+                state: Mutex::new(State {
+                    fed: Instant::now(),
+                    fired: false,
+                    stopped: false,
+                }),
+                condvar: Condvar::new(),
+            }),
+            actions: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Take `action` when the watchdog isn't fed in time, after any actions
+    /// added before it. Without any the watchdog takes [`Action::Log`].
+    ///
+    /// # Panics
+    ///
+    /// If the watchdog has already been cloned.
+    pub fn on_timeout(mut self, action: Action) -> Self {
+        Arc::get_mut(&mut self.actions)
+            .expect("actions are added before the watchdog is cloned")
+            .push(action);
+        self
+    }
+
+    /// How often the watchdog has to be fed
+    pub fn timeout(&self) -> Duration {
+        self.shared.timeout
+    }
+
+    /// Reset the timeout. If the actions were taken since the watchdog was
+    /// last fed, they'll be taken again the next time it isn't fed in time.
+    pub fn feed(&self) {
+        let mut state = self.shared.state.lock();
+        state.fed = Instant::now();
+        if std::mem::take(&mut state.fired) {
+            self.shared.condvar.notify_all();
+        }
+    }
+
+    /// Start watching on a new thread, counting the timeout from now, until
+    /// the returned [`Running`] is dropped. The actions are taken once each
+    /// time the watchdog goes a whole timeout without being fed.
+    pub fn start(&self) -> io::Result<Running> {
+        {
+            let mut state = self.shared.state.lock();
+            state.fed = Instant::now();
+            state.fired = false;
+            state.stopped = false;
+        }
+        let watchdog = self.clone();
+        let thread = thread::Builder::new()
+            .name("futility-watchdog".into())
+            .spawn(move || watchdog.watch())?;
+        Ok(Running {
+            shared: Arc::clone(&self.shared),
+            thread: Some(thread),
+        })
+    }
+
+    fn watch(&self) {
+        let shared = &*self.shared;
+        let mut state = shared.state.lock();
+        while !state.stopped {
+            if state.fired {
+                state = wait(&shared.condvar, state, None);
+                continue;
+            }
+            let starved = state.fed.elapsed();
+            if starved < shared.timeout {
+                state = wait(&shared.condvar, state, Some(shared.timeout - starved));
+                continue;
+            }
+            state.fired = true;
+            drop(state);
+            match self.actions.is_empty() {
+                true => Action::Log.take(starved),
+                false => self.actions.iter().for_each(|action| action.take(starved)),
+            }
+            state = shared.state.lock();
+        }
+    }
+}
+
+fn wait<'a>(
+    condvar: &Condvar,
+    state: MutexGuard<'a, State>,
+    timeout: Option<Duration>,
+) -> MutexGuard<'a, State> {
+    match timeout {
+        Some(timeout) => condvar
+            .wait_timeout(state, timeout)
+            .map(|(state, _)| state)
+            .unwrap_or_else(|e| e.into_inner().0),
+        None => condvar.wait(state).unwrap_or_else(|e| e.into_inner()),
+    }
+}
+
+/// A watchdog thread from [`Watchdog::start`], which is stopped and joined
+/// when this is dropped
+#[must_use = "dropping this stops the watchdog"]
+pub struct Running {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Running {
+    /// Stop the watchdog, the same as dropping this
+    pub fn stop(self) {}
+}
+
+impl fmt::Debug for Running {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Running").finish_non_exhaustive()
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.shared.state.lock().stopped = true;
+        self.shared.condvar.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
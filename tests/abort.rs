@@ -0,0 +1,48 @@
+#![cfg(feature = "abort")]
+
+use futility::abort::AbortGuard;
+use std::{env, panic, process::Command};
+
+#[test]
+pub fn disarmed_or_dropped_normally() {
+    let guard = AbortGuard::new("testing");
+    assert_eq!(guard.reason(), "testing");
+    drop(guard);
+
+    let res = panic::catch_unwind(|| {
+        AbortGuard::new("testing").disarm();
+        panic!("after the critical section");
+    });
+    assert!(res.is_err());
+}
+
+#[test]
+pub fn aborts_when_unwinding() {
+    if env::var_os("FUTILITY_ABORT_GUARD").is_some() {
+        let _ = panic::catch_unwind(|| {
+            let _guard = AbortGuard::new("writing the journal");
+            panic!("disk full");
+        });
+        unreachable!("the process aborted");
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "aborts_when_unwinding", "--nocapture"])
+        .env("FUTILITY_ABORT_GUARD", "1")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success(), "{stderr}");
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        assert_eq!(output.status.signal(), Some(6), "{stderr}");
+    }
+    assert!(stderr.contains("disk full"), "{stderr}");
+    assert!(
+        stderr.contains(
+            "aborting: a panic unwound out of a critical section while writing the journal"
+        ),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,100 @@
+#![cfg(feature = "args")]
+
+use futility::{
+    args::{Args, ArgsError},
+    exit::{self, ExitCoded},
+};
+
+fn args() -> Args {
+    Args::new("cp")
+        .about("Copy files")
+        .flag("-v, --verbose", "Print each file as it's copied")
+        .flag("--dry-run", "Don't copy anything")
+        .flag("-f", "Overwrite existing files")
+        .option("-m, --mode <MODE>", "Set the mode of copied files")
+        .option("--exclude <GLOB>", "Skip files matching GLOB")
+        .positional("DEST", "Where to copy to")
+        .rest("SOURCE", "The files to copy")
+}
+
+#[test]
+pub fn parse_flags_options_and_positionals() {
+    let parsed = args()
+        .parse([
+            "-vvf",
+            "-m644",
+            "--exclude=*.tmp",
+            "out",
+            "--exclude",
+            "*.bak",
+            "a.txt",
+            "--",
+            "--not-a-flag",
+        ])
+        .unwrap();
+    assert_eq!(parsed.count("verbose"), 2);
+    assert!(parsed.flag("f"));
+    assert!(!parsed.flag("dry-run"));
+    assert_eq!(parsed.parse::<u32>("mode").unwrap(), Some(644));
+    assert_eq!(parsed.values("exclude"), ["*.tmp", "*.bak"]);
+    assert_eq!(parsed.value("exclude"), Some("*.bak"));
+    assert_eq!(parsed.value("DEST"), Some("out"));
+    assert_eq!(parsed.rest(), ["a.txt", "--not-a-flag"]);
+
+    let parsed = args().parse(["--mode", "x", "out"]).unwrap();
+    assert_eq!(
+        parsed.parse::<u32>("mode").unwrap_err().to_string(),
+        "invalid value \"x\" for mode: invalid digit found in string"
+    );
+}
+
+#[test]
+pub fn parse_errors() {
+    let err = |args: &[&str]| args_error(args.iter().copied());
+    assert_eq!(err(&["--nope", "out"]), ArgsError::Unknown("--nope".into()));
+    assert_eq!(err(&["-x"]), ArgsError::Unknown("-x".into()));
+    assert_eq!(err(&["out", "-m"]), ArgsError::MissingValue("-m".into()));
+    assert_eq!(
+        err(&["--dry-run=yes"]),
+        ArgsError::UnexpectedValue("--dry-run".into())
+    );
+    assert_eq!(err(&[]), ArgsError::MissingPositional("DEST".into()));
+    assert_eq!(err(&["-v"]).code(), exit::EX_USAGE);
+
+    let err = Args::new("true").parse(["extra"]).unwrap_err();
+    assert_eq!(err, ArgsError::UnexpectedPositional("extra".into()));
+}
+
+fn args_error<'a>(args: impl IntoIterator<Item = &'a str>) -> ArgsError {
+    self::args().parse(args).unwrap_err()
+}
+
+#[test]
+pub fn help() {
+    let err = args().parse(["out", "--help"]).unwrap_err();
+    assert_eq!(err.code(), exit::EX_OK);
+    assert_eq!(
+        err.to_string(),
+        "Copy files
+
+Usage: cp [OPTIONS] DEST [SOURCE]...
+
+Arguments:
+  DEST    Where to copy to
+  SOURCE  The files to copy
+
+Options:
+  -v, --verbose         Print each file as it's copied
+      --dry-run         Don't copy anything
+  -f                    Overwrite existing files
+  -m, --mode <MODE>     Set the mode of copied files
+      --exclude <GLOB>  Skip files matching GLOB
+  -h, --help            Print help"
+    );
+}
+
+#[test]
+#[should_panic(expected = "`--mode` should have a value like `<VALUE>`")]
+pub fn option_without_value_panics() {
+    let _ = Args::new("cp").option("--mode", "Set the mode");
+}
@@ -0,0 +1,47 @@
+#![cfg(all(feature = "atexit", any(unix, windows)))]
+
+use futility::terminate::Terminate;
+use std::{env, io, process, process::Command};
+
+fn run_child(name: &str, var: &str) -> process::Output {
+    Command::new(env::current_exe().unwrap())
+        .args(["--exact", name, "--nocapture"])
+        .env(var, "1")
+        .output()
+        .unwrap()
+}
+
+#[test]
+pub fn process_exit_runs_critical() {
+    if env::var_os("FUTILITY_PROCESS_EXIT").is_some() {
+        let _ = Terminate::<io::Error>::new()
+            .at_exit(|| println!("at_exit ran"))
+            .at_exit_critical(|| println!("critical cleanup ran"))
+            .register_libc_atexit()
+            .execute(|| process::exit(3));
+        return;
+    }
+
+    let output = run_child("process_exit_runs_critical", "FUTILITY_PROCESS_EXIT");
+    assert_eq!(output.status.code(), Some(3));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("critical cleanup ran").count(), 1);
+    assert!(!stdout.contains("at_exit ran"));
+}
+
+#[test]
+pub fn normal_exit_runs_critical_once() {
+    if env::var_os("FUTILITY_NORMAL_EXIT").is_some() {
+        Terminate::<io::Error>::new()
+            .at_exit_critical(|| println!("critical cleanup ran"))
+            .register_libc_atexit()
+            .execute(|| Ok(()))
+            .unwrap();
+        process::exit(0);
+    }
+
+    let output = run_child("normal_exit_runs_critical_once", "FUTILITY_NORMAL_EXIT");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("critical cleanup ran").count(), 1);
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "budget")]
+
+use futility::budget::{Deadline, Expired};
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+#[test]
+pub fn checkpoint() {
+    let deadline = Deadline::after(Duration::from_secs(5));
+    assert_eq!(deadline.budget(), Duration::from_secs(5));
+    assert!(deadline.remaining() <= Duration::from_secs(5));
+    assert!(!deadline.is_expired());
+    assert_eq!(deadline.checkpoint(), Ok(()));
+
+    let deadline = Deadline::after(Duration::from_millis(20));
+    let err = deadline.checkpoint().and_then(|()| {
+        std::thread::sleep(Duration::from_millis(20));
+        deadline.checkpoint()
+    });
+    assert_eq!(
+        err,
+        Err(Expired {
+            budget: Duration::from_millis(20)
+        })
+    );
+    assert_eq!(deadline.remaining(), Duration::ZERO);
+    assert_eq!(err.unwrap_err().to_string(), "deadline expired after 20ms");
+    let err = io::Error::from(deadline.checkpoint().unwrap_err());
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+}
+
+#[test]
+pub fn until() {
+    let at = Instant::now() + Duration::from_secs(5);
+    let deadline = Deadline::until(at);
+    assert_eq!(deadline.at(), at);
+    assert!(deadline.budget() <= Duration::from_secs(5));
+
+    let deadline = Deadline::until(Instant::now() - Duration::from_secs(1));
+    assert_eq!(deadline.budget(), Duration::ZERO);
+    assert!(deadline.is_expired());
+}
+
+#[test]
+pub fn sub_budgets() {
+    let deadline = Deadline::after(Duration::from_secs(10));
+
+    let limited = deadline.limit(Duration::from_secs(1));
+    assert!(limited.at() < deadline.at());
+    assert!(limited.remaining() <= Duration::from_secs(1));
+    // A limit past the deadline is cut short by it
+    assert_eq!(deadline.limit(Duration::from_secs(60)).at(), deadline.at());
+
+    let half = deadline.split(2);
+    assert!(half.remaining() <= Duration::from_secs(5));
+    assert!(half.remaining() > Duration::from_secs(4));
+    assert!(deadline.split(0).at() <= deadline.at());
+}
+
+#[test]
+pub fn unbounded() {
+    let deadline = Deadline::after(Duration::MAX);
+    assert_eq!(deadline.budget(), Duration::MAX);
+    assert!(!deadline.is_expired());
+    assert!(deadline.remaining() > Duration::from_secs(60 * 60 * 24 * 365));
+    // Limits are still cut short by it
+    let limited = deadline.limit(Duration::MAX);
+    assert!(limited.at() <= deadline.at());
+    assert!(!limited.is_expired());
+}
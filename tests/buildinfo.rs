@@ -0,0 +1,42 @@
+#![cfg(feature = "buildinfo")]
+
+use futility::{build_info, buildinfo::BuildInfo};
+
+#[test]
+pub fn build_info_of_the_calling_crate() {
+    const BUILD: BuildInfo = build_info!();
+    assert_eq!(BUILD.name, env!("CARGO_PKG_NAME"));
+    assert_eq!(BUILD.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(
+        BUILD.profile,
+        if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        }
+    );
+    // This crate doesn't have a build script calling `emit`
+    assert_eq!(BUILD.git_sha, None);
+    assert_eq!(BUILD.target, None);
+    assert_eq!(
+        BUILD.to_string(),
+        format!("futility {} ({})", BUILD.version, BUILD.profile)
+    );
+}
+
+#[test]
+pub fn build_info_display() {
+    let build = BuildInfo::__new(
+        "app",
+        "1.2.3",
+        Some("3f2a9c1d8e7b6a5f4e3d2c1b0a9f8e7d6c5b4a39"),
+        Some("x86_64-unknown-linux-gnu"),
+        Some("release"),
+        true,
+    );
+    assert_eq!(build.short_sha(), Some("3f2a9c1d8"));
+    assert_eq!(
+        build.to_string(),
+        "app 1.2.3 (3f2a9c1d8 x86_64-unknown-linux-gnu release)"
+    );
+}
@@ -0,0 +1,187 @@
+#![cfg(feature = "config")]
+
+use futility::config::{Invalid, LoadError, Loader, Source};
+use serde::{Deserialize, Serialize};
+use std::{env, fs, path::PathBuf, process};
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Config {
+    name: String,
+    debug: bool,
+    server: Server,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+struct Server {
+    host: String,
+    port: u16,
+    workers: Option<u32>,
+}
+
+fn defaults() -> Config {
+    Config {
+        name: "app".into(),
+        debug: false,
+        server: Server {
+            host: "localhost".into(),
+            port: 8080,
+            workers: None,
+        },
+        tags: Vec::new(),
+    }
+}
+
+fn config_file(name: &str, contents: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("futility-config-{}-{name}", process::id()));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+pub fn layers_override_each_other() {
+    let file = config_file(
+        "layers.json",
+        r#"{ "name": "from-file", "server": { "port": 9000 }, "tags": ["a", "b"] }"#,
+    );
+    env::set_var("FUTILITY_LAYERS_SERVER__PORT", "9100");
+    env::set_var("FUTILITY_LAYERS_DEBUG", "true");
+
+    let (config, sources) = Loader::new()
+        .defaults(&defaults())
+        .file(&file)
+        .optional_file("does-not-exist.json")
+        .env("FUTILITY_LAYERS_")
+        .args(["server.workers=4"])
+        .load_with_sources()
+        .unwrap();
+    assert_eq!(
+        config,
+        Config {
+            name: "from-file".into(),
+            debug: true,
+            server: Server {
+                host: "localhost".into(),
+                port: 9100,
+                workers: Some(4),
+            },
+            tags: vec!["a".into(), "b".into()],
+        }
+    );
+
+    assert_eq!(sources.get("name"), Some(&Source::File(file.clone())));
+    assert_eq!(sources.get("server.host"), Some(&Source::Defaults));
+    assert_eq!(
+        sources.get("server.port"),
+        Some(&Source::Env("FUTILITY_LAYERS_SERVER__PORT".into()))
+    );
+    assert_eq!(
+        sources.get("server.workers"),
+        Some(&Source::Arg("server.workers=4".into()))
+    );
+    assert_eq!(sources.get("tags.1"), Some(&Source::File(file.clone())));
+    fs::remove_file(file).unwrap();
+}
+
+#[test]
+pub fn invalid_values_name_their_source() {
+    env::set_var("FUTILITY_INVALID_SERVER__PORT", "eighty");
+    let err = Loader::<Config>::new()
+        .defaults(&defaults())
+        .env("FUTILITY_INVALID_")
+        .load()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid value for `server.port` from the environment variable \
+         `FUTILITY_INVALID_SERVER__PORT`: invalid value: \"eighty\", expected u16"
+    );
+
+    let file = config_file("invalid.json", r#"{ "tags": ["a", 2] }"#);
+    let err = Loader::<Config>::new()
+        .defaults(&defaults())
+        .file(&file)
+        .load()
+        .unwrap_err();
+    match err {
+        LoadError::Invalid { key, from, .. } => {
+            assert_eq!(key, "tags.1");
+            assert_eq!(from, Some(Source::File(file.clone())));
+        }
+        err => panic!("unexpected error: {err}"),
+    }
+    fs::remove_file(file).unwrap();
+
+    let err = Loader::<Config>::new()
+        .args(["name=app"])
+        .load()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid configuration: missing field `debug`"
+    );
+}
+
+#[test]
+pub fn validation_errors_name_their_source() {
+    let err = Loader::new()
+        .defaults(&defaults())
+        .args(["server.port=0"])
+        .validate(|config: &Config| match config.server.port {
+            0 => Err(Invalid::new("server.port", "the port can't be 0")),
+            _ => Ok(()),
+        })
+        .load()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid value for `server.port` from the argument `server.port=0`: the port can't be 0"
+    );
+}
+
+#[test]
+pub fn layers_that_cant_be_read() {
+    let err = Loader::<Config>::new()
+        .file("does-not-exist.json")
+        .load()
+        .unwrap_err();
+    assert!(matches!(err, LoadError::Read { .. }));
+
+    let file = config_file("broken.json", "{ name: ");
+    let err = Loader::<Config>::new().file(&file).load().unwrap_err();
+    assert!(matches!(err, LoadError::Parse { .. }));
+    fs::remove_file(file).unwrap();
+
+    let file = config_file("config.ini", "name = app");
+    let err = Loader::<Config>::new().file(&file).load().unwrap_err();
+    assert!(matches!(err, LoadError::UnknownFormat { .. }));
+    fs::remove_file(file).unwrap();
+
+    let err = Loader::<Config>::new().args(["debug"]).load().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "the argument `debug` isn't in the form `key=value`"
+    );
+}
+
+#[test]
+#[cfg(feature = "config-toml")]
+pub fn toml_files() {
+    let file = config_file(
+        "config.toml",
+        "name = \"from-toml\"\ntags = [\"x\"]\n\n[server]\nport = 7000\n",
+    );
+    let (config, sources) = Loader::new()
+        .defaults(&defaults())
+        .file(&file)
+        .load_with_sources()
+        .unwrap();
+    assert_eq!(config.name, "from-toml");
+    assert_eq!(config.server.port, 7000);
+    assert_eq!(config.tags, ["x"]);
+    assert_eq!(
+        sources.get("server.port"),
+        Some(&Source::File(file.clone()))
+    );
+    fs::remove_file(file).unwrap();
+}
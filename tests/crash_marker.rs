@@ -0,0 +1,46 @@
+#![cfg(all(unix, feature = "crash-marker"))]
+
+use futility::terminate::Terminate;
+use std::{env, fs, io, path::PathBuf, process::Command};
+
+fn marker_dir(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("futility-{name}-{}", std::process::id()))
+}
+
+#[test]
+pub fn crash_marker_written_on_crash() {
+    if let Some(dir) = env::var_os("FUTILITY_CRASH_MARKER_DIR") {
+        let _ = Terminate::<io::Error>::new()
+            .capture_crash_markers(dir)
+            .execute(|| std::process::abort());
+        return;
+    }
+
+    let dir = marker_dir("crash-marker-crash");
+    let status = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "crash_marker_written_on_crash", "--nocapture"])
+        .env("FUTILITY_CRASH_MARKER_DIR", &dir)
+        .status()
+        .unwrap();
+    assert!(!status.success());
+
+    let markers = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    assert_eq!(markers.len(), 1);
+    assert_eq!(markers[0].extension().unwrap(), "crash");
+    let marker = fs::read_to_string(&markers[0]).unwrap();
+    assert!(marker.starts_with("signal = 6\n"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+pub fn crash_marker_removed_without_crash() -> Result<(), io::Error> {
+    let dir = marker_dir("crash-marker-clean");
+    Terminate::<io::Error>::new()
+        .capture_crash_markers(&dir)
+        .execute(|| Ok(()))?;
+    assert_eq!(fs::read_dir(&dir)?.count(), 0);
+    fs::remove_dir_all(&dir)
+}
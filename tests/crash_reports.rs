@@ -0,0 +1,33 @@
+#![cfg(feature = "crash-reports")]
+
+use futility::terminate::{CrashReports, Terminate};
+use std::{
+    error::Error,
+    fs,
+    panic::{self, AssertUnwindSafe},
+};
+
+#[test]
+pub fn crash_report_written_on_panic() {
+    let dir = std::env::temp_dir().join(format!("futility-crash-{}", std::process::id()));
+    let terminate = Terminate::<Box<dyn Error>>::new()
+        .crash_reports(CrashReports::new(&dir).name("crash-test").version("1.2.3"));
+
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        terminate.execute(|| panic!("Oh no a crash"))
+    }));
+    assert!(res.is_err());
+
+    let reports = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    assert_eq!(reports.len(), 1);
+    let report = fs::read_to_string(&reports[0]).unwrap();
+    assert!(report.contains("name = \"crash-test\""));
+    assert!(report.contains("version = \"1.2.3\""));
+    assert!(report.contains("message = \"Oh no a crash\""));
+    #[cfg(feature = "diagnostics")]
+    assert!(report.contains("[diagnostics]"));
+    fs::remove_dir_all(&dir).unwrap();
+}
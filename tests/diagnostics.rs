@@ -0,0 +1,49 @@
+#![cfg(feature = "diagnostics")]
+
+use futility::diagnostics;
+use std::env;
+
+#[test]
+pub fn diagnostics_describe_the_machine() {
+    let diagnostics = diagnostics::collect_for([env::temp_dir(), "/does/not/exist".into()]);
+    assert_eq!(diagnostics.os, env::consts::OS);
+    assert_eq!(diagnostics.arch, env::consts::ARCH);
+    assert!(diagnostics.cpus.unwrap_or(1) >= 1);
+
+    let rendered = diagnostics.to_string();
+    assert!(rendered.starts_with(&format!("os = {:?}", env::consts::OS)));
+
+    #[cfg(unix)]
+    {
+        assert!(diagnostics.kernel.is_some());
+        assert_eq!(diagnostics.disks.len(), 1);
+        let disk = &diagnostics.disks[0];
+        assert_eq!(disk.path, env::temp_dir());
+        assert!(disk.available <= disk.total);
+        let nofile = diagnostics
+            .rlimits
+            .iter()
+            .find(|rlimit| rlimit.resource == "RLIMIT_NOFILE")
+            .unwrap();
+        assert!(nofile.soft <= nofile.hard || nofile.hard.is_none());
+        assert!(rendered.contains("RLIMIT_NOFILE = { soft = "));
+    }
+}
+
+#[test]
+pub fn diagnostics_cover_the_current_directory() {
+    let diagnostics = diagnostics::collect();
+    #[cfg(unix)]
+    assert_eq!(diagnostics.disks[0].path, env::current_dir().unwrap());
+    #[cfg(not(unix))]
+    assert!(diagnostics.disks.is_empty());
+}
+
+#[test]
+#[cfg(feature = "serde")]
+pub fn diagnostics_serialize() {
+    let diagnostics = diagnostics::collect();
+    let value = serde_json::to_value(&diagnostics).unwrap();
+    assert_eq!(value["os"], env::consts::OS);
+    assert!(value["disks"].is_array());
+}
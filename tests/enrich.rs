@@ -0,0 +1,70 @@
+#![cfg(feature = "terminate")]
+
+use futility::terminate::{
+    enrich::ErrorContext,
+    reporter::{Json, Plain},
+    Terminate,
+};
+use std::{env, error::Error, process::Command};
+
+fn context() -> ErrorContext {
+    ErrorContext::new()
+        .version("1.2.3")
+        .git_sha("abc123")
+        .env("FUTILITY_ENRICH_*")
+        .redact("*hidden*")
+}
+
+fn fails() -> Result<(), Box<dyn Error>> {
+    Err("Always Fails".into())
+}
+
+fn run_child(reporter: &str) -> String {
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "enrich_errors", "--nocapture"])
+        .env("FUTILITY_ENRICH_REPORTER", reporter)
+        .env("FUTILITY_ENRICH_LOG", "debug")
+        .env("FUTILITY_ENRICH_API_TOKEN", "hunter2")
+        .env("FUTILITY_ENRICH_HIDDEN", "hunter3")
+        .env("FUTILITY_OTHER", "excluded")
+        .output()
+        .unwrap();
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+pub fn enrich_errors() {
+    match env::var("FUTILITY_ENRICH_REPORTER").as_deref() {
+        Ok("plain") => {
+            let _ = Terminate::new()
+                .enrich_errors(context())
+                .reporter(Plain)
+                .run(fails);
+            return;
+        }
+        Ok("json") => {
+            let _ = Terminate::new()
+                .enrich_errors(context())
+                .reporter(Json)
+                .run(fails);
+            return;
+        }
+        _ => {}
+    }
+
+    let stderr = run_child("plain");
+    assert!(stderr.contains("error: Always Fails\n\nversion: 1.2.3 (abc123)\narguments: ["));
+    assert!(stderr.contains("environment:\n"));
+    assert!(stderr.contains("  FUTILITY_ENRICH_API_TOKEN=<redacted>\n"));
+    assert!(stderr.contains("  FUTILITY_ENRICH_HIDDEN=<redacted>\n"));
+    assert!(stderr.contains("  FUTILITY_ENRICH_LOG=debug\n"));
+    assert!(!stderr.contains("hunter"));
+    assert!(!stderr.contains("FUTILITY_OTHER"));
+
+    let stderr = run_child("json");
+    assert!(stderr.contains(
+        "{\"error\":\"Always Fails\",\"reason\":\"error\",\"context\":{\"version\":\"1.2.3\",\"git_sha\":\"abc123\",\"arguments\":["
+    ));
+    assert!(stderr.contains("\"FUTILITY_ENRICH_LOG\":\"debug\""));
+    assert!(!stderr.contains("hunter"));
+}
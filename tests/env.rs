@@ -0,0 +1,161 @@
+#![cfg(feature = "env")]
+
+use futility::{
+    env::{self, EnvError, MissingVars, ScopedVar},
+    scoped_vars,
+};
+use std::{env as std_env, net::IpAddr, panic};
+
+#[test]
+pub fn parse_vars() {
+    std_env::set_var("FUTILITY_ENV_PORT", "9000");
+    std_env::set_var("FUTILITY_ENV_HOST", "not an ip");
+    std_env::remove_var("FUTILITY_ENV_UNSET");
+
+    assert_eq!(env::var_parsed::<u16>("FUTILITY_ENV_PORT"), Ok(9000));
+    assert_eq!(env::var_or::<u16>("FUTILITY_ENV_UNSET", 8080), Ok(8080));
+    assert_eq!(env::var_or::<u16>("FUTILITY_ENV_PORT", 8080), Ok(9000));
+    assert_eq!(env::var_opt::<u16>("FUTILITY_ENV_UNSET"), Ok(None));
+    assert_eq!(
+        env::var_parsed::<u16>("FUTILITY_ENV_UNSET"),
+        Err(EnvError::Missing {
+            name: "FUTILITY_ENV_UNSET".into()
+        })
+    );
+
+    let err = env::var_or::<IpAddr>("FUTILITY_ENV_HOST", [127, 0, 0, 1].into()).unwrap_err();
+    assert_eq!(err.name(), "FUTILITY_ENV_HOST");
+    assert_eq!(
+        err.to_string(),
+        "FUTILITY_ENV_HOST has an invalid value \"not an ip\": invalid IP address syntax"
+    );
+}
+
+#[test]
+pub fn require_all_reports_every_problem() {
+    std_env::set_var("FUTILITY_ENV_PRESENT", "yes");
+    std_env::remove_var("FUTILITY_ENV_MISSING_A");
+    std_env::remove_var("FUTILITY_ENV_MISSING_B");
+
+    assert!(env::require_all(&["FUTILITY_ENV_PRESENT"]).is_ok());
+    let err: MissingVars = env::require_all(&[
+        "FUTILITY_ENV_MISSING_A",
+        "FUTILITY_ENV_PRESENT",
+        "FUTILITY_ENV_MISSING_B",
+    ])
+    .unwrap_err();
+    assert_eq!(err.errors.len(), 2);
+    assert_eq!(
+        err.to_string(),
+        "invalid environment:\n  FUTILITY_ENV_MISSING_A is not set\n  FUTILITY_ENV_MISSING_B is not set"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+pub fn not_unicode() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    std_env::set_var("FUTILITY_ENV_BYTES", OsStr::from_bytes(&[0xff]));
+    assert_eq!(
+        env::var_opt::<String>("FUTILITY_ENV_BYTES"),
+        Err(EnvError::NotUnicode {
+            name: "FUTILITY_ENV_BYTES".into()
+        })
+    );
+}
+
+#[derive(Debug, PartialEq, env::FromEnv)]
+#[env(prefix = "FUTILITY_DERIVE_")]
+struct Config {
+    #[env(name = "PORT", default = "8080")]
+    port: u16,
+    host: IpAddr,
+    workers: Option<usize>,
+    #[env(default = "2")]
+    threads: Option<usize>,
+    #[env(name = "FUTILITY_DERIVE_NAME_OVERRIDE")]
+    name: String,
+}
+
+#[test]
+pub fn derive_from_env() {
+    use futility::env::FromEnv;
+
+    for var in [
+        "PORT",
+        "HOST",
+        "WORKERS",
+        "THREADS",
+        "FUTILITY_DERIVE_NAME_OVERRIDE",
+    ] {
+        std_env::remove_var(format!("FUTILITY_DERIVE_{var}"));
+    }
+    std_env::set_var("FUTILITY_DERIVE_PORT", "not a port");
+    let err = Config::from_env().unwrap_err();
+    let names = err.errors.iter().map(EnvError::name).collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "FUTILITY_DERIVE_PORT",
+            "FUTILITY_DERIVE_HOST",
+            "FUTILITY_DERIVE_FUTILITY_DERIVE_NAME_OVERRIDE"
+        ]
+    );
+
+    std_env::remove_var("FUTILITY_DERIVE_PORT");
+    std_env::set_var("FUTILITY_DERIVE_HOST", "::1");
+    std_env::set_var("FUTILITY_DERIVE_WORKERS", "4");
+    std_env::set_var("FUTILITY_DERIVE_FUTILITY_DERIVE_NAME_OVERRIDE", "app");
+    assert_eq!(
+        Config::from_env(),
+        Ok(Config {
+            port: 8080,
+            host: "::1".parse().unwrap(),
+            workers: Some(4),
+            threads: Some(2),
+            name: "app".into(),
+        })
+    );
+}
+
+#[test]
+pub fn scoped_vars_are_put_back() {
+    std_env::set_var("FUTILITY_SCOPED_SET", "before");
+    std_env::remove_var("FUTILITY_SCOPED_UNSET");
+    {
+        let set = ScopedVar::set("FUTILITY_SCOPED_SET", "during");
+        let unset = ScopedVar::set("FUTILITY_SCOPED_UNSET", "during");
+        assert_eq!(set.previous(), Some("before".as_ref()));
+        assert_eq!(unset.previous(), None);
+        assert_eq!(std_env::var("FUTILITY_SCOPED_SET").unwrap(), "during");
+        assert_eq!(std_env::var("FUTILITY_SCOPED_UNSET").unwrap(), "during");
+        {
+            let _removed = ScopedVar::remove("FUTILITY_SCOPED_SET");
+            assert!(std_env::var_os("FUTILITY_SCOPED_SET").is_none());
+        }
+        assert_eq!(std_env::var("FUTILITY_SCOPED_SET").unwrap(), "during");
+    }
+    assert_eq!(std_env::var("FUTILITY_SCOPED_SET").unwrap(), "before");
+    assert!(std_env::var_os("FUTILITY_SCOPED_UNSET").is_none());
+}
+
+#[test]
+pub fn scoped_vars_macro() {
+    std_env::set_var("FUTILITY_SCOPED_MACRO_A", "a");
+    std_env::remove_var("FUTILITY_SCOPED_MACRO_B");
+    let res = panic::catch_unwind(|| {
+        let vars = scoped_vars! {
+            "FUTILITY_SCOPED_MACRO_A" => "first",
+            "FUTILITY_SCOPED_MACRO_B" => String::from("b"),
+            "FUTILITY_SCOPED_MACRO_A" => "second",
+        };
+        assert_eq!(vars.vars().len(), 3);
+        assert_eq!(std_env::var("FUTILITY_SCOPED_MACRO_A").unwrap(), "second");
+        assert_eq!(std_env::var("FUTILITY_SCOPED_MACRO_B").unwrap(), "b");
+        panic!("put back while unwinding");
+    });
+    assert!(res.is_err());
+    assert_eq!(std_env::var("FUTILITY_SCOPED_MACRO_A").unwrap(), "a");
+    assert!(std_env::var_os("FUTILITY_SCOPED_MACRO_B").is_none());
+}
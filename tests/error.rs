@@ -0,0 +1,315 @@
+#![cfg(feature = "try-catch")]
+
+use futility::{
+    bail, ensure, ensure_eq, ensure_matches, ensure_ne,
+    error::{Adhoc, ContextError, ContextExt, ErrorChainExt},
+    try_,
+};
+use std::io;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+enum AppError {
+    #[error("{0}")]
+    Message(String),
+    #[error("not found: {0}")]
+    NotFound(&'static str),
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::Message(message)
+    }
+}
+
+fn lookup(name: &'static str) -> Result<u32, AppError> {
+    ensure!(!name.is_empty());
+    ensure!(name.is_ascii(), "{name:?} isn't ascii");
+    match name {
+        "one" => Ok(1),
+        "two" => bail!("{} is reserved", name),
+        _ => bail!(AppError::NotFound(name)),
+    }
+}
+
+#[test]
+pub fn bail_and_ensure_custom_error() {
+    assert_eq!(lookup("one"), Ok(1));
+    assert_eq!(
+        lookup(""),
+        Err(AppError::Message(
+            "condition failed: `!name.is_empty()`".into()
+        ))
+    );
+    assert_eq!(
+        lookup("ö"),
+        Err(AppError::Message("\"ö\" isn't ascii".into()))
+    );
+    assert_eq!(
+        lookup("two"),
+        Err(AppError::Message("two is reserved".into()))
+    );
+    assert_eq!(lookup("three"), Err(AppError::NotFound("three")));
+}
+
+#[test]
+pub fn bail_in_try_block() {
+    let mut caught = None;
+    let value = try_!({
+        let value = 3;
+        ensure!(value < 2, "{value} is too big");
+        value
+    } catch AppError as err {
+        caught = Some(err);
+        0
+    });
+    assert_eq!(value, 0);
+    assert_eq!(caught, Some(AppError::Message("3 is too big".into())));
+}
+
+fn parse_pair(input: &str) -> Result<(u8, u8), AppError> {
+    let parts = input.split(',').collect::<Vec<_>>();
+    ensure_eq!(parts.len(), 2);
+    let pair = (parts[0].parse().ok(), parts[1].parse().ok());
+    ensure_matches!(pair, (Some(_), Some(_)), "{input:?} isn't a pair of bytes");
+    let (Some(left), Some(right)) = pair else {
+        unreachable!()
+    };
+    ensure_ne!(left, right, AppError::NotFound("distinct values"));
+    ensure_ne!(left + 1, right);
+    ensure_matches!(left, 0..=9 if left < right);
+    ensure_eq!(right - left, 2, "{right} is too far from {left}");
+    Ok((left, right))
+}
+
+#[test]
+pub fn ensure_comparisons() {
+    assert_eq!(parse_pair("1,3"), Ok((1, 3)));
+    assert_eq!(
+        parse_pair("1"),
+        Err(AppError::Message(
+            "condition failed: `parts.len() == 2` (left: `1`, right: `2`)".into()
+        ))
+    );
+    assert_eq!(
+        parse_pair("1,x"),
+        Err(AppError::Message(
+            "\"1,x\" isn't a pair of bytes (value: `(Some(1), None)`)".into()
+        ))
+    );
+    assert_eq!(
+        parse_pair("4,4"),
+        Err(AppError::NotFound("distinct values"))
+    );
+    assert_eq!(
+        parse_pair("4,5"),
+        Err(AppError::Message(
+            "condition failed: `left + 1 != right` (left: `5`, right: `5`)".into()
+        ))
+    );
+    assert_eq!(
+        parse_pair("12,14"),
+        Err(AppError::Message(
+            "condition failed: `left` doesn't match `0..=9 if left < right` (value: `12`)".into()
+        ))
+    );
+    assert_eq!(
+        parse_pair("1,5"),
+        Err(AppError::Message(
+            "5 is too far from 1 (left: `4`, right: `2`)".into()
+        ))
+    );
+
+    let value = try_!({
+        ensure_eq!(1 + 1, 3);
+        1
+    } catch AppError as err {
+        assert!(matches!(err, AppError::Message(_)));
+        0
+    });
+    assert_eq!(value, 0);
+}
+
+#[derive(Debug)]
+struct Wrapper(io::Error);
+
+impl std::fmt::Display for Wrapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("wrapped")
+    }
+}
+
+impl std::error::Error for Wrapper {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+#[test]
+pub fn error_chain() {
+    let err = Wrapper(io::Error::new(io::ErrorKind::NotFound, "missing"));
+    assert_eq!(
+        err.chain().map(|err| err.to_string()).collect::<Vec<_>>(),
+        ["wrapped", "missing"]
+    );
+    assert_eq!(
+        err.find_source::<io::Error>().unwrap().kind(),
+        io::ErrorKind::NotFound
+    );
+    assert!(err.find_source::<Wrapper>().is_some());
+    assert!(err.find_source::<std::fmt::Error>().is_none());
+    assert_eq!(err.root_cause().to_string(), "missing");
+
+    let boxed: Box<dyn std::error::Error + Send + Sync> = Box::new(err);
+    assert_eq!(boxed.chain().count(), 2);
+    assert_eq!(boxed.root_cause().to_string(), "missing");
+
+    let single = io::Error::other("alone");
+    assert_eq!(single.chain().count(), 1);
+    assert_eq!(single.root_cause().to_string(), "alone");
+}
+
+fn load(name: &str) -> Result<u32, Adhoc> {
+    ensure!(!name.is_empty());
+    let value = name
+        .parse::<u32>()
+        .with_context(|| format!("{name:?} isn't a number"))?;
+    if value == 0 {
+        return Err(Wrapper(io::Error::other("zero")).into());
+    }
+    Ok(value)
+}
+
+#[test]
+pub fn adhoc() {
+    assert_eq!(load("7").unwrap(), 7);
+    assert_eq!(
+        load("").unwrap_err().to_string(),
+        "condition failed: `!name.is_empty()`"
+    );
+
+    let err = load("x").unwrap_err().context("failed to load");
+    assert_eq!(err.to_string(), "failed to load");
+    assert_eq!(
+        format!("{err:#}"),
+        "failed to load: \"x\" isn't a number: invalid digit found in string"
+    );
+    assert_eq!(
+        format!("{err:?}"),
+        "failed to load\n\nCaused by:\n    0: \"x\" isn't a number\n    1: invalid digit found in string"
+    );
+    assert!(err.downcast_ref::<std::num::ParseIntError>().is_some());
+    assert_eq!(err.chain().count(), 3);
+
+    let err = load("0").unwrap_err();
+    assert_eq!(format!("{err:?}"), "wrapped\n\nCaused by:\n    0: zero");
+    assert_eq!(err.root_cause().to_string(), "zero");
+    assert!(err.into_boxed().downcast::<Wrapper>().is_ok());
+
+    assert_eq!(
+        format!("{:?}", Adhoc::msg(format_args!("{} left", 3))),
+        "3 left"
+    );
+
+    let value = try_!({
+        load("x")?
+    } catch Adhoc as err {
+        assert!(err.find_source::<std::num::ParseIntError>().is_some());
+        0
+    });
+    assert_eq!(value, 0);
+}
+
+#[derive(Debug, ContextError)]
+#[context("failed to open {}", path.display())]
+struct OpenFile {
+    path: std::path::PathBuf,
+    #[source]
+    err: io::Error,
+}
+
+#[derive(Debug, ContextError)]
+#[context("bad port {_0}")]
+struct BadPort(u16, #[source] Box<dyn std::error::Error + Send + Sync>);
+
+#[derive(Debug, ContextError)]
+#[context("failed to start")]
+struct Start(Adhoc);
+
+#[derive(Debug, ContextError)]
+#[context("invalid {kind} setting")]
+struct Invalid<E: std::error::Error + 'static> {
+    kind: &'static str,
+    #[source]
+    source: E,
+}
+
+#[derive(Debug, ContextError)]
+#[context("failed to load {name}")]
+struct Load<E> {
+    name: &'static str,
+    #[source]
+    source: E,
+}
+
+#[derive(Debug, ContextError)]
+#[context("failed to read {f}")]
+struct Read {
+    f: &'static str,
+    #[source]
+    err: io::Error,
+}
+
+#[test]
+pub fn derive_context_error() {
+    let err = OpenFile {
+        path: "/etc/app.toml".into(),
+        err: io::Error::new(io::ErrorKind::NotFound, "missing"),
+    };
+    assert_eq!(err.to_string(), "failed to open /etc/app.toml");
+    assert_eq!(err.root_cause().to_string(), "missing");
+
+    let err = BadPort(80, "privileged".into());
+    assert_eq!(
+        err.chain().map(|err| err.to_string()).collect::<Vec<_>>(),
+        ["bad port 80", "privileged"]
+    );
+
+    let start = || -> Result<(), Start> {
+        load("x")?;
+        Ok(())
+    };
+    let err = start().unwrap_err();
+    assert_eq!(err.to_string(), "failed to start");
+    assert_eq!(err.chain().count(), 3);
+    assert!(err.find_source::<std::num::ParseIntError>().is_some());
+
+    let err = Invalid {
+        kind: "port",
+        source: "x".parse::<u16>().unwrap_err(),
+    };
+    assert_eq!(err.to_string(), "invalid port setting");
+
+    // Generic sources only need to be errors for the error impl
+    let err = Load {
+        name: "config",
+        source: io::Error::other("missing"),
+    };
+    assert_eq!(err.to_string(), "failed to load config");
+    assert_eq!(err.root_cause().to_string(), "missing");
+
+    // A field named `f` doesn't get in the way of the formatter
+    let err = Read {
+        f: "app.toml",
+        err: io::Error::other("denied"),
+    };
+    assert_eq!(err.to_string(), "failed to read app.toml");
+
+    let value = try_!({
+        load("0")?
+    } catch Start as err {
+        assert_eq!(err.root_cause().to_string(), "zero");
+        0
+    });
+    assert_eq!(value, 0);
+}
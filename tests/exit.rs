@@ -0,0 +1,103 @@
+#![cfg(feature = "terminate")]
+
+use futility::{
+    exit::{self, ExitCoded},
+    terminate::Terminate,
+};
+use std::{env, fmt, io, process::ExitCode};
+
+mod common;
+
+#[derive(Debug)]
+enum CliError {
+    Usage,
+    Config(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Usage => f.write_str("bad usage"),
+            Self::Config(key) => write!(f, "missing config key {key}"),
+        }
+    }
+}
+
+impl ExitCoded for CliError {
+    fn code(&self) -> u8 {
+        match self {
+            Self::Usage => exit::EX_USAGE,
+            Self::Config(_) => exit::EX_CONFIG,
+        }
+    }
+}
+
+#[test]
+pub fn exit_codes() {
+    assert_eq!(CliError::Usage.code(), 64);
+    assert_eq!(
+        CliError::Config("port".into()).exit_code(),
+        ExitCode::from(78)
+    );
+    assert_eq!(Box::new(CliError::Usage).code(), exit::EX_USAGE);
+    assert_eq!(
+        io::Error::from(io::ErrorKind::NotFound).code(),
+        exit::EX_NOINPUT
+    );
+    assert_eq!(
+        io::Error::from(io::ErrorKind::PermissionDenied).code(),
+        exit::EX_NOPERM
+    );
+    assert_eq!(io::Error::other("disk on fire").code(), exit::EX_IOERR);
+}
+
+#[test]
+pub fn terminate_exit_coded() {
+    let _serial = common::serial();
+    let code = Terminate::new()
+        .exit_coded()
+        .run(|| Err(CliError::Config("port".into())));
+    assert_eq!(code, ExitCode::from(exit::EX_CONFIG));
+
+    let code = Terminate::<CliError>::new().exit_coded().run(|| Ok(()));
+    assert_eq!(code, ExitCode::SUCCESS);
+}
+
+#[test]
+pub fn exit_with_prints_and_exits() {
+    if env::var_os("FUTILITY_EXIT_WITH").is_some() {
+        exit::exit_with(CliError::Usage);
+    }
+
+    let output = std::process::Command::new(env::current_exe().unwrap())
+        .args(["--exact", "exit_with_prints_and_exits", "--nocapture"])
+        .env("FUTILITY_EXIT_WITH", "1")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(exit::EX_USAGE.into()));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("error: bad usage"));
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ExitCoded)]
+#[exit_code(exit::EX_SOFTWARE)]
+enum DerivedError<T> {
+    #[exit_code(exit::EX_USAGE)]
+    Usage(T),
+    #[exit_code(3)]
+    Config {
+        key: T,
+    },
+    Bug,
+}
+
+#[derive(Debug, ExitCoded)]
+struct Unconfigured;
+
+#[test]
+pub fn derive_exit_coded() {
+    assert_eq!(DerivedError::Usage("-x").code(), exit::EX_USAGE);
+    assert_eq!(DerivedError::Config { key: "port" }.code(), 3);
+    assert_eq!(DerivedError::<()>::Bug.code(), exit::EX_SOFTWARE);
+    assert_eq!(Unconfigured.code(), 1);
+}
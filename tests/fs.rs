@@ -0,0 +1,147 @@
+#![cfg(feature = "fs")]
+
+use futility::fs::{self, ScopedCwd, TempDirGuard, TempFileGuard};
+use std::{
+    env,
+    io::{self, Read, Seek, Write},
+    mem, panic,
+};
+
+mod common;
+
+#[test]
+pub fn removed_on_drop() {
+    let dir = TempDirGuard::new("futility-fs-").unwrap();
+    let path = dir.path().to_owned();
+    assert!(path.is_dir());
+    assert!(path
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("futility-fs-"));
+    std::fs::write(path.join("nested"), "data").unwrap();
+
+    let mut file = TempFileGuard::new_in(&dir, "file-").unwrap();
+    assert!(file.path().starts_with(&path));
+    file.file_mut().write_all(b"hello").unwrap();
+    file.file_mut().rewind().unwrap();
+    let mut contents = String::new();
+    file.file().read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "hello");
+    let file_path = file.path().to_owned();
+    drop(file);
+    assert!(!file_path.exists());
+
+    drop(dir);
+    assert!(!path.exists());
+}
+
+#[test]
+pub fn unique_names() {
+    let first = TempFileGuard::new("futility-fs-").unwrap();
+    let second = TempFileGuard::new("futility-fs-").unwrap();
+    assert_ne!(first.path(), second.path());
+}
+
+#[test]
+pub fn keep() {
+    // Pending paths are global, so this takes turns with the tests removing
+    // them
+    let _serial = common::serial();
+    let dir = TempDirGuard::new("futility-fs-").unwrap().remove_at_exit();
+    let path = dir.keep();
+    let (file_path, _file) = TempFileGuard::new_in(&path, "kept-").unwrap().keep();
+    // Kept paths are no longer pending either
+    fs::remove_pending();
+    assert!(file_path.is_file());
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+pub fn remove_pending() {
+    let _serial = common::serial();
+    let dir = TempDirGuard::new("futility-fs-").unwrap().remove_at_exit();
+    let file = TempFileGuard::new("futility-fs-").unwrap().remove_at_exit();
+    let paths = [dir.path().to_owned(), file.path().to_owned()];
+    // Neither guard's destructor runs, as with `process::exit`
+    mem::forget(dir);
+    mem::forget(file);
+    fs::remove_pending();
+    assert!(paths.iter().all(|path| !path.exists()));
+}
+
+#[cfg(feature = "terminate")]
+#[test]
+pub fn removed_when_terminate_exits() {
+    use futility::terminate::Terminate;
+    use std::{path::PathBuf, sync::Mutex};
+
+    static PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+    let _serial = common::serial();
+    // Exiting empties the registry, so the second run has to register again
+    for _ in 0..2 {
+        Terminate::<std::io::Error>::new()
+            .execute(|| {
+                // Dropped guards are no longer pending
+                for _ in 0..8 {
+                    drop(TempDirGuard::new("futility-fs-")?.remove_at_exit());
+                }
+                let dir = TempDirGuard::new("futility-fs-")?.remove_at_exit();
+                *PATH.lock().unwrap() = Some(dir.path().to_owned());
+                mem::forget(dir);
+                Ok(())
+            })
+            .unwrap();
+        assert!(!PATH.lock().unwrap().take().unwrap().exists());
+    }
+}
+
+#[test]
+pub fn scoped_cwd_changes_back() {
+    let _serial = common::serial();
+    let original = env::current_dir().unwrap();
+    let dir = TempDirGuard::new("futility-cwd-").unwrap();
+    let canonical = dir.path().canonicalize().unwrap();
+
+    let res = panic::catch_unwind(|| {
+        let cwd = ScopedCwd::change(dir.path()).unwrap();
+        assert_eq!(cwd.previous(), original);
+        assert_eq!(env::current_dir().unwrap(), canonical);
+        panic!("changed back while unwinding");
+    });
+    assert!(res.is_err());
+    assert_eq!(env::current_dir().unwrap(), original);
+
+    let cwd = ScopedCwd::change(dir.path()).unwrap();
+    cwd.restore().unwrap();
+    assert_eq!(env::current_dir().unwrap(), original);
+
+    let err = ScopedCwd::change(dir.path().join("missing")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(err.to_string().contains("missing"), "{err}");
+}
+
+#[test]
+pub fn scoped_cwd_original_removed() {
+    let _serial = common::serial();
+    let original = env::current_dir().unwrap();
+    let outer = TempDirGuard::new("futility-cwd-outer-").unwrap();
+    let inner = TempDirGuard::new("futility-cwd-inner-").unwrap();
+
+    let outer_cwd = ScopedCwd::change(outer.path()).unwrap();
+    let inner_cwd = ScopedCwd::change(inner.path()).unwrap();
+    let previous = inner_cwd.previous().to_owned();
+    std::fs::remove_dir(outer.keep()).unwrap();
+    let err = inner_cwd.restore().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "couldn't change back to the working directory {}: it no longer exists",
+            previous.display()
+        )
+    );
+    outer_cwd.restore().unwrap();
+    assert_eq!(env::current_dir().unwrap(), original);
+}
@@ -0,0 +1,66 @@
+#![cfg(all(feature = "guards", feature = "std"))]
+
+use futility::{defer, guard::ScopeGuard};
+use std::{
+    cell::RefCell,
+    panic::{self, AssertUnwindSafe},
+};
+
+#[test]
+pub fn defer_runs_in_reverse_order() {
+    let order = RefCell::new(Vec::new());
+    {
+        defer! { order.borrow_mut().push(1); }
+        defer! { order.borrow_mut().push(2); }
+        order.borrow_mut().push(0);
+    }
+    assert_eq!(*order.borrow(), [0, 2, 1]);
+}
+
+#[test]
+pub fn defer_runs_on_panic() {
+    let ran = RefCell::new(false);
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        defer! { *ran.borrow_mut() = true; }
+        panic!("Oh no");
+    }));
+    assert!(res.is_err());
+    assert!(*ran.borrow());
+}
+
+#[test]
+pub fn scope_guard_dismiss() {
+    let cleaned = RefCell::new(Vec::new());
+    {
+        let mut guard = ScopeGuard::new(vec![1], |value| cleaned.borrow_mut().extend(value));
+        guard.push(2);
+    }
+    assert_eq!(*cleaned.borrow(), [1, 2]);
+
+    let guard = ScopeGuard::new(vec![3], |value| cleaned.borrow_mut().extend(value));
+    assert_eq!(ScopeGuard::dismiss(guard), [3]);
+    assert_eq!(*cleaned.borrow(), [1, 2]);
+}
+
+#[test]
+pub fn success_and_unwind_guards() {
+    use futility::{defer_on_success, defer_on_unwind};
+
+    let order = RefCell::new(Vec::new());
+    {
+        defer_on_success! { order.borrow_mut().push("commit"); }
+        defer_on_unwind! { order.borrow_mut().push("roll back"); }
+    }
+    assert_eq!(*order.borrow(), ["commit"]);
+
+    order.borrow_mut().clear();
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        defer_on_success! { order.borrow_mut().push("commit"); }
+        let _guard = ScopeGuard::on_unwind("transaction", |name| {
+            order.borrow_mut().push(name);
+        });
+        panic!("Oh no");
+    }));
+    assert!(res.is_err());
+    assert_eq!(*order.borrow(), ["transaction"]);
+}
@@ -0,0 +1,87 @@
+#![cfg(all(unix, feature = "terminate"))]
+
+mod common;
+
+use futility::terminate::{InstanceError, InstanceLock, Terminate};
+use std::{env, panic, path::PathBuf, process};
+
+fn lock_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("futility-{name}-{}.lock", process::id()))
+}
+
+#[test]
+pub fn single_instance_already_running() {
+    let _serial = common::serial();
+    let path = lock_path("already-running");
+    let lock = InstanceLock::acquire(&path).unwrap();
+
+    let err = Terminate::<InstanceError>::new()
+        .single_instance(&path)
+        .execute(|| panic!("main should not run"))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        InstanceError::AlreadyRunning { pid: Some(pid), .. } if pid == process::id()
+    ));
+    assert!(err
+        .to_string()
+        .starts_with(&format!("already running (pid {})", process::id())));
+
+    drop(lock);
+    assert!(!path.exists());
+    Terminate::<InstanceError>::new()
+        .single_instance(&path)
+        .execute(|| {
+            assert!(lock_path("already-running").exists());
+            Ok(())
+        })
+        .unwrap();
+    assert!(!path.exists());
+}
+
+#[test]
+pub fn single_instance_released_on_panic() {
+    let _serial = common::serial();
+    let path = lock_path("panic");
+    let res = panic::catch_unwind(|| {
+        Terminate::<InstanceError>::new()
+            .single_instance(lock_path("panic"))
+            .execute(|| panic!("Oh no"))
+    });
+    assert!(res.is_err());
+    assert!(!path.exists());
+    drop(InstanceLock::acquire(&path).unwrap());
+}
+
+#[test]
+pub fn instance_lock_is_never_held_twice() {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    };
+
+    static HOLDERS: AtomicUsize = AtomicUsize::new(0);
+
+    // Locks are taken and dropped in a tight loop so that a lock is often
+    // taken on a file another thread is removing
+    let path = lock_path("held-twice");
+    let threads = (0..8)
+        .map(|_| {
+            let path = path.clone();
+            thread::spawn(move || {
+                for _ in 0..2000 {
+                    if let Ok(lock) = InstanceLock::acquire(&path) {
+                        assert_eq!(HOLDERS.fetch_add(1, Ordering::SeqCst), 0);
+                        thread::yield_now();
+                        HOLDERS.fetch_sub(1, Ordering::SeqCst);
+                        drop(lock);
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert!(!path.exists());
+}
@@ -0,0 +1,107 @@
+#![cfg(feature = "io")]
+
+use futility::{
+    io::{self, Retrying},
+    retry::{FixedDelay, RetryPolicy},
+};
+use std::{
+    collections::VecDeque,
+    io::{ErrorKind, Read, Write},
+    time::Duration,
+};
+
+/// Hands out each of its results in turn, then reports the end of the input
+struct Scripted(VecDeque<std::io::Result<&'static [u8]>>);
+
+impl Scripted {
+    fn new(script: impl IntoIterator<Item = std::io::Result<&'static [u8]>>) -> Self {
+        Self(script.into_iter().collect())
+    }
+}
+
+impl Read for Scripted {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.0.pop_front() {
+            Some(Ok(bytes)) => {
+                buf[..bytes.len()].copy_from_slice(bytes);
+                Ok(bytes.len())
+            }
+            Some(Err(err)) => Err(err),
+            None => Ok(0),
+        }
+    }
+}
+
+impl Write for Scripted {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.0.pop_front() {
+            Some(Ok(_)) => Ok(buf.len().min(2)),
+            Some(Err(err)) => Err(err),
+            None => Ok(0),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn interrupted() -> std::io::Result<&'static [u8]> {
+    Err(ErrorKind::Interrupted.into())
+}
+
+#[test]
+pub fn read_full() {
+    let mut reader = Scripted::new([Ok(&b"ab"[..]), interrupted(), Ok(b"cd"), interrupted()]);
+    let mut buf = [0; 8];
+    assert_eq!(io::read_full(&mut reader, &mut buf).unwrap(), 4);
+    assert_eq!(&buf[..4], b"abcd");
+
+    let mut reader = Scripted::new([Ok(&b"ab"[..]), Err(ErrorKind::BrokenPipe.into())]);
+    let err = io::read_full(&mut reader, &mut buf).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+}
+
+#[test]
+pub fn write_full() {
+    let mut writer = Scripted::new([Ok(&b""[..]), interrupted(), Ok(b""), Ok(b"")]);
+    io::write_full(&mut writer, b"hello").unwrap();
+    assert!(writer.0.is_empty());
+
+    let mut writer = Scripted::new([Ok(&b""[..])]);
+    let err = io::write_full(&mut writer, b"hello").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WriteZero);
+}
+
+#[test]
+pub fn retrying() {
+    let policy = || FixedDelay::new(Duration::ZERO).max_attempts(3);
+
+    let reader = Scripted::new([
+        Err(ErrorKind::WouldBlock.into()),
+        Err(ErrorKind::TimedOut.into()),
+        Ok(&b"ok"[..]),
+    ]);
+    let mut reader = Retrying::new(reader, policy());
+    let mut read = String::new();
+    reader.read_to_string(&mut read).unwrap();
+    assert_eq!(read, "ok");
+
+    let reader = Scripted::new([
+        Err(ErrorKind::WouldBlock.into()),
+        Err(ErrorKind::WouldBlock.into()),
+        Err(ErrorKind::WouldBlock.into()),
+        Ok(&b"late"[..]),
+    ]);
+    let mut reader = Retrying::new(reader, policy());
+    let err = reader.read(&mut [0; 4]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    // The next call starts the policy over
+    assert_eq!(reader.read(&mut [0; 4]).unwrap(), 4);
+
+    let writer = Scripted::new([Err(ErrorKind::PermissionDenied.into()), Ok(&b""[..])]);
+    let mut writer = Retrying::new(writer, policy());
+    let err = writer.write(b"x").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    assert_eq!(writer.into_inner().0.len(), 1);
+}
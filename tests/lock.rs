@@ -0,0 +1,73 @@
+#![cfg(feature = "lock")]
+
+use futility::lock::{Mutex, Poisoned, RwLock};
+use std::{panic, thread};
+
+fn poison_mutex(mutex: &Mutex<Vec<u32>>) {
+    thread::scope(|s| {
+        let _ = s
+            .spawn(|| {
+                mutex.lock().push(99);
+                let _guard = mutex.lock();
+                panic!("poisoning the mutex");
+            })
+            .join();
+    });
+}
+
+#[test]
+pub fn mutex() {
+    let mutex = Mutex::new(vec![1]);
+    mutex.lock().push(2);
+    assert_eq!(*mutex.lock_checked().unwrap(), [1, 2]);
+
+    poison_mutex(&mutex);
+    assert!(mutex.is_poisoned());
+    assert_eq!(mutex.lock_checked().unwrap_err(), Poisoned);
+    // Ignoring the poison leaves it in place
+    assert_eq!(*mutex.lock(), [1, 2, 99]);
+    assert_eq!(*mutex.try_lock().unwrap(), [1, 2, 99]);
+    assert!(mutex.is_poisoned());
+
+    let mut recovered = false;
+    drop(mutex.lock_or_recover(|values| {
+        values.retain(|v| *v != 99);
+        recovered = true;
+    }));
+    assert!(recovered);
+    assert!(!mutex.is_poisoned());
+    assert_eq!(*mutex.lock_checked().unwrap(), [1, 2]);
+    // Nothing to recover from the second time
+    drop(mutex.lock_or_recover(|_| panic!("not poisoned")));
+
+    let guard = mutex.lock();
+    assert!(mutex.try_lock().is_none());
+    assert_eq!(format!("{mutex:?}"), "Mutex(<locked>)");
+    drop(guard);
+    assert_eq!(format!("{mutex:?}"), "Mutex([1, 2])");
+
+    poison_mutex(&mutex);
+    assert_eq!(mutex.into_inner(), [1, 2, 99]);
+}
+
+#[test]
+pub fn rw_lock() {
+    let lock = RwLock::new(String::from("a"));
+    lock.write().push('b');
+    assert_eq!(*lock.read(), "ab");
+
+    let _ = panic::catch_unwind(|| {
+        let _guard = lock.write();
+        panic!("poisoning the lock");
+    });
+    assert!(lock.is_poisoned());
+    assert_eq!(lock.read_checked().unwrap_err(), Poisoned);
+    assert_eq!(lock.write_checked().unwrap_err(), Poisoned);
+    assert_eq!(*lock.read(), "ab");
+    assert_eq!(format!("{lock:?}"), "RwLock(\"ab\")");
+
+    lock.write_or_recover(|value| value.clear()).push('c');
+    assert!(!lock.is_poisoned());
+    assert_eq!(*lock.read_checked().unwrap(), "c");
+    assert_eq!(lock.into_inner(), "c");
+}
@@ -0,0 +1,103 @@
+#![cfg(feature = "log")]
+
+use futility::{
+    log,
+    log::{Level, SimpleLogger},
+};
+use std::{env, fs};
+
+mod common;
+
+fn log_file(name: &str) -> std::path::PathBuf {
+    let path = env::temp_dir().join(format!("futility-log-{}-{name}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[test]
+pub fn filter_by_level_and_target() {
+    let _serial = common::serial();
+    let path = log_file("filter");
+    env::set_var(
+        "FUTILITY_LOG_TEST",
+        "warn,log::db=trace,log::db::pool=error",
+    );
+    SimpleLogger::new()
+        .env("FUTILITY_LOG_TEST")
+        .file(&path)
+        .install()
+        .unwrap();
+    log!(Level::Info, "not written");
+    log!(Level::Warn, "disk {}% full", 91);
+    log!(Level::Trace, target: "log::db", "query took {}ms", 3);
+    log!(Level::Trace, target: "log::dbx", "prefix isn't a module");
+    log!(Level::Warn, target: "log::db::pool", "more specific wins");
+    log!(Level::Error, target: "log::db::pool", "pool exhausted");
+    assert!(log::enabled(Level::Debug, "log::db::query"));
+    assert!(!log::enabled(Level::Debug, "log"));
+    log::flush();
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "WARN  log: disk 91% full\n\
+         TRACE log::db: query took 3ms\n\
+         ERROR log::db::pool: pool exhausted\n"
+    );
+    let _ = fs::remove_file(path);
+}
+
+#[test]
+pub fn invalid_filter() {
+    let _serial = common::serial();
+    env::set_var("FUTILITY_LOG_INVALID", "loud");
+    let err = SimpleLogger::new()
+        .env("FUTILITY_LOG_INVALID")
+        .install()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid log filter: invalid log level \"loud\", expected error, warn, info, debug, or trace"
+    );
+}
+
+#[cfg(feature = "terminate")]
+#[test]
+pub fn terminate_install_simple_logger() {
+    use futility::{log::LogError, terminate::Terminate};
+
+    let _serial = common::serial();
+    env::remove_var("RUST_LOG");
+    Terminate::<LogError>::new()
+        .install_simple_logger()
+        .execute(|| {
+            assert!(log::enabled(Level::Info, "app"));
+            assert!(!log::enabled(Level::Debug, "app"));
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[cfg(feature = "log-facade")]
+#[test]
+pub fn log_crate_macros() {
+    let _serial = common::serial();
+    let path = log_file("facade");
+    env::set_var("FUTILITY_LOG_FACADE", "info,facade::db=debug");
+    SimpleLogger::new()
+        .env("FUTILITY_LOG_FACADE")
+        .file(&path)
+        .install()
+        .unwrap();
+    assert_eq!(::log::max_level(), ::log::LevelFilter::Debug);
+    ::log::info!(target: "facade", "listening on port {}", 8080);
+    ::log::debug!(target: "facade", "not written");
+    ::log::debug!(target: "facade::db", "connected");
+    ::log::logger().flush();
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "INFO  facade: listening on port 8080\n\
+         DEBUG facade::db: connected\n"
+    );
+    let _ = fs::remove_file(path);
+}
@@ -0,0 +1,26 @@
+#![cfg(feature = "terminate")]
+
+use color_eyre::eyre::{eyre, Report};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static EXITED: AtomicBool = AtomicBool::new(false);
+
+fn report(err: Report) -> Report {
+    err.wrap_err("We're at the top of main")
+}
+
+fn cleanup() {
+    EXITED.store(true, Ordering::SeqCst);
+}
+
+#[futility::main(on_error = report, at_exit = cleanup, report_runtime)]
+fn fails() -> Result<(), Report> {
+    Err(eyre!("Always Fails"))
+}
+
+#[test]
+pub fn main_attribute() {
+    let err = fails().unwrap_err();
+    assert_eq!(err.to_string(), "We're at the top of main");
+    assert!(EXITED.load(Ordering::SeqCst));
+}
@@ -0,0 +1,81 @@
+#![cfg(feature = "metrics")]
+
+mod common;
+
+use futility::{counter, gauge, metrics};
+use std::thread;
+
+#[test]
+pub fn counters_and_gauges() {
+    let _serial = common::serial();
+    let handles = (0..4)
+        .map(|_| {
+            thread::spawn(|| {
+                for _ in 0..100 {
+                    counter!("test.counted").inc();
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(counter!("test.counted").get(), 400);
+    counter!(String::from("test.counted")).add(10);
+    assert_eq!(metrics::counter("test.counted").get(), 410);
+
+    gauge!("test.level").set(5);
+    gauge!("test.level").dec();
+    gauge!("test.level").add(-10);
+    assert_eq!(gauge!("test.level").get(), -6);
+
+    let snapshot = metrics::snapshot();
+    assert_eq!(snapshot.counter("test.counted"), Some(410));
+    assert_eq!(snapshot.gauge("test.level"), Some(-6));
+    assert_eq!(snapshot.counter("test.level"), None);
+    assert!(snapshot
+        .to_string()
+        .contains("test.counted: 410\ntest.level: -6"));
+}
+
+#[test]
+pub fn reset() {
+    let _serial = common::serial();
+    counter!("test.reset").add(3);
+    metrics::reset();
+    assert_eq!(metrics::snapshot().counter("test.reset"), Some(0));
+}
+
+#[cfg(feature = "terminate")]
+#[test]
+pub fn report_metrics() {
+    use futility::terminate::{ExitInfo, Terminate};
+    use std::{env, io, process::Command};
+
+    if env::var_os("FUTILITY_METRICS_REPORT").is_some() {
+        Terminate::<io::Error>::new()
+            .report_metrics()
+            .at_exit_with(|info: &ExitInfo| {
+                assert_eq!(info.metrics.counter("files"), Some(2));
+            })
+            .execute(|| {
+                counter!("files").add(2);
+                gauge!("workers").set(4);
+                Ok(())
+            })
+            .unwrap();
+        return;
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "report_metrics", "--nocapture"])
+        .env("FUTILITY_METRICS_REPORT", "1")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(
+        stderr.contains("metrics:\n  files: 2\n  workers: 4\n"),
+        "{stderr}"
+    );
+}
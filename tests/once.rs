@@ -0,0 +1,66 @@
+#![cfg(feature = "once")]
+
+use futility::once::{OnceResult, TryLazy};
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    thread,
+};
+
+#[test]
+pub fn once_result() {
+    let cell = OnceResult::new();
+    assert_eq!(cell.get(), None);
+    assert_eq!(cell.get_or_try_init(|| Err("not yet")), Err("not yet"));
+    assert_eq!(cell.get(), None);
+    assert_eq!(cell.get_or_try_init(|| Ok::<_, &str>(1)), Ok(&1));
+    assert_eq!(
+        cell.get_or_try_init(|| -> Result<_, &str> { panic!("already initialized") }),
+        Ok(&1)
+    );
+    assert_eq!(format!("{cell:?}"), "OnceResult(1)");
+    assert_eq!(cell.into_inner(), Some(1));
+}
+
+static CALLS: AtomicU32 = AtomicU32::new(0);
+static LAZY: TryLazy<u32, String> = TryLazy::new(|| match CALLS.fetch_add(1, Ordering::SeqCst) {
+    0 => Err("first call fails".into()),
+    n => Ok(n),
+});
+
+#[test]
+pub fn try_lazy_retries_on_next_access() {
+    assert_eq!(format!("{LAZY:?}"), "TryLazy(<uninit>)");
+    assert_eq!(LAZY.force(), Err("first call fails".into()));
+    assert_eq!(LAZY.get(), None);
+
+    let values = (0..4)
+        .map(|_| thread::spawn(|| *LAZY.force().unwrap()))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect::<Vec<_>>();
+    // Only one initialization succeeded, and every thread saw its value
+    assert_eq!(values, [1; 4]);
+    assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+    assert_eq!(LAZY.get(), Some(&1));
+}
+
+#[cfg(feature = "retry")]
+#[test]
+pub fn try_lazy_force_retry() {
+    use futility::retry::{FixedDelay, RetryPolicy};
+    use std::{cell::Cell, time::Duration};
+
+    let attempts = Cell::new(0);
+    let lazy = TryLazy::new(|| {
+        attempts.set(attempts.get() + 1);
+        match attempts.get() {
+            5 => Ok("connected"),
+            n => Err(n),
+        }
+    });
+    let policy = FixedDelay::new(Duration::ZERO).max_attempts(3);
+    assert_eq!(lazy.force_retry(policy), Err(3));
+    assert_eq!(lazy.force_retry(policy), Ok(&"connected"));
+    assert_eq!(attempts.get(), 5);
+}
@@ -0,0 +1,163 @@
+#![cfg(feature = "terminate")]
+
+use futility::{
+    panic::{panic_message, payload_as_str, PanicDetails, NON_STRING_PAYLOAD},
+    terminate::PanicPayload,
+};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::Mutex,
+    thread,
+};
+
+mod common;
+
+#[test]
+pub fn payload_as_str_downcasts_strings() {
+    let _serial = common::serial();
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let str_payload = panic::catch_unwind(|| panic!("static")).unwrap_err();
+    let string_payload =
+        panic::catch_unwind(|| panic!("{}", String::from("formatted"))).unwrap_err();
+    let other_payload = panic::catch_unwind(|| panic::panic_any(3_u8)).unwrap_err();
+    panic::set_hook(hook);
+
+    assert_eq!(payload_as_str(&*str_payload), Some("static"));
+    assert_eq!(payload_as_str(&*string_payload), Some("formatted"));
+    assert_eq!(payload_as_str(&*other_payload), None);
+    assert_eq!(
+        PanicDetails::from_payload(&*other_payload).message,
+        NON_STRING_PAYLOAD
+    );
+    assert_eq!(
+        PanicDetails::from(&PanicPayload::caught(&*string_payload)).message,
+        "formatted"
+    );
+}
+
+#[test]
+pub fn details_from_hook() {
+    let _serial = common::serial();
+    static SEEN: Mutex<Vec<(Option<String>, PanicDetails)>> = Mutex::new(Vec::new());
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|info| {
+        let message = panic_message(info).map(Into::into);
+        SEEN.lock()
+            .unwrap()
+            .push((message, PanicDetails::from_hook(info)));
+    }));
+    let line = line!() + 3;
+    let result = thread::Builder::new()
+        .name("worker".into())
+        .spawn(|| panic!("in a thread"))
+        .unwrap()
+        .join();
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| panic::panic_any(3_u8)));
+    panic::set_hook(hook);
+    assert!(result.is_err());
+
+    let mut seen = SEEN.lock().unwrap();
+    let (message, details) = seen.remove(0);
+    assert_eq!(message.as_deref(), Some("in a thread"));
+    assert_eq!(details.message, "in a thread");
+    assert_eq!(details.thread.as_deref(), Some("worker"));
+    let location = format!("tests/panic.rs:{line}:19");
+    assert_eq!(details.location.as_deref(), Some(location.as_str()));
+    assert_eq!(
+        details.to_string(),
+        format!("panicked in thread 'worker' at {location}: in a thread")
+    );
+
+    let (message, details) = seen.remove(0);
+    assert_eq!(message, None);
+    assert_eq!(details.message, NON_STRING_PAYLOAD);
+}
+
+#[test]
+pub fn catch_panics() {
+    let _serial = common::serial();
+    assert_eq!(futility::panic::catch::<_, PanicDetails>(|| 1).unwrap(), 1);
+
+    let line = line!() + 1;
+    let err: PanicDetails = futility::panic::catch(|| panic!("caught")).unwrap_err();
+    assert_eq!(err.message, "caught");
+    assert!(err
+        .location
+        .unwrap()
+        .starts_with(&format!("tests/panic.rs:{line}:")));
+
+    // Errors are created from the details with From
+    let err: Box<dyn std::error::Error> =
+        futility::panic::catch_result(|| -> Result<(), Box<dyn std::error::Error>> {
+            panic::panic_any(1_u32)
+        })
+        .unwrap_err();
+    assert!(err.to_string().ends_with(NON_STRING_PAYLOAD));
+    let err: Box<dyn std::error::Error> =
+        futility::panic::catch_result(|| Err::<(), _>("returned".into())).unwrap_err();
+    assert_eq!(err.to_string(), "returned");
+
+    // Captured state that isn't unwind safe needs catch_asserted
+    let mut count = 0;
+    let err = futility::panic::catch_asserted::<(), PanicDetails>(|| {
+        count += 1;
+        panic!("after {count}");
+    })
+    .unwrap_err();
+    assert_eq!(err.message, "after 1");
+
+    // A panic caught inside doesn't leave its details for a later panic that
+    // skips the hook
+    futility::panic::catch::<_, PanicDetails>(|| {
+        let _ = panic::catch_unwind(|| panic!("handled inside"));
+    })
+    .unwrap();
+    let err: PanicDetails =
+        futility::panic::catch(|| panic::resume_unwind(Box::new("resumed"))).unwrap_err();
+    assert_eq!(err.message, "resumed");
+    assert_eq!(err.location, None);
+}
+
+#[test]
+pub fn scoped_hook() {
+    use std::sync::Arc;
+
+    let _serial = common::serial();
+    let caught = Arc::new(Mutex::new(Vec::new()));
+    let push = |tag: &'static str| {
+        let caught = caught.clone();
+        move |info: &panic::PanicHookInfo<'_>| {
+            caught
+                .lock()
+                .unwrap()
+                .push(format!("{tag}: {}", panic_message(info).unwrap()))
+        }
+    };
+
+    let res = futility::panic::scoped_hook(push("outer"), || {
+        let _ = panic::catch_unwind(|| panic!("first"));
+        futility::panic::scoped_hook(push("inner"), || {
+            let _ = panic::catch_unwind(|| panic!("second"));
+        });
+        // Caught panics and other threads don't go to the scoped hook
+        let _ = futility::panic::catch::<(), PanicDetails>(|| panic!("caught"));
+        thread::spawn(|| panic!("elsewhere")).join().unwrap_err();
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            futility::panic::scoped_hook(push("unwound"), || panic!("third"))
+        }))
+        .unwrap_err();
+        let _ = panic::catch_unwind(|| panic!("fourth"));
+        5
+    });
+    assert_eq!(res, 5);
+    assert_eq!(
+        *caught.lock().unwrap(),
+        [
+            "outer: first",
+            "inner: second",
+            "unwound: third",
+            "outer: fourth"
+        ]
+    );
+}
@@ -0,0 +1,123 @@
+#![cfg(feature = "pool")]
+
+use futility::{
+    budget::Deadline,
+    pool::{JobError, ThreadPool},
+};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+#[test]
+pub fn shutdown_runs_queued_jobs_and_reports_errors() {
+    let pool = ThreadPool::new(3).unwrap();
+    let ran = Arc::new(AtomicUsize::new(0));
+    for n in 0..20 {
+        let ran = Arc::clone(&ran);
+        let index = pool
+            .try_execute(move || {
+                ran.fetch_add(1, Ordering::SeqCst);
+                match n {
+                    5 => Err("bad input"),
+                    11 => panic!("job {n} blew up"),
+                    _ => Ok(()),
+                }
+            })
+            .unwrap();
+        assert_eq!(index, n);
+    }
+
+    let err = pool
+        .shutdown(Deadline::after(Duration::from_secs(10)))
+        .unwrap_err();
+    assert_eq!(ran.load(Ordering::SeqCst), 20);
+    match &err.errors[..] {
+        [(5, JobError::Failed(failed)), (11, JobError::Panicked(details))] => {
+            assert_eq!(failed.to_string(), "bad input");
+            assert_eq!(details.message, "job 11 blew up");
+        }
+        errors => panic!("unexpected errors: {errors:?}"),
+    }
+
+    assert!(!pool.is_open());
+    assert!(pool.execute(|| {}).is_err());
+    assert!(pool.shutdown(Deadline::after(Duration::ZERO)).is_ok());
+}
+
+#[test]
+pub fn deadline_cancels_queued_jobs() {
+    let pool = ThreadPool::new(1).unwrap();
+    let (release, blocked) = mpsc::channel::<()>();
+    let (started, wait_started) = mpsc::channel();
+    pool.execute(move || {
+        started.send(()).unwrap();
+        let _ = blocked.recv();
+    })
+    .unwrap();
+    for _ in 0..3 {
+        pool.execute(|| {}).unwrap();
+    }
+    wait_started.recv().unwrap();
+    assert_eq!(pool.queued(), 3);
+
+    let err = pool
+        .shutdown(Deadline::after(Duration::from_millis(50)))
+        .unwrap_err();
+    let errors = err
+        .errors
+        .iter()
+        .map(|(index, err)| (*index, err.to_string()));
+    assert_eq!(
+        errors.collect::<Vec<_>>(),
+        [
+            (
+                0,
+                "the job was still running at the shutdown deadline".into()
+            ),
+            (1, "the job was cancelled before it started".into()),
+            (2, "the job was cancelled before it started".into()),
+            (3, "the job was cancelled before it started".into()),
+        ]
+    );
+    release.send(()).unwrap();
+}
+
+#[test]
+pub fn shutdown_now_skips_queued_jobs() {
+    let pool = ThreadPool::new(1).unwrap();
+    let (release, blocked) = mpsc::channel::<()>();
+    let (started, wait_started) = mpsc::channel();
+    let ran = Arc::new(AtomicUsize::new(0));
+    pool.execute(move || {
+        started.send(()).unwrap();
+        let _ = blocked.recv();
+    })
+    .unwrap();
+    for _ in 0..3 {
+        let ran = Arc::clone(&ran);
+        pool.execute(move || {
+            ran.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+    }
+    wait_started.recv().unwrap();
+    let releasing = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        release.send(()).unwrap();
+    });
+
+    let err = pool
+        .shutdown_now(Deadline::after(Duration::from_secs(10)))
+        .unwrap_err();
+    assert_eq!(ran.load(Ordering::SeqCst), 0);
+    assert_eq!(err.errors.len(), 3);
+    assert!(err
+        .into_errors()
+        .all(|err| matches!(err, JobError::Cancelled)));
+    releasing.join().unwrap();
+}
@@ -0,0 +1,210 @@
+#![cfg(all(unix, feature = "process"))]
+
+use futility::{
+    exit::ExitCoded,
+    process::{self, ChildGuard, Failure, Supervisor},
+    retry::{FixedDelay, RetryPolicy},
+    shutdown::ShutdownToken,
+    signal::Signal,
+};
+use std::{
+    os::unix::process::ExitStatusExt,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+fn sleep() -> Command {
+    let mut command = Command::new("sleep");
+    command.arg("30");
+    command
+}
+
+fn is_running(pid: u32) -> bool {
+    // Signal 0 only checks that the process exists. A reaped child no longer
+    // does.
+    unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+}
+
+#[test]
+pub fn kills_on_drop() {
+    let guard = ChildGuard::spawn(&mut sleep()).unwrap();
+    let pid = guard.id();
+    assert!(is_running(pid));
+    drop(guard);
+    assert!(!is_running(pid));
+}
+
+#[test]
+pub fn signal_then_kill() {
+    let guard = ChildGuard::spawn(&mut sleep())
+        .unwrap()
+        .signal(Signal::TERM)
+        .grace(Duration::from_secs(10));
+    let status = guard.stop().unwrap();
+    assert_eq!(status.signal(), Some(libc::SIGTERM));
+
+    // A child that ignores the signal is killed after the grace period
+    let mut guard = ChildGuard::spawn(
+        Command::new("sh")
+            .args(["-c", "trap '' TERM; echo ready; sleep 30"])
+            .stdout(Stdio::piped()),
+    )
+    .unwrap()
+    .signal(Signal::TERM)
+    .grace(Duration::from_millis(100));
+    let mut ready = [0; 6];
+    std::io::Read::read_exact(guard.stdout.as_mut().unwrap(), &mut ready).unwrap();
+    let start = Instant::now();
+    let status = guard.stop().unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert_eq!(status.signal(), Some(libc::SIGKILL));
+}
+
+#[test]
+pub fn detach() {
+    let guard = ChildGuard::spawn(&mut sleep()).unwrap();
+    let mut child = guard.detach();
+    assert!(is_running(child.id()));
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+pub fn wait_timeout() {
+    let mut guard = ChildGuard::spawn(&mut sleep()).unwrap();
+    assert_eq!(guard.wait_timeout(Duration::from_millis(20)).unwrap(), None);
+
+    let mut child = Command::new("true").spawn().unwrap();
+    let status = process::wait_timeout(&mut child, Duration::from_secs(10)).unwrap();
+    assert!(status.unwrap().success());
+
+    let mut child = Command::new("true").spawn().unwrap();
+    let status = process::wait_timeout(&mut child, Duration::MAX).unwrap();
+    assert!(status.unwrap().success());
+
+    // An exited child is left alone
+    let mut guard = ChildGuard::spawn(&mut Command::new("true")).unwrap();
+    guard.wait().unwrap();
+    assert!(guard.stop().unwrap().success());
+}
+
+fn counting(dir: &std::path::Path, succeed_on: u32) -> Command {
+    // Each run appends a line to a file and fails until it has run
+    // `succeed_on` times
+    let mut command = Command::new("sh");
+    command.args([
+        "-c",
+        &format!(
+            "echo run >> {0}/runs; test $(wc -l < {0}/runs) -ge {succeed_on}",
+            dir.display()
+        ),
+    ]);
+    command
+}
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("futility-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+pub fn supervisor_restarts() {
+    let dir = temp_dir("supervisor-restarts");
+    Supervisor::new(counting(&dir, 3), FixedDelay::new(Duration::ZERO))
+        .shutdown_token(ShutdownToken::new())
+        .run()
+        .unwrap();
+    let runs = std::fs::read_to_string(dir.join("runs")).unwrap();
+    assert_eq!(runs.lines().count(), 3);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+pub fn supervisor_gives_up() {
+    let dir = temp_dir("supervisor-gives-up");
+    let err = Supervisor::new(
+        counting(&dir, 10),
+        FixedDelay::new(Duration::ZERO).max_attempts(2),
+    )
+    .shutdown_token(ShutdownToken::new())
+    .run()
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "`sh` failed 2 times, giving up:\n  exited with exit status: 1\n  exited with exit status: 1"
+    );
+    assert_eq!(err.code(), 1);
+    assert!(futility::terminate::child::find(&err).is_some());
+
+    let err = Supervisor::new(
+        Command::new("/does/not/exist"),
+        FixedDelay::new(Duration::ZERO).max_attempts(1),
+    )
+    .shutdown_token(ShutdownToken::new())
+    .run()
+    .unwrap_err();
+    assert!(matches!(err.failures[..], [Failure::Spawn(_)]));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+pub fn supervisor_keeps_the_last_failures() {
+    let dir = temp_dir("supervisor-last-failures");
+    let err = Supervisor::new(
+        counting(&dir, 100),
+        FixedDelay::new(Duration::ZERO).max_attempts(12),
+    )
+    .shutdown_token(ShutdownToken::new())
+    .run()
+    .unwrap_err();
+    assert_eq!(err.failed, 12);
+    assert_eq!(err.failures.len(), 10);
+    assert!(err
+        .to_string()
+        .starts_with("`sh` failed 12 times, giving up, the last 10 were:\n"));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+pub fn supervisor_resets_after_a_healthy_run() {
+    let dir = temp_dir("supervisor-reset");
+    // The second run lasts long enough to reset the policy, so it gives up on
+    // the third rather than the second
+    let mut command = Command::new("sh");
+    command.args([
+        "-c",
+        &format!(
+            "echo run >> {0}/runs; test $(wc -l < {0}/runs) -eq 2 && sleep 0.3; exit 1",
+            dir.display()
+        ),
+    ]);
+    let err = Supervisor::new(command, FixedDelay::new(Duration::ZERO).max_attempts(2))
+        .reset_after(Duration::from_millis(200))
+        .shutdown_token(ShutdownToken::new())
+        .run()
+        .unwrap_err();
+    let runs = std::fs::read_to_string(dir.join("runs")).unwrap();
+    assert_eq!(runs.lines().count(), 3);
+    assert_eq!(err.failed, 2);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+pub fn supervisor_shutdown() {
+    let shutdown = ShutdownToken::new();
+    let supervisor = std::thread::spawn({
+        let shutdown = shutdown.clone();
+        move || {
+            Supervisor::new(sleep(), FixedDelay::new(Duration::ZERO))
+                .shutdown_token(shutdown)
+                .run()
+        }
+    });
+    std::thread::sleep(Duration::from_millis(50));
+    let start = Instant::now();
+    shutdown.trigger();
+    supervisor.join().unwrap().unwrap();
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
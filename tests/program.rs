@@ -0,0 +1,58 @@
+#![cfg(feature = "terminate")]
+
+use futility::terminate::{Program, ProgramContext, Terminate};
+use std::{
+    error::Error,
+    sync::{Arc, Mutex},
+};
+
+struct Recorder {
+    calls: Arc<Mutex<Vec<&'static str>>>,
+    fail_install: bool,
+}
+
+impl Program for Recorder {
+    type Error = Box<dyn Error>;
+
+    fn install(&mut self) -> Result<(), Self::Error> {
+        self.calls.lock().unwrap().push("install");
+        if self.fail_install {
+            return Err("install failed".into());
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, ctx: &mut ProgramContext) -> Result<(), Self::Error> {
+        assert!(!ctx.is_shutting_down());
+        self.calls.lock().unwrap().push("run");
+        Ok(())
+    }
+
+    fn shutdown(&mut self) {
+        self.calls.lock().unwrap().push("shutdown");
+    }
+}
+
+#[test]
+pub fn run_program() -> Result<(), Box<dyn Error>> {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    Terminate::new().run_program(Recorder {
+        calls: Arc::clone(&calls),
+        fail_install: false,
+    })?;
+    assert_eq!(*calls.lock().unwrap(), ["install", "run", "shutdown"]);
+    Ok(())
+}
+
+#[test]
+pub fn run_program_install_fails() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let err = Terminate::new()
+        .run_program(Recorder {
+            calls: Arc::clone(&calls),
+            fail_install: true,
+        })
+        .unwrap_err();
+    assert_eq!(err.to_string(), "install failed");
+    assert_eq!(*calls.lock().unwrap(), ["install"]);
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "rate")]
+
+use futility::rate::{Debouncer, Limiter};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+mod common;
+
+#[test]
+pub fn limiter_bursts_then_paces() {
+    let limiter = Limiter::new(3, Duration::from_millis(60));
+    assert_eq!(limiter.available(), 3);
+    assert!(limiter.try_acquire());
+    assert!(limiter.try_acquire());
+    assert!(limiter.try_acquire());
+    assert!(!limiter.try_acquire());
+    assert_eq!(limiter.available(), 0);
+
+    // One token refills every 20ms
+    let start = Instant::now();
+    limiter.acquire();
+    limiter.acquire();
+    assert!(start.elapsed() >= Duration::from_millis(40));
+
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(limiter.available(), 3);
+}
+
+#[test]
+pub fn limiter_acquire_async() {
+    let limiter = Limiter::new(1, Duration::from_millis(20));
+    let start = Instant::now();
+    common::block_on(async {
+        limiter.acquire_async().await;
+        limiter.acquire_async().await;
+    });
+    assert!(start.elapsed() >= Duration::from_millis(20));
+}
+
+#[test]
+pub fn limiter_without_a_rate() {
+    let limiter = Limiter::new(0, Duration::ZERO);
+    assert!((0..100).all(|_| limiter.try_acquire()));
+}
+
+#[test]
+pub fn debouncer() {
+    let debouncer = Debouncer::new(Duration::from_millis(50));
+    assert!(debouncer.ready());
+    assert!(!debouncer.ready());
+    let mut calls = Vec::new();
+    assert!(!debouncer.call(|suppressed| calls.push(suppressed)));
+    assert_eq!(debouncer.suppressed(), 2);
+
+    thread::sleep(Duration::from_millis(50));
+    assert!(debouncer.call(|suppressed| calls.push(suppressed)));
+    assert!(!debouncer.call(|suppressed| calls.push(suppressed)));
+    assert_eq!(calls, [2]);
+    assert_eq!(debouncer.suppressed(), 1);
+}
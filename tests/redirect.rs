@@ -0,0 +1,29 @@
+#![cfg(all(unix, feature = "terminate"))]
+
+use futility::terminate::{OutputTarget, Terminate};
+use std::{
+    env, fs,
+    io::{self, Write},
+};
+
+#[test]
+pub fn redirect_output_to_file() -> Result<(), io::Error> {
+    let path = env::temp_dir().join(format!("futility-redirect-{}.log", std::process::id()));
+    Terminate::<io::Error>::new()
+        .redirect_output(OutputTarget::File(path.clone()))
+        .at_exit(|| {
+            let _ = writeln!(io::stderr(), "Written to stderr in at_exit");
+        })
+        .execute(|| {
+            writeln!(io::stdout(), "Written to stdout in main")?;
+            Ok(())
+        })?;
+
+    let output = fs::read_to_string(&path)?;
+    fs::remove_file(&path)?;
+    assert_eq!(
+        output,
+        "Written to stdout in main\nWritten to stderr in at_exit\n"
+    );
+    Ok(())
+}
@@ -0,0 +1,59 @@
+#![cfg(feature = "terminate")]
+
+mod common;
+
+use futility::terminate::{ExitPriority, Terminate};
+use std::{io, sync::Mutex};
+
+static ORDER: Mutex<Vec<&str>> = Mutex::new(Vec::new());
+
+#[test]
+pub fn at_exit_macro_runs_lifo() -> Result<(), io::Error> {
+    let _serial = common::serial();
+    ORDER.lock().unwrap().clear();
+    Terminate::<io::Error>::new()
+        .at_exit(|| ORDER.lock().unwrap().push("at_exit"))
+        .execute(|| {
+            futility::at_exit!(|| ORDER.lock().unwrap().push("first"));
+            futility::at_exit!(|| ORDER.lock().unwrap().push("second"));
+            Ok(())
+        })?;
+    assert_eq!(*ORDER.lock().unwrap(), ["at_exit", "second", "first"]);
+    Ok(())
+}
+
+#[test]
+pub fn exit_hooks_run_by_priority() -> Result<(), io::Error> {
+    let _serial = common::serial();
+    ORDER.lock().unwrap().clear();
+    Terminate::<io::Error>::new()
+        .at_exit(|| ORDER.lock().unwrap().push("at_exit"))
+        .exit_hook(ExitPriority::Release, || {
+            ORDER.lock().unwrap().push("release hook")
+        })
+        .exit_hook(ExitPriority::Report, || {
+            ORDER.lock().unwrap().push("report hook")
+        })
+        .execute(|| {
+            futility::at_exit!(|| ORDER.lock().unwrap().push("release"));
+            futility::at_exit!(ExitPriority::Flush, || {
+                ORDER.lock().unwrap().push("flush")
+            });
+            futility::at_exit!(ExitPriority::Report, || {
+                ORDER.lock().unwrap().push("report")
+            });
+            Ok(())
+        })?;
+    assert_eq!(
+        *ORDER.lock().unwrap(),
+        [
+            "at_exit",
+            "report",
+            "report hook",
+            "flush",
+            "release",
+            "release hook"
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,90 @@
+#![cfg(feature = "report")]
+
+use futility::report::Report;
+use std::{error::Error, fmt, io};
+
+#[derive(Debug)]
+struct Outer(Middle);
+
+#[derive(Debug)]
+struct Middle(io::Error);
+
+impl fmt::Display for Outer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("failed to start")
+    }
+}
+
+impl Error for Outer {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+impl fmt::Display for Middle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("failed to load config")
+    }
+}
+
+impl Error for Middle {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+fn error() -> Outer {
+    Outer(Middle(io::Error::other("config.toml is missing")))
+}
+
+#[test]
+pub fn block() {
+    let err = error();
+    assert_eq!(
+        Report::new(&err).color(false).to_string(),
+        "failed to start\n\
+         \n\
+         caused by:\n  \
+           1: failed to load config\n  \
+           2: config.toml is missing"
+    );
+    assert_eq!(
+        Report::new(io::Error::other("no sources"))
+            .color(false)
+            .to_string(),
+        "no sources"
+    );
+}
+
+#[test]
+pub fn compact() {
+    let boxed: Box<dyn Error + Send + Sync> = Box::new(error());
+    assert_eq!(
+        Report::new(&*boxed).compact().color(false).to_string(),
+        "failed to start: failed to load config: config.toml is missing"
+    );
+}
+
+#[test]
+pub fn colored() {
+    let report = Report::new(Middle(io::Error::other("gone"))).color(true);
+    assert_eq!(
+        report.to_string(),
+        "\x1b[1mfailed to load config\x1b[0m\n\n\x1b[1;33mcaused by:\x1b[0m\n  \x1b[2m1\x1b[0m: gone"
+    );
+    assert_eq!(
+        format!("{:?}", report.compact()),
+        "\x1b[1mfailed to load config\x1b[0m: gone"
+    );
+}
+
+#[test]
+pub fn from_error() {
+    fn load() -> Result<(), Report<Outer>> {
+        Err(error())?;
+        Ok(())
+    }
+    let report = load().unwrap_err();
+    assert_eq!(report.error().to_string(), "failed to start");
+    assert!(report.into_error().source().is_some());
+}
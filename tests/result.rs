@@ -0,0 +1,123 @@
+#![cfg(feature = "result")]
+
+use futility::{
+    log::{self, SimpleLogger},
+    prelude::*,
+    retry::FixedDelay,
+};
+use std::{cell::Cell, env, error::Error, fs, time::Duration};
+
+#[test]
+pub fn tap_err() {
+    let mut seen = None;
+    let res: Result<(), &str> = Err("boom");
+    assert_eq!(res.tap_err(|err| seen = Some(*err)), Err("boom"));
+    assert_eq!(seen, Some("boom"));
+
+    let res: Result<u8, &str> = Ok(1);
+    assert_eq!(res.tap_err(|_| panic!("not an error")), Ok(1));
+}
+
+#[test]
+pub fn log_and_warn() {
+    let path = env::temp_dir().join(format!("futility-result-{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    SimpleLogger::default().file(&path).install().unwrap();
+
+    let res: Result<u8, &str> = Err("disk full");
+    assert_eq!(res.log_err("app::store"), Err("disk full"));
+    assert_eq!(Ok::<_, &str>(1).log_err("app::store"), Ok(1));
+    let line = line!() + 1;
+    assert_eq!(Err::<u8, _>("cache is stale").warn_on_err(), None);
+    assert_eq!(Ok::<_, &str>(2).warn_on_err(), Some(2));
+    log::flush();
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        format!(
+            "ERROR app::store: disk full\n\
+             WARN  futility::result: tests/result.rs:{line}:47: cache is stale\n"
+        )
+    );
+    let _ = fs::remove_file(path);
+}
+
+#[test]
+pub fn context_with() {
+    let res: Result<(), Box<dyn Error>> =
+        "x".parse::<u16>().map(drop).context_with(|| "invalid port");
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "invalid port: invalid digit found in string"
+    );
+
+    let res: Result<u16, String> = "80"
+        .parse::<u16>()
+        .context_with(|| -> &str { panic!("only created on error") });
+    assert_eq!(res, Ok(80));
+}
+
+#[test]
+pub fn or_retry() {
+    let attempts = Cell::new(1);
+    let policy = FixedDelay::new(Duration::ZERO).max_attempts(3);
+    let res = Err("refused").or_retry(policy, || {
+        attempts.set(attempts.get() + 1);
+        Err::<(), _>("refused")
+    });
+    assert_eq!(res, Err("refused"));
+    // The first attempt already failed
+    assert_eq!(attempts.get(), 3);
+
+    let res = Err("refused").or_retry(FixedDelay::new(Duration::ZERO), || Ok(4));
+    assert_eq!(res, Ok(4));
+    let res = Ok(5).or_retry(FixedDelay::new(Duration::ZERO), || -> Result<_, ()> {
+        panic!("already succeeded")
+    });
+    assert_eq!(res, Ok(5));
+}
+
+#[test]
+pub fn collect_all_errors() {
+    let ports = ["80", "443"]
+        .iter()
+        .map(|port| port.parse::<u16>())
+        .collect_all_errors::<Vec<_>>();
+    assert_eq!(ports, Ok(vec![80, 443]));
+
+    let mut seen = 0;
+    let res = (0..6)
+        .inspect(|_| seen += 1)
+        .map(|n| match n % 3 {
+            0 => Err(format!("{n} is a multiple of 3")),
+            _ => Ok(n),
+        })
+        .collect_all_errors::<Vec<_>>();
+    // Every item is looked at, even after the first error
+    assert_eq!(seen, 6);
+    let err = res.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "2 errors:\n  [0] 0 is a multiple of 3\n  [3] 3 is a multiple of 3"
+    );
+    assert_eq!(
+        err.into_errors().collect::<Vec<_>>(),
+        ["0 is a multiple of 3", "3 is a multiple of 3"]
+    );
+}
+
+#[test]
+pub fn finally() {
+    let mut calls = 0;
+    assert_eq!(Ok::<_, &str>(1).finally(|| calls += 1), Ok(1));
+    assert_eq!(Err::<u8, _>("boom").finally(|| calls += 1), Err("boom"));
+    assert_eq!(calls, 2);
+
+    let mut seen = Vec::new();
+    let res = Ok::<u8, &str>(1)
+        .finally_with(|res| seen.push(*res))
+        .and(Err("boom"))
+        .finally_with(|res| seen.push(*res));
+    assert_eq!(res, Err("boom"));
+    assert_eq!(seen, [Ok(1), Err("boom")]);
+}
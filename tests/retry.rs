@@ -0,0 +1,330 @@
+#![cfg(feature = "retry")]
+
+use futility::retry::{retry, retry_if, ExponentialBackoff, FixedDelay, Jitter, RetryPolicy};
+use std::{cell::Cell, time::Duration};
+
+mod common;
+
+#[test]
+pub fn retry_until_success() {
+    let attempts = Cell::new(0);
+    let res = retry(FixedDelay::new(Duration::ZERO), || {
+        attempts.set(attempts.get() + 1);
+        match attempts.get() {
+            5 => Ok(attempts.get()),
+            _ => Err("not yet"),
+        }
+    });
+    assert_eq!(res, Ok(5));
+}
+
+#[test]
+pub fn retry_max_attempts() {
+    let attempts = Cell::new(0);
+    let res: Result<(), _> = retry(FixedDelay::new(Duration::ZERO).max_attempts(3), || {
+        attempts.set(attempts.get() + 1);
+        Err(attempts.get())
+    });
+    assert_eq!(res, Err(3));
+}
+
+#[test]
+pub fn retry_if_stops_on_permanent_errors() {
+    let attempts = Cell::new(0);
+    let res: Result<(), _> = retry_if(
+        FixedDelay::new(Duration::ZERO),
+        |err: &&str| *err != "not found",
+        || {
+            attempts.set(attempts.get() + 1);
+            match attempts.get() {
+                1 => Err("timed out"),
+                _ => Err("not found"),
+            }
+        },
+    );
+    assert_eq!(res, Err("not found"));
+    assert_eq!(attempts.get(), 2);
+}
+
+#[test]
+pub fn exponential_backoff_delays() {
+    let mut policy = ExponentialBackoff::new(Duration::from_millis(100))
+        .factor(3.0)
+        .max_delay(Duration::from_secs(1));
+    let delays = (1..=4)
+        .map(|attempt| policy.next_delay(attempt, Duration::ZERO).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        delays,
+        [
+            Duration::from_millis(100),
+            Duration::from_millis(300),
+            Duration::from_millis(900),
+            Duration::from_secs(1),
+        ]
+    );
+    assert_eq!(
+        policy.next_delay(u32::MAX, Duration::ZERO),
+        Some(Duration::from_secs(1))
+    );
+}
+
+#[test]
+pub fn max_elapsed_and_jitter() {
+    let mut policy = FixedDelay::new(Duration::from_secs(1)).max_elapsed(Duration::from_secs(10));
+    assert!(policy.next_delay(1, Duration::from_secs(9)).is_some());
+    assert!(policy.next_delay(1, Duration::from_millis(9001)).is_none());
+    let mut policy = FixedDelay::new(Duration::MAX).max_elapsed(Duration::MAX);
+    assert!(policy.next_delay(1, Duration::from_secs(1)).is_none());
+
+    let mut full = FixedDelay::new(Duration::from_secs(1)).jitter(Jitter::Full);
+    let mut equal = FixedDelay::new(Duration::from_secs(1)).jitter(Jitter::Equal);
+    for _ in 0..100 {
+        assert!(full.next_delay(1, Duration::ZERO).unwrap() <= Duration::from_secs(1));
+        let delay = equal.next_delay(1, Duration::ZERO).unwrap();
+        assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_secs(1));
+    }
+}
+
+#[test]
+pub fn retry_async_until_success() {
+    use futility::retry::{retry_async, retry_async_with};
+    use std::time::Instant;
+
+    let attempts = Cell::new(0);
+    let start = Instant::now();
+    let res = common::block_on(retry_async(
+        FixedDelay::new(Duration::from_millis(10)),
+        || async {
+            attempts.set(attempts.get() + 1);
+            match attempts.get() {
+                3 => Ok(attempts.get()),
+                _ => Err("not yet"),
+            }
+        },
+    ));
+    assert_eq!(res, Ok(3));
+    assert!(start.elapsed() >= Duration::from_millis(20));
+
+    let slept = Cell::new(Duration::ZERO);
+    let res: Result<(), _> = common::block_on(retry_async_with(
+        FixedDelay::new(Duration::from_secs(60)).max_attempts(4),
+        |delay| {
+            slept.set(slept.get() + delay);
+            async {}
+        },
+        |_: &&str| true,
+        || async { Err("always fails") },
+    ));
+    assert_eq!(res, Err("always fails"));
+    assert_eq!(slept.get(), Duration::from_secs(180));
+}
+
+#[test]
+pub fn retry_macro() {
+    use std::io;
+
+    let attempts = Cell::new(0);
+    let res: Result<u32, io::Error> = futility::retry!(FixedDelay::new(Duration::ZERO), {
+        attempts.set(attempts.get() + 1);
+        if attempts.get() < 3 {
+            Err(io::Error::other("not yet"))?;
+        }
+        attempts.get()
+    });
+    assert_eq!(res.unwrap(), 3);
+
+    let res = futility::retry!(FixedDelay::new(Duration::ZERO).max_attempts(2), {
+        "not a number".parse::<u32>()?
+    }
+        as std::num::ParseIntError);
+    assert!(res.is_err());
+}
+
+#[test]
+pub fn retry_within_deadline() {
+    use futility::budget::Deadline;
+
+    let mut policy = FixedDelay::new(Duration::from_secs(1))
+        .deadline(Deadline::after(Duration::from_millis(1500)));
+    assert_eq!(
+        policy.next_delay(1, Duration::ZERO),
+        Some(Duration::from_secs(1))
+    );
+    let mut policy = FixedDelay::new(Duration::from_secs(1))
+        .deadline(Deadline::after(Duration::from_millis(500)));
+    assert_eq!(policy.next_delay(1, Duration::ZERO), None);
+
+    let attempts = Cell::new(0);
+    let res: Result<(), _> = retry(
+        FixedDelay::new(Duration::from_millis(5))
+            .deadline(Deadline::after(Duration::from_millis(50))),
+        || {
+            attempts.set(attempts.get() + 1);
+            Err("refused")
+        },
+    );
+    assert_eq!(res, Err("refused"));
+    assert!((2..=11).contains(&attempts.get()));
+}
+
+#[test]
+pub fn loop_until_ready() {
+    use futility::{
+        retry::{loop_until, LoopStopped},
+        shutdown::ShutdownToken,
+    };
+    use std::{ops::ControlFlow, thread, time::Instant};
+
+    let token = ShutdownToken::new();
+    let polls = Cell::new(0);
+    let ready = loop_until(FixedDelay::new(Duration::ZERO), &token, || {
+        polls.set(polls.get() + 1);
+        match polls.get() {
+            3 => ControlFlow::Break("ready"),
+            _ => ControlFlow::Continue(()),
+        }
+    });
+    assert_eq!(ready, Ok("ready"));
+
+    let gave_up = loop_until(
+        FixedDelay::new(Duration::ZERO).max_attempts(4),
+        &token,
+        || ControlFlow::<()>::Continue(()),
+    );
+    assert_eq!(gave_up, Err(LoopStopped::GaveUp { attempts: 4 }));
+    assert_eq!(
+        gave_up.unwrap_err().to_string(),
+        "gave up waiting after 4 attempts"
+    );
+
+    // A shutdown interrupts the wait between polls
+    let start = Instant::now();
+    let stopper = thread::spawn({
+        let token = token.clone();
+        move || {
+            thread::sleep(Duration::from_millis(20));
+            token.trigger();
+        }
+    });
+    let stopped = loop_until(FixedDelay::new(Duration::from_secs(60)), &token, || {
+        ControlFlow::<()>::Continue(())
+    });
+    stopper.join().unwrap();
+    assert_eq!(stopped, Err(LoopStopped::Shutdown));
+    assert!(start.elapsed() < Duration::from_secs(60));
+
+    let never_polled = loop_until(
+        FixedDelay::new(Duration::ZERO),
+        &token,
+        || -> ControlFlow<()> { panic!("polled after a shutdown") },
+    );
+    assert_eq!(never_polled, Err(LoopStopped::Shutdown));
+}
+
+#[test]
+pub fn policies_are_iterators() {
+    let delays = ExponentialBackoff::new(Duration::from_millis(10))
+        .max_delay(Duration::from_millis(30))
+        .max_attempts(5)
+        .into_iter()
+        .collect::<Vec<_>>();
+    assert_eq!(
+        delays,
+        [10, 20, 30, 30].map(Duration::from_millis),
+        "the policy gives up on the fifth attempt"
+    );
+
+    let mut delays = FixedDelay::new(Duration::from_millis(1)).delays();
+    assert_eq!(delays.attempts(), 0);
+    assert_eq!(delays.sleep(), Some(Duration::from_millis(1)));
+    assert_eq!(delays.by_ref().take(3).count(), 3);
+    assert_eq!(delays.attempts(), 4);
+
+    let mut delays = FixedDelay::new(Duration::from_millis(1))
+        .max_attempts(3)
+        .delays();
+    let waited = common::block_on(async {
+        let mut waited = Vec::new();
+        while let Some(delay) = delays.next_async().await {
+            waited.push(delay);
+        }
+        waited
+    });
+    assert_eq!(waited, [Duration::from_millis(1); 2]);
+    assert_eq!(delays.attempts(), 3);
+}
+
+#[cfg(feature = "futures-core")]
+#[test]
+pub fn delays_are_streams() {
+    use futility::retry::ThreadSleeper;
+    use futures_core::Stream;
+    use std::{future, pin::Pin, time::Instant};
+
+    let mut delays = FixedDelay::new(Duration::from_millis(5))
+        .max_attempts(3)
+        .delays()
+        .into_stream(ThreadSleeper);
+    let start = Instant::now();
+    let waited = common::block_on(async {
+        let mut waited = Vec::new();
+        while let Some(delay) = future::poll_fn(|cx| Pin::new(&mut delays).poll_next(cx)).await {
+            waited.push(delay);
+        }
+        waited
+    });
+    assert_eq!(waited, [Duration::from_millis(5); 2]);
+    assert!(start.elapsed() >= Duration::from_millis(10));
+}
+
+#[cfg(any(feature = "tokio", feature = "async-std", feature = "smol"))]
+async fn retry_with_sleeper(sleeper: impl futility::retry::Sleeper) -> Result<u32, &'static str> {
+    use futility::retry::retry_async_with;
+
+    let attempts = Cell::new(0);
+    retry_async_with(
+        FixedDelay::new(Duration::from_millis(1)).max_attempts(3),
+        sleeper,
+        |_: &&str| true,
+        || async {
+            attempts.set(attempts.get() + 1);
+            match attempts.get() {
+                3 => Ok(3),
+                _ => Err("not yet"),
+            }
+        },
+    )
+    .await
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+pub fn retry_async_with_tokio() {
+    use futility::retry::TokioSleeper;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    assert_eq!(runtime.block_on(retry_with_sleeper(TokioSleeper)), Ok(3));
+}
+
+#[cfg(feature = "async-std")]
+#[test]
+pub fn retry_async_with_async_std() {
+    use futility::retry::AsyncStdSleeper;
+
+    assert_eq!(
+        async_std::task::block_on(retry_with_sleeper(AsyncStdSleeper)),
+        Ok(3)
+    );
+}
+
+#[cfg(feature = "smol")]
+#[test]
+pub fn retry_async_with_smol() {
+    use futility::retry::SmolSleeper;
+
+    assert_eq!(smol::block_on(retry_with_sleeper(SmolSleeper)), Ok(3));
+}
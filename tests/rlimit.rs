@@ -0,0 +1,67 @@
+#![cfg(all(unix, feature = "rlimit"))]
+// rlim_t is a u64 on most, but not all, platforms
+#![allow(clippy::unnecessary_cast)]
+
+use futility::terminate::{rlimit, Limit, Resource, RlimitError, Terminate};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The soft and hard limit of `resource` straight from `getrlimit`
+fn getrlimit(resource: libc::c_int) -> (u64, u64) {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: limit is a valid rlimit for getrlimit to write into
+    assert_eq!(unsafe { libc::getrlimit(resource as _, &mut limit) }, 0);
+    (limit.rlim_cur as u64, limit.rlim_max as u64)
+}
+
+#[test]
+pub fn rlimits_set_during_install() -> Result<(), RlimitError> {
+    // What's configured, for main to check
+    static NOFILE: AtomicU64 = AtomicU64::new(0);
+    static NOFILE_HARD: AtomicU64 = AtomicU64::new(0);
+    static CORE_HARD: AtomicU64 = AtomicU64::new(0);
+
+    // One more than the current soft limit, which fits under any hard limit
+    // other than one equal to it
+    let (soft, hard) = getrlimit(libc::RLIMIT_NOFILE as _);
+    NOFILE.store(soft.saturating_add(1).min(hard), Ordering::SeqCst);
+    NOFILE_HARD.store(hard, Ordering::SeqCst);
+    CORE_HARD.store(getrlimit(libc::RLIMIT_CORE as _).1, Ordering::SeqCst);
+    Terminate::new()
+        .raise_nofile_limit(NOFILE.load(Ordering::SeqCst))
+        .set_rlimits([(
+            Resource::Core,
+            Limit {
+                soft: 0,
+                hard: CORE_HARD.load(Ordering::SeqCst),
+            },
+        )])
+        .execute(|| {
+            assert_eq!(
+                getrlimit(libc::RLIMIT_CORE as _),
+                (0, CORE_HARD.load(Ordering::SeqCst))
+            );
+            assert_eq!(
+                getrlimit(libc::RLIMIT_NOFILE as _),
+                (
+                    NOFILE.load(Ordering::SeqCst),
+                    NOFILE_HARD.load(Ordering::SeqCst)
+                )
+            );
+            Ok(())
+        })
+}
+
+#[test]
+pub fn rlimit_failure_goes_through_on_error() {
+    let core = rlimit::get(Resource::Core).unwrap();
+    let err = Terminate::new()
+        .set_rlimits([(Resource::Core, Limit { soft: 1, hard: 0 })])
+        .on_error(|err: RlimitError| err)
+        .execute(|| panic!("main should not run"))
+        .unwrap_err();
+    assert_eq!(err.resource, Resource::Core);
+    assert_eq!(rlimit::get(Resource::Core).unwrap(), core);
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "runtime")]
+
+use futility::terminate::{runtime, Terminate};
+use std::{
+    io,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+pub fn execute_async() -> Result<(), io::Error> {
+    Terminate::<io::Error>::new()
+        .runtime(|builder| {
+            builder
+                .worker_threads(2)
+                .thread_name("app")
+                .shutdown_timeout(Duration::from_secs(5))
+        })
+        .execute_async(|| async {
+            let (send, recv) = mpsc::channel();
+            assert!(runtime::spawn(async move {
+                send.send(thread::current().name().map(String::from))
+                    .unwrap();
+            }));
+            assert_eq!(recv.recv().unwrap().as_deref(), Some("app"));
+            // The runtime has a timer
+            let start = Instant::now();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            assert!(start.elapsed() >= Duration::from_millis(20));
+            Ok(())
+        })?;
+    assert!(!runtime::spawn(async {}));
+    Ok(())
+}
+
+#[test]
+pub fn execute_async_shutdown_timeout() -> Result<(), io::Error> {
+    let start = Instant::now();
+    Terminate::<io::Error>::new()
+        .runtime(|builder| builder.shutdown_timeout(Duration::from_millis(50)))
+        .execute_async(|| async {
+            let (send, recv) = mpsc::channel();
+            runtime::spawn(async move {
+                send.send(()).unwrap();
+                // Blocks the worker thread rather than yielding, so the task
+                // can't be cancelled
+                thread::sleep(Duration::from_secs(30));
+            });
+            recv.recv().unwrap();
+            // Never finishes, and is cancelled at shutdown
+            runtime::spawn(std::future::pending());
+            Ok(())
+        })?;
+    assert!(start.elapsed() < Duration::from_secs(10));
+    Ok(())
+}
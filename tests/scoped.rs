@@ -0,0 +1,33 @@
+#![cfg(feature = "terminate")]
+
+use futility::terminate::Scoped;
+use std::{
+    error::Error,
+    panic::{self, AssertUnwindSafe},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static AT_EXIT: AtomicUsize = AtomicUsize::new(0);
+
+#[test]
+pub fn scoped_error() {
+    let err = Scoped::<Box<dyn Error>>::new()
+        .install(|| Err("no connection".into()))
+        .on_error(|err| format!("scope failed: {err}").into())
+        .run(|| -> Result<(), _> { panic!("scope should not run") })
+        .unwrap_err();
+    assert_eq!(err.to_string(), "scope failed: no connection");
+}
+
+#[test]
+pub fn scoped_at_exit_on_panic() {
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        Scoped::<Box<dyn Error>>::new()
+            .at_exit(|| {
+                AT_EXIT.fetch_add(1, Ordering::SeqCst);
+            })
+            .run(|| -> Result<(), _> { panic!("Oh no") })
+    }));
+    assert!(res.is_err());
+    assert_eq!(AT_EXIT.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "shutdown")]
+
+use futility::shutdown::ShutdownToken;
+use std::{thread, time::Duration};
+
+mod common;
+
+#[test]
+pub fn trigger_wakes_waiters() {
+    let token = ShutdownToken::new();
+    assert!(!token.is_triggered());
+    assert!(!token.wait_timeout(Duration::from_millis(1)));
+
+    let waiter = thread::spawn({
+        let token = token.clone();
+        move || token.wait()
+    });
+    token.trigger();
+    waiter.join().unwrap();
+    assert!(token.is_triggered());
+    assert!(token.wait_timeout(Duration::ZERO));
+}
+
+#[test]
+pub fn children() {
+    let root = ShutdownToken::new();
+    let server = root.child();
+    let connection = server.child();
+    let other = root.child();
+
+    // Triggering a child leaves its parent alone
+    connection.trigger();
+    assert!(connection.is_triggered());
+    assert!(!server.is_triggered());
+    assert!(!root.is_triggered());
+
+    let waiter = thread::spawn({
+        let other = other.clone();
+        move || other.wait()
+    });
+    root.trigger();
+    waiter.join().unwrap();
+    assert!(server.is_triggered());
+    assert!(other.is_triggered());
+
+    // A child of a triggered token starts out triggered
+    assert!(root.child().is_triggered());
+}
+
+#[test]
+pub fn dropped_children() {
+    let root = ShutdownToken::new();
+    for _ in 0..100 {
+        drop(root.child());
+    }
+    let child = root.child();
+    root.trigger();
+    assert!(child.is_triggered());
+}
+
+#[cfg(feature = "async")]
+#[test]
+pub fn cancelled() {
+    let root = ShutdownToken::new();
+    let child = root.child();
+    let trigger = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        root.trigger();
+    });
+    common::block_on(child.cancelled());
+    trigger.join().unwrap();
+    common::block_on(child.cancelled());
+}
@@ -0,0 +1,389 @@
+#![cfg(all(unix, feature = "terminate"))]
+
+use futility::terminate::{self, ExitReason, ShutdownPolicy, Signal, Terminate};
+use std::{
+    env, io,
+    process::Command,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+static RELOADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Signal handling is process wide so only one test can use it at a time
+static SIGNALS: Mutex<()> = Mutex::new(());
+
+fn wait_for(count: &AtomicUsize, expected: usize) {
+    for _ in 0..100 {
+        if count.load(Ordering::SeqCst) == expected {
+            return;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("Timed out waiting for {expected}");
+}
+
+fn raise(signal: i32) {
+    // SAFETY: raise has no memory safety requirements
+    unsafe {
+        libc::raise(signal);
+    }
+}
+
+#[test]
+pub fn reload_on_sighup() -> Result<(), io::Error> {
+    let _guard = SIGNALS.lock().unwrap_or_else(|e| e.into_inner());
+    Terminate::<io::Error>::new()
+        .on_reload(|| {
+            RELOADS.fetch_add(1, Ordering::SeqCst);
+        })
+        .execute(|| {
+            assert!(terminate::handle().trigger_reload());
+            assert_eq!(RELOADS.load(Ordering::SeqCst), 1);
+            raise(libc::SIGHUP);
+            wait_for(&RELOADS, 2);
+            Ok(())
+        })?;
+    assert!(!terminate::handle().trigger_reload());
+    Ok(())
+}
+
+#[test]
+pub fn trigger_reload_without_on_reload() -> Result<(), io::Error> {
+    use futility::signal::Signals;
+
+    let _guard = SIGNALS.lock().unwrap_or_else(|e| e.into_inner());
+    let listener = Signals::new([Signal::HUP]).listen()?;
+    Terminate::<io::Error>::new()
+        .handle_signals(ShutdownPolicy::Graceful)
+        .execute(|| {
+            assert!(!terminate::handle().trigger_reload());
+            Ok(())
+        })?;
+    // The listener isn't sent a SIGHUP
+    assert_eq!(listener.try_recv(), None);
+    Ok(())
+}
+
+#[test]
+pub fn shutdown_on_sigterm() -> Result<(), io::Error> {
+    let _guard = SIGNALS.lock().unwrap_or_else(|e| e.into_inner());
+    Terminate::<io::Error>::new()
+        .handle_signals(ShutdownPolicy::Graceful)
+        .at_exit_with(|info| {
+            assert_eq!(
+                info.reason,
+                ExitReason::Signal(Signal::from_raw(libc::SIGTERM))
+            );
+        })
+        .execute(|| {
+            let shutdown = terminate::handle().shutdown_token();
+            raise(libc::SIGTERM);
+            assert!(shutdown.wait_timeout(Duration::from_secs(5)));
+            Ok(())
+        })
+}
+
+#[test]
+pub fn second_sigint_forces_exit() {
+    if env::var_os("FUTILITY_FORCE_EXIT").is_some() {
+        let _ = Terminate::<io::Error>::new()
+            .handle_signals(ShutdownPolicy::Escalate)
+            .at_exit_critical(|| println!("critical cleanup ran"))
+            .execute(|| {
+                let shutdown = terminate::handle().shutdown_token();
+                raise(libc::SIGINT);
+                shutdown.wait();
+                raise(libc::SIGINT);
+                thread::sleep(Duration::from_secs(5));
+                Ok(())
+            });
+        return;
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "second_sigint_forces_exit", "--nocapture"])
+        .env("FUTILITY_FORCE_EXIT", "1")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(128 + libc::SIGINT));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("critical cleanup ran"));
+}
+
+#[test]
+pub fn reexec_on_sigusr2() {
+    if env::var_os("FUTILITY_REEXEC").is_some() {
+        let _ = Terminate::<io::Error>::new()
+            .reexec_on(ExitReason::Signal(Signal::from_raw(libc::SIGUSR2)))
+            .at_exit(|| println!("at_exit ran"))
+            .execute(|| {
+                if env::var_os("FUTILITY_REEXECED").is_some() {
+                    println!("re-executed");
+                    return Ok(());
+                }
+                env::set_var("FUTILITY_REEXECED", "1");
+                let shutdown = terminate::handle().shutdown_token();
+                raise(libc::SIGUSR2);
+                shutdown.wait();
+                Ok(())
+            });
+        return;
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "reexec_on_sigusr2", "--nocapture"])
+        .env("FUTILITY_REEXEC", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("at_exit ran").count(), 2);
+    assert!(stdout.contains("re-executed"));
+}
+
+#[test]
+pub fn validate_conflicting_signals() {
+    let err = Terminate::<io::Error>::new()
+        .handle_signals(ShutdownPolicy::Graceful)
+        .reexec_on(ExitReason::Signal(Signal::from_raw(libc::SIGTERM)))
+        .validate()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "SIGTERM is used by both `handle_signals` and `reexec_on`"
+    );
+}
+
+#[test]
+pub fn validate_grace_period_without_signals() {
+    use futility::terminate::ConfigError;
+
+    let err = Terminate::<io::Error>::new()
+        .grace_period(Duration::from_millis(100), Duration::from_secs(5))
+        .validate()
+        .unwrap_err();
+    assert_eq!(err, ConfigError::GraceWithoutSignals);
+}
+
+#[test]
+pub fn grace_period_forces_exit() {
+    if env::var_os("FUTILITY_GRACE_PERIOD").is_some() {
+        let _ = Terminate::<io::Error>::new()
+            .handle_signals(ShutdownPolicy::Graceful)
+            .grace_period(Duration::from_millis(100), Duration::from_secs(5))
+            .at_exit_with(|info| println!("at_exit ran: {}", info.reason))
+            .execute(|| {
+                raise(libc::SIGTERM);
+                // Ignore the shutdown token
+                thread::sleep(Duration::from_secs(10));
+                Ok(())
+            });
+        return;
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "grace_period_forces_exit", "--nocapture"])
+        .env("FUTILITY_GRACE_PERIOD", "1")
+        .output()
+        .unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(terminate::GRACE_PERIOD_EXIT_CODE)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("at_exit ran: timeout"));
+}
+
+static CUSTOM: AtomicUsize = AtomicUsize::new(0);
+
+#[test]
+pub fn custom_signal_actions() {
+    use futility::terminate::signal_action::Action;
+
+    // The shutdown token is process wide and stays triggered, so this runs in
+    // its own process to not affect the other tests
+    if env::var_os("FUTILITY_SIGNAL_ACTIONS").is_some() {
+        let _ = Terminate::<io::Error>::new()
+            .signal(
+                Signal::USR1,
+                Action::Custom(|| {
+                    CUSTOM.fetch_add(1, Ordering::SeqCst);
+                }),
+            )
+            .signal(Signal::USR2, Action::Ignore)
+            .signal(Signal::QUIT, Action::Shutdown)
+            .at_exit_with(|info| println!("at_exit ran: {}", info.reason))
+            .execute(|| {
+                raise(libc::SIGUSR1);
+                wait_for(&CUSTOM, 1);
+                raise(libc::SIGUSR2);
+                let shutdown = terminate::handle().shutdown_token();
+                assert!(!shutdown.is_triggered());
+                raise(libc::SIGQUIT);
+                assert!(shutdown.wait_timeout(Duration::from_secs(5)));
+                Ok(())
+            });
+        return;
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "custom_signal_actions", "--nocapture"])
+        .env("FUTILITY_SIGNAL_ACTIONS", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("at_exit ran: received SIGQUIT"));
+}
+
+#[test]
+pub fn force_exit_action() {
+    use futility::terminate::signal_action::Action;
+
+    if env::var_os("FUTILITY_FORCE_EXIT_ACTION").is_some() {
+        let _ = Terminate::<io::Error>::new()
+            .signal(Signal::USR1, Action::ForceExit)
+            .at_exit_critical(|| println!("critical cleanup ran"))
+            .execute(|| {
+                raise(libc::SIGUSR1);
+                thread::sleep(Duration::from_secs(5));
+                Ok(())
+            });
+        return;
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "force_exit_action", "--nocapture"])
+        .env("FUTILITY_FORCE_EXIT_ACTION", "1")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(128 + libc::SIGUSR1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("critical cleanup ran"));
+}
+
+#[test]
+pub fn validate_conflicting_signal_action() {
+    use futility::terminate::signal_action::Action;
+
+    let err = Terminate::<io::Error>::new()
+        .on_reload(|| {})
+        .signal(Signal::HUP, Action::Ignore)
+        .validate()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "SIGHUP is used by both `on_reload` and `signal`"
+    );
+}
+
+#[test]
+pub fn standalone_handlers_and_listeners() {
+    use futility::signal::{self, Signals};
+    use std::sync::Arc;
+
+    let _guard = SIGNALS.lock().unwrap_or_else(|e| e.into_inner());
+    let handled = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&handled);
+    let handler = Signals::new([Signal::USR1])
+        .handle(move |signal| {
+            assert_eq!(signal, Signal::USR1);
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+    let listener = Signals::new([Signal::USR1, Signal::USR2]).listen().unwrap();
+
+    // Both get a signal they registered for
+    raise(libc::SIGUSR1);
+    wait_for(&handled, 1);
+    assert_eq!(listener.recv(), Signal::USR1);
+
+    // Only the listener registered for SIGUSR2
+    assert!(signal::deliver(Signal::USR2));
+    assert_eq!(listener.iter().next(), Some(Signal::USR2));
+    assert_eq!(handled.load(Ordering::SeqCst), 1);
+
+    // Nothing is registered for SIGHUP
+    assert!(!signal::deliver(Signal::HUP));
+
+    handler.stop();
+    raise(libc::SIGUSR1);
+    assert_eq!(listener.recv(), Signal::USR1);
+    assert_eq!(handled.load(Ordering::SeqCst), 1);
+
+    drop(listener);
+    assert!(!signal::deliver(Signal::USR1));
+}
+
+#[test]
+pub fn listener_recv_async() {
+    use futility::signal::Signals;
+    use std::{
+        future::Future,
+        pin::pin,
+        sync::Arc,
+        task::{Context, Poll, Wake},
+    };
+
+    struct Unpark(thread::Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let _guard = SIGNALS.lock().unwrap_or_else(|e| e.into_inner());
+    let listener = Signals::new([Signal::WINCH]).listen().unwrap();
+    let mut recv = pin!(listener.recv_async());
+    let waker = Arc::new(Unpark(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    assert!(recv.as_mut().poll(&mut cx).is_pending());
+
+    raise(libc::SIGWINCH);
+    let signal = loop {
+        match recv.as_mut().poll(&mut cx) {
+            Poll::Ready(signal) => break signal,
+            Poll::Pending => thread::park(),
+        }
+    };
+    assert_eq!(signal, Signal::WINCH);
+}
+
+#[test]
+pub fn listener_without_blocking() {
+    use futility::signal::{self, Signals};
+
+    let _guard = SIGNALS.lock().unwrap_or_else(|e| e.into_inner());
+    let listener = Signals::new([Signal::USR1, Signal::USR2]).listen().unwrap();
+    assert_eq!(listener.try_recv(), None);
+    assert_eq!(listener.recv_timeout(Duration::from_millis(10)), None);
+
+    assert!(signal::deliver(Signal::USR1));
+    assert_eq!(
+        listener.recv_timeout(Duration::from_secs(5)),
+        Some(Signal::USR1)
+    );
+
+    raise(libc::SIGUSR2);
+    raise(libc::SIGUSR1);
+    // Delivery happens on another thread, so wait for both to be queued
+    let first = listener.recv();
+    let second = listener.recv_timeout(Duration::from_secs(5));
+    assert_eq!(
+        [Some(first), second],
+        [Some(Signal::USR2), Some(Signal::USR1)]
+    );
+    assert_eq!(listener.try_iter().count(), 0);
+
+    assert!(signal::deliver(Signal::USR2));
+    let mut signals = listener.into_iter();
+    assert_eq!(signals.next(), Some(Signal::USR2));
+    assert_eq!(signals.listener().try_iter().next(), None);
+    drop(signals);
+
+    let mut signals = Signals::new([Signal::USR1]).iter().unwrap();
+    raise(libc::SIGUSR1);
+    assert_eq!(signals.next(), Some(Signal::USR1));
+}
@@ -0,0 +1,245 @@
+#![cfg(feature = "supervisor")]
+
+use futility::{
+    retry::{FixedDelay, RetryPolicy},
+    shutdown::ShutdownToken,
+    supervisor::{Failure, Restart, Supervised, Supervisor, SupervisorError},
+};
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// A service that records when it starts and stops in `events`, and waits to
+/// be told to stop
+fn recorded(name: &'static str, events: &Arc<Mutex<Vec<String>>>) -> Supervised {
+    let events = Arc::clone(events);
+    Supervised::new(name, move |shutdown: ShutdownToken| {
+        events.lock().unwrap().push(format!("start {name}"));
+        shutdown.wait();
+        events.lock().unwrap().push(format!("stop {name}"));
+        Ok::<_, io::Error>(())
+    })
+}
+
+#[test]
+pub fn services_start_and_stop_in_dependency_order() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let shutdown = ShutdownToken::new();
+    let stopper = {
+        let events = Arc::clone(&events);
+        let shutdown = shutdown.clone();
+        thread::spawn(move || {
+            while events.lock().unwrap().len() < 3 {
+                thread::sleep(Duration::from_millis(1));
+            }
+            shutdown.trigger();
+        })
+    };
+
+    Supervisor::new()
+        .shutdown_token(shutdown)
+        .service(
+            recorded("api", &events)
+                .depends_on("cache")
+                .depends_on("db"),
+        )
+        .service(recorded("cache", &events).depends_on("db"))
+        .service(recorded("db", &events))
+        .run()
+        .unwrap();
+    stopper.join().unwrap();
+
+    let events = events.lock().unwrap();
+    // Each service is started once its dependencies have been, but can get
+    // to running before them
+    let mut started = events[..3].to_vec();
+    started.sort();
+    assert_eq!(started, ["start api", "start cache", "start db"]);
+    assert_eq!(events[3..], ["stop api", "stop cache", "stop db"]);
+}
+
+#[test]
+pub fn failing_services_are_restarted_then_escalated() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let runs = Arc::new(AtomicUsize::new(0));
+    let failing = {
+        let runs = Arc::clone(&runs);
+        Supervised::new("failing", move |_: ShutdownToken| {
+            match runs.fetch_add(1, Ordering::SeqCst) + 1 {
+                run @ 2 => panic!("run {run} panicked"),
+                run => Err(format!("run {run} failed")),
+            }
+        })
+        .depends_on("db")
+        .policy(FixedDelay::new(Duration::from_millis(1)).max_attempts(3))
+    };
+
+    let err = Supervisor::new()
+        .shutdown_token(ShutdownToken::new())
+        .service(recorded("db", &events))
+        .service(failing)
+        .run()
+        .unwrap_err();
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+    match err {
+        SupervisorError::Escalated { service, failures } => {
+            assert_eq!(service, "failing");
+            assert!(matches!(
+                &failures[..],
+                [Failure::Failed(_), Failure::Panicked(_), Failure::Failed(_)]
+            ));
+        }
+        err => panic!("unexpected error: {err}"),
+    }
+    assert_eq!(*events.lock().unwrap(), ["start db", "stop db"]);
+}
+
+#[test]
+pub fn services_stopping_on_their_own() {
+    let runs = Arc::new(AtomicUsize::new(0));
+    let flaky = {
+        let runs = Arc::clone(&runs);
+        Supervised::new("flaky", move |_: ShutdownToken| {
+            match runs.fetch_add(1, Ordering::SeqCst) + 1 {
+                3 => Ok(()),
+                _ => Err("not yet"),
+            }
+        })
+        .policy(FixedDelay::new(Duration::from_millis(1)).max_attempts(5))
+    };
+    let once =
+        Supervised::new("once", |_: ShutdownToken| Ok::<_, io::Error>(())).restart(Restart::Never);
+
+    Supervisor::new()
+        .shutdown_token(ShutdownToken::new())
+        .service(flaky)
+        .service(once)
+        .run()
+        .unwrap();
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+    let err = Supervisor::new()
+        .shutdown_token(ShutdownToken::new())
+        .service(Supervised::new("never", |_: ShutdownToken| Err("failed")).restart(Restart::Never))
+        .run()
+        .unwrap_err();
+    assert!(matches!(err, SupervisorError::Escalated { failures, .. } if failures.len() == 1));
+}
+
+#[test]
+pub fn dependencies_are_checked_before_starting() {
+    let service = |name| Supervised::new(name, |_: ShutdownToken| -> io::Result<()> { panic!() });
+
+    let err = Supervisor::new()
+        .service(service("api").depends_on("db"))
+        .run()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "service `api` depends on `db`, which isn't supervised"
+    );
+
+    let err = Supervisor::new()
+        .service(service("a").depends_on("b"))
+        .service(service("b").depends_on("c"))
+        .service(service("c").depends_on("a"))
+        .run()
+        .unwrap_err();
+    assert!(matches!(err, SupervisorError::DependencyCycle { .. }));
+
+    let err = Supervisor::new()
+        .service(service("db"))
+        .service(service("db"))
+        .run()
+        .unwrap_err();
+    assert_eq!(err.to_string(), "more than one service is named `db`");
+}
+
+#[test]
+pub fn always_restarts_clean_exits_without_the_policy() {
+    let runs = Arc::new(AtomicUsize::new(0));
+    let shutdown = ShutdownToken::new();
+    let polling = {
+        let runs = Arc::clone(&runs);
+        let shutdown = shutdown.clone();
+        Supervised::new("polling", move |_: ShutdownToken| {
+            if runs.fetch_add(1, Ordering::SeqCst) + 1 == 10 {
+                shutdown.trigger();
+            }
+            Ok::<_, io::Error>(())
+        })
+        .restart(Restart::Always)
+        .policy(FixedDelay::new(Duration::from_millis(1)).max_attempts(2))
+    };
+
+    Supervisor::new()
+        .shutdown_token(shutdown)
+        .service(polling)
+        .run()
+        .unwrap();
+    // It keeps being restarted until the supervisor notices the shutdown
+    assert!(runs.load(Ordering::SeqCst) >= 10);
+}
+
+#[test]
+pub fn healthy_runs_reset_the_policy() {
+    let runs = Arc::new(AtomicUsize::new(0));
+    let recovering = {
+        let runs = Arc::clone(&runs);
+        Supervised::new("recovering", move |_: ShutdownToken| {
+            let run = runs.fetch_add(1, Ordering::SeqCst) + 1;
+            if run == 2 {
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(format!("run {run} failed"))
+        })
+        .policy(FixedDelay::new(Duration::from_millis(1)).max_attempts(2))
+        .reset_after(Duration::from_millis(20))
+    };
+
+    let err = Supervisor::new()
+        .shutdown_token(ShutdownToken::new())
+        .service(recovering)
+        .run()
+        .unwrap_err();
+    // Without the reset the policy would give up after the second run
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+    match err {
+        SupervisorError::Escalated { failures, .. } => {
+            let failures = failures.iter().map(ToString::to_string).collect::<Vec<_>>();
+            assert_eq!(failures, ["failed: run 2 failed", "failed: run 3 failed"]);
+        }
+        err => panic!("unexpected error: {err}"),
+    }
+}
+
+#[test]
+pub fn grace_can_be_unbounded() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let shutdown = ShutdownToken::new();
+    let stopper = {
+        let events = Arc::clone(&events);
+        let shutdown = shutdown.clone();
+        thread::spawn(move || {
+            while events.lock().unwrap().is_empty() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            shutdown.trigger();
+        })
+    };
+
+    Supervisor::new()
+        .shutdown_token(shutdown)
+        .grace(Duration::MAX)
+        .service(recorded("db", &events))
+        .run()
+        .unwrap();
+    stopper.join().unwrap();
+    assert_eq!(*events.lock().unwrap(), ["start db", "stop db"]);
+}
@@ -0,0 +1,85 @@
+#![cfg(feature = "otel")]
+
+use futility::terminate::Terminate;
+use opentelemetry::{
+    global,
+    trace::{Tracer, TracerProvider},
+};
+use opentelemetry_sdk::{
+    error::OTelSdkResult,
+    trace::{SdkTracerProvider, SpanData, SpanExporter},
+};
+use std::{
+    io,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+static FLUSHED: AtomicBool = AtomicBool::new(false);
+static AT_EXIT: AtomicBool = AtomicBool::new(false);
+
+#[test]
+pub fn flush_after_at_exit() -> Result<(), io::Error> {
+    Terminate::<io::Error>::new()
+        .at_exit(|| AT_EXIT.store(true, Ordering::SeqCst))
+        .flush_telemetry(
+            || -> Result<(), io::Error> {
+                assert!(AT_EXIT.load(Ordering::SeqCst));
+                FLUSHED.store(true, Ordering::SeqCst);
+                Ok(())
+            },
+            Duration::from_secs(5),
+        )
+        .execute(|| Ok(()))?;
+    assert!(FLUSHED.load(Ordering::SeqCst));
+    Ok(())
+}
+
+#[test]
+pub fn flush_timeout() -> Result<(), io::Error> {
+    let start = Instant::now();
+    Terminate::<io::Error>::new()
+        .flush_telemetry(
+            || -> Result<(), io::Error> {
+                thread::sleep(Duration::from_secs(10));
+                Ok(())
+            },
+            Duration::from_millis(50),
+        )
+        .execute(|| Ok(()))?;
+    assert!(start.elapsed() < Duration::from_secs(5));
+    Ok(())
+}
+
+static EXPORTED: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts the spans it's given
+#[derive(Debug)]
+struct CountingExporter;
+
+impl SpanExporter for CountingExporter {
+    async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+        EXPORTED.fetch_add(batch.len(), Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[test]
+pub fn flush_tracer_provider() -> Result<(), io::Error> {
+    // The batch processor holds on to spans until it's flushed
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(CountingExporter)
+        .build();
+    global::set_tracer_provider(provider.clone());
+    Terminate::<io::Error>::new()
+        .flush_telemetry(provider, Duration::from_secs(5))
+        .execute(|| {
+            global::tracer_provider()
+                .tracer("futility")
+                .in_span("main", |_| {});
+            Ok(())
+        })?;
+    assert_eq!(EXPORTED.load(Ordering::SeqCst), 1);
+    Ok(())
+}
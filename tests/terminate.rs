@@ -1,5 +1,7 @@
+#![cfg(feature = "terminate")]
+
 use color_eyre::eyre::Report;
-use futility::terminate::Terminate;
+use futility::terminate::{ExitReason, Terminate};
 use std::error::Error;
 
 #[test]
@@ -72,3 +74,536 @@ fn execute<E>() -> Result<(), E> {
     println!("I'm the actual program and can be both a closure or just a function");
     Ok(())
 }
+
+#[test]
+pub fn terminate_builder() -> Result<(), Report> {
+    Terminate::builder()
+        .at_exit(at_exit)
+        .panic_with(|_| eprintln!("Oh no a panic!"))
+        .on_error(eyre_on_error)
+        .execute(execute)
+}
+
+#[test]
+pub fn terminate_run_plain_error() {
+    use futility::terminate::ErrorStyle;
+    use std::process::ExitCode;
+
+    let code = Terminate::new()
+        .error_style(ErrorStyle::Plain)
+        .run(|| -> Result<(), Box<dyn Error>> { Err("Always Fails".into()) });
+    assert_eq!(code, ExitCode::FAILURE);
+
+    let code = Terminate::new()
+        .error_style(ErrorStyle::Plain)
+        .run(execute::<Box<dyn Error>>);
+    assert_eq!(code, ExitCode::SUCCESS);
+}
+
+#[test]
+pub fn terminate_unknown_subcommand() {
+    use futility::terminate::{Main, UnknownSubcommand};
+
+    #[derive(Debug)]
+    struct SubcommandError(String);
+    impl std::fmt::Display for SubcommandError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(&self.0)
+        }
+    }
+    impl From<UnknownSubcommand> for SubcommandError {
+        fn from(err: UnknownSubcommand) -> Self {
+            Self(err.to_string())
+        }
+    }
+
+    let subcommands: &[(&str, Main<SubcommandError>)] = &[("build", execute), ("clean", execute)];
+    assert!(Terminate::new()
+        .execute_subcommand("build", subcommands)
+        .is_ok());
+    let err = Terminate::new()
+        .execute_subcommand("test", subcommands)
+        .unwrap_err();
+    assert_eq!(
+        err.0,
+        "unknown subcommand `test`, expected one of: build, clean"
+    );
+}
+
+#[test]
+pub fn terminate_report_memory() -> Result<(), Box<dyn Error>> {
+    Terminate::new()
+        .report_memory()
+        .at_exit_with(|info| {
+            if cfg!(unix) {
+                assert!(info.peak_memory.unwrap() > 0);
+            }
+        })
+        .execute(execute)
+}
+
+#[test]
+pub fn terminate_report_runtime() -> Result<(), Box<dyn Error>> {
+    Terminate::new()
+        .report_runtime()
+        .at_exit_with(|info| assert!(info.runtime >= std::time::Duration::from_millis(10)))
+        .execute(|| {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            Ok(())
+        })
+}
+
+#[test]
+pub fn terminate_map_install_error() {
+    let err = Terminate::new()
+        .install_with_error(|| -> Result<(), std::io::Error> {
+            Err(std::io::Error::other("no config file"))
+        })
+        .map_install_error(|err| Report::new(err).wrap_err("Failed to start up"))
+        .execute(|| panic!("main should not run"))
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Failed to start up");
+}
+
+#[test]
+pub fn terminate_panic_to_error() {
+    let err = Terminate::<Box<dyn Error>>::new()
+        .panic_to_error(|panic| {
+            assert!(panic.location().unwrap().starts_with("tests/terminate.rs"));
+            assert_eq!(panic.downcast_ref::<&str>(), Some(&"Oh no"));
+            panic.message().unwrap().into()
+        })
+        .execute(|| panic!("Oh no"))
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Oh no");
+}
+
+#[test]
+pub fn terminate_panic_to_error_after_replaced_hook() {
+    for _ in 0..2 {
+        let err = Terminate::<Box<dyn Error>>::new()
+            .replace_panic(|_| {})
+            .panic_to_error(|panic| panic.location().unwrap().into())
+            .execute(|| panic!("Oh no"))
+            .unwrap_err();
+        assert!(err.to_string().starts_with("tests/terminate.rs"));
+    }
+}
+
+#[test]
+pub fn terminate_on_install_error() {
+    let err = Terminate::<Box<dyn Error>>::new()
+        .install(|| Err("missing config".into()))
+        .on_install_error(|err| format!("startup failed: {err}").into())
+        .on_error(|_| panic!("on_error should not run for install errors"))
+        .execute(|| panic!("main should not run"))
+        .unwrap_err();
+    assert_eq!(err.to_string(), "startup failed: missing config");
+
+    let err = Terminate::<Box<dyn Error>>::new()
+        .on_install_error(|_| panic!("on_install_error should not run for program errors"))
+        .on_error(|err| format!("program failed: {err}").into())
+        .execute(|| Err("bad input".into()))
+        .unwrap_err();
+    assert_eq!(err.to_string(), "program failed: bad input");
+}
+
+thread_local! {
+    static REASON: std::cell::Cell<Option<ExitReason>> = const { std::cell::Cell::new(None) };
+}
+
+fn exit_reason(main: fn() -> Result<(), Box<dyn Error>>) -> Option<ExitReason> {
+    let _ = Terminate::new()
+        .install(|| Ok(()))
+        .panic_to_error(|_| "panicked".into())
+        .at_exit_with(|info| REASON.with(|reason| reason.set(Some(info.reason))))
+        .execute(main);
+    REASON.with(|reason| reason.take())
+}
+
+#[test]
+pub fn terminate_exit_reason() {
+    assert_eq!(exit_reason(|| Ok(())), Some(ExitReason::Success));
+    assert_eq!(exit_reason(|| Err("Oh no".into())), Some(ExitReason::Error));
+    assert_eq!(exit_reason(|| panic!("Oh no")), Some(ExitReason::Panic));
+
+    let _ = Terminate::<Box<dyn Error>>::new()
+        .install(|| Err("Oh no".into()))
+        .at_exit_with(|info| REASON.with(|reason| reason.set(Some(info.reason))))
+        .execute(|| Ok(()));
+    assert_eq!(
+        REASON.with(|reason| reason.take()),
+        Some(ExitReason::InstallError)
+    );
+}
+
+#[test]
+pub fn terminate_execute_timed() {
+    let (res, timings) = Terminate::<Box<dyn Error>>::new()
+        .install(|| {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            Ok(())
+        })
+        .at_exit_with(|info| assert!(info.timings.install >= std::time::Duration::from_millis(20)))
+        .execute_timed(|| {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            Ok(())
+        });
+    res.unwrap();
+    assert!(timings.install >= std::time::Duration::from_millis(20));
+    assert!(timings.main >= std::time::Duration::from_millis(20));
+}
+
+#[test]
+pub fn terminate_scoped_env() -> Result<(), Box<dyn Error>> {
+    std::env::set_var("FUTILITY_SCOPED_ENV_SET", "before");
+    std::env::remove_var("FUTILITY_SCOPED_ENV_UNSET");
+    Terminate::<Box<dyn Error>>::new()
+        .scoped_env([
+            ("FUTILITY_SCOPED_ENV_SET", "during"),
+            ("FUTILITY_SCOPED_ENV_UNSET", "during"),
+        ])
+        .execute(|| {
+            assert_eq!(std::env::var("FUTILITY_SCOPED_ENV_SET")?, "during");
+            assert_eq!(std::env::var("FUTILITY_SCOPED_ENV_UNSET")?, "during");
+            assert!(futility::terminate::environment::starting_environment().is_some());
+            Ok(())
+        })?;
+    assert_eq!(std::env::var("FUTILITY_SCOPED_ENV_SET")?, "before");
+    assert!(std::env::var_os("FUTILITY_SCOPED_ENV_UNSET").is_none());
+    Ok(())
+}
+
+#[test]
+pub fn terminate_working_dir() -> Result<(), Box<dyn Error>> {
+    use futility::terminate::{environment, handle};
+
+    let original = std::env::current_dir()?;
+    Terminate::<Box<dyn Error>>::new()
+        .working_dir(std::env::temp_dir())
+        .install(|| {
+            assert_eq!(
+                std::env::current_dir()?,
+                std::env::temp_dir().canonicalize()?
+            );
+            Ok(())
+        })
+        .execute(|| {
+            assert_eq!(
+                std::env::current_dir()?,
+                std::env::temp_dir().canonicalize()?
+            );
+            assert_eq!(handle().original_dir(), environment::original_dir());
+            assert!(environment::original_dir().is_some());
+            Ok(())
+        })?;
+    assert_eq!(std::env::current_dir()?, original);
+    assert!(environment::original_dir().is_none());
+
+    let err = Terminate::<Box<dyn Error>>::new()
+        .working_dir("/futility/does/not/exist")
+        .execute(|| panic!("main should not run"))
+        .unwrap_err();
+    assert!(err.downcast_ref::<std::io::Error>().is_some());
+    assert_eq!(std::env::current_dir()?, original);
+    Ok(())
+}
+
+#[test]
+pub fn terminate_preflight() {
+    use futility::terminate::{Preflight, PreflightError};
+
+    let err = Terminate::<PreflightError>::new()
+        .preflight(
+            Preflight::new()
+                .check("first", || Err("first failed"))
+                .check("second", || Ok::<_, PreflightError>(()))
+                .check("third", || Err("third failed")),
+        )
+        .install(|| panic!("install should not run"))
+        .execute(|| Ok(()))
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "2 preflight check(s) failed:\n  - first: first failed\n  - third: third failed"
+    );
+}
+
+#[test]
+pub fn terminate_validate() {
+    use futility::terminate::{ConfigError, Preflight, PreflightError};
+
+    let plan = Terminate::<PreflightError>::new()
+        .scoped_env([("FUTILITY_VALIDATE", "1")])
+        .preflight(Preflight::new().check("first", || Ok::<_, PreflightError>(())))
+        .install(|| panic!("install should not run"))
+        .on_error(|err| err)
+        .report_runtime()
+        .at_exit(|| panic!("at_exit should not run"))
+        .validate()
+        .unwrap();
+    assert_eq!(
+        plan.to_string(),
+        "1. set environment variables: FUTILITY_VALIDATE\n\
+         2. run preflight checks: first\n\
+         3. run install\n\
+         4. run main\n\
+         5. call on_error on failure\n\
+         6. report the runtime\n\
+         7. run at_exit"
+    );
+    assert!(std::env::var_os("FUTILITY_VALIDATE").is_none());
+
+    let err = Terminate::<Box<dyn Error>>::new()
+        .on_install_error(|err| err)
+        .validate()
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ConfigError::UnusedHook {
+            hook: "on_install_error",
+            ..
+        }
+    ));
+}
+
+#[test]
+pub fn terminate_harness() {
+    use futility::terminate::harness::{Harness, Hook};
+
+    let terminate = Terminate::<Box<dyn Error>>::new()
+        .install(|| Err("no config".into()))
+        .on_install_error(|err| err)
+        .on_error(|err| err)
+        .at_exit(|| {})
+        .at_exit_critical(|| {});
+    let recording = Harness::new(terminate).run(|| panic!("main should not run"));
+    assert_eq!(recording.result.unwrap_err().to_string(), "no config");
+    assert_eq!(
+        recording.hooks,
+        [
+            Hook::Install,
+            Hook::OnInstallError {
+                error: "no config".into()
+            },
+            Hook::AtExit {
+                reason: ExitReason::InstallError
+            },
+            Hook::AtExitCritical,
+        ]
+    );
+}
+
+#[test]
+pub fn terminate_reporter() {
+    use futility::terminate::ErrorReporter;
+    use std::process::ExitCode;
+
+    struct Config;
+
+    impl ErrorReporter<Box<dyn Error>> for Config {
+        fn report(&self, err: &Box<dyn Error>, reason: ExitReason) -> ExitCode {
+            assert_eq!(err.to_string(), "no config");
+            match reason {
+                ExitReason::InstallError => ExitCode::from(78),
+                _ => ExitCode::FAILURE,
+            }
+        }
+    }
+
+    let code = Terminate::new()
+        .install(|| Err("no config".into()))
+        .reporter(Config)
+        .run(|| Ok(()));
+    assert_eq!(code, ExitCode::from(78));
+
+    let code = Terminate::<Box<dyn Error>>::new()
+        .reporter(futility::terminate::reporter::Json)
+        .run(|| Err("bad \"input\"".into()));
+    assert_eq!(code, ExitCode::FAILURE);
+}
+
+#[test]
+pub fn terminate_nested_execute() {
+    use futility::terminate::AlreadyExecuting;
+
+    #[derive(Debug)]
+    struct NestedError(Option<AlreadyExecuting>);
+    impl std::fmt::Display for NestedError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl From<AlreadyExecuting> for NestedError {
+        fn from(err: AlreadyExecuting) -> Self {
+            Self(Some(err))
+        }
+    }
+
+    let err = Terminate::<NestedError>::new()
+        .on_error(|_| panic!("on_error of the outer program should not run"))
+        .execute(|| {
+            let err = Terminate::<NestedError>::new()
+                .already_executing_error()
+                .on_error(|_| panic!("on_error of the nested program should not run"))
+                .execute(|| panic!("the nested program should not run"))
+                .unwrap_err();
+            assert!(err.0.is_some());
+            Ok(())
+        });
+    assert!(err.is_ok());
+
+    let res = std::panic::catch_unwind(|| {
+        Terminate::<Box<dyn Error>>::new()
+            .execute(|| Terminate::<Box<dyn Error>>::new().execute(|| Ok(())))
+    });
+    assert!(res.is_err());
+    // The guard is released once the outer Terminate unwinds
+    Terminate::<Box<dyn Error>>::new()
+        .execute(|| Ok(()))
+        .unwrap();
+}
+
+#[test]
+pub fn terminate_concurrent_execute_waits() {
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    static RUNNING: AtomicBool = AtomicBool::new(false);
+
+    fn main() -> Result<(), Box<dyn Error>> {
+        assert!(!RUNNING.swap(true, Ordering::SeqCst));
+        thread::sleep(Duration::from_millis(20));
+        RUNNING.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| thread::spawn(|| Terminate::<Box<dyn Error>>::new().execute(main).unwrap()))
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+#[cfg(unix)]
+#[test]
+pub fn terminate_propagate_child_status() {
+    use futility::terminate::ChildFailed;
+    use std::process::{Command, ExitCode};
+
+    let code = Terminate::<Box<dyn Error>>::new()
+        .propagate_child_status()
+        .run(|| {
+            let status = Command::new("sh").args(["-c", "exit 3"]).status()?;
+            ChildFailed::check("sh", status)?;
+            Ok(())
+        });
+    assert_eq!(code, ExitCode::from(3));
+}
+
+#[cfg(unix)]
+#[test]
+pub fn terminate_panic_abort() {
+    use futility::terminate::PanicPolicy;
+    use std::{env, os::unix::process::ExitStatusExt, process::Command};
+
+    if env::var_os("FUTILITY_PANIC_ABORT").is_some() {
+        let _ = Terminate::<Box<dyn Error>>::new()
+            .on_panic(PanicPolicy::Abort)
+            .at_exit(|| println!("at_exit ran"))
+            .at_exit_critical(|| println!("critical cleanup ran"))
+            .execute(|| panic!("Oh no"));
+        return;
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "terminate_panic_abort", "--nocapture"])
+        .env("FUTILITY_PANIC_ABORT", "1")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.signal(), Some(libc::SIGABRT));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("critical cleanup ran"));
+    assert!(!stdout.contains("at_exit ran"));
+}
+
+#[test]
+pub fn terminate_heartbeat() -> Result<(), Box<dyn Error>> {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    static BEATS: AtomicUsize = AtomicUsize::new(0);
+    static AT_EXIT_BEATS: AtomicUsize = AtomicUsize::new(0);
+
+    Terminate::<Box<dyn Error>>::new()
+        .heartbeat(Duration::from_millis(5), || {
+            BEATS.fetch_add(1, Ordering::SeqCst);
+        })
+        .at_exit(|| AT_EXIT_BEATS.store(BEATS.load(Ordering::SeqCst), Ordering::SeqCst))
+        .execute(|| {
+            thread::sleep(Duration::from_millis(100));
+            Ok(())
+        })?;
+    let beats = BEATS.load(Ordering::SeqCst);
+    assert!(beats > 0);
+    assert_eq!(AT_EXIT_BEATS.load(Ordering::SeqCst), beats);
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(BEATS.load(Ordering::SeqCst), beats);
+    Ok(())
+}
+
+#[test]
+pub fn terminate_execute_outcome() {
+    use futility::terminate::handle;
+
+    let outcome = Terminate::<Box<dyn Error>>::new().execute_outcome(|| Ok(()));
+    assert!(outcome.is_success());
+    assert_eq!(outcome.reason, ExitReason::Success);
+    assert!(!outcome.panicked);
+    assert!(!outcome.workers_failed);
+
+    let outcome = Terminate::<Box<dyn Error>>::new()
+        .on_error(|err| format!("failed: {err}").into())
+        .execute_outcome(|| Err("Oh no".into()));
+    assert_eq!(outcome.reason, ExitReason::Error);
+    assert!(!outcome.panicked);
+    assert_eq!(
+        outcome.into_result().unwrap_err().to_string(),
+        "failed: Oh no"
+    );
+
+    let outcome = Terminate::<Box<dyn Error>>::new().execute_outcome(|| {
+        handle().spawn_tracked("panics", |_| -> Result<(), std::io::Error> {
+            panic!("Oh no")
+        })?;
+        Ok(())
+    });
+    assert_eq!(outcome.reason, ExitReason::Success);
+    assert!(outcome.panicked);
+    assert!(outcome.workers_failed);
+    assert!(!outcome.is_success());
+}
+
+#[test]
+pub fn terminate_panic_with_payload() {
+    use std::sync::Mutex;
+
+    static MESSAGE: Mutex<Option<String>> = Mutex::new(None);
+
+    let err = Terminate::<Box<dyn Error>>::new()
+        .panic_with_payload(|panic| {
+            *MESSAGE.lock().unwrap() = panic.message().map(Into::into);
+        })
+        .panic_to_error(|_| "panicked".into())
+        .execute(|| panic!("Oh no"))
+        .unwrap_err();
+    assert_eq!(err.to_string(), "panicked");
+    assert_eq!(MESSAGE.lock().unwrap().as_deref(), Some("Oh no"));
+}
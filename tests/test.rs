@@ -0,0 +1,50 @@
+#![cfg(feature = "test")]
+
+use futility::test::fixture;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static TORN_DOWN: AtomicUsize = AtomicUsize::new(0);
+
+fn numbers() -> Vec<u32> {
+    vec![1, 2, 3]
+}
+
+fn check_numbers(numbers: Vec<u32>) {
+    assert_eq!(numbers, [1, 2, 3, 4]);
+    TORN_DOWN.fetch_add(1, Ordering::SeqCst);
+}
+
+#[futility::fixture(setup = numbers, teardown = check_numbers)]
+fn fixture_attribute(numbers: &mut Vec<u32>) {
+    numbers.push(4);
+}
+
+#[futility::fixture(setup = numbers)]
+fn fixture_attribute_without_teardown(numbers: &mut Vec<u32>) -> Result<(), String> {
+    numbers.pop().map(drop).ok_or_else(|| "empty".into())
+}
+
+#[test]
+fn fixture_tears_down_after_a_panic() {
+    let torn_down = TORN_DOWN.load(Ordering::SeqCst);
+    let failure = fixture(numbers, check_numbers, |numbers| -> () {
+        numbers.push(4);
+        panic!("Oh no")
+    })
+    .unwrap_err();
+    assert!(failure
+        .report()
+        .starts_with("test panicked at tests/test.rs"));
+    assert!(TORN_DOWN.load(Ordering::SeqCst) > torn_down);
+
+    // Both failures are reported when the teardown fails as well
+    let failure =
+        fixture(numbers, check_numbers, |_| Err::<(), _>("no fourth number")).unwrap_err();
+    let (test, teardown) = failure.report().split_once("\n\n").unwrap();
+    assert_eq!(test, "test returned an error: \"no fourth number\"");
+    assert!(teardown.starts_with("teardown panicked at tests/test.rs"));
+
+    let failure = fixture(|| -> u32 { panic!("no setup") }, |_| {}, |_| ()).unwrap_err();
+    assert!(failure.report().starts_with("setup panicked at"));
+    assert!(failure.report().ends_with("no setup"));
+}
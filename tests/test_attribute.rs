@@ -0,0 +1,50 @@
+#![cfg(all(feature = "terminate", feature = "test"))]
+
+use futility::terminate::harness::TestCase;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static INSTALLS: AtomicUsize = AtomicUsize::new(0);
+static EXITS: AtomicUsize = AtomicUsize::new(0);
+
+fn setup() -> Result<(), String> {
+    INSTALLS.fetch_add(1, Ordering::SeqCst);
+    Ok(())
+}
+
+fn cleanup() {
+    EXITS.fetch_add(1, Ordering::SeqCst);
+}
+
+#[futility::test(install_once = setup, at_exit = cleanup)]
+fn attribute_unit_test() {
+    assert!(INSTALLS.load(Ordering::SeqCst) >= 1);
+}
+
+#[futility::test(install_once = setup)]
+fn attribute_result_test() -> Result<(), String> {
+    assert!(INSTALLS.load(Ordering::SeqCst) >= 1);
+    Ok(())
+}
+
+#[test]
+fn panics_become_failures_and_at_exit_runs() {
+    let failure = TestCase::new()
+        .at_exit(cleanup)
+        .run(|| -> () { panic!("Oh no") })
+        .unwrap_err();
+    assert!(failure
+        .report()
+        .starts_with("test panicked at tests/test_attribute.rs"));
+    assert!(failure.report().ends_with("Oh no"));
+    assert!(EXITS.load(Ordering::SeqCst) >= 1);
+    assert!(INSTALLS.load(Ordering::SeqCst) <= 1);
+}
+
+#[test]
+fn install_failures_become_failures() {
+    let failure = TestCase::new()
+        .install(|| Err("no database"))
+        .run(|| ())
+        .unwrap_err();
+    assert_eq!(failure.report(), "install failed: \"no database\"");
+}
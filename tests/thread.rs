@@ -0,0 +1,60 @@
+#![cfg(feature = "thread")]
+
+use futility::{panic::PanicDetails, thread::scope_try};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+#[derive(Debug, PartialEq)]
+enum TaskError {
+    Failed(u32),
+    Panicked(String),
+}
+
+impl From<PanicDetails> for TaskError {
+    fn from(details: PanicDetails) -> Self {
+        Self::Panicked(details.message)
+    }
+}
+
+#[test]
+pub fn every_value_in_spawn_order() {
+    let total = AtomicU32::new(0);
+    let res = scope_try(|s| {
+        for n in 0..4 {
+            let total = &total;
+            let index = s.spawn_try(move || -> Result<_, TaskError> {
+                total.fetch_add(n, Ordering::SeqCst);
+                Ok(n * 10)
+            });
+            assert_eq!(index, n as usize);
+        }
+    });
+    assert_eq!(res.unwrap(), [0, 10, 20, 30]);
+    assert_eq!(total.load(Ordering::SeqCst), 6);
+}
+
+#[test]
+pub fn every_error_and_panic() {
+    let res = scope_try(|s| {
+        for n in 0..5 {
+            s.spawn_try(move || match n {
+                1 => Err(TaskError::Failed(n)),
+                3 => panic!("task {n} panicked"),
+                _ => Ok(n),
+            });
+        }
+    });
+    let err = res.unwrap_err();
+    assert_eq!(
+        err.errors,
+        [
+            (1, TaskError::Failed(1)),
+            (3, TaskError::Panicked("task 3 panicked".into()))
+        ]
+    );
+}
+
+#[test]
+pub fn empty_scope() {
+    let res = scope_try::<(), TaskError, _>(|_| {});
+    assert_eq!(res.unwrap(), Vec::<()>::new());
+}
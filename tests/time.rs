@@ -0,0 +1,125 @@
+#![cfg(feature = "time")]
+
+use futility::{
+    log::{self, Level, SimpleLogger},
+    time::{self, ScopeTimer, Sink, Stopwatch},
+    time_scope,
+};
+use std::{
+    env, fs,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+#[test]
+pub fn stopwatch() {
+    let mut stopwatch = Stopwatch::new();
+    assert!(!stopwatch.is_running());
+    assert_eq!(stopwatch.elapsed(), Duration::ZERO);
+
+    stopwatch.resume();
+    thread::sleep(Duration::from_millis(10));
+    let paused = stopwatch.pause();
+    assert!(paused >= Duration::from_millis(10));
+    thread::sleep(Duration::from_millis(10));
+    assert_eq!(stopwatch.elapsed(), paused);
+
+    assert_eq!(stopwatch.lap(), paused);
+    assert_eq!(stopwatch.lap(), Duration::ZERO);
+    stopwatch.resume();
+    thread::sleep(Duration::from_millis(10));
+    assert!(stopwatch.lap() >= Duration::from_millis(10));
+
+    stopwatch.reset();
+    assert!(stopwatch.is_running());
+    assert!(stopwatch.elapsed() < paused + Duration::from_millis(10));
+    assert!(Stopwatch::start().is_running());
+}
+
+#[test]
+pub fn scope_sinks() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    {
+        let seen = seen.clone();
+        time_scope!(
+            "callback",
+            Sink::callback(move |label, elapsed| seen
+                .lock()
+                .unwrap()
+                .push((label.to_string(), elapsed)))
+        );
+        thread::sleep(Duration::from_millis(5));
+    }
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 1);
+    assert_eq!(seen[0].0, "callback");
+    assert!(seen[0].1 >= Duration::from_millis(5));
+
+    for _ in 0..3 {
+        time_scope!(String::from("scope_sinks recorded"), Sink::Record);
+    }
+    let summary = time::summary();
+    let (_, recorded) = summary
+        .iter()
+        .find(|(label, _)| label == "scope_sinks recorded")
+        .unwrap();
+    assert_eq!(recorded.count, 3);
+    assert!(recorded.min <= recorded.mean() && recorded.mean() <= recorded.max);
+}
+
+#[test]
+pub fn scope_logged() {
+    let path = env::temp_dir().join(format!("futility-time-{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    SimpleLogger::default()
+        .level(Level::Debug)
+        .file(&path)
+        .install()
+        .unwrap();
+    {
+        time_scope!("logged");
+    }
+    drop(ScopeTimer::new("info", "app").sink(Sink::Log(Level::Info)));
+    log::flush();
+
+    let logged = fs::read_to_string(&path).unwrap();
+    let lines = logged.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("DEBUG time: logged took "));
+    assert!(lines[1].starts_with("INFO  app: info took "));
+    let _ = fs::remove_file(path);
+}
+
+#[cfg(feature = "terminate")]
+#[test]
+pub fn report_runtime_summary() {
+    use futility::terminate::Terminate;
+    use std::io;
+
+    if env::var_os("FUTILITY_TIME_SUMMARY").is_some() {
+        Terminate::<io::Error>::new()
+            .report_runtime()
+            .execute(|| {
+                time::record("parse", Duration::from_millis(2));
+                time::record("parse", Duration::from_millis(4));
+                Ok(())
+            })
+            .unwrap();
+        return;
+    }
+
+    let output = std::process::Command::new(env::current_exe().unwrap())
+        .args(["--exact", "report_runtime_summary", "--nocapture"])
+        .env("FUTILITY_TIME_SUMMARY", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "timings:\n  parse: 2 calls, total 6.00ms, mean 3.00ms, min 2.00ms, max 4.00ms\n"
+        ),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,131 @@
+#![cfg(feature = "timeout")]
+
+use futility::timeout::{self, Deadline, TimedOut, TimeoutError};
+use std::{
+    panic,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+mod common;
+
+#[test]
+pub fn finishes_in_time() {
+    let value = timeout::run(Duration::from_secs(5), || 1 + 1).unwrap();
+    assert_eq!(value, 2);
+}
+
+#[test]
+pub fn times_out_and_joins_worker() {
+    let finished = Arc::new(AtomicBool::new(false));
+    let worker_finished = Arc::clone(&finished);
+    let start = Instant::now();
+    let Err(TimeoutError::TimedOut(err)) = timeout::run(Duration::from_millis(20), move || {
+        thread::sleep(Duration::from_millis(200));
+        worker_finished.store(true, Ordering::SeqCst);
+    }) else {
+        panic!("expected the function to time out");
+    };
+    assert!(start.elapsed() < Duration::from_millis(200));
+    assert_eq!(err.timeout, Duration::from_millis(20));
+    assert_eq!(err.to_string(), "timed out after 20ms");
+    assert!(!err.worker_finished());
+    assert!(err.join_worker());
+    assert!(finished.load(Ordering::SeqCst));
+}
+
+#[test]
+pub fn deadline_stops_worker() {
+    let result = timeout::run_with_deadline(Duration::from_secs(5), |deadline| {
+        assert!(deadline.remaining() <= Duration::from_secs(5));
+        deadline.check()
+    });
+    assert!(result.unwrap().is_ok());
+
+    let deadline = Deadline::after(Duration::ZERO);
+    assert!(deadline.is_expired());
+    let err: TimedOut = deadline.check().unwrap_err();
+    assert!(err.worker_finished());
+}
+
+#[test]
+pub fn run_within_deadline() {
+    let deadline = Deadline::after(Duration::from_millis(20));
+    let value = timeout::run_within(deadline, |deadline| deadline.remaining()).unwrap();
+    assert!(value <= Duration::from_millis(20));
+
+    let Err(TimeoutError::TimedOut(err)) = timeout::run_within(deadline, |deadline| {
+        while !deadline.is_expired() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        thread::sleep(Duration::from_millis(50));
+    }) else {
+        panic!("expected the function to time out");
+    };
+    assert_eq!(err.timeout, Duration::from_millis(20));
+    assert!(err.join_worker());
+}
+
+#[test]
+pub fn panics_are_resumed() {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let payload = panic::catch_unwind(|| timeout::run(Duration::from_secs(5), || panic!("worker")))
+        .unwrap_err();
+    panic::set_hook(hook);
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"worker"));
+}
+
+#[test]
+pub fn async_timeouts() {
+    use futility::timeout::{deadline_at, timeout_async, timeout_async_with, Elapsed};
+    use std::{future, io};
+
+    let value = common::block_on(timeout_async(Duration::from_secs(5), async { 3 }));
+    assert_eq!(value, Ok(3));
+
+    let start = Instant::now();
+    let elapsed = common::block_on(timeout_async(
+        Duration::from_millis(20),
+        future::pending::<()>(),
+    ));
+    assert!(start.elapsed() >= Duration::from_millis(20));
+    assert!(matches!(elapsed, Err(Elapsed { .. })));
+
+    let elapsed = common::block_on(deadline_at(Instant::now(), future::pending::<()>()));
+    let err = io::Error::from(elapsed.unwrap_err());
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+    // A sleeper that's already done times out anything that isn't
+    let elapsed = common::block_on(timeout_async_with(
+        |_| future::ready(()),
+        Duration::from_secs(60),
+        future::pending::<()>(),
+    ));
+    assert!(elapsed.is_err());
+}
+
+#[test]
+pub fn deadline_at_measures_from_the_first_poll() {
+    use futility::timeout::deadline_at_with;
+    use std::{future, sync::Mutex};
+
+    static SLEPT: Mutex<Option<Duration>> = Mutex::new(None);
+
+    let deadline = Instant::now() + Duration::from_millis(100);
+    let timeout = deadline_at_with(
+        |delay| {
+            *SLEPT.lock().unwrap() = Some(delay);
+            future::ready(())
+        },
+        deadline,
+        future::pending::<()>(),
+    );
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(common::block_on(timeout).is_err());
+    assert!(SLEPT.lock().unwrap().unwrap() <= Duration::from_millis(50));
+}
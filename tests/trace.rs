@@ -0,0 +1,94 @@
+#![cfg(feature = "trace")]
+
+mod common;
+
+use futility::{span, trace};
+use std::{thread, time::Duration};
+
+#[test]
+pub fn nested_spans() {
+    let _serial = common::serial();
+    trace::take();
+    {
+        let _outer = span!("outer");
+        for i in 0..2 {
+            let _inner = span!("inner {}", i);
+            let _leaf = span!(String::from("leaf"));
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+    thread::spawn(|| drop(span!("other thread")))
+        .join()
+        .unwrap();
+
+    let trace = trace::take();
+    assert_eq!(trace.spans.len(), 2);
+    let outer = &trace.spans[0];
+    assert_eq!(outer.name, "outer");
+    assert_eq!(
+        outer
+            .children
+            .iter()
+            .map(|span| &*span.name)
+            .collect::<Vec<_>>(),
+        ["inner 0", "inner 1"]
+    );
+    assert_eq!(outer.children[1].children[0].name, "leaf");
+    assert!(outer.duration >= outer.children[0].duration + outer.children[1].duration);
+    assert_eq!(trace.spans[1].name, "other thread");
+    assert!(trace::take().is_empty());
+
+    let lines = trace.to_string();
+    let lines = lines.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 6);
+    assert!(lines[0].starts_with("outer: "));
+    assert!(lines[1].starts_with("  inner 0: "));
+    assert!(lines[2].starts_with("    leaf: "));
+    assert!(lines[5].starts_with("other thread: "));
+}
+
+#[test]
+pub fn guards_dropped_out_of_order() {
+    let _serial = common::serial();
+    trace::take();
+    let outer = span!("outer");
+    let inner = span!("inner");
+    drop(outer);
+    // The inner span already ended along with the outer one
+    drop(inner);
+
+    let trace = trace::take();
+    assert_eq!(trace.spans.len(), 1);
+    assert_eq!(trace.spans[0].children[0].name, "inner");
+}
+
+#[cfg(feature = "terminate")]
+#[test]
+pub fn report_trace() {
+    use futility::terminate::Terminate;
+    use std::{env, io, process::Command};
+
+    if env::var_os("FUTILITY_TRACE_REPORT").is_some() {
+        Terminate::<io::Error>::new()
+            .report_trace()
+            .execute(|| {
+                let _main = span!("main");
+                drop(span!("load"));
+                Ok(())
+            })
+            .unwrap();
+        return;
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "report_trace", "--nocapture"])
+        .env("FUTILITY_TRACE_REPORT", "1")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    let report = stderr.split("trace:\n").nth(1).expect(&stderr);
+    let lines = report.lines().collect::<Vec<_>>();
+    assert!(lines[0].starts_with("  main: "), "{stderr}");
+    assert!(lines[1].starts_with("    load: "), "{stderr}");
+}
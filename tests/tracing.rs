@@ -0,0 +1,114 @@
+#![cfg(all(feature = "tracing", feature = "terminate"))]
+
+use futility::terminate::Terminate;
+use std::{
+    error::Error,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Metadata, Subscriber,
+};
+
+/// Records `phase:outcome` for every lifecycle event and `panic:location` for
+/// every panic
+#[derive(Clone, Default)]
+struct Recorder {
+    events: Arc<Mutex<Vec<String>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+#[derive(Default)]
+struct Fields {
+    phase: String,
+    outcome: String,
+    location: String,
+    duration: bool,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "phase" => self.phase = value.into(),
+            "outcome" => self.outcome = value.into(),
+            "location" => self.location = value.into(),
+            _ => {}
+        }
+    }
+
+    fn record_f64(&mut self, field: &Field, _: f64) {
+        self.duration |= field.name() == "duration_ms";
+    }
+
+    fn record_debug(&mut self, _: &Field, _: &dyn fmt::Debug) {}
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        matches!(metadata.target(), "futility::lifecycle" | "futility::panic")
+    }
+
+    fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let mut events = self.events.lock().unwrap();
+        if event.metadata().target() == "futility::panic" {
+            events.push(format!("panic:{}", fields.location));
+        } else {
+            assert!(fields.duration);
+            events.push(format!("{}:{}", fields.phase, fields.outcome));
+        }
+    }
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+#[test]
+pub fn lifecycle_events() {
+    let recorder = Recorder::default();
+    let events = Arc::clone(&recorder.events);
+    tracing::subscriber::with_default(recorder, || {
+        let _ = Terminate::<Box<dyn Error>>::new()
+            .install(|| Ok(()))
+            .on_error(|err| err)
+            .at_exit(|| {})
+            .execute(|| Err("Oh no".into()));
+    });
+    assert_eq!(
+        *events.lock().unwrap(),
+        ["install:ok", "main:error", "on_error:ok", "at_exit:ok"]
+    );
+}
+
+#[test]
+pub fn log_panics() {
+    let recorder = Recorder::default();
+    let events = Arc::clone(&recorder.events);
+    tracing::subscriber::with_default(recorder, || {
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            Terminate::<Box<dyn Error>>::new()
+                .log_panics()
+                .execute(|| panic!("Oh no"))
+        }));
+        assert!(res.is_err());
+    });
+    let events = events.lock().unwrap();
+    assert!(events
+        .iter()
+        .any(|event| event.starts_with("panic:tests/tracing.rs")));
+}
@@ -0,0 +1,175 @@
+#![cfg(feature = "terminate")]
+
+mod common;
+
+use futility::terminate::{reporter::Plain, tty, Terminate};
+use std::{env, error::Error, io, process::Command};
+
+#[test]
+pub fn detect_tty_force_color() -> Result<(), io::Error> {
+    let _serial = common::serial();
+    Terminate::<io::Error>::new()
+        .scoped_env([("FORCE_COLOR", "1")])
+        .detect_tty()
+        .install(|| {
+            assert!(tty::output_style().stdout_color);
+            assert!(tty::output_style().stderr_color);
+            Ok(())
+        })
+        .execute(|| Ok(()))?;
+
+    Terminate::<io::Error>::new()
+        .scoped_env([("FORCE_COLOR", "0"), ("CLICOLOR_FORCE", "1")])
+        .detect_tty()
+        .execute(|| {
+            assert!(!tty::output_style().stdout_color);
+            assert!(!tty::output_style().stderr_color);
+            Ok(())
+        })
+}
+
+#[test]
+pub fn detect_tty_colors_reporter() {
+    if env::var_os("FUTILITY_TTY_REPORTER").is_some() {
+        let _ = Terminate::new()
+            .detect_tty()
+            .reporter(Plain)
+            .run(|| -> Result<(), Box<dyn Error>> { Err("Always Fails".into()) });
+        return;
+    }
+
+    let run = |force_color| {
+        let output = Command::new(env::current_exe().unwrap())
+            .args(["--exact", "detect_tty_colors_reporter", "--nocapture"])
+            .env("FUTILITY_TTY_REPORTER", "1")
+            .env("FORCE_COLOR", force_color)
+            .env_remove("NO_COLOR")
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+    assert!(run("1").contains("\x1b[1;31merror\x1b[0m: Always Fails"));
+    assert!(run("0").contains("error: Always Fails"));
+}
+
+/// Run this test binary again as `test` with stdin, stdout, and stderr all
+/// attached to a new pseudo terminal, returning whether the terminal was put
+/// back in canonical mode with echo afterwards and everything it printed
+#[cfg(unix)]
+fn run_in_pty(test: &str, case: &str) -> (bool, String) {
+    use std::{
+        fs::File,
+        io::Read,
+        os::fd::{AsRawFd, FromRawFd, OwnedFd},
+        process::Stdio,
+    };
+
+    let (mut master, mut slave) = (0, 0);
+    // SAFETY: master and slave are valid for openpty to write into
+    let res = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            std::ptr::null(),
+        )
+    };
+    assert_eq!(res, 0, "{}", io::Error::last_os_error());
+    // SAFETY: openpty opened both of these and nothing else owns them
+    let (mut master, slave) = unsafe { (File::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+    let stdio = || Stdio::from(slave.try_clone().unwrap());
+    let status = Command::new(env::current_exe().unwrap())
+        .args(["--exact", test, "--nocapture", "--test-threads=1"])
+        .env("FUTILITY_TTY_GUARD", case)
+        .stdin(stdio())
+        .stdout(stdio())
+        .stderr(stdio())
+        .status()
+        .unwrap();
+    assert!(!status.success());
+
+    let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+    // SAFETY: slave is a terminal and termios is valid to write into
+    assert_eq!(
+        unsafe { libc::tcgetattr(slave.as_raw_fd(), termios.as_mut_ptr()) },
+        0
+    );
+    // SAFETY: tcgetattr filled in termios
+    let termios = unsafe { termios.assume_init() };
+    let restored = termios.c_lflag & (libc::ECHO | libc::ICANON) == (libc::ECHO | libc::ICANON);
+
+    // Everything the child wrote is buffered in the pseudo terminal, so read
+    // it without blocking once it's gone
+    // SAFETY: master is an open file descriptor
+    unsafe {
+        let fd = master.as_raw_fd();
+        libc::fcntl(
+            fd,
+            libc::F_SETFL,
+            libc::fcntl(fd, libc::F_GETFL) | libc::O_NONBLOCK,
+        );
+    }
+    let mut output = Vec::new();
+    let mut buf = [0; 4096];
+    while let Ok(read @ 1..) = master.read(&mut buf) {
+        output.extend_from_slice(&buf[..read]);
+    }
+    (restored, String::from_utf8_lossy(&output).into_owned())
+}
+
+/// Enter the alternate screen and raw mode, checking that raw mode took
+#[cfg(unix)]
+fn enter_guards() -> (tty::AltScreenGuard, tty::RawModeGuard) {
+    let screen = tty::AltScreenGuard::enter().unwrap();
+    let raw = tty::RawModeGuard::enable().unwrap();
+    let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+    // SAFETY: stdin is a terminal and termios is valid to write into
+    assert_eq!(unsafe { libc::tcgetattr(0, termios.as_mut_ptr()) }, 0);
+    // SAFETY: tcgetattr filled in termios
+    assert_eq!(unsafe { termios.assume_init() }.c_lflag & libc::ECHO, 0);
+    (screen, raw)
+}
+
+#[test]
+#[cfg(unix)]
+pub fn terminal_guards_restore_on_panic() {
+    if env::var_os("FUTILITY_TTY_GUARD").is_some() {
+        let _guards = enter_guards();
+        panic!("Oh no the interface crashed");
+    }
+
+    let (restored, output) = run_in_pty("terminal_guards_restore_on_panic", "panic");
+    assert!(restored, "{output:?}");
+    let entered = output.find("\x1b[?1049h").unwrap();
+    let left = output.find("\x1b[?1049l").unwrap();
+    let panicked = output.find("Oh no the interface crashed").unwrap();
+    assert!(entered < left && left < panicked, "{output:?}");
+}
+
+#[test]
+#[cfg(all(unix, feature = "atexit"))]
+pub fn terminal_guards_restore_on_exit() {
+    if env::var_os("FUTILITY_TTY_GUARD").is_some() {
+        let _ = Terminate::<io::Error>::new()
+            .at_exit_critical(tty::restore_pending)
+            .register_libc_atexit()
+            .execute(|| {
+                let _guards = enter_guards();
+                std::process::exit(3)
+            });
+        return;
+    }
+
+    let (restored, output) = run_in_pty("terminal_guards_restore_on_exit", "exit");
+    assert!(restored, "{output:?}");
+    assert!(output.contains("\x1b[?1049l"), "{output:?}");
+}
+
+#[test]
+pub fn terminal_guards_need_a_terminal() {
+    if std::io::IsTerminal::is_terminal(&io::stdout()) {
+        return;
+    }
+    assert!(tty::AltScreenGuard::enter().is_err());
+}
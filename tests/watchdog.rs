@@ -0,0 +1,95 @@
+#![cfg(feature = "watchdog")]
+
+use futility::{
+    shutdown::ShutdownToken,
+    watchdog::{Action, Watchdog},
+};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+#[test]
+pub fn fires_once_per_timeout() {
+    let fired = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&fired);
+    let shutdown = ShutdownToken::new();
+    let watchdog = Watchdog::new(Duration::from_millis(50))
+        .on_timeout(Action::Call(Box::new(move |starved| {
+            assert!(starved >= Duration::from_millis(50));
+            counter.fetch_add(1, Ordering::SeqCst);
+        })))
+        .on_timeout(Action::Shutdown(shutdown.clone()));
+    assert_eq!(watchdog.timeout(), Duration::from_millis(50));
+
+    let running = watchdog.start().unwrap();
+    // Fed in time, so nothing happens
+    for _ in 0..5 {
+        thread::sleep(Duration::from_millis(10));
+        watchdog.clone().feed();
+    }
+    assert_eq!(fired.load(Ordering::SeqCst), 0);
+
+    // Starved, the actions are taken once until it's fed again
+    assert!(shutdown.wait_timeout(Duration::from_secs(5)));
+    thread::sleep(Duration::from_millis(120));
+    assert_eq!(fired.load(Ordering::SeqCst), 1);
+
+    watchdog.feed();
+    for _ in 0..500 {
+        if fired.load(Ordering::SeqCst) == 2 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(fired.load(Ordering::SeqCst), 2);
+
+    running.stop();
+    watchdog.feed();
+    thread::sleep(Duration::from_millis(120));
+    assert_eq!(fired.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+#[should_panic(expected = "before the watchdog is cloned")]
+pub fn actions_are_added_before_cloning() {
+    let watchdog = Watchdog::new(Duration::from_secs(1));
+    let _feeder = watchdog.clone();
+    let _ = watchdog.on_timeout(Action::Log);
+}
+
+#[cfg(feature = "terminate")]
+#[test]
+pub fn started_by_terminate() {
+    use futility::terminate::Terminate;
+    use std::{io, sync::atomic::AtomicBool};
+
+    static FIRED: AtomicBool = AtomicBool::new(false);
+
+    let watchdog =
+        Watchdog::new(Duration::from_millis(20)).on_timeout(Action::Call(Box::new(|_| {
+            FIRED.store(true, Ordering::SeqCst)
+        })));
+    let plan = Terminate::<io::Error>::new()
+        .watchdog(&watchdog)
+        .validate()
+        .unwrap();
+    assert!(plan.to_string().contains("start the watchdog"));
+
+    Terminate::<io::Error>::new()
+        .watchdog(&watchdog)
+        .execute(|| {
+            for _ in 0..500 {
+                if FIRED.load(Ordering::SeqCst) {
+                    return Ok(());
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            panic!("the watchdog never fired");
+        })
+        .unwrap();
+}
@@ -0,0 +1,128 @@
+#![cfg(feature = "terminate")]
+
+use futility::terminate::{self, Terminate, WorkerError, WorkerErrors};
+use std::{
+    error::Error,
+    fmt, io,
+    process::{Command, ExitCode},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+mod common;
+
+static STOPPED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug)]
+enum AppError {
+    Io(io::Error),
+    Workers(WorkerErrors),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Io(err) => write!(f, "{err}"),
+            AppError::Workers(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(err: io::Error) -> Self {
+        AppError::Io(err)
+    }
+}
+
+impl From<WorkerErrors> for AppError {
+    fn from(err: WorkerErrors) -> Self {
+        AppError::Workers(err)
+    }
+}
+
+#[test]
+pub fn workers_joined_before_at_exit() {
+    let _serial = common::serial();
+    let err = Terminate::<AppError>::new()
+        .worker_timeout(Duration::from_secs(5))
+        .propagate_worker_errors()
+        .on_error(|err| {
+            assert!(STOPPED.load(Ordering::SeqCst));
+            err
+        })
+        .at_exit(|| assert!(STOPPED.load(Ordering::SeqCst)))
+        .execute(|| {
+            let handle = terminate::handle();
+            handle.spawn_tracked("waits", |shutdown| {
+                shutdown.wait();
+                STOPPED.store(true, Ordering::SeqCst);
+                Ok::<_, io::Error>(())
+            })?;
+            handle.spawn_tracked("panics", |_| -> Result<(), io::Error> { panic!("Oh no") })?;
+            Ok(())
+        })
+        .unwrap_err();
+    match err {
+        AppError::Workers(err) => {
+            assert!(err.panicked());
+            assert!(matches!(
+                &err.errors[..],
+                [WorkerError::Panicked { name, message }] if name == "panics" && message == "Oh no"
+            ));
+        }
+        err => panic!("unexpected error: {err}"),
+    }
+}
+
+#[test]
+pub fn worker_errors_fail_run() {
+    if std::env::var_os("FUTILITY_WORKER_FAILS").is_some() {
+        let code = Terminate::new().run(|| -> Result<(), Box<dyn Error>> {
+            terminate::handle().spawn_tracked("fails", |_| Err("disk full"))?;
+            Ok(())
+        });
+        assert_eq!(code, ExitCode::FAILURE);
+        std::process::exit(if code == ExitCode::FAILURE { 1 } else { 0 });
+    }
+
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "worker_errors_fail_run", "--nocapture"])
+        .env("FUTILITY_WORKER_FAILS", "1")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("error: worker `fails` failed: disk full"),
+        "{stderr}"
+    );
+}
+
+#[test]
+pub fn each_execute_gets_a_new_shutdown_token() -> Result<(), io::Error> {
+    let _serial = common::serial();
+    for _ in 0..2 {
+        Terminate::<io::Error>::new().execute(|| {
+            let handle = terminate::handle();
+            assert!(!handle.shutdown_token().is_triggered());
+            handle.spawn_tracked("waits", |shutdown| {
+                shutdown.wait();
+                Ok::<_, io::Error>(())
+            })
+        })?;
+    }
+    Ok(())
+}
+
+#[test]
+pub fn worker_timeout_can_be_unbounded() -> Result<(), io::Error> {
+    let _serial = common::serial();
+    Terminate::<io::Error>::new()
+        .worker_timeout(Duration::MAX)
+        .execute(|| {
+            terminate::handle().spawn_tracked("waits", |shutdown| {
+                shutdown.wait();
+                Ok::<_, io::Error>(())
+            })
+        })
+}